@@ -0,0 +1,155 @@
+//! Throughput of a session's event path under a fast token stream.
+//!
+//! Run with `cargo bench -p backend --bench event_throughput`.
+
+use anyhow::Result;
+use backend::{
+    AppEvent, ChunkCoalescer, CliIoPayload, CliIoType, DEFAULT_CHUNK_WINDOW,
+    EVENT_CHANNEL_CAPACITY, EventEmitter,
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+const CHUNKS: usize = 10_000;
+/// Spacing of the chunks, about what a fast model streams at.
+const CHUNK_INTERVAL: Duration = Duration::from_micros(100);
+
+/// Serializes every event the way the Tauri and WebSocket emitters do, so
+/// the per-event cost is part of the measurement.
+#[derive(Clone, Default)]
+struct SerializingEmitter {
+    bytes: Arc<AtomicUsize>,
+}
+
+impl EventEmitter for SerializingEmitter {
+    fn emit(&self, event: AppEvent) -> Result<()> {
+        let (name, payload) = event.to_wire()?;
+        let message = serde_json::to_string(&serde_json::json!({
+            "event": name,
+            "payload": payload,
+        }))?;
+        self.bytes.fetch_add(message.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// The events a session sends for one streamed chunk: the raw line for the
+/// debug console, then the text parsed from it.
+fn chunk(index: usize) -> [AppEvent; 2] {
+    let text = format!("token{index} ");
+    let line = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "session/update",
+        "params": {
+            "sessionId": "bench",
+            "update": {
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": text },
+            },
+        },
+    });
+    [
+        AppEvent::CliIo {
+            session_id: "bench".to_string(),
+            payload: CliIoPayload {
+                io_type: CliIoType::Output,
+                data: line.to_string(),
+            },
+        },
+        AppEvent::AiOutput {
+            session_id: "bench".to_string(),
+            payload: text,
+        },
+    ]
+}
+
+fn bench_coalescing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("coalescing");
+    group.throughput(Throughput::Elements(CHUNKS as u64));
+
+    for window in [
+        Duration::ZERO,
+        DEFAULT_CHUNK_WINDOW,
+        Duration::from_millis(50),
+    ] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}ms", window.as_millis())),
+            &window,
+            |b, &window| {
+                b.iter(|| {
+                    let emitter = SerializingEmitter::default();
+                    let mut coalescer = ChunkCoalescer::new(window);
+                    let start = Instant::now();
+                    for index in 0..CHUNKS {
+                        let now = start + CHUNK_INTERVAL * index as u32;
+                        for event in chunk(index) {
+                            for event in coalescer.push(event, now) {
+                                emitter.emit(event).unwrap();
+                            }
+                        }
+                    }
+                    for event in coalescer.flush() {
+                        emitter.emit(event).unwrap();
+                    }
+                    black_box(emitter.bytes.load(Ordering::Relaxed))
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_channels(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("channels");
+    group.throughput(Throughput::Elements(CHUNKS as u64));
+
+    group.bench_function("unbounded", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let producer = tokio::spawn(async move {
+                    for event in (0..CHUNKS).flat_map(chunk) {
+                        tx.send(event).unwrap();
+                    }
+                });
+                let mut received = 0;
+                while let Some(event) = rx.recv().await {
+                    black_box(event);
+                    received += 1;
+                }
+                producer.await.unwrap();
+                received
+            })
+        });
+    });
+
+    group.bench_function("bounded", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let (tx, mut rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+                let producer = tokio::spawn(async move {
+                    for event in (0..CHUNKS).flat_map(chunk) {
+                        tx.send(event).await.unwrap();
+                    }
+                });
+                let mut received = 0;
+                while let Some(event) = rx.recv().await {
+                    black_box(event);
+                    received += 1;
+                }
+                producer.await.unwrap();
+                received
+            })
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_coalescing, bench_channels);
+criterion_main!(benches);
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// ACP Protocol Types
@@ -92,7 +93,7 @@ pub struct SessionPromptParams {
 }
 
 /// Content block for prompts and responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentBlock {
     Text { text: String },
@@ -103,7 +104,7 @@ pub enum ContentBlock {
 }
 
 /// Resource information for embedded resources
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceInfo {
     pub uri: String,
     pub text: String,
@@ -118,7 +119,7 @@ pub struct SessionUpdateParams {
 }
 
 /// Session update types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "sessionUpdate", rename_all = "snake_case")]
 pub enum SessionUpdate {
     #[serde(rename = "agent_message_chunk")]
@@ -171,7 +172,7 @@ impl SessionUpdate {
 }
 
 /// Tool call status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ToolCallStatus {
     Pending,
@@ -181,7 +182,7 @@ pub enum ToolCallStatus {
 }
 
 /// Tool call kind
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ToolCallKind {
     Read,
@@ -193,7 +194,7 @@ pub enum ToolCallKind {
 }
 
 /// Tool call content item
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ToolCallContentItem {
     Content {
@@ -209,7 +210,7 @@ pub enum ToolCallContentItem {
 }
 
 /// Location information for tool calls
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Location {
     pub path: String,
     pub line: Option<u32>,
@@ -217,7 +218,7 @@ pub struct Location {
 }
 
 /// Session/request_permission notification parameters
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionRequestPermissionParams {
     #[serde(rename = "sessionId")]
     pub session_id: String,
@@ -227,7 +228,7 @@ pub struct SessionRequestPermissionParams {
 }
 
 /// Permission option for user selection
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PermissionOption {
     #[serde(rename = "optionId")]
     pub option_id: String,
@@ -236,7 +237,7 @@ pub struct PermissionOption {
 }
 
 /// Permission option kinds
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PermissionOptionKind {
     AllowOnce,
@@ -246,7 +247,7 @@ pub enum PermissionOptionKind {
 }
 
 /// Tool call information in permission request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PermissionToolCall {
     #[serde(rename = "toolCallId")]
     pub tool_call_id: String,
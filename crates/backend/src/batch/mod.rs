@@ -0,0 +1,782 @@
+use crate::acp::{SessionUpdate, ToolCallKind, ToolCallStatus};
+use crate::events::{AppEvent, DEFAULT_CHUNK_WINDOW, EventEmitter};
+use crate::headless::{HeadlessPermissions, HeadlessSession};
+use crate::paths::DataDir;
+use crate::session::{SessionLaunchConfig, SessionParams};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{Semaphore, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Upper bound for `max_concurrency`; every session is a CLI process.
+pub const MAX_CONCURRENCY: usize = 8;
+
+/// How long a project's turn may take when the request doesn't say.
+const DEFAULT_PROJECT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Diffs larger than this are cut off in the report.
+const MAX_DIFF_BYTES: usize = 256 * 1024;
+
+/// A prompt to run against several projects.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchRequest {
+    /// Project directories, run in this order.
+    pub projects: Vec<String>,
+    /// Prompt sent to every project. `{{project_name}}` and `{{project_path}}`
+    /// are replaced with the project's directory name and path.
+    pub prompt: String,
+    /// Provider profile from the credential vault; the Gemini CLI's own login
+    /// is used without one.
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub permissions: HeadlessPermissions,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// Give up on a project after this many seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Keep each project's session in its conversation history.
+    #[serde(default = "default_record_history")]
+    pub record_history: bool,
+}
+
+fn default_max_concurrency() -> usize {
+    2
+}
+
+fn default_record_history() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Running,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ProjectRunStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed { error: String },
+    Cancelled,
+}
+
+impl ProjectRunStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed { .. } => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A tool call the agent made, with its last known status.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchToolCall {
+    pub id: String,
+    pub title: String,
+    pub kind: ToolCallKind,
+    pub status: ToolCallStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchProjectResult {
+    pub project_path: String,
+    pub status: ProjectRunStatus,
+    /// The agent's reply to the prompt.
+    pub final_message: Option<String>,
+    pub tool_calls: Vec<BatchToolCall>,
+    pub permission_requests: usize,
+    /// `git diff HEAD` after the run, or `None` outside a git repository.
+    pub diff: Option<String>,
+    pub untracked_files: Vec<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl BatchProjectResult {
+    fn pending(project_path: &str) -> Self {
+        Self {
+            project_path: project_path.to_string(),
+            status: ProjectRunStatus::Pending,
+            final_message: None,
+            tool_calls: Vec::new(),
+            permission_requests: 0,
+            diff: None,
+            untracked_files: Vec::new(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    fn finish(mut self, status: ProjectRunStatus) -> Self {
+        self.status = status;
+        self.finished_at = Some(Utc::now());
+        self
+    }
+}
+
+/// Summary of a batch. Results are in the order of the request's projects.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchReport {
+    pub id: String,
+    pub prompt: String,
+    pub status: BatchStatus,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub results: Vec<BatchProjectResult>,
+}
+
+impl BatchReport {
+    /// Render the report as `json` or `markdown`.
+    pub fn export(&self, format: &str) -> Result<String> {
+        match format {
+            "json" => {
+                serde_json::to_string_pretty(self).context("Failed to serialize batch report")
+            }
+            "markdown" => Ok(self.to_markdown()),
+            _ => anyhow::bail!("Unsupported export format: {format}"),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let count = |label: &str| {
+            self.results
+                .iter()
+                .filter(|r| r.status.label() == label)
+                .count()
+        };
+
+        let mut md = format!("# Batch {}\n\n", self.id);
+        md.push_str(&format!(
+            "**Prompt:**\n\n> {}\n\n",
+            self.prompt.replace('\n', "\n> ")
+        ));
+        md.push_str(&format!(
+            "**Status:** {:?} ({} succeeded, {} failed, {} cancelled of {})\n",
+            self.status,
+            count("succeeded"),
+            count("failed"),
+            count("cancelled"),
+            self.results.len()
+        ));
+        md.push_str(&format!("**Started:** {}\n", self.created_at.to_rfc3339()));
+        if let Some(finished_at) = self.finished_at {
+            md.push_str(&format!("**Finished:** {}\n", finished_at.to_rfc3339()));
+        }
+
+        for result in &self.results {
+            md.push_str(&format!(
+                "\n## {} ({})\n\n",
+                result.project_path,
+                result.status.label()
+            ));
+            if let ProjectRunStatus::Failed { error } = &result.status {
+                md.push_str(&format!("**Error:** {error}\n\n"));
+            }
+            if let Some(message) = &result.final_message {
+                md.push_str(&format!("### Final message\n\n{}\n\n", message.trim()));
+            }
+            if !result.tool_calls.is_empty() {
+                md.push_str("### Tool calls\n\n");
+                for call in &result.tool_calls {
+                    md.push_str(&format!(
+                        "- {} ({:?}, {:?})\n",
+                        call.title, call.kind, call.status
+                    ));
+                }
+                md.push('\n');
+            }
+            match &result.diff {
+                Some(diff) if !diff.trim().is_empty() => {
+                    md.push_str(&format!(
+                        "### Diff\n\n```diff\n{}\n```\n\n",
+                        diff.trim_end()
+                    ));
+                }
+                _ => {}
+            }
+            if !result.untracked_files.is_empty() {
+                md.push_str("### New files\n\n");
+                for file in &result.untracked_files {
+                    md.push_str(&format!("- {file}\n"));
+                }
+                md.push('\n');
+            }
+        }
+        md
+    }
+}
+
+/// Payload of the `batch-progress-{batch_id}` events. `finished` is the last
+/// one of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchEvent {
+    Project {
+        index: usize,
+        result: BatchProjectResult,
+    },
+    Finished {
+        status: BatchStatus,
+    },
+}
+
+struct BatchEntry {
+    report: BatchReport,
+    cancel: watch::Sender<bool>,
+}
+
+/// Runs batches in the background and keeps their reports.
+#[derive(Clone, Default)]
+pub struct BatchManager {
+    batches: Arc<Mutex<HashMap<String, BatchEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl BatchManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start running `request` with the CLI selected by `launch` and return
+    /// the initial report. Conversation logs go to `data_dir`. Progress is
+    /// emitted as `batch-progress-{batch_id}` events.
+    pub fn start<E: EventEmitter + 'static>(
+        &self,
+        request: BatchRequest,
+        launch: SessionLaunchConfig,
+        data_dir: DataDir,
+        emitter: E,
+    ) -> Result<BatchReport> {
+        if request.projects.is_empty() {
+            anyhow::bail!("A batch needs at least one project");
+        }
+        if request.prompt.trim().is_empty() {
+            anyhow::bail!("A batch needs a prompt");
+        }
+
+        let id = format!("batch-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let report = BatchReport {
+            id: id.clone(),
+            prompt: request.prompt.clone(),
+            status: BatchStatus::Running,
+            created_at: Utc::now(),
+            finished_at: None,
+            results: request
+                .projects
+                .iter()
+                .map(|path| BatchProjectResult::pending(path))
+                .collect(),
+        };
+
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        self.lock()?.insert(
+            id.clone(),
+            BatchEntry {
+                report: report.clone(),
+                cancel: cancel_tx,
+            },
+        );
+        debug!(
+            "Starting {id} over {} projects, {} at a time",
+            request.projects.len(),
+            request.max_concurrency
+        );
+
+        tokio::spawn(run_batch(
+            self.clone(),
+            id,
+            request,
+            launch,
+            data_dir,
+            emitter,
+            cancel_rx,
+        ));
+        Ok(report)
+    }
+
+    /// All batches, newest first.
+    pub fn list(&self) -> Result<Vec<BatchReport>> {
+        let mut reports: Vec<BatchReport> =
+            self.lock()?.values().map(|e| e.report.clone()).collect();
+        reports.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+        Ok(reports)
+    }
+
+    pub fn get(&self, batch_id: &str) -> Result<BatchReport> {
+        self.lock()?
+            .get(batch_id)
+            .map(|e| e.report.clone())
+            .with_context(|| format!("Batch not found: {batch_id}"))
+    }
+
+    /// Stop a batch. Running sessions are killed and projects that haven't
+    /// started are skipped.
+    pub fn cancel(&self, batch_id: &str) -> Result<()> {
+        let batches = self.lock()?;
+        let entry = batches
+            .get(batch_id)
+            .with_context(|| format!("Batch not found: {batch_id}"))?;
+        let _ = entry.cancel.send(true);
+        Ok(())
+    }
+
+    /// Wait until the batch is finished and return its report.
+    pub async fn wait(&self, batch_id: &str) -> Result<BatchReport> {
+        loop {
+            let report = self.get(batch_id)?;
+            if report.status != BatchStatus::Running {
+                return Ok(report);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    fn update(&self, batch_id: &str, f: impl FnOnce(&mut BatchReport)) {
+        if let Ok(mut batches) = self.lock()
+            && let Some(entry) = batches.get_mut(batch_id)
+        {
+            f(&mut entry.report);
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, BatchEntry>>> {
+        self.batches
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock batches mutex"))
+    }
+}
+
+async fn run_batch<E: EventEmitter>(
+    manager: BatchManager,
+    batch_id: String,
+    request: BatchRequest,
+    launch: SessionLaunchConfig,
+    data_dir: DataDir,
+    emitter: E,
+    mut cancel: watch::Receiver<bool>,
+) {
+    let publish = |index: usize, result: BatchProjectResult| {
+        let _ = emitter.emit(AppEvent::BatchProgress {
+            batch_id: batch_id.clone(),
+            payload: BatchEvent::Project {
+                index,
+                result: result.clone(),
+            },
+        });
+        manager.update(&batch_id, |report| report.results[index] = result);
+    };
+
+    let semaphore = Arc::new(Semaphore::new(
+        request.max_concurrency.clamp(1, MAX_CONCURRENCY),
+    ));
+    let timeout = request
+        .timeout_secs
+        .map_or(DEFAULT_PROJECT_TIMEOUT, Duration::from_secs);
+    let mut tasks = JoinSet::new();
+
+    for (index, path) in request.projects.iter().enumerate() {
+        let permit = tokio::select! {
+            permit = Arc::clone(&semaphore).acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => break,
+            },
+            _ = cancelled(&mut cancel) => break,
+        };
+
+        let mut running = BatchProjectResult::pending(path);
+        running.status = ProjectRunStatus::Running;
+        running.started_at = Some(Utc::now());
+        publish(index, running.clone());
+
+        let params = SessionParams {
+            session_id: format!("{batch_id}-{index}"),
+            working_directory: path.clone(),
+            model: launch.model.clone(),
+            backend_config: launch.backend_config.clone(),
+            gemini_auth: launch.gemini_auth.clone(),
+            llxprt_config: launch.llxprt_config.clone(),
+            record_history: request.record_history,
+            chunk_window: DEFAULT_CHUNK_WINDOW,
+            data_dir: data_dir.clone(),
+            env: launch.env.clone(),
+        };
+        let prompt = render_prompt(&request.prompt, path);
+        let permissions = request.permissions;
+        let mut cancel = cancel.clone();
+        tasks.spawn(async move {
+            let _permit = permit;
+            let result = tokio::select! {
+                result = run_project(running.clone(), params, prompt, permissions, timeout) => result,
+                // Dropping the session kills its CLI process.
+                _ = cancelled(&mut cancel) => running.finish(ProjectRunStatus::Cancelled),
+            };
+            (index, result)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => publish(index, result),
+            Err(e) => warn!("Batch {batch_id} task failed: {e}"),
+        }
+    }
+
+    let status = if *cancel.borrow() {
+        BatchStatus::Cancelled
+    } else {
+        BatchStatus::Completed
+    };
+    manager.update(&batch_id, |report| {
+        for result in &mut report.results {
+            if matches!(
+                result.status,
+                ProjectRunStatus::Pending | ProjectRunStatus::Running
+            ) {
+                result.status = ProjectRunStatus::Cancelled;
+            }
+        }
+        report.status = status;
+        report.finished_at = Some(Utc::now());
+    });
+    debug!("Batch {batch_id} finished: {status:?}");
+    let _ = emitter.emit(AppEvent::BatchProgress {
+        batch_id,
+        payload: BatchEvent::Finished { status },
+    });
+}
+
+async fn run_project(
+    result: BatchProjectResult,
+    params: SessionParams,
+    prompt: String,
+    permissions: HeadlessPermissions,
+    timeout: Duration,
+) -> BatchProjectResult {
+    let dir = Path::new(&params.working_directory).to_path_buf();
+    if !dir.is_dir() {
+        return result.finish(ProjectRunStatus::Failed {
+            error: format!("Project directory not found: {}", dir.display()),
+        });
+    }
+
+    let turn = async {
+        let mut session = HeadlessSession::start(params, permissions).await?;
+        session.prompt(&prompt, timeout).await
+    }
+    .await;
+
+    let (diff, untracked_files) = git_changes(&dir).await;
+    let mut result = BatchProjectResult {
+        diff,
+        untracked_files,
+        ..result
+    };
+    match turn {
+        Ok(turn) => {
+            result.final_message = Some(turn.text);
+            result.tool_calls = summarize_tool_calls(&turn.tool_calls);
+            result.permission_requests = turn.permission_requests;
+            result.finish(ProjectRunStatus::Succeeded)
+        }
+        Err(e) => result.finish(ProjectRunStatus::Failed {
+            error: format!("{e:#}"),
+        }),
+    }
+}
+
+async fn cancelled(cancel: &mut watch::Receiver<bool>) {
+    // The sender lives as long as the batch entry, so an error only means the
+    // batch can no longer be cancelled.
+    if cancel.wait_for(|cancelled| *cancelled).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+fn render_prompt(template: &str, project_path: &str) -> String {
+    let name = Path::new(project_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| project_path.to_string());
+    template
+        .replace("{{project_name}}", &name)
+        .replace("{{project_path}}", project_path)
+}
+
+/// Fold tool call updates into one entry per call.
+fn summarize_tool_calls(updates: &[SessionUpdate]) -> Vec<BatchToolCall> {
+    let mut calls: Vec<BatchToolCall> = Vec::new();
+    for update in updates {
+        match update {
+            SessionUpdate::ToolCall {
+                tool_call_id,
+                title,
+                kind,
+                status,
+                ..
+            } => calls.push(BatchToolCall {
+                id: tool_call_id.clone(),
+                title: title.clone(),
+                kind: kind.clone(),
+                status: status.clone(),
+            }),
+            SessionUpdate::ToolCallUpdate {
+                tool_call_id,
+                status,
+                ..
+            } => {
+                if let Some(call) = calls.iter_mut().find(|call| &call.id == tool_call_id) {
+                    call.status = status.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    calls
+}
+
+/// The working tree's changes against HEAD and its new files.
+async fn git_changes(dir: &Path) -> (Option<String>, Vec<String>) {
+    let diff = git(dir, &["diff", "HEAD", "--no-color"])
+        .await
+        .map(|mut diff| {
+            if diff.len() > MAX_DIFF_BYTES {
+                let mut end = MAX_DIFF_BYTES;
+                while !diff.is_char_boundary(end) {
+                    end -= 1;
+                }
+                diff.truncate(end);
+                diff.push_str("\n... diff truncated ...\n");
+            }
+            diff
+        });
+    let untracked = git(dir, &["ls-files", "--others", "--exclude-standard"])
+        .await
+        .map(|files| files.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    (diff, untracked)
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(dir).args(args);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    let output = cmd.output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::MockEventEmitter;
+    use tempfile::TempDir;
+
+    fn launch() -> SessionLaunchConfig {
+        SessionLaunchConfig {
+            model: "gemini-2.5-flash".to_string(),
+            backend_config: None,
+            gemini_auth: None,
+            llxprt_config: None,
+            env: HashMap::new(),
+        }
+    }
+
+    fn request(projects: &[&str]) -> BatchRequest {
+        BatchRequest {
+            projects: projects.iter().map(|p| p.to_string()).collect(),
+            prompt: "Update the license header in {{project_name}}".to_string(),
+            profile_id: None,
+            model: None,
+            permissions: HeadlessPermissions::RejectAll,
+            max_concurrency: 2,
+            timeout_secs: Some(5),
+            record_history: false,
+        }
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        assert_eq!(
+            render_prompt("Fix {{project_name}} at {{project_path}}", "/src/app"),
+            "Fix app at /src/app"
+        );
+    }
+
+    #[test]
+    fn test_request_defaults() {
+        let request: BatchRequest =
+            serde_json::from_str(r#"{"projects": ["/a"], "prompt": "hi"}"#).unwrap();
+        assert_eq!(request.max_concurrency, 2);
+        assert_eq!(request.permissions, HeadlessPermissions::RejectAll);
+        assert!(request.record_history);
+    }
+
+    #[test]
+    fn test_summarize_tool_calls_applies_updates() {
+        let updates: Vec<SessionUpdate> = serde_json::from_value(serde_json::json!([
+            {"sessionUpdate": "tool_call", "toolCallId": "t1", "status": "pending",
+             "title": "Edit LICENSE", "content": [], "locations": [], "kind": "edit"},
+            {"sessionUpdate": "tool_call_update", "toolCallId": "t1", "status": "completed",
+             "content": []}
+        ]))
+        .unwrap();
+        let calls = summarize_tool_calls(&updates);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].title, "Edit LICENSE");
+        assert!(matches!(calls[0].status, ToolCallStatus::Completed));
+    }
+
+    #[tokio::test]
+    async fn test_git_changes() {
+        let dir = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        if !git(&["init", "-q"]).status.success() {
+            return;
+        }
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-q",
+            "-m",
+            "init",
+        ]);
+        std::fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "new\n").unwrap();
+
+        let (diff, untracked) = git_changes(dir.path()).await;
+        let diff = diff.unwrap();
+        assert!(diff.contains("-one"));
+        assert!(diff.contains("+two"));
+        assert_eq!(untracked, vec!["b.txt".to_string()]);
+
+        let outside = TempDir::new().unwrap();
+        assert_eq!(git_changes(outside.path()).await.0, None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_every_project() {
+        let manager = BatchManager::new();
+        let emitter = MockEventEmitter::new();
+        let report = manager
+            .start(
+                request(&["/definitely/missing/one", "/definitely/missing/two"]),
+                launch(),
+                DataDir::default(),
+                emitter.clone(),
+            )
+            .unwrap();
+        assert_eq!(report.status, BatchStatus::Running);
+
+        let report = manager.wait(&report.id).await.unwrap();
+        assert_eq!(report.status, BatchStatus::Completed);
+        assert_eq!(report.results.len(), 2);
+        for result in &report.results {
+            assert!(matches!(result.status, ProjectRunStatus::Failed { .. }));
+            assert!(result.finished_at.is_some());
+        }
+        assert_eq!(report.results[1].project_path, "/definitely/missing/two");
+
+        let events = emitter.get_events_by_name(&format!("batch-progress-{}", report.id));
+        assert_eq!(
+            events.last().unwrap(),
+            &serde_json::json!({"type": "finished", "status": "completed"})
+        );
+        assert_eq!(manager.list().unwrap().len(), 1);
+
+        let markdown = report.export("markdown").unwrap();
+        assert!(markdown.contains("## /definitely/missing/one (failed)"));
+        assert!(markdown.contains("0 succeeded, 2 failed"));
+        assert!(report.export("json").unwrap().contains("\"failed\""));
+        assert!(report.export("pdf").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_batch_skips_pending_projects() {
+        let manager = BatchManager::new();
+        let mut request = request(&["/missing/a", "/missing/b", "/missing/c"]);
+        request.max_concurrency = 1;
+        let report = manager
+            .start(
+                request,
+                launch(),
+                DataDir::default(),
+                MockEventEmitter::new(),
+            )
+            .unwrap();
+        manager.cancel(&report.id).unwrap();
+
+        let report = manager.wait(&report.id).await.unwrap();
+        assert_eq!(report.status, BatchStatus::Cancelled);
+        assert!(report.results.iter().all(
+            |r| r.status != ProjectRunStatus::Pending && r.status != ProjectRunStatus::Running
+        ));
+        assert!(manager.cancel("batch-missing").is_err());
+    }
+
+    #[test]
+    fn test_start_validates_request() {
+        let manager = BatchManager::new();
+        assert!(
+            manager
+                .start(
+                    request(&[]),
+                    launch(),
+                    DataDir::default(),
+                    MockEventEmitter::new()
+                )
+                .is_err()
+        );
+        let mut empty_prompt = request(&["/a"]);
+        empty_prompt.prompt = "  ".to_string();
+        assert!(
+            manager
+                .start(
+                    empty_prompt,
+                    launch(),
+                    DataDir::default(),
+                    MockEventEmitter::new()
+                )
+                .is_err()
+        );
+    }
+}
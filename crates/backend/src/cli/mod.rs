@@ -1,4 +1,5 @@
 use crate::events::ToolCallLocation;
+use schemars::JsonSchema;
 use serde::de::{Deserializer, Error as DeError};
 use serde::{Deserialize, Serialize};
 
@@ -131,7 +132,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CommandResult {
     pub command: String,
     pub success: bool,
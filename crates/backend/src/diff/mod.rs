@@ -0,0 +1,559 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+
+/// Locks held while a file is read, edited and written back, by canonical
+/// path, so two edits to one file can't both start from the same content.
+static FILE_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Number of unchanged lines shown around each hunk when the caller does not
+/// ask for a specific amount.
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// How a proposed `Diff` relates to the file currently on disk.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// `old_text` matches the file, so the edit can be applied.
+    Applicable,
+    /// The file already contains `new_text` in place of `old_text`.
+    AlreadyApplied,
+    /// The file does not exist and `old_text` is empty.
+    NewFile,
+    /// `old_text` occurs more than once, so the edit target is unclear.
+    Ambiguous,
+    /// The file changed since the diff was produced.
+    Conflict,
+}
+
+/// Rendered view of a `Diff` tool call against the current file contents.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct DiffPreview {
+    pub path: String,
+    pub status: DiffStatus,
+    pub unified_diff: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Outcome of applying or reverting a `Diff`.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct DiffApplyResult {
+    pub path: String,
+    pub applied: bool,
+    pub status: DiffStatus,
+    pub conflict: Option<String>,
+}
+
+/// Where `needle` sits inside the file contents.
+enum Match {
+    Whole,
+    Range(usize),
+    Missing,
+    Multiple,
+}
+
+fn locate(haystack: &str, needle: &str) -> Match {
+    if haystack == needle {
+        return Match::Whole;
+    }
+    if needle.is_empty() {
+        return Match::Missing;
+    }
+    let mut matches = haystack.match_indices(needle);
+    match (matches.next(), matches.next()) {
+        (Some((start, _)), None) => Match::Range(start),
+        (Some(_), Some(_)) => Match::Multiple,
+        (None, _) => Match::Missing,
+    }
+}
+
+fn read_current(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Compute the status of replacing `from` with `to`, and the resulting file
+/// contents when the replacement is possible.
+fn resolve(current: Option<&str>, from: &str, to: &str) -> (DiffStatus, Option<String>) {
+    let Some(current) = current else {
+        return if from.is_empty() {
+            (DiffStatus::NewFile, Some(to.to_string()))
+        } else {
+            (DiffStatus::Conflict, None)
+        };
+    };
+
+    match locate(current, from) {
+        Match::Whole => (DiffStatus::Applicable, Some(to.to_string())),
+        Match::Range(start) => {
+            let mut updated = String::with_capacity(current.len() - from.len() + to.len());
+            updated.push_str(&current[..start]);
+            updated.push_str(to);
+            updated.push_str(&current[start + from.len()..]);
+            (DiffStatus::Applicable, Some(updated))
+        }
+        Match::Multiple => (DiffStatus::Ambiguous, None),
+        Match::Missing => match locate(current, to) {
+            Match::Whole | Match::Range(_) => (DiffStatus::AlreadyApplied, None),
+            _ => (DiffStatus::Conflict, None),
+        },
+    }
+}
+
+fn conflict_message(status: DiffStatus) -> Option<String> {
+    match status {
+        DiffStatus::Conflict => Some("File contents no longer match the expected text".to_string()),
+        DiffStatus::Ambiguous => {
+            Some("Expected text occurs more than once in the file".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Render a unified diff between two texts, returning the diff along with the
+/// number of added and removed lines.
+pub fn render_unified_diff(
+    path: &str,
+    before: &str,
+    after: &str,
+    context_lines: usize,
+) -> (String, usize, usize) {
+    let diff = TextDiff::from_lines(before, after);
+    let (mut additions, mut deletions) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => additions += 1,
+            similar::ChangeTag::Delete => deletions += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+
+    let rendered = diff
+        .unified_diff()
+        .context_radius(context_lines)
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string();
+
+    (rendered, additions, deletions)
+}
+
+/// Preview a `Diff` tool call against the file on disk.
+///
+/// When the edit still applies, the unified diff is computed from the real
+/// file so hunks carry surrounding context. Otherwise the diff falls back to
+/// `old_text` -> `new_text` so the proposed change can still be shown.
+pub async fn preview_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+    context_lines: Option<usize>,
+) -> Result<DiffPreview> {
+    let context_lines = context_lines.unwrap_or(DEFAULT_CONTEXT_LINES);
+    let current = read_current(Path::new(&path))?;
+    let (status, updated) = resolve(current.as_deref(), &old_text, &new_text);
+
+    let (before, after) = match (&current, &updated) {
+        (Some(current), Some(updated)) => (current.as_str(), updated.as_str()),
+        (None, Some(updated)) => ("", updated.as_str()),
+        _ => (old_text.as_str(), new_text.as_str()),
+    };
+    let (unified_diff, additions, deletions) =
+        render_unified_diff(&path, before, after, context_lines);
+
+    Ok(DiffPreview {
+        path,
+        status,
+        unified_diff,
+        additions,
+        deletions,
+    })
+}
+
+/// Apply a `Diff` tool call to disk, replacing `old_text` with `new_text`.
+pub async fn apply_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+) -> Result<DiffApplyResult> {
+    replace_on_disk(path, &old_text, &new_text, false).await
+}
+
+/// Undo a previously applied `Diff`, replacing `new_text` with `old_text`.
+/// Reverting a diff that created a file removes the file again.
+pub async fn revert_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+) -> Result<DiffApplyResult> {
+    replace_on_disk(path, &new_text, &old_text, true).await
+}
+
+async fn replace_on_disk(
+    path: String,
+    from: &str,
+    to: &str,
+    reverting: bool,
+) -> Result<DiffApplyResult> {
+    let key = lock_key(Path::new(&path));
+    let lock = FILE_LOCKS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(key.clone())
+        .or_default()
+        .clone();
+    let result = {
+        let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        replace_locked(path, from, to, reverting)
+    };
+    // Forget the lock once no other edit holds or waits for it
+    let mut locks = FILE_LOCKS.lock().unwrap_or_else(PoisonError::into_inner);
+    if Arc::strong_count(&lock) == 2 {
+        locks.remove(&key);
+    }
+    result
+}
+
+/// The path `path` is locked under: its canonical form, which for a file that
+/// doesn't exist yet is its canonical parent joined with its name.
+fn lock_key(path: &Path) -> PathBuf {
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn replace_locked(path: String, from: &str, to: &str, reverting: bool) -> Result<DiffApplyResult> {
+    let file_path = Path::new(&path);
+    let current = read_current(file_path)?;
+
+    // Reverting a file creation: the file must still hold exactly what was written.
+    if reverting && to.is_empty() {
+        let status = match current.as_deref() {
+            Some(current) if current == from => {
+                std::fs::remove_file(file_path)
+                    .with_context(|| format!("Failed to remove {path}"))?;
+                DiffStatus::Applicable
+            }
+            None => DiffStatus::AlreadyApplied,
+            Some(_) => DiffStatus::Conflict,
+        };
+        return Ok(DiffApplyResult {
+            path,
+            applied: status == DiffStatus::Applicable,
+            status,
+            conflict: conflict_message(status),
+        });
+    }
+
+    // Reverting a deletion: nothing marks where the text was, so it can only
+    // be put back as the whole file
+    if reverting && from.is_empty() {
+        let status = match current.as_deref() {
+            None | Some("") => {
+                write_atomic(file_path, to)?;
+                DiffStatus::Applicable
+            }
+            Some(current) if current.contains(to) => DiffStatus::AlreadyApplied,
+            Some(_) => DiffStatus::Conflict,
+        };
+        return Ok(DiffApplyResult {
+            path,
+            applied: status == DiffStatus::Applicable,
+            status,
+            conflict: (status == DiffStatus::Conflict).then(|| {
+                "Deleted text can only be restored into an empty or missing file".to_string()
+            }),
+        });
+    }
+
+    let (status, updated) = resolve(current.as_deref(), from, to);
+    if let Some(updated) = updated {
+        write_atomic(file_path, &updated)?;
+    }
+
+    Ok(DiffApplyResult {
+        path,
+        applied: matches!(status, DiffStatus::Applicable | DiffStatus::NewFile),
+        status,
+        conflict: conflict_message(status),
+    })
+}
+
+/// Write `content` to a sibling temporary file and rename it over `path`, so
+/// readers never observe a partially written file.
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if !parent.exists() {
+        anyhow::bail!("Parent directory does not exist: {}", parent.display());
+    }
+
+    // A unique name, so concurrent writes to the same file don't share it
+    let mut tmp = tempfile::Builder::new()
+        .prefix(".diff-")
+        .suffix(".tmp")
+        .tempfile_in(parent)
+        .with_context(|| format!("Failed to create a temporary file in {}", parent.display()))?;
+    tmp.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {}", tmp.path().display()))?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        let _ = tmp.as_file().set_permissions(metadata.permissions());
+    }
+    // The temporary file is removed if this fails
+    tmp.persist(path)
+        .map_err(|e| e.error)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file_in(dir: &TempDir, name: &str, content: Option<&str>) -> String {
+        let path = dir.path().join(name);
+        if let Some(content) = content {
+            std::fs::write(&path, content).unwrap();
+        }
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_preview_whole_file_uses_disk_context() {
+        let dir = TempDir::new().unwrap();
+        let original = "a\nb\nc\nd\ne\nf\ng\n";
+        let path = file_in(&dir, "f.txt", Some(original));
+
+        let preview = preview_diff(
+            path.clone(),
+            original.to_string(),
+            original.replace("d\n", "D\n"),
+            Some(1),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(preview.status, DiffStatus::Applicable);
+        assert_eq!(preview.additions, 1);
+        assert_eq!(preview.deletions, 1);
+        assert!(preview.unified_diff.contains("-d\n"));
+        assert!(preview.unified_diff.contains("+D\n"));
+        assert!(preview.unified_diff.contains(" c\n"));
+        assert!(!preview.unified_diff.contains(" a\n"));
+    }
+
+    #[tokio::test]
+    async fn test_preview_fragment_match() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "f.rs", Some("fn a() {}\nfn b() {}\nfn c() {}\n"));
+
+        let preview = preview_diff(
+            path,
+            "fn b() {}".to_string(),
+            "fn b() { todo!() }".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(preview.status, DiffStatus::Applicable);
+        assert!(preview.unified_diff.contains(" fn a() {}\n"));
+        assert!(preview.unified_diff.contains("+fn b() { todo!() }\n"));
+    }
+
+    #[tokio::test]
+    async fn test_preview_statuses() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "f.txt", Some("x\nx\n"));
+        let missing = file_in(&dir, "new.txt", None);
+
+        let ambiguous = preview_diff(path.clone(), "x".into(), "y".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(ambiguous.status, DiffStatus::Ambiguous);
+
+        let conflict = preview_diff(path.clone(), "z".into(), "w".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(conflict.status, DiffStatus::Conflict);
+        assert!(conflict.unified_diff.contains("-z"));
+
+        let applied = preview_diff(path, "q\n".into(), "x\nx\n".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(applied.status, DiffStatus::AlreadyApplied);
+
+        let new_file = preview_diff(missing, String::new(), "hello\n".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(new_file.status, DiffStatus::NewFile);
+        assert_eq!(new_file.additions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply_and_revert_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let original = "one\ntwo\nthree\n";
+        let path = file_in(&dir, "f.txt", Some(original));
+
+        let result = apply_diff(path.clone(), "two".into(), "TWO".into())
+            .await
+            .unwrap();
+        assert!(result.applied);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\nTWO\nthree\n");
+
+        let again = apply_diff(path.clone(), "two".into(), "TWO".into())
+            .await
+            .unwrap();
+        assert!(!again.applied);
+        assert_eq!(again.status, DiffStatus::AlreadyApplied);
+
+        let reverted = revert_diff(path.clone(), "two".into(), "TWO".into())
+            .await
+            .unwrap();
+        assert!(reverted.applied);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply_reports_conflict_without_writing() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "f.txt", Some("changed by user\n"));
+
+        let result = apply_diff(path.clone(), "original\n".into(), "edited\n".into())
+            .await
+            .unwrap();
+
+        assert!(!result.applied);
+        assert_eq!(result.status, DiffStatus::Conflict);
+        assert!(result.conflict.is_some());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "changed by user\n");
+    }
+
+    #[tokio::test]
+    async fn test_new_file_apply_and_revert() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "new.txt", None);
+
+        let result = apply_diff(path.clone(), String::new(), "hello\n".into())
+            .await
+            .unwrap();
+        assert!(result.applied);
+        assert_eq!(result.status, DiffStatus::NewFile);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\n");
+
+        let reverted = revert_diff(path.clone(), String::new(), "hello\n".into())
+            .await
+            .unwrap();
+        assert!(reverted.applied);
+        assert!(!Path::new(&path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_revert_new_file_conflict_when_modified() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "new.txt", Some("hello\nmore\n"));
+
+        let result = revert_diff(path.clone(), String::new(), "hello\n".into())
+            .await
+            .unwrap();
+        assert!(!result.applied);
+        assert_eq!(result.status, DiffStatus::Conflict);
+        assert!(Path::new(&path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_revert_deletion() {
+        let dir = TempDir::new().unwrap();
+        let original = "keep\ndrop\n";
+        let path = file_in(&dir, "f.txt", Some(original));
+
+        let result = apply_diff(path.clone(), original.into(), String::new())
+            .await
+            .unwrap();
+        assert!(result.applied);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+        let reverted = revert_diff(path.clone(), original.into(), String::new())
+            .await
+            .unwrap();
+        assert!(reverted.applied);
+        assert_eq!(reverted.status, DiffStatus::Applicable);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        let again = revert_diff(path.clone(), original.into(), String::new())
+            .await
+            .unwrap();
+        assert_eq!(again.status, DiffStatus::AlreadyApplied);
+
+        // A deleted fragment has no position to go back to
+        let fragment = file_in(&dir, "g.txt", Some("keep\n"));
+        let conflict = revert_diff(fragment.clone(), "drop\n".into(), String::new())
+            .await
+            .unwrap();
+        assert!(!conflict.applied);
+        assert_eq!(conflict.status, DiffStatus::Conflict);
+        assert!(conflict.conflict.is_some());
+        assert_eq!(std::fs::read_to_string(&fragment).unwrap(), "keep\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_applies_to_one_file() {
+        let dir = TempDir::new().unwrap();
+        let path = file_in(&dir, "f.txt", Some("a b c d e f g h\n"));
+
+        let edits = ["a", "b", "c", "d", "e", "f", "g", "h"].map(|word| {
+            let path = path.clone();
+            tokio::spawn(
+                async move { apply_diff(path, word.to_string(), word.to_uppercase()).await },
+            )
+        });
+        for edit in edits {
+            assert!(edit.await.unwrap().unwrap().applied);
+        }
+
+        // No edit is lost to another one writing back what it read earlier
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "A B C D E F G H\n");
+        // No temporary files or locks are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(
+            !FILE_LOCKS
+                .lock()
+                .unwrap()
+                .contains_key(&lock_key(Path::new(&path)))
+        );
+    }
+
+    #[test]
+    fn test_diff_status_serialization() {
+        assert_eq!(
+            serde_json::to_value(DiffStatus::AlreadyApplied).unwrap(),
+            "already_applied"
+        );
+        assert_eq!(
+            serde_json::to_value(DiffStatus::NewFile).unwrap(),
+            "new_file"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Merging runs of streamed text chunks into fewer events.
+//!
+//! Fast models can stream thousands of tiny chunks a second, and each one
+//! would otherwise become its own emit, IPC call and WebSocket frame. The
+//! [`ChunkCoalescer`] holds on to `ai-output` and `ai-thought` text for up to
+//! a window, appending consecutive chunks of the same kind and session, and
+//! lets every other event through in order. The `cli-io` lines each chunk
+//! arrives in are merged the same way, but on their own, since they
+//! interleave with the text line by line.
+
+use super::{AppEvent, CliIoPayload, CliIoType};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long a text chunk may wait for more text before it is emitted.
+pub const DEFAULT_CHUNK_WINDOW: Duration = Duration::from_millis(16);
+
+pub struct ChunkCoalescer {
+    window: Duration,
+    io: Option<PendingChunk>,
+    text: Option<PendingChunk>,
+}
+
+#[derive(PartialEq)]
+enum ChunkKind {
+    Output,
+    Thought,
+    CliIo(CliIoType),
+}
+
+struct PendingChunk {
+    session_id: String,
+    kind: ChunkKind,
+    text: String,
+    deadline: Instant,
+}
+
+impl PendingChunk {
+    fn into_event(self) -> AppEvent {
+        let Self {
+            session_id,
+            kind,
+            text,
+            ..
+        } = self;
+        match kind {
+            ChunkKind::Output => AppEvent::AiOutput {
+                session_id,
+                payload: text,
+            },
+            ChunkKind::Thought => AppEvent::AiThought {
+                session_id,
+                payload: text,
+            },
+            ChunkKind::CliIo(io_type) => AppEvent::CliIo {
+                session_id,
+                payload: CliIoPayload {
+                    io_type,
+                    data: text,
+                },
+            },
+        }
+    }
+}
+
+impl ChunkCoalescer {
+    /// A zero `window` turns coalescing off.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            io: None,
+            text: None,
+        }
+    }
+
+    /// Take the next event, returning the events that are ready to be
+    /// emitted, in order. Text chunks and `cli-io` lines are held back until
+    /// [`deadline`](Self::deadline) unless other events push them out first.
+    pub fn push(
+        &mut self,
+        event: AppEvent,
+        now: Instant,
+    ) -> impl Iterator<Item = AppEvent> + use<> {
+        let (session_id, kind, text) = match event {
+            AppEvent::AiOutput {
+                session_id,
+                payload,
+            } if !self.window.is_zero() => (session_id, ChunkKind::Output, payload),
+            AppEvent::AiThought {
+                session_id,
+                payload,
+            } if !self.window.is_zero() => (session_id, ChunkKind::Thought, payload),
+            AppEvent::CliIo {
+                session_id,
+                payload,
+            } if !self.window.is_zero() => {
+                (session_id, ChunkKind::CliIo(payload.io_type), payload.data)
+            }
+            event => {
+                let [io, text] = self.take_pending();
+                return [io, text, Some(event)].into_iter().flatten();
+            }
+        };
+
+        let slot = match kind {
+            ChunkKind::CliIo(_) => &mut self.io,
+            _ => &mut self.text,
+        };
+        if let Some(pending) = slot
+            && pending.session_id == session_id
+            && pending.kind == kind
+            && now < pending.deadline
+        {
+            // Lines are read one at a time, so they never contain one themselves
+            if matches!(kind, ChunkKind::CliIo(_)) {
+                pending.text.push('\n');
+            }
+            pending.text.push_str(&text);
+            return [None, None, None].into_iter().flatten();
+        }
+
+        let flushed = slot.replace(PendingChunk {
+            session_id,
+            kind,
+            text,
+            deadline: now + self.window,
+        });
+        [flushed.map(PendingChunk::into_event), None, None]
+            .into_iter()
+            .flatten()
+    }
+
+    /// When the held back text is due, if there is any.
+    pub fn deadline(&self) -> Option<Instant> {
+        [&self.io, &self.text]
+            .into_iter()
+            .flatten()
+            .map(|pending| pending.deadline)
+            .min()
+    }
+
+    /// Release the held back text, `cli-io` lines first since the text was
+    /// read from them.
+    pub fn flush(&mut self) -> impl Iterator<Item = AppEvent> + use<> {
+        self.take_pending().into_iter().flatten()
+    }
+
+    fn take_pending(&mut self) -> [Option<AppEvent>; 2] {
+        [
+            self.io.take().map(PendingChunk::into_event),
+            self.text.take().map(PendingChunk::into_event),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(20);
+
+    fn output(session_id: &str, text: &str) -> AppEvent {
+        AppEvent::AiOutput {
+            session_id: session_id.to_string(),
+            payload: text.to_string(),
+        }
+    }
+
+    fn thought(session_id: &str, text: &str) -> AppEvent {
+        AppEvent::AiThought {
+            session_id: session_id.to_string(),
+            payload: text.to_string(),
+        }
+    }
+
+    fn wire(events: impl IntoIterator<Item = AppEvent>) -> Vec<(String, serde_json::Value)> {
+        events
+            .into_iter()
+            .map(|event| event.to_wire().unwrap())
+            .collect()
+    }
+
+    fn io(session_id: &str, io_type: CliIoType, data: &str) -> AppEvent {
+        AppEvent::CliIo {
+            session_id: session_id.to_string(),
+            payload: CliIoPayload {
+                io_type,
+                data: data.to_string(),
+            },
+        }
+    }
+
+    fn pair(name: &str, payload: &str) -> (String, serde_json::Value) {
+        (name.to_string(), payload.into())
+    }
+
+    #[test]
+    fn test_merges_consecutive_chunks() {
+        let mut coalescer = ChunkCoalescer::new(WINDOW);
+        let now = Instant::now();
+
+        assert_eq!(coalescer.push(output("a", "Hel"), now).count(), 0);
+        assert_eq!(coalescer.push(output("a", "lo"), now).count(), 0);
+        assert_eq!(coalescer.deadline(), Some(now + WINDOW));
+        assert_eq!(wire(coalescer.flush()), vec![pair("ai-output-a", "Hello")]);
+        assert_eq!(coalescer.deadline(), None);
+    }
+
+    #[test]
+    fn test_other_events_flush_pending_text_first() {
+        let mut coalescer = ChunkCoalescer::new(WINDOW);
+        let now = Instant::now();
+
+        assert_eq!(coalescer.push(output("a", "done"), now).count(), 0);
+        let turn_finished = AppEvent::AiTurnFinished {
+            session_id: "a".to_string(),
+            payload: true,
+        };
+        let events = wire(coalescer.push(turn_finished, now));
+        assert_eq!(
+            events,
+            vec![
+                pair("ai-output-a", "done"),
+                ("ai-turn-finished-a".to_string(), true.into()),
+            ]
+        );
+        assert_eq!(coalescer.flush().count(), 0);
+    }
+
+    #[test]
+    fn test_does_not_merge_across_kinds_or_sessions() {
+        let mut coalescer = ChunkCoalescer::new(WINDOW);
+        let now = Instant::now();
+
+        assert_eq!(coalescer.push(thought("a", "hmm"), now).count(), 0);
+        let events = wire(coalescer.push(output("a", "ok"), now));
+        assert_eq!(events, vec![pair("ai-thought-a", "hmm")]);
+        let events = wire(coalescer.push(output("b", "hi"), now));
+        assert_eq!(events, vec![pair("ai-output-a", "ok")]);
+        assert_eq!(wire(coalescer.flush()), vec![pair("ai-output-b", "hi")]);
+    }
+
+    #[test]
+    fn test_chunk_after_deadline_starts_new_event() {
+        let mut coalescer = ChunkCoalescer::new(WINDOW);
+        let now = Instant::now();
+
+        assert_eq!(coalescer.push(output("a", "one"), now).count(), 0);
+        let events = wire(coalescer.push(output("a", "two"), now + WINDOW));
+        assert_eq!(events, vec![pair("ai-output-a", "one")]);
+        assert_eq!(wire(coalescer.flush()), vec![pair("ai-output-a", "two")]);
+    }
+
+    #[test]
+    fn test_zero_window_passes_chunks_through() {
+        let mut coalescer = ChunkCoalescer::new(Duration::ZERO);
+        let events = wire(coalescer.push(output("a", "hi"), Instant::now()));
+        assert_eq!(events, vec![pair("ai-output-a", "hi")]);
+        assert_eq!(coalescer.deadline(), None);
+    }
+
+    #[test]
+    fn test_merges_cli_io_lines_alongside_text() {
+        let mut coalescer = ChunkCoalescer::new(WINDOW);
+        let now = Instant::now();
+
+        for (line, text) in [("{\"n\":1}", "Hel"), ("{\"n\":2}", "lo")] {
+            assert_eq!(
+                coalescer
+                    .push(io("a", CliIoType::Output, line), now)
+                    .count(),
+                0
+            );
+            assert_eq!(coalescer.push(output("a", text), now).count(), 0);
+        }
+        let events = wire(coalescer.push(io("a", CliIoType::Input, "{}"), now));
+        let cli_io = |io_type: &str, data: &str| {
+            (
+                "cli-io-a".to_string(),
+                serde_json::json!({ "type": io_type, "data": data }),
+            )
+        };
+        assert_eq!(events, vec![cli_io("output", "{\"n\":1}\n{\"n\":2}")]);
+        assert_eq!(
+            wire(coalescer.flush()),
+            vec![cli_io("input", "{}"), pair("ai-output-a", "Hello")]
+        );
+    }
+}
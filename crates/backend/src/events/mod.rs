@@ -1,8 +1,197 @@
-use anyhow::Result;
+use crate::acp::{SessionRequestPermissionParams, SessionUpdate};
+use crate::batch::BatchEvent;
+use crate::cli::CommandResult;
+use crate::jobs::CommandOutputEvent;
+use crate::session::ProcessStatus;
+use crate::terminal::TerminalEvent;
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+mod coalesce;
+mod recording;
+mod schema;
+
+pub use coalesce::{ChunkCoalescer, DEFAULT_CHUNK_WINDOW};
+pub use recording::{
+    RECORD_EVENTS_ENV, REPLAY_EVENTS_ENV, REPLAY_SPEED_ENV, RecordedEvent, RecordingEventEmitter,
+    Replay,
+};
+pub use schema::{event_schema, event_typescript};
+
 pub trait EventEmitter: Send + Sync + Clone {
-    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> Result<()>;
+    fn emit(&self, event: AppEvent) -> Result<()>;
+}
+
+/// Every event the backend pushes to a frontend.
+///
+/// On the wire an event is a name plus a JSON payload: the name is the
+/// `event` tag, suffixed with the id of the session, job, terminal or batch
+/// the event belongs to (`ai-output-{session_id}`), and the payload is the
+/// `payload` field. `frontend/src/types/events.ts` and
+/// `crates/backend/schema/events.schema.json` are generated from this enum.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum AppEvent {
+    /// Raw JSON-RPC traffic with the CLI, for the debug console.
+    CliIo {
+        session_id: String,
+        payload: CliIoPayload,
+    },
+    AiOutput {
+        session_id: String,
+        payload: String,
+    },
+    AiThought {
+        session_id: String,
+        payload: String,
+    },
+    /// The agent finished its turn; the payload is always `true`.
+    AiTurnFinished {
+        session_id: String,
+        payload: bool,
+    },
+    AiError {
+        session_id: String,
+        payload: String,
+    },
+    SessionProgress {
+        session_id: String,
+        payload: SessionProgressPayload,
+    },
+    AcpSessionUpdate {
+        session_id: String,
+        payload: SessionUpdate,
+    },
+    AcpPermissionRequest {
+        session_id: String,
+        payload: PermissionRequestPayload,
+    },
+    GeminiOutput {
+        session_id: String,
+        payload: GeminiOutputPayload,
+    },
+    GeminiThought {
+        session_id: String,
+        payload: GeminiThoughtPayload,
+    },
+    GeminiToolCall {
+        session_id: String,
+        payload: ToolCallEvent,
+    },
+    GeminiToolCallUpdate {
+        session_id: String,
+        payload: ToolCallUpdate,
+    },
+    GeminiToolCallConfirmation {
+        session_id: String,
+        payload: ToolCallConfirmationRequest,
+    },
+    GeminiError {
+        session_id: String,
+        payload: ErrorPayload,
+    },
+    CommandResult {
+        payload: CommandResult,
+    },
+    /// Periodic status of every session process.
+    ProcessStatusChanged {
+        payload: Vec<ProcessStatus>,
+    },
+    CommandOutput {
+        job_id: String,
+        payload: CommandOutputEvent,
+    },
+    TerminalOutput {
+        terminal_id: String,
+        payload: TerminalEvent,
+    },
+    BatchProgress {
+        batch_id: String,
+        payload: BatchEvent,
+    },
+}
+
+impl AppEvent {
+    /// The `event` tag, i.e. the event name without the id suffix.
+    pub fn family(&self) -> &'static str {
+        match self {
+            Self::CliIo { .. } => "cli-io",
+            Self::AiOutput { .. } => "ai-output",
+            Self::AiThought { .. } => "ai-thought",
+            Self::AiTurnFinished { .. } => "ai-turn-finished",
+            Self::AiError { .. } => "ai-error",
+            Self::SessionProgress { .. } => "session-progress",
+            Self::AcpSessionUpdate { .. } => "acp-session-update",
+            Self::AcpPermissionRequest { .. } => "acp-permission-request",
+            Self::GeminiOutput { .. } => "gemini-output",
+            Self::GeminiThought { .. } => "gemini-thought",
+            Self::GeminiToolCall { .. } => "gemini-tool-call",
+            Self::GeminiToolCallUpdate { .. } => "gemini-tool-call-update",
+            Self::GeminiToolCallConfirmation { .. } => "gemini-tool-call-confirmation",
+            Self::GeminiError { .. } => "gemini-error",
+            Self::CommandResult { .. } => "command-result",
+            Self::ProcessStatusChanged { .. } => "process-status-changed",
+            Self::CommandOutput { .. } => "command-output",
+            Self::TerminalOutput { .. } => "terminal-output",
+            Self::BatchProgress { .. } => "batch-progress",
+        }
+    }
+
+    /// The session the event belongs to, if it belongs to one.
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Self::CliIo { session_id, .. }
+            | Self::AiOutput { session_id, .. }
+            | Self::AiThought { session_id, .. }
+            | Self::AiTurnFinished { session_id, .. }
+            | Self::AiError { session_id, .. }
+            | Self::SessionProgress { session_id, .. }
+            | Self::AcpSessionUpdate { session_id, .. }
+            | Self::AcpPermissionRequest { session_id, .. }
+            | Self::GeminiOutput { session_id, .. }
+            | Self::GeminiThought { session_id, .. }
+            | Self::GeminiToolCall { session_id, .. }
+            | Self::GeminiToolCallUpdate { session_id, .. }
+            | Self::GeminiToolCallConfirmation { session_id, .. }
+            | Self::GeminiError { session_id, .. } => Some(session_id),
+            Self::CommandResult { .. }
+            | Self::ProcessStatusChanged { .. }
+            | Self::CommandOutput { .. }
+            | Self::TerminalOutput { .. }
+            | Self::BatchProgress { .. } => None,
+        }
+    }
+
+    /// Split the event into the name frontends listen for and its payload.
+    pub fn to_wire(&self) -> Result<(String, serde_json::Value)> {
+        let serde_json::Value::Object(mut fields) =
+            serde_json::to_value(self).context("Failed to serialize event")?
+        else {
+            anyhow::bail!("Event did not serialize to an object");
+        };
+        let kind = fields
+            .remove("event")
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .context("Event has no name")?;
+        debug_assert_eq!(kind, self.family(), "AppEvent::family is out of date");
+        let payload = fields.remove("payload").unwrap_or_default();
+
+        // Whatever is left is the id the event is scoped to, if any.
+        let name = match fields.values().next().and_then(serde_json::Value::as_str) {
+            Some(id) => format!("{kind}-{id}"),
+            None => kind,
+        };
+        Ok((name, payload))
+    }
+}
+
+/// Payload of `acp-permission-request-{session_id}`: the CLI's request plus
+/// the JSON-RPC id the answer has to be sent back with.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PermissionRequestPayload {
+    pub request_id: u64,
+    pub request: SessionRequestPermissionParams,
 }
 
 #[derive(Debug, Clone)]
@@ -59,14 +248,16 @@ pub enum InternalEvent {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CliIoPayload {
     #[serde(rename = "type")]
     pub io_type: CliIoType,
+    /// One or more lines, separated by `\n`: lines read in quick succession
+    /// are sent together.
     pub data: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum CliIoType {
     Input,
@@ -74,22 +265,22 @@ pub enum CliIoType {
     Error,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeminiOutputPayload {
     pub text: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeminiThoughtPayload {
     pub thought: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ErrorPayload {
     pub error: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionProgressPayload {
     pub stage: SessionProgressStage,
     pub message: String,
@@ -97,7 +288,7 @@ pub struct SessionProgressPayload {
     pub details: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SessionProgressStage {
     Starting,
@@ -110,7 +301,7 @@ pub enum SessionProgressStage {
     Failed,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolCallEvent {
     pub id: u32,
     pub name: String,
@@ -120,7 +311,7 @@ pub struct ToolCallEvent {
     pub status: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolCallUpdate {
     #[serde(rename = "toolCallId")]
@@ -129,7 +320,7 @@ pub struct ToolCallUpdate {
     pub content: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolCallConfirmationRequest {
     pub request_id: u32,
@@ -141,12 +332,12 @@ pub struct ToolCallConfirmationRequest {
     pub locations: Vec<ToolCallLocation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolCallLocation {
     pub path: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolCallConfirmationContent {
     #[serde(rename = "type")]
     pub content_type: String,
@@ -158,7 +349,7 @@ pub struct ToolCallConfirmationContent {
     pub new_text: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolCallConfirmation {
     #[serde(rename = "type")]
     pub confirmation_type: String,
@@ -281,19 +472,20 @@ impl MockEventEmitter {
 
 #[cfg(test)]
 impl EventEmitter for MockEventEmitter {
-    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> Result<()> {
-        // Serialize the payload to JSON for storage and comparison
-        let json_payload = serde_json::to_value(payload)?;
+    fn emit(&self, event: AppEvent) -> Result<()> {
+        // Store events the way frontends see them, for storage and comparison
+        let (name, json_payload) = event.to_wire()?;
 
-        // Store the event
-        self.events
+        // Update event count
+        *self
+            .event_counts
             .lock()
             .unwrap()
-            .push((event.to_string(), json_payload));
+            .entry(name.clone())
+            .or_insert(0) += 1;
 
-        // Update event count
-        let mut counts = self.event_counts.lock().unwrap();
-        *counts.entry(event.to_string()).or_insert(0) += 1;
+        // Store the event
+        self.events.lock().unwrap().push((name, json_payload));
 
         Ok(())
     }
@@ -785,17 +977,27 @@ mod tests {
         }
     }
 
+    fn output(session_id: &str, text: &str) -> AppEvent {
+        AppEvent::AiOutput {
+            session_id: session_id.to_string(),
+            payload: text.to_string(),
+        }
+    }
+
     #[test]
     fn test_mock_event_emitter() {
         let emitter = MockEventEmitter::new();
         let cloned_emitter = emitter.clone();
 
         // Test that emit works without panicking
-        let result = emitter.emit("test-event", "test-payload");
+        let result = emitter.emit(output("s1", "test-payload"));
         assert!(result.is_ok());
 
         // Test with cloned emitter
-        let result = cloned_emitter.emit("test-event-2", json!({"key": "value"}));
+        let result = cloned_emitter.emit(AppEvent::AiError {
+            session_id: "s1".to_string(),
+            payload: "boom".to_string(),
+        });
         assert!(result.is_ok());
 
         // Test with complex payload
@@ -803,32 +1005,39 @@ mod tests {
             io_type: CliIoType::Output,
             data: "complex test data".to_string(),
         };
-        let result = emitter.emit("cli-io", payload);
+        let result = emitter.emit(AppEvent::CliIo {
+            session_id: "s1".to_string(),
+            payload,
+        });
         assert!(result.is_ok());
 
         // Test event capture functionality
         assert_eq!(emitter.total_events(), 3);
-        assert_eq!(emitter.get_event_count("test-event"), 1);
-        assert_eq!(emitter.get_event_count("test-event-2"), 1);
-        assert_eq!(emitter.get_event_count("cli-io"), 1);
+        assert_eq!(emitter.get_event_count("ai-output-s1"), 1);
+        assert_eq!(emitter.get_event_count("ai-error-s1"), 1);
+        assert_eq!(emitter.get_event_count("cli-io-s1"), 1);
 
         // Test event retrieval
         let events = emitter.get_events();
         assert_eq!(events.len(), 3);
-        assert_eq!(events[0].0, "test-event");
+        assert_eq!(events[0].0, "ai-output-s1");
 
         // Test event filtering
-        let cli_events = emitter.get_events_by_name("cli-io");
+        let cli_events = emitter.get_events_by_name("cli-io-s1");
         assert_eq!(cli_events.len(), 1);
+        assert_eq!(
+            cli_events[0],
+            json!({"type": "output", "data": "complex test data"})
+        );
 
         // Test has_event
-        assert!(emitter.has_event("test-event"));
+        assert!(emitter.has_event("ai-output-s1"));
         assert!(!emitter.has_event("nonexistent-event"));
 
         // Test clear functionality
         emitter.clear();
         assert_eq!(emitter.total_events(), 0);
-        assert!(!emitter.has_event("test-event"));
+        assert!(!emitter.has_event("ai-output-s1"));
     }
 
     #[test]
@@ -836,20 +1045,20 @@ mod tests {
         let emitter = MockEventEmitter::new();
 
         // Test event sequence
-        emitter.emit("event-1", "payload-1").unwrap();
-        emitter.emit("event-2", "payload-2").unwrap();
-        emitter.emit("event-1", "payload-3").unwrap();
+        emitter.emit(output("1", "payload-1")).unwrap();
+        emitter.emit(output("2", "payload-2")).unwrap();
+        emitter.emit(output("1", "payload-3")).unwrap();
 
         // Test sequence verification
-        assert!(emitter.verify_event_sequence(&["event-1", "event-2"]));
-        assert!(!emitter.verify_event_sequence(&["event-2", "event-1"]));
+        assert!(emitter.verify_event_sequence(&["ai-output-1", "ai-output-2"]));
+        assert!(!emitter.verify_event_sequence(&["ai-output-2", "ai-output-1"]));
 
         // Test last event retrieval
-        let last_event_1 = emitter.get_last_event("event-1");
+        let last_event_1 = emitter.get_last_event("ai-output-1");
         assert!(last_event_1.is_some());
         assert_eq!(last_event_1.unwrap(), json!("payload-3"));
 
-        let last_event_2 = emitter.get_last_event("event-2");
+        let last_event_2 = emitter.get_last_event("ai-output-2");
         assert!(last_event_2.is_some());
         assert_eq!(last_event_2.unwrap(), json!("payload-2"));
 
@@ -863,7 +1072,7 @@ mod tests {
         let emitter = MockEventEmitter::new();
 
         // Test immediate success
-        emitter.emit("test", "payload").unwrap();
+        emitter.emit(output("test", "payload")).unwrap();
         assert!(emitter.wait_for_events(1, 100));
 
         // Test timeout (should be fast since we're not actually waiting)
@@ -873,7 +1082,7 @@ mod tests {
     #[test]
     fn test_event_emitter_trait_bounds() {
         fn test_emitter<T: EventEmitter>(emitter: T) -> Result<()> {
-            emitter.emit("test", "payload")
+            emitter.emit(output("test", "payload"))
         }
 
         let mock_emitter = MockEventEmitter::new();
@@ -881,6 +1090,61 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_app_event_wire_names() {
+        let (name, payload) = output("abc", "hello").to_wire().unwrap();
+        assert_eq!(name, "ai-output-abc");
+        assert_eq!(payload, json!("hello"));
+        assert_eq!(output("abc", "hello").session_id(), Some("abc"));
+        assert_eq!(output("abc", "hello").family(), "ai-output");
+
+        let (name, payload) = AppEvent::ProcessStatusChanged {
+            payload: Vec::new(),
+        }
+        .to_wire()
+        .unwrap();
+        assert_eq!(name, "process-status-changed");
+        assert_eq!(payload, json!([]));
+        assert!(
+            AppEvent::ProcessStatusChanged {
+                payload: Vec::new()
+            }
+            .session_id()
+            .is_none()
+        );
+
+        let (name, payload) = AppEvent::CommandOutput {
+            job_id: "job-1".to_string(),
+            payload: CommandOutputEvent::Stdout {
+                data: "hi".to_string(),
+            },
+        }
+        .to_wire()
+        .unwrap();
+        assert_eq!(name, "command-output-job-1");
+        assert_eq!(payload, json!({"type": "stdout", "data": "hi"}));
+
+        let (name, _) = AppEvent::GeminiToolCallConfirmation {
+            session_id: "abc".to_string(),
+            payload: ToolCallConfirmationRequest {
+                request_id: 1,
+                session_id: "abc".to_string(),
+                label: "Run".to_string(),
+                icon: "terminal".to_string(),
+                content: None,
+                confirmation: ToolCallConfirmation {
+                    confirmation_type: "exec".to_string(),
+                    root_command: None,
+                    command: None,
+                },
+                locations: Vec::new(),
+            },
+        }
+        .to_wire()
+        .unwrap();
+        assert_eq!(name, "gemini-tool-call-confirmation-abc");
+    }
+
     #[test]
     fn test_struct_cloning() {
         let location = ToolCallLocation {
@@ -0,0 +1,337 @@
+//! Recording event streams to JSONL files and playing them back.
+//!
+//! A recording is one [`RecordedEvent`] per line: the event as
+//! [`AppEvent`] serializes it, plus when it was emitted. Replaying one
+//! re-emits the events with the same spacing (or faster) without any CLI
+//! process, so a bug report or demo can be played back in the UI.
+//! Recordings are redacted like the RPC logs, since they end up attached to
+//! bug reports.
+
+use super::{AppEvent, EventEmitter};
+use crate::redact::Redactor;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// File to record every emitted event to, e.g.
+/// `GEMINI_CLI_DESKTOP_RECORD_EVENTS=/tmp/session.jsonl`.
+pub const RECORD_EVENTS_ENV: &str = "GEMINI_CLI_DESKTOP_RECORD_EVENTS";
+/// Recording to play back instead of running sessions.
+pub const REPLAY_EVENTS_ENV: &str = "GEMINI_CLI_DESKTOP_REPLAY_EVENTS";
+/// How much faster than recorded to play it back; defaults to 1.
+pub const REPLAY_SPEED_ENV: &str = "GEMINI_CLI_DESKTOP_REPLAY_SPEED";
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since recording started.
+    pub elapsed_ms: u64,
+    pub event: AppEvent,
+}
+
+/// Wraps an emitter, writing every event to a recording before passing it on.
+/// Without a recording file it only passes events on.
+#[derive(Clone)]
+pub struct RecordingEventEmitter<E> {
+    inner: E,
+    recorder: Option<Arc<Recorder>>,
+}
+
+struct Recorder {
+    started: Instant,
+    redactor: Redactor,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl<E: EventEmitter> RecordingEventEmitter<E> {
+    /// Pass events on without recording them.
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            recorder: None,
+        }
+    }
+
+    /// Record every event to `path`, replacing any earlier recording there:
+    /// `elapsed_ms` restarts with every recording, so appending would mix up
+    /// their timelines. The file is only readable by the current user.
+    pub fn to_file(inner: E, path: &Path) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .with_context(|| format!("Failed to open event recording {}", path.display()))?;
+        // The mode only applies to new files
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .with_context(|| {
+                    format!("Failed to restrict event recording {}", path.display())
+                })?;
+        }
+        Ok(Self {
+            inner,
+            recorder: Some(Arc::new(Recorder {
+                started: Instant::now(),
+                redactor: Redactor::default(),
+                writer: Mutex::new(BufWriter::new(file)),
+            })),
+        })
+    }
+
+    /// Record to the file named by `GEMINI_CLI_DESKTOP_RECORD_EVENTS`, if set.
+    /// A recording that can't be opened is logged and skipped.
+    pub fn from_env(inner: E) -> Self {
+        let Some(path) = std::env::var_os(RECORD_EVENTS_ENV).map(PathBuf::from) else {
+            return Self::new(inner);
+        };
+        match Self::to_file(inner.clone(), &path) {
+            Ok(emitter) => {
+                info!("Recording events to {}", path.display());
+                emitter
+            }
+            Err(e) => {
+                error!("Not recording events: {e:#}");
+                Self::new(inner)
+            }
+        }
+    }
+}
+
+impl Recorder {
+    fn record(&self, event: &AppEvent) -> Result<()> {
+        let line = serde_json::to_string(&RecordedEvent {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            event: event.clone(),
+        })
+        .context("Failed to serialize recorded event")?;
+        let line = self.redactor.redact_json_line(&line);
+
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock event recording"))?;
+        // Flush every line so a crash leaves a usable recording
+        writeln!(writer, "{line}")
+            .and_then(|()| writer.flush())
+            .context("Failed to write event recording")
+    }
+}
+
+impl<E: EventEmitter> EventEmitter for RecordingEventEmitter<E> {
+    fn emit(&self, event: AppEvent) -> Result<()> {
+        // A full disk shouldn't take the UI down with it
+        if let Some(recorder) = &self.recorder
+            && let Err(e) = recorder.record(&event)
+        {
+            warn!("{e:#}");
+        }
+        self.inner.emit(event)
+    }
+}
+
+/// A recording loaded for playback.
+#[derive(Debug)]
+pub struct Replay {
+    events: Vec<RecordedEvent>,
+    speed: f64,
+}
+
+impl Replay {
+    /// Load the recording at `path`, to be played `speed` times faster than
+    /// it was recorded.
+    pub fn load(path: &Path, speed: f64) -> Result<Self> {
+        anyhow::ensure!(
+            speed.is_finite() && speed > 0.0,
+            "Replay speed must be a positive number, not {speed}"
+        );
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open event recording {}", path.display()))?;
+
+        let mut events = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.context("Failed to read event recording")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line).with_context(|| {
+                format!("Invalid event on line {} of {}", index + 1, path.display())
+            })?;
+            events.push(event);
+        }
+        Ok(Self { events, speed })
+    }
+
+    /// The recording named by `GEMINI_CLI_DESKTOP_REPLAY_EVENTS`, if set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(path) = std::env::var_os(REPLAY_EVENTS_ENV).map(PathBuf::from) else {
+            return Ok(None);
+        };
+        let speed = match std::env::var(REPLAY_SPEED_ENV) {
+            Ok(speed) => speed
+                .parse()
+                .with_context(|| format!("Invalid {REPLAY_SPEED_ENV} '{speed}'"))?,
+            Err(_) => 1.0,
+        };
+        Self::load(&path, speed).map(Some)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Emit the recorded events with their original spacing, divided by the
+    /// replay speed. Playback starts with the first event, not at the time
+    /// recording started.
+    pub async fn play<E: EventEmitter>(&self, emitter: &E) -> Result<()> {
+        let Some(first) = self.events.first() else {
+            return Ok(());
+        };
+        let start = tokio::time::Instant::now();
+        for recorded in &self.events {
+            let offset = recorded.elapsed_ms.saturating_sub(first.elapsed_ms);
+            let due = Duration::from_secs_f64(offset as f64 / 1000.0 / self.speed);
+            tokio::time::sleep_until(start + due).await;
+            emitter.emit(recorded.event.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::MockEventEmitter;
+    use tempfile::TempDir;
+
+    fn output(session_id: &str, text: &str) -> AppEvent {
+        AppEvent::AiOutput {
+            session_id: session_id.to_string(),
+            payload: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_recording_forwards_and_writes_events() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let inner = MockEventEmitter::new();
+        let emitter = RecordingEventEmitter::to_file(inner.clone(), &path).unwrap();
+
+        emitter.emit(output("abc", "hello")).unwrap();
+        emitter
+            .emit(AppEvent::AiTurnFinished {
+                session_id: "abc".to_string(),
+                payload: true,
+            })
+            .unwrap();
+
+        assert_eq!(inner.get_event_count("ai-output-abc"), 1);
+        assert_eq!(inner.get_event_count("ai-turn-finished-abc"), 1);
+
+        let replay = Replay::load(&path, 1.0).unwrap();
+        assert_eq!(replay.len(), 2);
+        assert_eq!(replay.events[0].event.family(), "ai-output");
+        assert!(replay.events[0].elapsed_ms <= replay.events[1].elapsed_ms);
+    }
+
+    #[test]
+    fn test_recording_replaces_existing_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        for text in ["one", "two"] {
+            let emitter = RecordingEventEmitter::to_file(MockEventEmitter::new(), &path).unwrap();
+            emitter.emit(output("abc", text)).unwrap();
+        }
+        let replay = Replay::load(&path, 1.0).unwrap();
+        assert_eq!(replay.len(), 1);
+        assert!(matches!(
+            &replay.events[0].event,
+            AppEvent::AiOutput { payload, .. } if payload == "two"
+        ));
+    }
+
+    #[test]
+    fn test_recording_redacts_secrets() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let inner = MockEventEmitter::new();
+        let emitter = RecordingEventEmitter::to_file(inner.clone(), &path).unwrap();
+
+        emitter
+            .emit(output("abc", "Authorization: Bearer sk-abcdef123456"))
+            .unwrap();
+
+        let recording = std::fs::read_to_string(&path).unwrap();
+        assert!(!recording.contains("sk-abcdef123456"));
+        assert_eq!(Replay::load(&path, 1.0).unwrap().len(), 1);
+        // Only the recording is redacted, not what the UI sees
+        assert_eq!(
+            inner.get_events_by_name("ai-output-abc"),
+            vec!["Authorization: Bearer sk-abcdef123456"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recording_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        RecordingEventEmitter::to_file(MockEventEmitter::new(), &path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_replay_rejects_invalid_input() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(&path, "{\"elapsed_ms\":0,\"event\":{\"event\":\"nope\"}}\n").unwrap();
+
+        let err = Replay::load(&path, 1.0).unwrap_err();
+        assert!(format!("{err:#}").contains("line 1"));
+        assert!(Replay::load(&path, 0.0).is_err());
+        assert!(Replay::load(&dir.path().join("missing.jsonl"), 1.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_emits_events_in_order() {
+        let recorded = |elapsed_ms, text| RecordedEvent {
+            elapsed_ms,
+            event: output("abc", text),
+        };
+        let replay = Replay {
+            events: vec![recorded(5_000, "one"), recorded(5_020, "two")],
+            speed: 2.0,
+        };
+        let emitter = MockEventEmitter::new();
+
+        let started = std::time::Instant::now();
+        replay.play(&emitter).await.unwrap();
+
+        // The 5s before the first event are skipped and the 20ms gap halved
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let payloads = emitter.get_events_by_name("ai-output-abc");
+        assert_eq!(payloads, vec!["one", "two"]);
+    }
+}
@@ -0,0 +1,258 @@
+//! JSON Schema and TypeScript definitions generated from [`AppEvent`].
+//!
+//! The TypeScript is rendered from the schema rather than derived separately,
+//! so the two can never disagree. Both are checked in; the snapshot test below
+//! fails when they are out of date, and `UPDATE_EVENT_SCHEMA=1 cargo test -p
+//! backend events::schema` rewrites them.
+
+use super::AppEvent;
+use schemars::generate::SchemaSettings;
+use serde_json::{Map, Value};
+
+const TS_HEADER: &str = "\
+// Generated from the AppEvent enum in crates/backend/src/events/mod.rs.
+// Do not edit by hand; run `UPDATE_EVENT_SCHEMA=1 cargo test -p backend events::schema`.
+//
+// An event arrives as `{event}-{id}` (e.g. `ai-output-{session_id}`), or just
+// `{event}` for events without an id, with `payload` as its payload. Use
+// `AppEventPayload<\"ai-output\">` to get the payload type of an event.
+";
+
+/// JSON Schema of [`AppEvent`], describing events as they are serialized.
+pub fn event_schema() -> Value {
+    SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<AppEvent>()
+        .to_value()
+}
+
+/// TypeScript definitions of [`AppEvent`] and every type it refers to.
+pub fn event_typescript() -> String {
+    let schema = event_schema();
+    let mut out = String::from(TS_HEADER);
+
+    out.push('\n');
+    write_alias(&mut out, "AppEvent", &schema);
+    out.push_str(
+        "\nexport type AppEventPayload<E extends AppEvent[\"event\"]> = Extract<\n  AppEvent,\n  { event: E }\n>[\"payload\"];\n",
+    );
+
+    if let Some(Value::Object(defs)) = schema.get("$defs") {
+        for (name, def) in defs {
+            out.push('\n');
+            write_alias(&mut out, name, def);
+        }
+    }
+    out
+}
+
+fn write_alias(out: &mut String, name: &str, schema: &Value) {
+    write_doc(out, schema, "");
+    let rendered = render(schema, "");
+    let separator = if rendered.starts_with('\n') { "" } else { " " };
+    out.push_str(&format!("export type {name} ={separator}{rendered};\n"));
+}
+
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    let lines: Vec<&str> = description.lines().collect();
+    if let [line] = lines.as_slice() {
+        out.push_str(&format!("{indent}/** {line} */\n"));
+        return;
+    }
+    out.push_str(&format!("{indent}/**\n"));
+    for line in lines {
+        let line = format!("{indent} * {line}");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!("{indent} */\n"));
+}
+
+/// Render a schema as a TypeScript type. `indent` is the indentation of the
+/// line the type starts on.
+fn render(schema: &Value, indent: &str) -> String {
+    let Value::Object(schema) = schema else {
+        // A boolean schema: `true` accepts anything, `false` nothing.
+        return if schema.as_bool() == Some(false) {
+            "never"
+        } else {
+            "unknown"
+        }
+        .to_string();
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or_default().to_string();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        let values: Vec<String> = values.iter().map(Value::to_string).collect();
+        return values.join(" | ");
+    }
+    if let Some(Value::Array(variants)) = schema.get("oneOf").or_else(|| schema.get("anyOf")) {
+        return render_union(variants, indent);
+    }
+    if let Some(Value::Array(parts)) = schema.get("allOf") {
+        let parts: Vec<String> = parts.iter().map(|part| render(part, indent)).collect();
+        return parts.join(" & ");
+    }
+
+    match schema.get("type") {
+        Some(Value::Array(types)) => {
+            let types: Vec<String> = types
+                .iter()
+                .map(|ty| {
+                    let mut single = schema.clone();
+                    single.insert("type".to_string(), ty.clone());
+                    render(&Value::Object(single), indent)
+                })
+                .collect();
+            types.join(" | ")
+        }
+        Some(Value::String(ty)) => match ty.as_str() {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => match schema.get("items") {
+                Some(items) => format!("Array<{}>", render(items, indent)),
+                None => "unknown[]".to_string(),
+            },
+            "object" => render_object(schema, indent),
+            _ => "unknown".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+fn render_union(variants: &[Value], indent: &str) -> String {
+    let member_indent = format!("{indent}    ");
+    let members: Vec<String> = variants
+        .iter()
+        .map(|variant| render(variant, &member_indent))
+        .collect();
+    if members.iter().all(|member| !member.contains('\n')) {
+        return members.join(" | ");
+    }
+
+    let mut out = String::new();
+    for (variant, member) in variants.iter().zip(members) {
+        out.push('\n');
+        write_doc(&mut out, variant, &format!("{indent}  "));
+        out.push_str(&format!("{indent}  | {member}"));
+    }
+    out
+}
+
+fn render_object(schema: &Map<String, Value>, indent: &str) -> String {
+    let Some(Value::Object(properties)) = schema.get("properties") else {
+        return match schema.get("additionalProperties") {
+            Some(values @ Value::Object(_)) => {
+                format!("Record<string, {}>", render(values, indent))
+            }
+            _ => "Record<string, unknown>".to_string(),
+        };
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    // Required properties in declaration order, then the optional ones.
+    let mut names: Vec<&str> = required
+        .iter()
+        .copied()
+        .filter(|name| properties.contains_key(*name))
+        .collect();
+    names.extend(
+        properties
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !required.contains(name)),
+    );
+
+    let property_indent = format!("{indent}  ");
+    let mut out = String::from("{\n");
+    for name in names {
+        let property = &properties[name];
+        write_doc(&mut out, property, &property_indent);
+        let optional = if required.contains(&name) { "" } else { "?" };
+        out.push_str(&format!(
+            "{property_indent}{name}{optional}: {};\n",
+            render(property, &property_indent)
+        ));
+    }
+    out.push_str(indent);
+    out.push('}');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn check_snapshot(relative_path: &str, generated: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(relative_path);
+        if std::env::var_os("UPDATE_EVENT_SCHEMA").is_some() {
+            std::fs::write(&path, generated).unwrap();
+            return;
+        }
+
+        let committed = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "{} is out of date with the AppEvent enum; rerun with UPDATE_EVENT_SCHEMA=1 to regenerate it",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_event_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&event_schema()).unwrap() + "\n";
+        check_snapshot("schema/events.schema.json", &schema);
+    }
+
+    #[test]
+    fn test_event_typescript_is_up_to_date() {
+        check_snapshot("../../frontend/src/types/events.ts", &event_typescript());
+    }
+
+    #[test]
+    fn test_render_object_marks_optional_properties() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "b": { "type": ["string", "null"] },
+                "a": { "type": "integer", "description": "Count" },
+                "c": { "type": "array", "items": { "$ref": "#/$defs/Item" } }
+            },
+            "required": ["b", "a"]
+        });
+        assert_eq!(
+            render(&schema, ""),
+            "{\n  b: string | null;\n  /** Count */\n  a: number;\n  c?: Array<Item>;\n}"
+        );
+    }
+
+    #[test]
+    fn test_render_union_of_objects_is_multiline() {
+        let schema = serde_json::json!({
+            "oneOf": [
+                { "type": "object", "properties": { "kind": { "const": "a" } }, "required": ["kind"] },
+                { "type": "string", "enum": ["b", "c"] }
+            ]
+        });
+        assert_eq!(
+            render(&schema, ""),
+            "\n  | {\n      kind: \"a\";\n    }\n  | \"b\" | \"c\""
+        );
+    }
+}
@@ -1,14 +1,21 @@
 use anyhow::Result;
 use ignore::WalkBuilder;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use tokio::process::Command;
 
+mod policy;
+
+pub use policy::{
+    ALLOWED_PATHS_ENV, Access, PathPermissionError, PathPermissionReason, PathPolicy, READ_ONLY_ENV,
+};
+
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum VolumeType {
     LocalDisk,
@@ -19,7 +26,7 @@ pub enum VolumeType {
     FileSystem,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct DirEntry {
     pub name: String,
     pub is_directory: bool,
@@ -31,7 +38,7 @@ pub struct DirEntry {
     pub volume_type: Option<VolumeType>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct GitInfo {
     pub current_directory: String,
     pub branch: String,
@@ -41,7 +48,7 @@ pub struct GitInfo {
     pub has_untracked_files: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct FileContent {
     pub path: String,
     pub content: Option<String>,
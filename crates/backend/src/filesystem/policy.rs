@@ -0,0 +1,366 @@
+//! Which paths the filesystem commands may touch.
+//!
+//! A restricted policy allows the registered project roots plus a list of
+//! extra directories. Paths are canonicalized before they are checked, so
+//! `..` segments and symlinks can't lead out of an allowed directory.
+//! Denied directories are refused even inside allowed ones.
+
+use crate::paths::DataDir;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Extra allowed directories, separated like `PATH`. Unset leaves the
+/// policy unrestricted.
+pub const ALLOWED_PATHS_ENV: &str = "GEMINI_CLI_DESKTOP_ALLOWED_PATHS";
+/// Any value but `0`, `false`, `no` or `off` refuses every write.
+pub const READ_ONLY_ENV: &str = "GEMINI_CLI_DESKTOP_READ_ONLY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathPermissionReason {
+    OutsideAllowedRoots,
+    ReadOnly,
+}
+
+/// A filesystem command was refused by the [`PathPolicy`]. Frontends can
+/// find it in an error chain with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathPermissionError {
+    /// The path as requested.
+    pub path: String,
+    pub reason: PathPermissionReason,
+}
+
+impl fmt::Display for PathPermissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            PathPermissionReason::OutsideAllowedRoots => write!(
+                f,
+                "Permission denied: {} is outside the allowed directories",
+                self.path
+            ),
+            PathPermissionReason::ReadOnly => write!(
+                f,
+                "Permission denied: {} can't be changed in read-only mode",
+                self.path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathPermissionError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathPolicy {
+    /// Directories allowed besides the project roots; `None` allows every
+    /// path.
+    allowed_roots: Option<Vec<PathBuf>>,
+    /// Directories refused even inside allowed ones, such as other users'
+    /// data on a shared server.
+    denied_roots: Vec<PathBuf>,
+    read_only: bool,
+}
+
+impl PathPolicy {
+    /// Allow every path, for writing too.
+    pub fn unrestricted() -> Self {
+        Self::default()
+    }
+
+    /// Allow the registered project roots and `roots`.
+    pub fn restricted(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            allowed_roots: Some(roots.into_iter().collect()),
+            ..Self::default()
+        }
+    }
+
+    /// Also refuse `roots` and everything in them.
+    pub fn with_denied(mut self, roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.denied_roots.extend(roots);
+        self
+    }
+
+    /// Refuse every write when `read_only` is set.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Restricted to `GEMINI_CLI_DESKTOP_ALLOWED_PATHS` when it is set,
+    /// read-only when `GEMINI_CLI_DESKTOP_READ_ONLY` is.
+    pub fn from_env() -> Self {
+        let policy = match std::env::var_os(ALLOWED_PATHS_ENV) {
+            Some(paths) if !paths.is_empty() => Self::restricted(std::env::split_paths(&paths)),
+            _ => Self::unrestricted(),
+        };
+        policy.with_read_only(std::env::var(READ_ONLY_ENV).is_ok_and(|value| is_truthy(&value)))
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.allowed_roots.is_some()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The canonical form of `path`, if the policy allows `access` to it
+    /// given the projects in `data_dir`. Fails with a [`PathPermissionError`]
+    /// otherwise.
+    pub fn check(&self, data_dir: &DataDir, path: &str, access: Access) -> Result<PathBuf> {
+        // Projects only matter when the policy is restricted, so don't read
+        // their metadata otherwise
+        let project_roots = if self.is_restricted() {
+            crate::projects::project_roots(data_dir)
+        } else {
+            Vec::new()
+        };
+        self.check_with_projects(path, access, &project_roots)
+    }
+
+    fn check_with_projects(
+        &self,
+        path: &str,
+        access: Access,
+        project_roots: &[PathBuf],
+    ) -> Result<PathBuf> {
+        let denied = |reason| PathPermissionError {
+            path: path.to_string(),
+            reason,
+        };
+        if access == Access::Write && self.read_only {
+            return Err(denied(PathPermissionReason::ReadOnly).into());
+        }
+        let resolved =
+            resolve(Path::new(path)).with_context(|| format!("Failed to resolve path {path}"))?;
+        let is_inside = |roots: &[PathBuf]| {
+            roots
+                .iter()
+                .filter_map(|root| root.canonicalize().ok())
+                .any(|root| resolved.starts_with(root))
+        };
+        if is_inside(&self.denied_roots) {
+            return Err(denied(PathPermissionReason::OutsideAllowedRoots).into());
+        }
+        let Some(allowed_roots) = &self.allowed_roots else {
+            return Ok(resolved);
+        };
+        if is_inside(allowed_roots) || is_inside(project_roots) {
+            Ok(resolved)
+        } else {
+            Err(denied(PathPermissionReason::OutsideAllowedRoots).into())
+        }
+    }
+}
+
+/// Canonicalize `path`, which may not exist yet: its nearest existing
+/// ancestor is canonicalized and the rest appended, which can't contain
+/// symlinks.
+fn resolve(path: &Path) -> std::io::Result<PathBuf> {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        match existing.canonicalize() {
+            Ok(mut resolved) => {
+                for component in missing.iter().rev() {
+                    match component {
+                        Component::ParentDir => {
+                            resolved.pop();
+                        }
+                        Component::Normal(name) => resolved.push(name),
+                        _ => {}
+                    }
+                }
+                return Ok(resolved);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let (Some(component), Some(parent)) =
+                    (existing.components().next_back(), existing.parent())
+                else {
+                    return Err(e);
+                };
+                missing.push(component);
+                existing = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    let value = value.trim().to_ascii_lowercase();
+    !value.is_empty() && !["0", "false", "no", "off"].contains(&value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn reason(result: Result<PathBuf>) -> Option<PathPermissionReason> {
+        result
+            .unwrap_err()
+            .downcast_ref::<PathPermissionError>()
+            .map(|e| e.reason)
+    }
+
+    fn setup() -> (TempDir, PathBuf, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("project");
+        let outside = temp.path().join("outside");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        (temp, root, outside)
+    }
+
+    fn path(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+
+    #[test]
+    fn test_unrestricted_allows_everything() {
+        let (_temp, _root, outside) = setup();
+        let policy = PathPolicy::unrestricted();
+        let file = outside.join("secret.txt");
+        let resolved = policy
+            .check_with_projects(path(&file), Access::Write, &[])
+            .unwrap();
+        assert_eq!(resolved, file.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_restricted_to_roots() {
+        let (_temp, root, outside) = setup();
+        let policy = PathPolicy::restricted([root.clone()]);
+
+        assert!(
+            policy
+                .check_with_projects(path(&root.join("src/main.rs")), Access::Read, &[])
+                .is_ok()
+        );
+        // Files that don't exist yet can be created inside a root
+        assert!(
+            policy
+                .check_with_projects(path(&root.join("src/new/lib.rs")), Access::Write, &[])
+                .is_ok()
+        );
+        assert_eq!(
+            reason(policy.check_with_projects(
+                path(&outside.join("secret.txt")),
+                Access::Read,
+                &[]
+            )),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+    }
+
+    #[test]
+    fn test_project_roots_allowed() {
+        let (_temp, root, outside) = setup();
+        let policy = PathPolicy::restricted([root]);
+        let file = outside.join("secret.txt");
+        assert!(
+            policy
+                .check_with_projects(path(&file), Access::Read, &[outside.clone()])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_parent_segments_cannot_escape() {
+        let (_temp, root, _outside) = setup();
+        let policy = PathPolicy::restricted([root.clone()]);
+        let escape = root.join("src/../../outside/secret.txt");
+        assert_eq!(
+            reason(policy.check_with_projects(path(&escape), Access::Read, &[])),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+        let new_escape = root.join("missing/../../outside/new.txt");
+        assert_eq!(
+            reason(policy.check_with_projects(path(&new_escape), Access::Write, &[])),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+    }
+
+    #[test]
+    fn test_denied_roots_win() {
+        let (_temp, root, outside) = setup();
+        let file = root.join("src/main.rs");
+        for policy in [
+            PathPolicy::unrestricted(),
+            PathPolicy::restricted([root.clone()]),
+        ] {
+            let policy = policy.with_denied([outside.clone(), root.join("src")]);
+            assert_eq!(
+                reason(policy.check_with_projects(path(&file), Access::Read, &[root.clone()])),
+                Some(PathPermissionReason::OutsideAllowedRoots)
+            );
+            assert!(
+                policy
+                    .check_with_projects(path(&root), Access::Read, &[])
+                    .is_ok()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_cannot_escape() {
+        let (_temp, root, outside) = setup();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let policy = PathPolicy::restricted([root.clone()]);
+        assert_eq!(
+            reason(policy.check_with_projects(
+                path(&root.join("link/secret.txt")),
+                Access::Read,
+                &[]
+            )),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+    }
+
+    #[test]
+    fn test_read_only_refuses_writes() {
+        let (_temp, root, _outside) = setup();
+        let policy = PathPolicy::restricted([root.clone()]).with_read_only(true);
+        let file = root.join("src/main.rs");
+        assert!(
+            policy
+                .check_with_projects(path(&file), Access::Read, &[])
+                .is_ok()
+        );
+        let error = policy
+            .check_with_projects(path(&file), Access::Write, &[])
+            .unwrap_err();
+        let error = error.downcast_ref::<PathPermissionError>().unwrap();
+        assert_eq!(error.reason, PathPermissionReason::ReadOnly);
+        assert_eq!(error.path, path(&file));
+        assert_eq!(
+            serde_json::to_value(error).unwrap(),
+            serde_json::json!({ "path": path(&file), "reason": "read_only" })
+        );
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(is_truthy("1"));
+        assert!(is_truthy("TRUE"));
+        assert!(!is_truthy("off"));
+        assert!(!is_truthy(""));
+    }
+}
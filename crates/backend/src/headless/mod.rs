@@ -0,0 +1,315 @@
+use crate::acp::{
+    ContentBlock, PermissionOptionKind, PermissionOutcome, PermissionResult, SessionPromptParams,
+    SessionRequestPermissionParams, SessionUpdate,
+};
+use crate::events::{AppEvent, EventEmitter, PermissionRequestPayload};
+use crate::rpc::JsonRpcRequest;
+use crate::session::{self, SessionManager, SessionParams};
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// EventEmitter that forwards every event into a channel, so a session can be
+/// driven without a frontend listening for events.
+#[derive(Clone)]
+pub struct ChannelEventEmitter {
+    tx: mpsc::UnboundedSender<AppEvent>,
+}
+
+impl ChannelEventEmitter {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<AppEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+}
+
+impl EventEmitter for ChannelEventEmitter {
+    fn emit(&self, event: AppEvent) -> Result<()> {
+        // The receiver going away just means nobody is interested any more.
+        let _ = self.tx.send(event);
+        Ok(())
+    }
+}
+
+/// How a headless session answers `session/request_permission`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadlessPermissions {
+    /// Reject every tool call that asks for permission.
+    #[default]
+    RejectAll,
+    /// Allow every tool call once.
+    AllowAll,
+}
+
+impl HeadlessPermissions {
+    fn outcome_for(self, request: &SessionRequestPermissionParams) -> PermissionOutcome {
+        let wanted = |kind: &PermissionOptionKind| match self {
+            Self::RejectAll => matches!(kind, PermissionOptionKind::RejectOnce),
+            Self::AllowAll => matches!(kind, PermissionOptionKind::AllowOnce),
+        };
+        request
+            .options
+            .iter()
+            .find(|option| wanted(&option.kind))
+            .map(|option| PermissionOutcome::Selected {
+                option_id: option.option_id.clone(),
+            })
+            .unwrap_or(PermissionOutcome::Cancelled)
+    }
+}
+
+/// Everything the agent produced during one prompt turn.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HeadlessTurn {
+    pub text: String,
+    pub thoughts: String,
+    pub tool_calls: Vec<SessionUpdate>,
+    pub permission_requests: usize,
+}
+
+/// A short-lived CLI session with no frontend attached.
+///
+/// It owns its own `SessionManager`, so it never shows up in the process list
+/// of the main backend, and it is killed when dropped.
+pub struct HeadlessSession {
+    session_id: String,
+    session_manager: SessionManager,
+    events: mpsc::UnboundedReceiver<AppEvent>,
+    permissions: HeadlessPermissions,
+    next_request_id: u32,
+}
+
+impl HeadlessSession {
+    pub async fn start(params: SessionParams, permissions: HeadlessPermissions) -> Result<Self> {
+        let session_id = params.session_id.clone();
+        let (emitter, events) = ChannelEventEmitter::new();
+        let session_manager = SessionManager::new();
+
+        session::initialize_session(params, emitter, &session_manager)
+            .await
+            .context("Failed to start headless session")?;
+
+        Ok(Self {
+            session_id,
+            session_manager,
+            events,
+            permissions,
+            next_request_id: 1000,
+        })
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Send a text prompt and wait for the turn to finish.
+    pub async fn prompt(&mut self, text: &str, timeout: Duration) -> Result<HeadlessTurn> {
+        tokio::time::timeout(timeout, self.prompt_inner(text))
+            .await
+            .context("Timed out waiting for the agent to finish its turn")?
+    }
+
+    async fn prompt_inner(&mut self, text: &str) -> Result<HeadlessTurn> {
+        let (sender, acp_session_id) = {
+            let processes = self
+                .session_manager
+                .get_processes()
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Failed to lock processes mutex"))?;
+            let session = processes
+                .get(&self.session_id)
+                .context("Headless session is not running")?;
+            (
+                session
+                    .message_sender
+                    .clone()
+                    .context("No message sender available")?,
+                session
+                    .acp_session_id
+                    .clone()
+                    .context("No ACP session ID available")?,
+            )
+        };
+
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: self.next_request_id,
+            method: "session/prompt".to_string(),
+            params: serde_json::to_value(SessionPromptParams {
+                session_id: acp_session_id,
+                prompt: vec![ContentBlock::Text {
+                    text: text.to_string(),
+                }],
+            })
+            .context("Failed to serialize prompt params")?,
+        };
+        self.next_request_id += 1;
+
+        sender
+            .send(serde_json::to_string(&request).context("Failed to serialize prompt request")?)
+            .await
+            .context("Failed to send prompt to headless session")?;
+
+        let mut turn = HeadlessTurn::default();
+        while let Some(event) = self.events.recv().await {
+            match event {
+                AppEvent::AiOutput {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => turn.text.push_str(&payload),
+                AppEvent::AiThought {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => turn.thoughts.push_str(&payload),
+                AppEvent::AiTurnFinished { session_id, .. } if session_id == self.session_id => {
+                    return Ok(turn);
+                }
+                AppEvent::AiError {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => {
+                    anyhow::bail!("Agent reported an error: {payload}");
+                }
+                AppEvent::AcpSessionUpdate {
+                    session_id,
+                    payload:
+                        update @ (SessionUpdate::ToolCall { .. } | SessionUpdate::ToolCallUpdate { .. }),
+                } if session_id == self.session_id => turn.tool_calls.push(update),
+                AppEvent::AcpPermissionRequest {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => {
+                    turn.permission_requests += 1;
+                    self.answer_permission(payload).await?;
+                }
+                _ => {}
+            }
+        }
+
+        anyhow::bail!("Headless session ended before the turn finished")
+    }
+
+    async fn answer_permission(&self, payload: PermissionRequestPayload) -> Result<()> {
+        let PermissionRequestPayload {
+            request_id,
+            request,
+        } = payload;
+
+        let outcome = self.permissions.outcome_for(&request);
+        debug!(
+            "[HEADLESS] Answering permission request {request_id} for '{}' with {outcome:?}",
+            request.tool_call.title
+        );
+
+        session::send_response_to_cli(
+            &self.session_id,
+            u32::try_from(request_id).context("Permission request id out of range")?,
+            Some(
+                serde_json::to_value(PermissionResult { outcome })
+                    .context("Failed to serialize permission result")?,
+            ),
+            None,
+            self.session_manager.get_processes(),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Kill the underlying CLI process.
+    pub fn shutdown(&self) -> Result<()> {
+        self.session_manager.kill_process(&self.session_id)
+    }
+}
+
+impl Drop for HeadlessSession {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp::{PermissionOption, PermissionToolCall, ToolCallKind, ToolCallStatus};
+
+    fn output(session_id: &str, text: &str) -> AppEvent {
+        AppEvent::AiOutput {
+            session_id: session_id.to_string(),
+            payload: text.to_string(),
+        }
+    }
+
+    fn permission_request(
+        options: Vec<(&str, PermissionOptionKind)>,
+    ) -> SessionRequestPermissionParams {
+        SessionRequestPermissionParams {
+            session_id: "acp-1".to_string(),
+            options: options
+                .into_iter()
+                .map(|(id, kind)| PermissionOption {
+                    option_id: id.to_string(),
+                    name: id.to_string(),
+                    kind,
+                })
+                .collect(),
+            tool_call: PermissionToolCall {
+                tool_call_id: "tool-1".to_string(),
+                status: ToolCallStatus::Pending,
+                title: "Write file".to_string(),
+                content: vec![],
+                locations: vec![],
+                kind: ToolCallKind::Edit,
+                server_name: None,
+                tool_name: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_channel_event_emitter_forwards_events() {
+        let (emitter, mut rx) = ChannelEventEmitter::new();
+        emitter.emit(output("abc", "hello")).unwrap();
+
+        let (event, payload) = rx.try_recv().unwrap().to_wire().unwrap();
+        assert_eq!(event, "ai-output-abc");
+        assert_eq!(payload, "hello");
+    }
+
+    #[test]
+    fn test_channel_event_emitter_ignores_closed_receiver() {
+        let (emitter, rx) = ChannelEventEmitter::new();
+        drop(rx);
+        assert!(emitter.emit(output("abc", "hello")).is_ok());
+    }
+
+    #[test]
+    fn test_reject_all_picks_reject_option() {
+        let request = permission_request(vec![
+            ("proceed_once", PermissionOptionKind::AllowOnce),
+            ("cancel", PermissionOptionKind::RejectOnce),
+        ]);
+
+        match HeadlessPermissions::RejectAll.outcome_for(&request) {
+            PermissionOutcome::Selected { option_id } => assert_eq!(option_id, "cancel"),
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+        match HeadlessPermissions::AllowAll.outcome_for(&request) {
+            PermissionOutcome::Selected { option_id } => assert_eq!(option_id, "proceed_once"),
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_permissions_fall_back_to_cancelled() {
+        let request =
+            permission_request(vec![("proceed_always", PermissionOptionKind::AllowAlways)]);
+        assert!(matches!(
+            HeadlessPermissions::RejectAll.outcome_for(&request),
+            PermissionOutcome::Cancelled
+        ));
+    }
+}
@@ -0,0 +1,738 @@
+//! User hooks: shell commands and HTTP POSTs run when things happen in a
+//! session.
+//!
+//! Hooks are read from `~/.gemini-cli-desktop/hooks.json` and from
+//! `hooks.json` in the project's metadata directory when a session starts:
+//!
+//! ```json
+//! {
+//!   "hooks": [
+//!     { "on": "turn_finished", "command": "printf '\\a'" },
+//!     { "on": "permission_requested", "url": "http://localhost:9000/hook" },
+//!     { "on": "tool_call_completed", "tool": "edit", "command": "cargo fmt", "timeout_secs": 60 }
+//!   ]
+//! }
+//! ```
+//!
+//! Commands run through the shell in the session's working directory with a
+//! [`HookPayload`] on stdin; URLs get it as the body of a POST. Hooks run in
+//! the background, are killed after their timeout, and their results go to
+//! the log.
+
+use crate::acp::{SessionUpdate, ToolCallKind, ToolCallStatus};
+use crate::events::AppEvent;
+use crate::jobs::shell_command;
+use crate::paths::DataDir;
+use crate::projects;
+use crate::rpc::ProjectHasher;
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+use url::Url;
+
+pub const HOOKS_FILE_NAME: &str = "hooks.json";
+
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+/// Set on hook commands to the trigger that ran them.
+const HOOK_TRIGGER_ENV: &str = "GEMINI_CLI_DESKTOP_HOOK";
+/// Set on hook commands to the session that ran them.
+const HOOK_SESSION_ENV: &str = "GEMINI_CLI_DESKTOP_SESSION_ID";
+
+/// What a hook runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookTrigger {
+    TurnFinished,
+    ToolCallCompleted,
+    PermissionRequested,
+    /// The CLI exited without being asked to.
+    SessionCrashed,
+}
+
+/// What a hook does when it runs.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    /// Shell command, run in the session's working directory.
+    Command(String),
+    /// `http://` URL to POST to.
+    Url(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Hook {
+    pub on: HookTrigger,
+    #[serde(flatten)]
+    pub action: HookAction,
+    /// Only run for tool calls with this tool name or kind (such as `edit`),
+    /// compared case-insensitively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+/// Contents of a `hooks.json` file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct HookConfig {
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+impl HookConfig {
+    /// Read a hooks file; a missing file has no hooks.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        let config: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid hooks file {}", path.display()))?;
+        for hook in &config.hooks {
+            hook.validate()
+                .with_context(|| format!("Invalid hook in {}", path.display()))?;
+        }
+        Ok(config)
+    }
+}
+
+impl Hook {
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(self.timeout_secs > 0, "Hook timeout must be at least 1s");
+        if let HookAction::Url(url) = &self.action {
+            let url = Url::parse(url).with_context(|| format!("Invalid hook URL '{url}'"))?;
+            anyhow::ensure!(
+                url.scheme() == "http" && url.has_host(),
+                "Hook URLs must be http:// URLs, not '{url}'"
+            );
+        }
+        Ok(())
+    }
+
+    fn target(&self) -> &str {
+        match &self.action {
+            HookAction::Command(command) => command,
+            HookAction::Url(url) => url,
+        }
+    }
+}
+
+/// `hooks.json` in the data directory.
+pub fn global_hooks_path(data_dir: &DataDir) -> Option<PathBuf> {
+    data_dir.path().map(|dir| dir.join(HOOKS_FILE_NAME))
+}
+
+/// The hooks file for the project at `working_directory`.
+pub fn project_hooks_path(data_dir: &DataDir, working_directory: &str) -> Option<PathBuf> {
+    let sha256 = ProjectHasher::hash_path_or_raw(working_directory);
+    projects::project_file_path(data_dir, &sha256, HOOKS_FILE_NAME)
+}
+
+/// The tool call a hook runs for.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookTool {
+    pub id: String,
+    pub name: Option<String>,
+    pub kind: ToolCallKind,
+    pub title: String,
+}
+
+impl HookTool {
+    fn matches(&self, filter: &str) -> bool {
+        let kind = serde_json::to_value(&self.kind).ok();
+        self.name
+            .as_deref()
+            .into_iter()
+            .chain(kind.as_ref().and_then(|kind| kind.as_str()))
+            .any(|name| name.eq_ignore_ascii_case(filter))
+    }
+}
+
+/// What a hook is sent.
+#[derive(Debug, Serialize)]
+pub struct HookPayload<'a> {
+    pub hook: HookTrigger,
+    pub session_id: &'a str,
+    pub working_directory: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<&'a HookTool>,
+    /// The event that triggered the hook, as emitted to the UI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<&'a AppEvent>,
+}
+
+/// The hooks for one session, fed its events as they are emitted.
+#[derive(Clone)]
+pub struct SessionHooks {
+    session_id: String,
+    working_directory: String,
+    hooks: Arc<[Hook]>,
+    /// Set on hook commands on top of the app's environment.
+    env: Arc<HashMap<String, String>>,
+    /// Tool calls seen so far, since their updates don't repeat the tool.
+    tools: HashMap<String, HookTool>,
+}
+
+impl SessionHooks {
+    pub fn new(session_id: String, working_directory: String, hooks: Vec<Hook>) -> Self {
+        Self {
+            session_id,
+            working_directory,
+            hooks: hooks.into(),
+            env: Arc::default(),
+            tools: HashMap::new(),
+        }
+    }
+
+    /// The global and project hooks in `data_dir` for a session. Hooks files
+    /// that can't be read are logged and skipped.
+    pub fn load(session_id: &str, working_directory: &str, data_dir: &DataDir) -> Self {
+        let mut hooks = Vec::new();
+        for path in [
+            global_hooks_path(data_dir),
+            project_hooks_path(data_dir, working_directory),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            match HookConfig::load(&path) {
+                Ok(config) => hooks.extend(config.hooks),
+                Err(e) => warn!("Skipping hooks: {e:#}"),
+            }
+        }
+        if !hooks.is_empty() {
+            debug!("Loaded {} hooks for session {session_id}", hooks.len());
+        }
+        Self::new(session_id.to_string(), working_directory.to_string(), hooks)
+    }
+
+    /// Run hook commands with these variables set.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = Arc::new(env);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run the hooks triggered by an event.
+    pub fn observe(&mut self, event: &AppEvent) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some((trigger, tool)) = self.trigger_for(event) {
+            self.fire(trigger, tool.as_ref(), Some(event));
+        }
+    }
+
+    /// Run the `session_crashed` hooks.
+    pub fn session_crashed(&self) {
+        self.fire(HookTrigger::SessionCrashed, None, None);
+    }
+
+    fn trigger_for(&mut self, event: &AppEvent) -> Option<(HookTrigger, Option<HookTool>)> {
+        match event {
+            AppEvent::AiTurnFinished { .. } => Some((HookTrigger::TurnFinished, None)),
+            AppEvent::AcpPermissionRequest { payload, .. } => {
+                let tool_call = &payload.request.tool_call;
+                let tool = HookTool {
+                    id: tool_call.tool_call_id.clone(),
+                    name: tool_call.tool_name.clone(),
+                    kind: tool_call.kind.clone(),
+                    title: tool_call.title.clone(),
+                };
+                Some((HookTrigger::PermissionRequested, Some(tool)))
+            }
+            AppEvent::AcpSessionUpdate { payload, .. } => match payload {
+                SessionUpdate::ToolCall {
+                    tool_call_id,
+                    status,
+                    title,
+                    kind,
+                    tool_name,
+                    ..
+                } => {
+                    let tool = HookTool {
+                        id: tool_call_id.clone(),
+                        name: tool_name.clone(),
+                        kind: kind.clone(),
+                        title: title.clone(),
+                    };
+                    if matches!(status, ToolCallStatus::Completed) {
+                        Some((HookTrigger::ToolCallCompleted, Some(tool)))
+                    } else {
+                        self.tools.insert(tool_call_id.clone(), tool);
+                        None
+                    }
+                }
+                SessionUpdate::ToolCallUpdate {
+                    tool_call_id,
+                    status,
+                    ..
+                } => match status {
+                    ToolCallStatus::Completed => {
+                        let tool = self.tools.remove(tool_call_id);
+                        Some((HookTrigger::ToolCallCompleted, tool))
+                    }
+                    ToolCallStatus::Failed => {
+                        self.tools.remove(tool_call_id);
+                        None
+                    }
+                    ToolCallStatus::Pending | ToolCallStatus::InProgress => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn fire(&self, trigger: HookTrigger, tool: Option<&HookTool>, event: Option<&AppEvent>) {
+        let mut hooks = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.on == trigger)
+            .filter(|hook| match &hook.tool {
+                Some(filter) => tool.is_some_and(|tool| tool.matches(filter)),
+                None => true,
+            })
+            .peekable();
+        if hooks.peek().is_none() {
+            return;
+        }
+
+        let payload = HookPayload {
+            hook: trigger,
+            session_id: &self.session_id,
+            working_directory: &self.working_directory,
+            tool,
+            event,
+        };
+        let payload: Arc<str> = match serde_json::to_string(&payload) {
+            Ok(payload) => payload.into(),
+            Err(e) => {
+                warn!("Failed to serialize hook payload: {e}");
+                return;
+            }
+        };
+        for hook in hooks {
+            let hook = hook.clone();
+            let payload = Arc::clone(&payload);
+            let session_id = self.session_id.clone();
+            let working_directory = self.working_directory.clone();
+            let env = Arc::clone(&self.env);
+            tokio::spawn(async move {
+                let started = Instant::now();
+                let result = run_hook(&hook, &payload, &session_id, &working_directory, &env).await;
+                let elapsed = started.elapsed().as_millis();
+                match result {
+                    Ok(outcome) => info!(
+                        "Hook {:?} `{}` for session {session_id} {outcome} in {elapsed}ms",
+                        hook.on,
+                        hook.target()
+                    ),
+                    Err(e) => warn!(
+                        "Hook {:?} `{}` for session {session_id} failed after {elapsed}ms: {e:#}",
+                        hook.on,
+                        hook.target()
+                    ),
+                }
+            });
+        }
+    }
+}
+
+/// Run one hook to completion or its timeout.
+async fn run_hook(
+    hook: &Hook,
+    payload: &str,
+    session_id: &str,
+    working_directory: &str,
+    env: &HashMap<String, String>,
+) -> Result<String> {
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    let run = async {
+        match &hook.action {
+            HookAction::Command(command) => {
+                run_command(
+                    hook.on,
+                    command,
+                    payload,
+                    session_id,
+                    working_directory,
+                    env,
+                )
+                .await
+            }
+            HookAction::Url(url) => post_json(url, payload).await,
+        }
+    };
+    tokio::time::timeout(timeout, run)
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out after {}s", hook.timeout_secs))?
+}
+
+async fn run_command(
+    trigger: HookTrigger,
+    command: &str,
+    payload: &str,
+    session_id: &str,
+    working_directory: &str,
+    env: &HashMap<String, String>,
+) -> Result<String> {
+    let trigger = serde_json::to_value(trigger)?;
+    let mut child = shell_command(command)
+        .current_dir(working_directory)
+        .envs(env)
+        .env(HOOK_TRIGGER_ENV, trigger.as_str().unwrap_or_default())
+        .env(HOOK_SESSION_ENV, session_id)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start hook command")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Commands that don't read the payload may exit before it is written
+        if let Err(e) = stdin.write_all(payload.as_bytes()).await
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(e).context("Failed to write hook payload");
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("Failed to wait for hook command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        debug!("Hook `{command}` output: {}", stdout.trim());
+    }
+    anyhow::ensure!(
+        output.status.success(),
+        "Exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(format!("exited with {}", output.status))
+}
+
+/// POST `body` as JSON to an `http://` URL. Hooks mostly talk to local
+/// services, so this speaks just enough HTTP/1.1 to do that.
+async fn post_json(url: &str, body: &str) -> Result<String> {
+    let url = Url::parse(url).with_context(|| format!("Invalid hook URL '{url}'"))?;
+    anyhow::ensure!(url.scheme() == "http", "Hook URLs must be http:// URLs");
+    let host = url.host_str().context("Hook URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let host_header = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+
+    let mut stream = TcpStream::connect(format!("{host}:{port}"))
+        .await
+        .with_context(|| format!("Failed to connect to {host_header}"))?;
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host_header}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .context("Failed to send hook request")?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .await
+        .context("Failed to read hook response")?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .with_context(|| format!("Invalid HTTP response '{}'", status_line.trim()))?;
+    anyhow::ensure!((200..300).contains(&status), "Responded with {status}");
+    Ok(format!("responded with {status}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp::{PermissionToolCall, SessionRequestPermissionParams};
+    use crate::events::PermissionRequestPayload;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn hook(on: HookTrigger, action: HookAction) -> Hook {
+        Hook {
+            on,
+            action,
+            tool: None,
+            timeout_secs: 5,
+        }
+    }
+
+    fn tool_call(id: &str, status: ToolCallStatus, kind: ToolCallKind) -> AppEvent {
+        AppEvent::AcpSessionUpdate {
+            session_id: "abc".to_string(),
+            payload: SessionUpdate::ToolCall {
+                tool_call_id: id.to_string(),
+                status,
+                title: "Edit main.rs".to_string(),
+                content: Vec::new(),
+                locations: Vec::new(),
+                kind,
+                server_name: None,
+                tool_name: None,
+            },
+        }
+    }
+
+    fn tool_call_update(id: &str, status: ToolCallStatus) -> AppEvent {
+        AppEvent::AcpSessionUpdate {
+            session_id: "abc".to_string(),
+            payload: SessionUpdate::ToolCallUpdate {
+                tool_call_id: id.to_string(),
+                status,
+                content: Vec::new(),
+                server_name: None,
+                tool_name: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_config_parses_and_validates() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(HOOKS_FILE_NAME);
+        assert!(HookConfig::load(&path).unwrap().hooks.is_empty());
+
+        std::fs::write(
+            &path,
+            r#"{"hooks": [
+                {"on": "turn_finished", "command": "printf '\\a'"},
+                {"on": "tool_call_completed", "tool": "edit", "command": "cargo fmt", "timeout_secs": 60},
+                {"on": "permission_requested", "url": "http://localhost:9000/hook"}
+            ]}"#,
+        )
+        .unwrap();
+        let config = HookConfig::load(&path).unwrap();
+        assert_eq!(config.hooks.len(), 3);
+        assert_eq!(config.hooks[0].timeout_secs, DEFAULT_HOOK_TIMEOUT_SECS);
+        assert_eq!(config.hooks[1].tool.as_deref(), Some("edit"));
+        assert!(matches!(config.hooks[2].action, HookAction::Url(_)));
+
+        for invalid in [
+            r#"{"hooks": [{"on": "turn_finished"}]}"#,
+            r#"{"hooks": [{"on": "turn_finished", "url": "https://example.com"}]}"#,
+            r#"{"hooks": [{"on": "turn_finished", "command": "true", "timeout_secs": 0}]}"#,
+            r#"{"hooks": [{"on": "session_started", "command": "true"}]}"#,
+        ] {
+            std::fs::write(&path, invalid).unwrap();
+            assert!(HookConfig::load(&path).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_triggers_for_events() {
+        let mut hooks = SessionHooks::new("abc".to_string(), ".".to_string(), Vec::new());
+
+        let turn_finished = AppEvent::AiTurnFinished {
+            session_id: "abc".to_string(),
+            payload: true,
+        };
+        let (trigger, tool) = hooks.trigger_for(&turn_finished).unwrap();
+        assert_eq!(trigger, HookTrigger::TurnFinished);
+        assert!(tool.is_none());
+
+        let pending = tool_call("t1", ToolCallStatus::Pending, ToolCallKind::Edit);
+        assert!(hooks.trigger_for(&pending).is_none());
+        let (trigger, tool) = hooks
+            .trigger_for(&tool_call_update("t1", ToolCallStatus::Completed))
+            .unwrap();
+        assert_eq!(trigger, HookTrigger::ToolCallCompleted);
+        let tool = tool.unwrap();
+        assert!(tool.matches("Edit"));
+        assert!(!tool.matches("read"));
+        assert!(hooks.tools.is_empty());
+
+        let failed = tool_call_update("t2", ToolCallStatus::Failed);
+        assert!(hooks.trigger_for(&failed).is_none());
+
+        let permission = AppEvent::AcpPermissionRequest {
+            session_id: "abc".to_string(),
+            payload: PermissionRequestPayload {
+                request_id: 7,
+                request: SessionRequestPermissionParams {
+                    session_id: "acp".to_string(),
+                    options: Vec::new(),
+                    tool_call: PermissionToolCall {
+                        tool_call_id: "t3".to_string(),
+                        status: ToolCallStatus::Pending,
+                        title: "rm -rf target".to_string(),
+                        content: Vec::new(),
+                        locations: Vec::new(),
+                        kind: ToolCallKind::Execute,
+                        server_name: None,
+                        tool_name: Some("run_shell_command".to_string()),
+                    },
+                },
+            },
+        };
+        let (trigger, tool) = hooks.trigger_for(&permission).unwrap();
+        assert_eq!(trigger, HookTrigger::PermissionRequested);
+        assert!(tool.unwrap().matches("run_shell_command"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook_gets_payload_on_stdin() {
+        let dir = TempDir::new().unwrap();
+        let working_directory = dir.path().to_string_lossy().to_string();
+        let mut edit_hook = hook(
+            HookTrigger::ToolCallCompleted,
+            HookAction::Command("cat > edit.json".to_string()),
+        );
+        edit_hook.tool = Some("edit".to_string());
+        let mut read_hook = edit_hook.clone();
+        read_hook.tool = Some("read".to_string());
+        read_hook.action = HookAction::Command("touch read.json".to_string());
+        let mut hooks = SessionHooks::new(
+            "abc".to_string(),
+            working_directory,
+            vec![edit_hook, read_hook],
+        );
+
+        hooks.observe(&tool_call(
+            "t1",
+            ToolCallStatus::Pending,
+            ToolCallKind::Edit,
+        ));
+        hooks.observe(&tool_call_update("t1", ToolCallStatus::Completed));
+
+        let output = dir.path().join("edit.json");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let payload = loop {
+            if let Ok(content) = std::fs::read_to_string(&output)
+                && let Ok(payload) = serde_json::from_str::<serde_json::Value>(&content)
+            {
+                break payload;
+            }
+            assert!(Instant::now() < deadline, "hook did not run");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert_eq!(payload["hook"], "tool_call_completed");
+        assert_eq!(payload["session_id"], "abc");
+        assert_eq!(payload["tool"]["kind"], "edit");
+        assert_eq!(payload["event"]["event"], "acp-session-update");
+        assert!(!dir.path().join("read.json").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook_gets_env() {
+        let dir = TempDir::new().unwrap();
+        let hooks = SessionHooks::new(
+            "abc".to_string(),
+            dir.path().to_string_lossy().to_string(),
+            vec![hook(
+                HookTrigger::SessionCrashed,
+                HookAction::Command("echo \"$GREETING\" > env.txt".to_string()),
+            )],
+        )
+        .with_env(HashMap::from([(
+            "GREETING".to_string(),
+            "hello".to_string(),
+        )]));
+
+        hooks.session_crashed();
+
+        let output = dir.path().join("env.txt");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Ok(content) = std::fs::read_to_string(&output)
+                && content.ends_with('\n')
+            {
+                assert_eq!(content.trim(), "hello");
+                break;
+            }
+            assert!(Instant::now() < deadline, "hook did not run");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook_failures() {
+        let dir = TempDir::new().unwrap();
+        let working_directory = dir.path().to_string_lossy();
+
+        let failing = hook(
+            HookTrigger::SessionCrashed,
+            HookAction::Command("echo nope >&2; exit 3".to_string()),
+        );
+        let err = run_hook(&failing, "{}", "abc", &working_directory, &HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("nope"));
+
+        let mut slow = hook(
+            HookTrigger::SessionCrashed,
+            HookAction::Command("sleep 5".to_string()),
+        );
+        slow.timeout_secs = 1;
+        let err = run_hook(&slow, "{}", "abc", &working_directory, &HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_url_hook_posts_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("{\"ok\":true}") {
+                let read = socket.read(&mut buf).await.unwrap();
+                assert!(read > 0);
+                request.extend_from_slice(&buf[..read]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let url = format!("http://127.0.0.1:{port}/hook?x=1");
+        let outcome = post_json(&url, "{\"ok\":true}").await.unwrap();
+        assert_eq!(outcome, "responded with 204");
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook?x=1 HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+    }
+}
@@ -0,0 +1,573 @@
+use crate::events::{AppEvent, EventEmitter};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, warn};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// How long a cancelled job gets to exit before it is killed.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How many finished jobs are kept around for `list` after they exit.
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// A command to run as a job.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub command: String,
+    /// Directory the command runs in. Must exist.
+    pub working_directory: String,
+    /// Extra environment variables, added to the inherited environment.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Kill the job if it is still running after this many seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    /// The process exited on its own. `code` is `None` if it was ended by a
+    /// signal that didn't come from us.
+    Exited {
+        code: Option<i32>,
+    },
+    Cancelled,
+    Killed,
+    TimedOut,
+    Failed {
+        error: String,
+    },
+}
+
+impl JobStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self, JobStatus::Running)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct JobInfo {
+    pub id: String,
+    pub command: String,
+    pub working_directory: String,
+    pub pid: Option<u32>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: JobStatus,
+}
+
+/// Payload of the `command-output-{job_id}` events. Output chunks arrive in
+/// order and the `exit` event is always the last one for a job.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommandOutputEvent {
+    Stdout { data: String },
+    Stderr { data: String },
+    Exit { status: JobStatus },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobSignal {
+    Cancel,
+    Kill,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    signals: mpsc::UnboundedSender<JobSignal>,
+    done: watch::Receiver<bool>,
+}
+
+/// Runs shell commands in the background, streams their output as events and
+/// tracks them until they exit.
+#[derive(Clone, Default)]
+pub struct JobManager {
+    jobs: Arc<Mutex<HashMap<String, JobEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn `spec` and return immediately. Output and the exit status are
+    /// emitted as `command-output-{job_id}` events.
+    pub fn start<E: EventEmitter + 'static>(&self, spec: JobSpec, emitter: E) -> Result<JobInfo> {
+        let working_directory = PathBuf::from(&spec.working_directory);
+        if !working_directory.is_dir() {
+            anyhow::bail!(
+                "Working directory does not exist: {}",
+                spec.working_directory
+            );
+        }
+
+        let mut child = shell_command(&spec.command)
+            .current_dir(&working_directory)
+            .envs(&spec.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn command: {}", spec.command))?;
+
+        let id = format!("job-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let info = JobInfo {
+            id: id.clone(),
+            command: spec.command.clone(),
+            working_directory: spec.working_directory.clone(),
+            pid: child.id(),
+            started_at: Utc::now(),
+            finished_at: None,
+            status: JobStatus::Running,
+        };
+        debug!("Started job {id} (pid {:?}): {}", info.pid, spec.command);
+
+        let (signal_tx, signal_rx) = mpsc::unbounded_channel();
+        let (done_tx, done_rx) = watch::channel(false);
+        {
+            let mut jobs = self.lock()?;
+            prune_finished(&mut jobs);
+            jobs.insert(
+                id.clone(),
+                JobEntry {
+                    info: info.clone(),
+                    signals: signal_tx,
+                    done: done_rx,
+                },
+            );
+        }
+
+        let stdout = child.stdout.take().map(|out| {
+            tokio::spawn(stream_output(out, emitter.clone(), id.clone(), |data| {
+                CommandOutputEvent::Stdout { data }
+            }))
+        });
+        let stderr = child.stderr.take().map(|err| {
+            tokio::spawn(stream_output(err, emitter.clone(), id.clone(), |data| {
+                CommandOutputEvent::Stderr { data }
+            }))
+        });
+
+        let jobs = Arc::clone(&self.jobs);
+        let timeout = spec.timeout_secs.map(Duration::from_secs);
+        tokio::spawn(async move {
+            let status = supervise(&mut child, signal_rx, timeout).await;
+            // Drain the readers so the exit event follows all of the output.
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = reader.await;
+            }
+            debug!("Job {id} finished: {status:?}");
+
+            if let Ok(mut jobs) = jobs.lock()
+                && let Some(entry) = jobs.get_mut(&id)
+            {
+                entry.info.status = status.clone();
+                entry.info.finished_at = Some(Utc::now());
+            }
+            let exit = AppEvent::CommandOutput {
+                job_id: id.clone(),
+                payload: CommandOutputEvent::Exit { status },
+            };
+            if let Err(e) = emitter.emit(exit) {
+                warn!("Failed to emit exit event for job {id}: {e}");
+            }
+            let _ = done_tx.send(true);
+        });
+
+        Ok(info)
+    }
+
+    /// All running jobs and the most recently finished ones, oldest first.
+    pub fn list(&self) -> Result<Vec<JobInfo>> {
+        let mut jobs: Vec<JobInfo> = self.lock()?.values().map(|e| e.info.clone()).collect();
+        jobs.sort_by_key(|info| info.started_at);
+        Ok(jobs)
+    }
+
+    pub fn get(&self, job_id: &str) -> Result<JobInfo> {
+        self.lock()?
+            .get(job_id)
+            .map(|e| e.info.clone())
+            .with_context(|| format!("Job not found: {job_id}"))
+    }
+
+    /// Ask the job to stop (SIGTERM on Unix). It is killed if it hasn't
+    /// exited after a short grace period.
+    pub fn cancel(&self, job_id: &str) -> Result<()> {
+        self.signal(job_id, JobSignal::Cancel)
+    }
+
+    /// Kill the job and everything it spawned immediately.
+    pub fn kill(&self, job_id: &str) -> Result<()> {
+        self.signal(job_id, JobSignal::Kill)
+    }
+
+    /// Wait until the job has exited and return its final state.
+    pub async fn wait(&self, job_id: &str) -> Result<JobInfo> {
+        let mut done = self
+            .lock()?
+            .get(job_id)
+            .map(|e| e.done.clone())
+            .with_context(|| format!("Job not found: {job_id}"))?;
+        // An error means the supervisor is gone, which only happens after it
+        // has recorded the final status.
+        let _ = done.wait_for(|done| *done).await;
+        self.get(job_id)
+    }
+
+    fn signal(&self, job_id: &str, signal: JobSignal) -> Result<()> {
+        let jobs = self.lock()?;
+        let entry = jobs
+            .get(job_id)
+            .with_context(|| format!("Job not found: {job_id}"))?;
+        if entry.info.status.is_running() {
+            // The supervisor may have just exited; then there's nothing to stop.
+            let _ = entry.signals.send(signal);
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, JobEntry>>> {
+        self.jobs
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock jobs mutex"))
+    }
+}
+
+/// Wait for the child to exit while handling cancel/kill requests and the
+/// timeout.
+async fn supervise(
+    child: &mut Child,
+    mut signals: mpsc::UnboundedReceiver<JobSignal>,
+    timeout: Option<Duration>,
+) -> JobStatus {
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+
+    let mut requested: Option<JobStatus> = None;
+    let grace = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(grace);
+
+    loop {
+        tokio::select! {
+            result = child.wait() => {
+                return match (requested, result) {
+                    (Some(status), _) => status,
+                    (None, Ok(exit)) => JobStatus::Exited { code: exit.code() },
+                    (None, Err(e)) => JobStatus::Failed { error: e.to_string() },
+                };
+            }
+            Some(signal) = signals.recv() => match signal {
+                JobSignal::Cancel if requested.is_none() => {
+                    requested = Some(JobStatus::Cancelled);
+                    terminate(child, false);
+                    grace
+                        .as_mut()
+                        .reset(tokio::time::Instant::now() + CANCEL_GRACE_PERIOD);
+                }
+                JobSignal::Cancel => {}
+                JobSignal::Kill => {
+                    requested = Some(JobStatus::Killed);
+                    terminate(child, true);
+                }
+            },
+            _ = &mut deadline, if requested.is_none() => {
+                requested = Some(JobStatus::TimedOut);
+                terminate(child, true);
+            }
+            _ = &mut grace, if requested == Some(JobStatus::Cancelled) => {
+                terminate(child, true);
+            }
+        }
+    }
+}
+
+/// Signal the job's whole process group, so commands started by the shell
+/// stop too.
+fn terminate(child: &mut Child, force: bool) {
+    let Some(pid) = child.id() else {
+        return;
+    };
+
+    #[cfg(not(windows))]
+    let result = std::process::Command::new("kill")
+        .args([
+            if force { "-KILL" } else { "-TERM" },
+            "--",
+            &format!("-{pid}"),
+        ])
+        .output();
+
+    #[cfg(windows)]
+    let result = {
+        use std::os::windows::process::CommandExt;
+        let mut cmd = std::process::Command::new("taskkill");
+        cmd.args(["/PID", &pid.to_string(), "/T"]);
+        if force {
+            cmd.arg("/F");
+        }
+        cmd.creation_flags(CREATE_NO_WINDOW).output()
+    };
+
+    match result {
+        Ok(output) if !output.status.success() => {
+            debug!(
+                "Signalling process {pid} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            if force {
+                let _ = child.start_kill();
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("Failed to signal process {pid}: {e}");
+            let _ = child.start_kill();
+        }
+    }
+}
+
+pub(crate) fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd.exe");
+        cmd.args(["/C", command]).creation_flags(CREATE_NO_WINDOW);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-lc", command]).process_group(0);
+        cmd
+    }
+}
+
+async fn stream_output<R, E, F>(mut reader: R, emitter: E, job_id: String, wrap: F)
+where
+    R: AsyncRead + Unpin,
+    E: EventEmitter,
+    F: Fn(String) -> CommandOutputEvent,
+{
+    let publish = |data: String| {
+        let _ = emitter.emit(AppEvent::CommandOutput {
+            job_id: job_id.clone(),
+            payload: wrap(data),
+        });
+    };
+
+    let mut buf = [0u8; 8192];
+    let mut pending = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        pending.extend_from_slice(&buf[..n]);
+        let data = take_utf8_prefix(&mut pending);
+        if !data.is_empty() {
+            publish(data);
+        }
+    }
+    if !pending.is_empty() {
+        publish(String::from_utf8_lossy(&pending).into_owned());
+    }
+}
+
+/// Take the longest prefix of `bytes` that can be decoded, leaving a trailing
+/// incomplete UTF-8 sequence for the next read. Invalid bytes are replaced.
+pub(crate) fn take_utf8_prefix(bytes: &mut Vec<u8>) -> String {
+    let split = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        // `error_len() == None` means the input ends mid-character.
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => bytes.len(),
+    };
+    let rest = bytes.split_off(split);
+    let text = String::from_utf8_lossy(bytes).into_owned();
+    *bytes = rest;
+    text
+}
+
+fn prune_finished(jobs: &mut HashMap<String, JobEntry>) {
+    let mut finished: Vec<(DateTime<Utc>, String)> = jobs
+        .values()
+        .filter(|e| !e.info.status.is_running())
+        .map(|e| (e.info.started_at, e.info.id.clone()))
+        .collect();
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+        jobs.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::MockEventEmitter;
+    use tempfile::TempDir;
+
+    fn spec(command: &str, dir: &TempDir) -> JobSpec {
+        JobSpec {
+            command: command.to_string(),
+            working_directory: dir.path().to_string_lossy().to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn output_events(emitter: &MockEventEmitter, job_id: &str) -> Vec<CommandOutputEvent> {
+        emitter
+            .get_events_by_name(&format!("command-output-{job_id}"))
+            .into_iter()
+            .map(|v| serde_json::from_value(v).unwrap())
+            .collect()
+    }
+
+    fn stdout_text(events: &[CommandOutputEvent]) -> String {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                CommandOutputEvent::Stdout { data } => Some(data.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_take_utf8_prefix_keeps_partial_character() {
+        let mut bytes = "hé".as_bytes().to_vec();
+        bytes.pop();
+        assert_eq!(take_utf8_prefix(&mut bytes), "h");
+        assert_eq!(bytes, vec![0xc3]);
+
+        bytes.push(0xa9);
+        assert_eq!(take_utf8_prefix(&mut bytes), "é");
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_take_utf8_prefix_replaces_invalid_bytes() {
+        let mut bytes = vec![b'a', 0xff, b'b'];
+        assert_eq!(take_utf8_prefix(&mut bytes), "a\u{fffd}b");
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_start_rejects_missing_directory() {
+        let manager = JobManager::new();
+        let result = manager.start(
+            JobSpec {
+                command: "echo hi".to_string(),
+                working_directory: "/definitely/not/a/real/dir".to_string(),
+                ..Default::default()
+            },
+            MockEventEmitter::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_job_streams_output_and_exit_status() {
+        let dir = TempDir::new().unwrap();
+        let manager = JobManager::new();
+        let emitter = MockEventEmitter::new();
+        let mut spec = spec(
+            "echo \"$GREETING from $(pwd)\"; echo oops >&2; exit 3",
+            &dir,
+        );
+        spec.env.insert("GREETING".to_string(), "hello".to_string());
+
+        let info = manager.start(spec, emitter.clone()).unwrap();
+        assert!(info.status.is_running());
+        let finished = manager.wait(&info.id).await.unwrap();
+        assert_eq!(finished.status, JobStatus::Exited { code: Some(3) });
+        assert!(finished.finished_at.is_some());
+
+        let events = output_events(&emitter, &info.id);
+        let cwd = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            stdout_text(&events).trim(),
+            format!("hello from {}", cwd.display())
+        );
+        assert!(events.contains(&CommandOutputEvent::Stderr {
+            data: "oops\n".to_string()
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&CommandOutputEvent::Exit {
+                status: JobStatus::Exited { code: Some(3) }
+            })
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_job_times_out() {
+        let dir = TempDir::new().unwrap();
+        let manager = JobManager::new();
+        let mut spec = spec("sleep 30", &dir);
+        spec.timeout_secs = Some(1);
+
+        let info = manager.start(spec, MockEventEmitter::new()).unwrap();
+        let finished = manager.wait(&info.id).await.unwrap();
+        assert_eq!(finished.status, JobStatus::TimedOut);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_cancel_and_kill() {
+        let dir = TempDir::new().unwrap();
+        let manager = JobManager::new();
+
+        let cancelled = manager
+            .start(spec("sleep 30", &dir), MockEventEmitter::new())
+            .unwrap();
+        let killed = manager
+            .start(
+                spec("trap '' TERM; sleep 30", &dir),
+                MockEventEmitter::new(),
+            )
+            .unwrap();
+        assert_eq!(manager.list().unwrap().len(), 2);
+
+        manager.cancel(&cancelled.id).unwrap();
+        manager.kill(&killed.id).unwrap();
+        assert_eq!(
+            manager.wait(&cancelled.id).await.unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert_eq!(
+            manager.wait(&killed.id).await.unwrap().status,
+            JobStatus::Killed
+        );
+
+        // Signalling a finished job is a no-op.
+        assert!(manager.kill(&killed.id).is_ok());
+        assert!(manager.cancel("job-missing").is_err());
+    }
+}
@@ -1,12 +1,24 @@
 // Module declarations
 pub mod acp;
+pub mod batch;
 pub mod cli;
+pub mod diff;
 pub mod events;
 pub mod filesystem;
+pub mod headless;
+pub mod hooks;
+pub mod jobs;
+pub mod logging;
+pub mod paths;
 pub mod projects;
+pub mod redact;
+pub mod resources;
 pub mod rpc;
 pub mod search;
 pub mod session;
+pub mod terminal;
+pub mod title;
+pub mod vault;
 
 // Test utilities (only available in test builds)
 #[cfg(test)]
@@ -15,19 +27,27 @@ pub mod test_utils;
 // Re-exports
 pub use acp::{
     AuthenticateParams, ContentBlock, InitializeParams, InitializeResult, Location,
-    PermissionOutcome, PermissionResult, SessionNewParams, SessionNewResult, SessionPromptParams,
-    SessionPromptResult, SessionRequestPermissionParams, SessionUpdate, SessionUpdateParams,
-    ToolCallContentItem, ToolCallKind, ToolCallStatus,
+    PermissionOutcome, PermissionResult, SessionCancelParams, SessionNewParams, SessionNewResult,
+    SessionPromptParams, SessionPromptResult, SessionRequestPermissionParams, SessionUpdate,
+    SessionUpdateParams, ToolCallContentItem, ToolCallKind, ToolCallStatus,
 };
+pub use batch::{BatchEvent, BatchManager, BatchReport, BatchRequest, BatchStatus};
 pub use cli::{AssistantChunk, CommandResult, MessageChunk, StreamAssistantMessageChunkParams};
+pub use diff::{DiffApplyResult, DiffPreview, DiffStatus};
 pub use events::{
+    AppEvent,
+    ChunkCoalescer,
     CliIoPayload,
     CliIoType,
+    DEFAULT_CHUNK_WINDOW,
     ErrorPayload,
     EventEmitter,
     GeminiOutputPayload,
     GeminiThoughtPayload,
     InternalEvent,
+    PermissionRequestPayload,
+    RecordingEventEmitter,
+    Replay,
     // Legacy tool call types - kept for compatibility during ACP transition
     ToolCallConfirmation,
     ToolCallConfirmationContent,
@@ -36,38 +56,72 @@ pub use events::{
     ToolCallLocation,
     ToolCallUpdate,
 };
-pub use filesystem::{DirEntry, FileContent, GitInfo, VolumeType};
+pub use filesystem::{
+    Access, DirEntry, FileContent, GitInfo, PathPermissionError, PathPermissionReason, PathPolicy,
+    VolumeType,
+};
+pub use headless::{ChannelEventEmitter, HeadlessPermissions, HeadlessSession, HeadlessTurn};
+pub use hooks::{Hook, HookAction, HookConfig, HookTrigger, SessionHooks};
+pub use jobs::{CommandOutputEvent, JobInfo, JobManager, JobSpec, JobStatus};
+pub use logging::{LOG_FILTER_ENV, LogConfig, LogGuard};
+pub use paths::DataDir;
 pub use projects::{
-    EnrichedProject, ProjectListItem, ProjectMetadata, ProjectMetadataView, ProjectsResponse,
-    TouchThrottle, ensure_project_metadata, list_enriched_projects, list_projects,
-    make_enriched_project, maybe_touch_updated_at,
+    ConversationMetadata, EnrichedProject, ProjectHistorySettings, ProjectListItem,
+    ProjectMetadata, ProjectMetadataView, ProjectsResponse, TouchThrottle, ensure_project_metadata,
+    list_enriched_projects, list_projects, make_enriched_project, maybe_touch_updated_at,
 };
+pub use redact::Redactor;
+pub use resources::ProcessResources;
 pub use rpc::{JsonRpcError, JsonRpcRequest, JsonRpcResponse, RpcLogger};
 pub use search::{
     ConversationHistoryEntry, DetailedConversation, MessageMatch, RecentChat, SearchFilters,
     SearchResult,
 };
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 pub use session::{
-    GeminiAuthConfig, LLxprtConfig, PersistentSession, ProcessStatus, QwenConfig, SessionManager,
-    SessionParams, initialize_session,
+    EVENT_CHANNEL_CAPACITY, GeminiAuthConfig, LLxprtConfig, MESSAGE_CHANNEL_CAPACITY,
+    PersistentSession, ProcessStatus, QwenConfig, STATUS_MONITOR_INTERVAL, SessionActivity,
+    SessionLaunchConfig, SessionManager, SessionParams, StatusMonitor, TurnState,
+    initialize_session,
 };
+pub use terminal::{TerminalEvent, TerminalInfo, TerminalManager, TerminalSpec};
+pub use title::TitleGeneration;
+pub use vault::{KeySource, ProfileConfig, ProfileInput, ProviderProfile, Vault, VaultStatus};
 // Standard library imports
 use anyhow::{Context, Result};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::process::Command;
+use tracing::{debug, info};
 
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+/// Model used when neither the caller nor the session's profile picks one.
+const DEFAULT_SESSION_MODEL: &str = "gemini-2.5-flash";
+
+/// The agent CLIs sessions can run, as named in `ProcessStatus::backend_type`.
+pub const CLI_BACKENDS: [&str; 3] = ["gemini", "qwen", "llxprt"];
+
 /// Main backend interface for Gemini CLI functionality
 pub struct GeminiBackend<E: EventEmitter> {
     emitter: E,
     session_manager: SessionManager,
     next_request_id: Arc<Mutex<u32>>,
     touch_throttle: TouchThrottle,
+    vault: Arc<Mutex<Vault>>,
+    jobs: JobManager,
+    terminals: TerminalManager,
+    batches: BatchManager,
+    chunk_window: Duration,
+    /// CLIs sessions may use; `None` allows all of [`CLI_BACKENDS`].
+    enabled_backends: Option<Vec<String>>,
+    path_policy: PathPolicy,
+    data_dir: DataDir,
+    /// Extra environment for the CLIs, hooks, terminals and jobs started.
+    process_env: HashMap<String, String>,
 }
 
 impl<E: EventEmitter + 'static> GeminiBackend<E> {
@@ -78,9 +132,104 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             session_manager: SessionManager::new(),
             next_request_id: Arc::new(Mutex::new(1000)),
             touch_throttle: TouchThrottle::new(Duration::from_secs(60)),
+            vault: Arc::new(Mutex::new(Vault::new(
+                vault::default_vault_path(&DataDir::default())
+                    .unwrap_or_else(|| PathBuf::from("vault.json")),
+            ))),
+            jobs: JobManager::new(),
+            terminals: TerminalManager::new(),
+            batches: BatchManager::new(),
+            chunk_window: DEFAULT_CHUNK_WINDOW,
+            enabled_backends: None,
+            path_policy: PathPolicy::unrestricted(),
+            data_dir: DataDir::default(),
+            process_env: HashMap::new(),
         }
     }
 
+    /// Set how long sessions started from now on hold streamed text back to
+    /// merge it with the chunks that follow. Zero turns merging off.
+    pub fn with_chunk_window(mut self, window: Duration) -> Self {
+        self.chunk_window = window;
+        self
+    }
+
+    /// Only start sessions with these CLIs, out of [`CLI_BACKENDS`].
+    pub fn with_enabled_backends<I, S>(mut self, backends: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.enabled_backends = Some(backends.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Limit which paths the file commands may read and write.
+    pub fn with_path_policy(mut self, policy: PathPolicy) -> Self {
+        self.path_policy = policy;
+        self
+    }
+
+    pub fn path_policy(&self) -> &PathPolicy {
+        &self.path_policy
+    }
+
+    /// The canonical form of `path`, if the path policy allows `access` to
+    /// it. Commands work on this rather than on `path`, so a symlink or `..`
+    /// in `path` can't resolve differently once the check has passed.
+    fn checked_path(&self, path: &str, access: Access) -> Result<String> {
+        self.path_policy
+            .check(&self.data_dir, path, access)?
+            .into_os_string()
+            .into_string()
+            .map_err(|resolved| anyhow::anyhow!("{} isn't valid UTF-8", resolved.display()))
+    }
+
+    /// Keep projects, conversation logs, hooks and the vault in `dir`
+    /// instead of the shared data directory.
+    pub fn with_data_dir(mut self, dir: PathBuf) -> Self {
+        self.data_dir = DataDir::new(dir);
+        self.vault = Arc::new(Mutex::new(Vault::new(
+            vault::default_vault_path(&self.data_dir)
+                .unwrap_or_else(|| PathBuf::from("vault.json")),
+        )));
+        self
+    }
+
+    /// Set environment variables for every process the backend starts, on
+    /// top of its own environment.
+    pub fn with_process_env<I, K, V>(mut self, env: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.process_env = env
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self
+    }
+
+    pub fn data_dir(&self) -> &DataDir {
+        &self.data_dir
+    }
+
+    pub fn is_backend_enabled(&self, backend: &str) -> bool {
+        self.enabled_backends
+            .as_ref()
+            .is_none_or(|enabled| enabled.iter().any(|name| name == backend))
+    }
+
+    /// Fail unless sessions may use `backend`.
+    fn ensure_backend_enabled(&self, backend: &str) -> Result<()> {
+        anyhow::ensure!(
+            self.is_backend_enabled(backend),
+            "The {backend} backend is disabled"
+        );
+        Ok(())
+    }
+
     // =====================================
     // Event Helper Methods
     // =====================================
@@ -92,7 +241,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             data: data.to_string(),
         };
         self.emitter
-            .emit(&format!("cli-io-{session_id}"), payload)
+            .emit(AppEvent::CliIo {
+                session_id: session_id.to_string(),
+                payload,
+            })
             .context("Failed to emit CLI I/O event")
     }
 
@@ -102,7 +254,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             text: text.to_string(),
         };
         self.emitter
-            .emit(&format!("gemini-output-{session_id}"), payload)
+            .emit(AppEvent::GeminiOutput {
+                session_id: session_id.to_string(),
+                payload,
+            })
             .context("Failed to emit Gemini output event")
     }
 
@@ -112,24 +267,30 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             thought: thought.to_string(),
         };
         self.emitter
-            .emit(&format!("gemini-thought-{session_id}"), payload)
+            .emit(AppEvent::GeminiThought {
+                session_id: session_id.to_string(),
+                payload,
+            })
             .context("Failed to emit Gemini thought event")
     }
 
     /// Emit tool call event
     pub fn emit_tool_call(&self, session_id: &str, tool_call: &ToolCallEvent) -> Result<()> {
         self.emitter
-            .emit(&format!("gemini-tool-call-{session_id}"), tool_call.clone())
+            .emit(AppEvent::GeminiToolCall {
+                session_id: session_id.to_string(),
+                payload: tool_call.clone(),
+            })
             .context("Failed to emit tool call event")
     }
 
     /// Emit tool call update event
     pub fn emit_tool_call_update(&self, session_id: &str, update: &ToolCallUpdate) -> Result<()> {
         self.emitter
-            .emit(
-                &format!("gemini-tool-call-update-{session_id}"),
-                update.clone(),
-            )
+            .emit(AppEvent::GeminiToolCallUpdate {
+                session_id: session_id.to_string(),
+                payload: update.clone(),
+            })
             .context("Failed to emit tool call update event")
     }
 
@@ -140,10 +301,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         confirmation: &ToolCallConfirmationRequest,
     ) -> Result<()> {
         self.emitter
-            .emit(
-                &format!("gemini-tool-call-confirmation-{session_id}"),
-                confirmation.clone(),
-            )
+            .emit(AppEvent::GeminiToolCallConfirmation {
+                session_id: session_id.to_string(),
+                payload: confirmation.clone(),
+            })
             .context("Failed to emit tool call confirmation event")
     }
 
@@ -153,14 +314,19 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             error: error.to_string(),
         };
         self.emitter
-            .emit(&format!("gemini-error-{session_id}"), payload)
+            .emit(AppEvent::GeminiError {
+                session_id: session_id.to_string(),
+                payload,
+            })
             .context("Failed to emit error event")
     }
 
     /// Emit command result event
     pub fn emit_command_result(&self, result: &CommandResult) -> Result<()> {
         self.emitter
-            .emit("command-result", result.clone())
+            .emit(AppEvent::CommandResult {
+                payload: result.clone(),
+            })
             .context("Failed to emit command result event")
     }
 
@@ -207,6 +373,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         } else {
             "gemini"
         };
+        self.ensure_backend_enabled(requested_backend)?;
+        // Starting a session registers its directory as a project, which
+        // the path policy then allows
+        let working_directory = self.checked_path(&working_directory, Access::Read)?;
 
         {
             let processes = self.session_manager.get_processes();
@@ -216,18 +386,18 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
             {
                 // Check if the existing session is using the same backend type
                 if existing.backend_type == requested_backend {
-                    println!(
-                        "🔄 [SESSION-CHECK] Existing {requested_backend} session found for {session_id}, reusing"
+                    debug!(
+                        "[SESSION-CHECK] Existing {requested_backend} session found for {session_id}, reusing"
                     );
                     return Ok(());
                 } else {
                     // Different backend requested - kill the existing session first
-                    println!(
-                        "🔄 [SESSION-CHECK] Backend switch detected: {} -> {} for session {}",
+                    debug!(
+                        "[SESSION-CHECK] Backend switch detected: {} -> {} for session {}",
                         existing.backend_type, requested_backend, session_id
                     );
-                    println!(
-                        "🔄 [SESSION-CHECK] Killing existing {} session before starting {}",
+                    debug!(
+                        "[SESSION-CHECK] Killing existing {} session before starting {}",
                         existing.backend_type, requested_backend
                     );
                     // Drop the guard before calling kill_process to avoid deadlock
@@ -245,6 +415,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
                 backend_config,
                 gemini_auth,
                 llxprt_config,
+                record_history: true,
+                chunk_window: self.chunk_window,
+                data_dir: self.data_dir.clone(),
+                env: self.process_env.clone(),
             },
             self.emitter.clone(),
             &self.session_manager,
@@ -253,6 +427,35 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         Ok(())
     }
 
+    /// Initialize a session using the credentials of a vault profile, so the
+    /// caller never handles the API key. `model` overrides the profile's model.
+    pub async fn initialize_session_with_profile(
+        &self,
+        session_id: String,
+        working_directory: String,
+        model: Option<String>,
+        profile_id: &str,
+    ) -> Result<()> {
+        let profile_id = profile_id.to_string();
+        let profile = self
+            .with_vault(move |vault| vault.profile(&profile_id))
+            .await?;
+        let model = model
+            .or_else(|| profile.config.model().map(str::to_string))
+            .unwrap_or_else(|| DEFAULT_SESSION_MODEL.to_string());
+        let (backend_config, gemini_auth, llxprt_config) = profile.config.into_session_configs();
+
+        self.initialize_session(
+            session_id,
+            working_directory,
+            model,
+            backend_config,
+            gemini_auth,
+            llxprt_config,
+        )
+        .await
+    }
+
     /// Send a message to an existing session
     pub async fn send_message(
         &self,
@@ -260,7 +463,7 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         message: String,
         _conversation_history: String,
     ) -> Result<()> {
-        println!("📤 Sending message to session: {session_id}");
+        debug!("Sending message to session: {session_id}");
 
         let (message_sender, acp_session_id) = {
             let processes = self.session_manager.get_processes();
@@ -321,9 +524,46 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
 
         message_sender
             .send(request_json)
+            .await
             .context("Failed to send message through channel")?;
 
-        println!("✅ ACP session/prompt sent to session: {session_id}");
+        info!("ACP session/prompt sent to session: {session_id}");
+        Ok(())
+    }
+
+    /// Ask the CLI to stop the prompt turn running in a session. The turn
+    /// still ends with the usual `ai-turn-finished` event.
+    pub async fn cancel_prompt(&self, session_id: &str) -> Result<()> {
+        let (message_sender, acp_session_id) = {
+            let processes = self.session_manager.get_processes();
+            let processes = processes
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Failed to lock processes mutex"))?;
+            let session = processes
+                .get(session_id)
+                .with_context(|| format!("Session not found: {session_id}"))?;
+            (
+                session.message_sender.clone(),
+                session.acp_session_id.clone(),
+            )
+        };
+        let message_sender = message_sender.context("No message sender available")?;
+        let acp_session_id = acp_session_id.context("No ACP session ID available")?;
+
+        // session/cancel is a notification, so it has no id
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "session/cancel",
+            "params": SessionCancelParams {
+                session_id: acp_session_id,
+            },
+        });
+        message_sender
+            .send(notification.to_string())
+            .await
+            .context("Failed to send cancel through channel")?;
+
+        info!("ACP session/cancel sent to session: {session_id}");
         Ok(())
     }
 
@@ -473,161 +713,246 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         Ok(())
     }
 
-    /// Execute a confirmed command
+    /// Start a confirmed command as a background job and return right away.
+    /// Output streams as `command-output-{job_id}` events, ending with the exit
+    /// status.
     /// Note: Command execution security is now delegated to the underlying CLIs (Gemini CLI, Qwen Code, LLxprt Code)
-    pub async fn execute_confirmed_command(&self, command: String) -> Result<String> {
-        println!("🖥️ Executing confirmed command: {command}");
-        println!("⚠️  Note: Security filtering delegated to underlying CLI");
+    pub async fn execute_confirmed_command(&self, spec: JobSpec) -> Result<JobInfo> {
+        debug!(
+            "Executing confirmed command in {}: {}",
+            spec.working_directory, spec.command
+        );
+        debug!("Note: Security filtering delegated to underlying CLI");
+        let mut spec = spec;
+        spec.working_directory = self.checked_path(&spec.working_directory, Access::Read)?;
+        // The command's own variables win over the backend's
+        let env = std::mem::take(&mut spec.env);
+        spec.env = self.process_env.clone();
+        spec.env.extend(env);
+        self.jobs.start(spec, self.emitter.clone())
+    }
 
-        // Execute command directly - the CLIs handle security
-        let output = {
-            #[cfg(windows)]
-            {
-                const CREATE_NO_WINDOW: u32 = 0x08000000;
-                Command::new("cmd.exe")
-                    .args(["/C", &command])
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .output()
-                    .await
-            }
-            #[cfg(not(windows))]
-            {
-                Command::new("sh").args(["-lc", &command]).output().await
-            }
-        };
+    pub async fn list_jobs(&self) -> Result<Vec<JobInfo>> {
+        self.jobs.list()
+    }
 
-        match output {
-            Ok(result) => {
-                let stdout = String::from_utf8_lossy(&result.stdout);
-                let stderr = String::from_utf8_lossy(&result.stderr);
+    pub async fn cancel_job(&self, job_id: &str) -> Result<()> {
+        self.jobs.cancel(job_id)
+    }
 
-                if result.status.success() {
-                    let output_text = format!(
-                        "Exit code: {}\nOutput:\n{}",
-                        result.status.code().unwrap_or(0),
-                        stdout
-                    );
+    pub async fn kill_job(&self, job_id: &str) -> Result<()> {
+        self.jobs.kill(job_id)
+    }
 
-                    let _ = self.emit_command_result(&CommandResult {
-                        command: command.clone(),
-                        success: true,
-                        output: Some(output_text.clone()),
-                        error: None,
-                    });
+    /// Run `request.prompt` against every project in `request.projects`, a
+    /// few sessions at a time. Returns the initial report; progress is emitted
+    /// as `batch-progress-{batch_id}` events.
+    pub async fn start_batch(&self, mut request: BatchRequest) -> Result<BatchReport> {
+        request.projects = request
+            .projects
+            .iter()
+            .map(|project| self.checked_path(project, Access::Read))
+            .collect::<Result<_>>()?;
+        let mut launch = SessionLaunchConfig {
+            model: DEFAULT_SESSION_MODEL.to_string(),
+            backend_config: None,
+            gemini_auth: None,
+            llxprt_config: None,
+            env: self.process_env.clone(),
+        };
+        if let Some(profile_id) = request.profile_id.clone() {
+            let profile = self
+                .with_vault(move |vault| vault.profile(&profile_id))
+                .await?;
+            if let Some(model) = profile.config.model() {
+                launch.model = model.to_string();
+            }
+            (
+                launch.backend_config,
+                launch.gemini_auth,
+                launch.llxprt_config,
+            ) = profile.config.into_session_configs();
+        }
+        if let Some(model) = &request.model {
+            launch.model = model.clone();
+        }
+        self.ensure_backend_enabled(launch.backend_type())?;
+        self.batches
+            .start(request, launch, self.data_dir.clone(), self.emitter.clone())
+    }
 
-                    Ok(output_text)
-                } else {
-                    let error_text = format!(
-                        "Command execution failed - Exit code: {}\nError:\n{}\nOutput:\n{}",
-                        result.status.code().unwrap_or(-1),
-                        stderr,
-                        stdout
-                    );
+    pub async fn list_batches(&self) -> Result<Vec<BatchReport>> {
+        self.batches.list()
+    }
 
-                    let _ = self.emit_command_result(&CommandResult {
-                        command: command.clone(),
-                        success: false,
-                        output: None,
-                        error: Some(error_text.clone()),
-                    });
+    pub async fn get_batch(&self, batch_id: &str) -> Result<BatchReport> {
+        self.batches.get(batch_id)
+    }
+
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<()> {
+        self.batches.cancel(batch_id)
+    }
+
+    /// Export a batch report as `json` or `markdown`.
+    pub async fn export_batch(&self, batch_id: &str, format: &str) -> Result<String> {
+        self.batches.get(batch_id)?.export(format)
+    }
 
-                    anyhow::bail!("{}", error_text)
+    /// Open a terminal for a project. `working_directory` defaults to the
+    /// project root; a relative one is taken relative to it.
+    pub async fn spawn_terminal(
+        &self,
+        project_id: String,
+        working_directory: Option<String>,
+        shell: Option<String>,
+        cols: u16,
+        rows: u16,
+    ) -> Result<TerminalInfo> {
+        let root = projects::project_root(&self.data_dir, &project_id)
+            .with_context(|| format!("Unknown project: {project_id}"))?
+            .canonicalize()
+            .context("Failed to resolve project root")?;
+        // Absolute paths, `..` and symlinks must not lead out of the project
+        let working_directory = match working_directory {
+            Some(dir) => {
+                let resolved = root
+                    .join(&dir)
+                    .canonicalize()
+                    .with_context(|| format!("Working directory does not exist: {dir}"))?;
+                if !resolved.starts_with(&root) {
+                    return Err(PathPermissionError {
+                        path: dir,
+                        reason: PathPermissionReason::OutsideAllowedRoots,
+                    }
+                    .into());
                 }
+                resolved
             }
-            Err(e) => {
-                let error_text = format!("Failed to execute command: {}", e);
+            None => root,
+        };
+        // Nor into a directory the policy refuses, such as another user's data
+        let working_directory =
+            self.checked_path(&working_directory.to_string_lossy(), Access::Read)?;
+        self.terminals.spawn(
+            TerminalSpec {
+                project_id,
+                working_directory,
+                shell,
+                env: self.process_env.clone(),
+                cols,
+                rows,
+            },
+            self.emitter.clone(),
+        )
+    }
 
-                let _ = self.emit_command_result(&CommandResult {
-                    command: command.clone(),
-                    success: false,
-                    output: None,
-                    error: Some(error_text.clone()),
-                });
+    pub async fn list_terminals(&self, project_id: Option<&str>) -> Result<Vec<TerminalInfo>> {
+        self.terminals.list(project_id)
+    }
 
-                anyhow::bail!("{}", error_text)
-            }
-        }
+    pub async fn write_terminal(&self, terminal_id: &str, data: &str) -> Result<()> {
+        self.terminals.write(terminal_id, data.as_bytes())
+    }
+
+    pub async fn resize_terminal(&self, terminal_id: &str, cols: u16, rows: u16) -> Result<()> {
+        self.terminals.resize(terminal_id, cols, rows)
+    }
+
+    pub async fn terminal_scrollback(&self, terminal_id: &str) -> Result<String> {
+        self.terminals.scrollback(terminal_id)
+    }
+
+    pub async fn kill_terminal(&self, terminal_id: &str) -> Result<()> {
+        self.terminals.kill(terminal_id)
+    }
+
+    /// The manager behind the terminal APIs, for transports that stream a
+    /// terminal directly (see [`TerminalManager::subscribe`]).
+    pub fn terminals(&self) -> &TerminalManager {
+        &self.terminals
     }
 
     /// Generate a conversation title
+    ///
+    /// The title is requested from a short-lived headless session that uses the
+    /// same backend, model and credentials as `session_id` (or the default Gemini
+    /// CLI when there is no such session). Its traffic is not recorded in history.
+    /// If the agent cannot produce a title, one is derived from the message locally.
+    /// The result is stored in the conversation metadata when the session has a
+    /// history log.
     pub async fn generate_conversation_title(
         &self,
         message: String,
         model: Option<String>,
+        session_id: Option<String>,
     ) -> Result<String> {
-        let prompt = format!(
-            "Generate a short, concise title (3-6 words) for a conversation that starts with this user message: \"{}\". Only return the title, nothing else.",
-            message.chars().take(200).collect::<String>()
-        );
-
-        let model_to_use = model.unwrap_or_else(|| "gemini-2.5-flash".to_string());
+        Ok(self
+            .prepare_title_generation(message, model, session_id)?
+            .run()
+            .await)
+    }
 
-        let mut child = {
-            #[cfg(windows)]
-            {
-                Command::new("cmd.exe")
-                    .args(["/C", "gemini", "--model", &model_to_use])
-                    .stdin(std::process::Stdio::piped())
-                    .stdout(std::process::Stdio::piped())
-                    .stderr(std::process::Stdio::piped())
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .spawn()
-                    .context("Failed to spawn Gemini CLI process")?
-            }
-            #[cfg(not(windows))]
-            {
-                Command::new("gemini")
-                    .args(["--model", &model_to_use])
-                    .stdin(std::process::Stdio::piped())
-                    .stdout(std::process::Stdio::piped())
-                    .stderr(std::process::Stdio::piped())
-                    .spawn()
-                    .context("Failed to spawn Gemini CLI process")?
+    /// What [`Self::generate_conversation_title`] needs from the backend, so
+    /// a caller sharing the backend behind a lock can release it before the
+    /// agent is asked.
+    pub fn prepare_title_generation(
+        &self,
+        message: String,
+        model: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<TitleGeneration> {
+        let session_info = session_id.as_ref().and_then(|id| {
+            let processes = self.session_manager.get_processes().lock().ok()?;
+            let session = processes.get(id)?;
+            Some((
+                session.launch_config.clone(),
+                session.working_directory.clone(),
+                session.rpc_logger.log_path().map(Path::to_path_buf),
+            ))
+        });
+        let (launch_config, working_directory, log_path) = match session_info {
+            Some((launch_config, working_directory, log_path)) => {
+                (launch_config, Some(working_directory), log_path)
             }
+            None => (None, None, None),
         };
 
-        if let Some(stdin) = child.stdin.take() {
-            use tokio::io::AsyncWriteExt;
-            let mut stdin = stdin;
-            stdin
-                .write_all(prompt.as_bytes())
-                .await
-                .context("Failed to write prompt to stdin")?;
-            stdin.shutdown().await.context("Failed to shutdown stdin")?;
+        let mut launch_config = launch_config.unwrap_or_else(|| SessionLaunchConfig {
+            model: DEFAULT_SESSION_MODEL.to_string(),
+            backend_config: None,
+            gemini_auth: None,
+            llxprt_config: None,
+            env: self.process_env.clone(),
+        });
+        if let Some(model) = model {
+            launch_config.model = model;
         }
-
-        let output = child
-            .wait_with_output()
-            .await
-            .context("Failed to wait for Gemini CLI output")?;
-
-        if !output.status.success() {
-            let error_msg = format!(
-                "Gemini CLI failed with exit code {:?}: {}",
-                output.status.code(),
-                String::from_utf8_lossy(&output.stderr)
-            );
-            anyhow::bail!("{}", error_msg);
-        }
-
-        let raw_output = String::from_utf8_lossy(&output.stdout);
-
-        let title = raw_output
-            .trim()
-            .lines()
-            .last()
-            .unwrap_or("New Conversation")
-            .trim_matches('"')
-            .trim()
-            .to_string();
-
-        let final_title = if title.is_empty() || title.len() > 50 {
-            message.chars().take(30).collect::<String>()
-        } else {
-            title
+        self.ensure_backend_enabled(launch_config.backend_type())?;
+
+        let params = SessionParams {
+            session_id: format!(
+                "title-{}",
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis()
+            ),
+            working_directory: working_directory
+                .unwrap_or_else(|| std::env::temp_dir().to_string_lossy().to_string()),
+            model: launch_config.model,
+            backend_config: launch_config.backend_config,
+            gemini_auth: launch_config.gemini_auth,
+            llxprt_config: launch_config.llxprt_config,
+            record_history: false,
+            chunk_window: DEFAULT_CHUNK_WINDOW,
+            data_dir: self.data_dir.clone(),
+            env: launch_config.env,
         };
-
-        Ok(final_title)
+        Ok(TitleGeneration {
+            message,
+            params,
+            log_path,
+        })
     }
 
     /// Get all process statuses
@@ -635,6 +960,13 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         self.session_manager.get_process_statuses()
     }
 
+    /// Create a monitor that pushes `process-status-changed` every `interval`
+    /// while sessions are alive. The caller spawns [`StatusMonitor::run`].
+    pub fn status_monitor(&self, interval: Duration) -> StatusMonitor<E> {
+        self.session_manager
+            .status_monitor(self.emitter.clone(), interval)
+    }
+
     /// Kill a process by conversation ID
     pub fn kill_process(&self, conversation_id: &str) -> Result<()> {
         let result = self.session_manager.kill_process(conversation_id);
@@ -643,8 +975,10 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         if result.is_ok()
             && let Ok(statuses) = self.session_manager.get_process_statuses()
         {
-            println!("📡 [STATUS-WS] Emitting process status change after killing process");
-            let _ = self.emitter.emit("process-status-changed", &statuses);
+            debug!("[STATUS-WS] Emitting process status change after killing process");
+            let _ = self
+                .emitter
+                .emit(AppEvent::ProcessStatusChanged { payload: statuses });
         }
 
         result
@@ -652,21 +986,36 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
 
     /// Validate if a directory exists and is accessible
     pub async fn validate_directory(&self, path: String) -> Result<bool> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::validate_directory(path).await
     }
 
     /// Check if the given path is the user's home directory
     pub async fn is_home_directory(&self, path: String) -> Result<bool> {
-        filesystem::is_home_directory(path).await
+        match self.process_home() {
+            Some(home) => Ok(Path::new(home) == Path::new(&path)),
+            None => filesystem::is_home_directory(path).await,
+        }
     }
 
     /// Get the user's home directory path
     pub async fn get_home_directory(&self) -> Result<String> {
-        filesystem::get_home_directory().await
+        match self.process_home() {
+            Some(home) => Ok(home.clone()),
+            None => filesystem::get_home_directory().await,
+        }
+    }
+
+    /// The home directory set for the backend's processes, if any.
+    fn process_home(&self) -> Option<&String> {
+        self.process_env
+            .get("HOME")
+            .or_else(|| self.process_env.get("USERPROFILE"))
     }
 
     /// Get the parent directory of the given path
     pub async fn get_parent_directory(&self, path: String) -> Result<Option<String>> {
+        self.checked_path(&path, Access::Read)?;
         filesystem::get_parent_directory(path).await
     }
 
@@ -677,17 +1026,19 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
 
     /// List the contents of a directory
     pub async fn list_directory_contents(&self, path: String) -> Result<Vec<DirEntry>> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::list_directory_contents(path).await
     }
 
     /// List files recursively with gitignore support
     pub async fn list_files_recursive(&self, path: String) -> Result<Vec<DirEntry>> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::list_files_recursive(path).await
     }
 
     /// Get recent chats
     pub async fn get_recent_chats(&self) -> Result<Vec<RecentChat>> {
-        search::get_recent_chats().await
+        search::get_recent_chats(&self.data_dir).await
     }
 
     /// Search across all chat logs
@@ -696,18 +1047,18 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         query: String,
         filters: Option<SearchFilters>,
     ) -> Result<Vec<SearchResult>> {
-        search::search_chats(query, filters).await
+        search::search_chats(&self.data_dir, query, filters).await
     }
 
     /// List projects
     pub async fn list_projects(&self, limit: u32, offset: u32) -> Result<ProjectsResponse> {
         let lim = std::cmp::min(limit.max(1), 100);
-        list_projects(lim, offset)
+        list_projects(&self.data_dir, lim, offset)
     }
 
     /// Return enriched projects
     pub async fn list_enriched_projects(&self) -> Result<Vec<EnrichedProject>> {
-        list_enriched_projects()
+        list_enriched_projects(&self.data_dir)
     }
 
     /// Get an enriched project for a given sha256
@@ -716,52 +1067,159 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         sha256: String,
         external_root_path: String,
     ) -> Result<EnrichedProject> {
-        let external = Path::new(&external_root_path);
-        ensure_project_metadata(&sha256, Some(external))?;
-        let _ = maybe_touch_updated_at(&sha256, &self.touch_throttle);
-        Ok(make_enriched_project(&sha256, Some(external), false))
+        // A registered project's root is allowed by a restricted path policy,
+        // so only register directories the policy already allows, and only
+        // under their own hash
+        if ensure_project_metadata(&self.data_dir, &sha256, None).is_err() {
+            let root = self
+                .path_policy
+                .check(&self.data_dir, &external_root_path, Access::Read)?;
+            anyhow::ensure!(
+                rpc::ProjectHasher::hash_path_or_raw(&root.to_string_lossy()) == sha256,
+                "Project {sha256} isn't the project of {external_root_path}"
+            );
+            ensure_project_metadata(&self.data_dir, &sha256, Some(&root))?;
+        }
+        let _ = maybe_touch_updated_at(&self.data_dir, &sha256, &self.touch_throttle);
+        Ok(make_enriched_project(
+            &self.data_dir,
+            &sha256,
+            Some(Path::new(&external_root_path)),
+            false,
+        ))
     }
 
     /// Get discussions for a specific project
     pub async fn get_project_discussions(&self, project_id: &str) -> Result<Vec<RecentChat>> {
-        search::get_project_discussions(project_id).await
+        search::get_project_discussions(&self.data_dir, project_id).await
     }
 
     /// Get detailed conversation history with all messages
     pub async fn get_detailed_conversation(&self, chat_id: &str) -> Result<DetailedConversation> {
-        search::get_detailed_conversation(chat_id).await
+        search::get_detailed_conversation(&self.data_dir, chat_id).await
     }
 
     /// Export conversation history in various formats
     pub async fn export_conversation_history(&self, chat_id: &str, format: &str) -> Result<String> {
-        search::export_conversation_history(chat_id, format).await
+        search::export_conversation_history(&self.data_dir, chat_id, format).await
     }
 
     pub async fn delete_conversation(&self, chat_id: &str) -> Result<()> {
-        search::delete_conversation(chat_id).await
+        search::delete_conversation(&self.data_dir, chat_id).await
     }
 
     pub async fn delete_project(&self, project_id: &str) -> Result<()> {
-        projects::delete_project(project_id).await
+        projects::delete_project(&self.data_dir, project_id).await
+    }
+
+    /// Get what sessions in a project persist to its history
+    pub async fn get_project_history_settings(
+        &self,
+        project_id: &str,
+    ) -> Result<ProjectHistorySettings> {
+        Ok(projects::read_history_settings(&self.data_dir, project_id))
+    }
+
+    /// Update what sessions in a project persist to its history. Takes effect
+    /// for sessions started afterwards.
+    pub async fn set_project_history_settings(
+        &self,
+        project_id: &str,
+        settings: ProjectHistorySettings,
+    ) -> Result<()> {
+        projects::write_history_settings(&self.data_dir, project_id, &settings)
+    }
+
+    // =====================================
+    // Credential Vault
+    // =====================================
+
+    /// Run a vault operation on the blocking pool: key derivation is CPU-heavy
+    /// and the OS keyring client blocks.
+    async fn with_vault<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Vault) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let vault = self.vault.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut vault = vault
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Failed to lock vault mutex"))?;
+            f(&mut vault)
+        })
+        .await
+        .context("Vault task failed")?
+    }
+
+    pub async fn vault_status(&self) -> Result<VaultStatus> {
+        self.with_vault(|vault| Ok(vault.status())).await
+    }
+
+    /// Create the vault, protected by `passphrase` or, without one, by a key in
+    /// the OS keyring. The new vault starts unlocked.
+    pub async fn create_vault(&self, passphrase: Option<String>) -> Result<()> {
+        self.with_vault(move |vault| vault.create(passphrase.as_deref()))
+            .await
+    }
+
+    pub async fn unlock_vault(&self, passphrase: Option<String>) -> Result<()> {
+        self.with_vault(move |vault| vault.unlock(passphrase.as_deref()))
+            .await
+    }
+
+    pub async fn lock_vault(&self) -> Result<()> {
+        self.with_vault(|vault| {
+            vault.lock();
+            Ok(())
+        })
+        .await
+    }
+
+    /// List vault profiles with their API keys masked
+    pub async fn list_profiles(&self) -> Result<Vec<ProviderProfile>> {
+        self.with_vault(|vault| vault.list_profiles()).await
+    }
+
+    pub async fn create_profile(&self, input: ProfileInput) -> Result<ProviderProfile> {
+        self.with_vault(move |vault| vault.create_profile(input))
+            .await
+    }
+
+    pub async fn update_profile(
+        &self,
+        profile_id: String,
+        input: ProfileInput,
+    ) -> Result<ProviderProfile> {
+        self.with_vault(move |vault| vault.update_profile(&profile_id, input))
+            .await
+    }
+
+    pub async fn delete_profile(&self, profile_id: String) -> Result<()> {
+        self.with_vault(move |vault| vault.delete_profile(&profile_id))
+            .await
     }
 
     /// Get git repository information for a directory
     pub async fn get_git_info(&self, path: String) -> Result<Option<GitInfo>> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::get_git_info(path).await
     }
 
     /// Read file content
     pub async fn read_file_content(&self, path: String) -> Result<FileContent> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::read_file_content(path).await
     }
 
     /// Read binary file as base64 encoded string
     pub async fn read_binary_file_as_base64(&self, path: String) -> Result<String> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::read_binary_file_as_base64(path).await
     }
 
     /// Get the canonical path for a given path
     pub async fn get_canonical_path(&self, path: String) -> Result<String> {
+        let path = self.checked_path(&path, Access::Read)?;
         let canonical_path = std::path::Path::new(&path)
             .canonicalize()
             .context("Failed to canonicalize path")?;
@@ -774,13 +1232,49 @@ impl<E: EventEmitter + 'static> GeminiBackend<E> {
         path: String,
         force_text: bool,
     ) -> Result<FileContent> {
+        let path = self.checked_path(&path, Access::Read)?;
         filesystem::read_file_content_with_options(path, force_text).await
     }
 
     /// Write file content with safety checks
     pub async fn write_file_content(&self, path: String, content: String) -> Result<FileContent> {
+        let path = self.checked_path(&path, Access::Write)?;
         filesystem::write_file_content(path, content).await
     }
+
+    /// Render a `Diff` tool call as a unified diff against the file on disk
+    pub async fn preview_diff(
+        &self,
+        path: String,
+        old_text: String,
+        new_text: String,
+        context_lines: Option<usize>,
+    ) -> Result<DiffPreview> {
+        let path = self.checked_path(&path, Access::Read)?;
+        diff::preview_diff(path, old_text, new_text, context_lines).await
+    }
+
+    /// Apply a `Diff` tool call to disk, reporting conflicts instead of overwriting
+    pub async fn apply_diff(
+        &self,
+        path: String,
+        old_text: String,
+        new_text: String,
+    ) -> Result<DiffApplyResult> {
+        let path = self.checked_path(&path, Access::Write)?;
+        diff::apply_diff(path, old_text, new_text).await
+    }
+
+    /// Revert a previously applied `Diff` tool call
+    pub async fn revert_diff(
+        &self,
+        path: String,
+        old_text: String,
+        new_text: String,
+    ) -> Result<DiffApplyResult> {
+        let path = self.checked_path(&path, Access::Write)?;
+        diff::revert_diff(path, old_text, new_text).await
+    }
 }
 
 #[cfg(test)]
@@ -794,6 +1288,14 @@ mod tests {
         GeminiBackend::new(emitter)
     }
 
+    /// Whether `result` is the path policy refusing a path outside its roots.
+    fn is_refused<T>(result: Result<T>) -> bool {
+        result
+            .err()
+            .and_then(|e| e.downcast_ref::<PathPermissionError>().map(|e| e.reason))
+            == Some(PathPermissionReason::OutsideAllowedRoots)
+    }
+
     #[test]
     fn test_parse_single_mention() {
         let backend = create_test_backend();
@@ -985,4 +1487,331 @@ mod tests {
             _ => panic!("Expected ResourceLink"),
         }
     }
+
+    #[tokio::test]
+    async fn test_disabled_backend_rejected() {
+        let backend = create_test_backend().with_enabled_backends(["gemini"]);
+        assert!(backend.is_backend_enabled("gemini"));
+        assert!(!backend.is_backend_enabled("qwen"));
+
+        let err = backend
+            .initialize_session(
+                "session".to_string(),
+                ".".to_string(),
+                DEFAULT_SESSION_MODEL.to_string(),
+                None,
+                None,
+                Some(LLxprtConfig {
+                    provider: "openai".to_string(),
+                    api_key: "key".to_string(),
+                    model: "gpt-4o".to_string(),
+                    base_url: None,
+                }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "The llxprt backend is disabled");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_backend_rejected_for_batches() {
+        let backend = create_test_backend().with_enabled_backends(["qwen"]);
+        let request: BatchRequest = serde_json::from_value(serde_json::json!({
+            "projects": ["."],
+            "prompt": "Hello",
+        }))
+        .unwrap();
+
+        let err = backend.start_batch(request).await.unwrap_err();
+        assert_eq!(err.to_string(), "The gemini backend is disabled");
+        assert!(backend.list_batches().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_backend_rejected_for_titles() {
+        let backend = create_test_backend().with_enabled_backends(["qwen"]);
+
+        let err = backend
+            .generate_conversation_title("Fix the login page".to_string(), None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "The gemini backend is disabled");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_stay_within_the_path_policy() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let allowed = temp.path().join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        let backend = create_test_backend()
+            .with_data_dir(temp.path().join("data"))
+            .with_path_policy(PathPolicy::restricted([allowed.clone()]));
+
+        let err = backend
+            .initialize_session(
+                "session".to_string(),
+                "/".to_string(),
+                DEFAULT_SESSION_MODEL.to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+        assert!(backend.list_enriched_projects().await.unwrap().is_empty());
+        assert!(
+            backend
+                .list_directory_contents("/".to_string())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_policy_guards_commands_run_in_a_directory() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let allowed = temp.path().join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        let backend = create_test_backend()
+            .with_data_dir(temp.path().join("data"))
+            .with_path_policy(PathPolicy::restricted([allowed.clone()]));
+        let outside = temp.path().to_string_lossy().to_string();
+
+        assert!(is_refused(
+            backend
+                .execute_confirmed_command(JobSpec {
+                    command: "true".to_string(),
+                    working_directory: outside.clone(),
+                    env: HashMap::new(),
+                    timeout_secs: None,
+                })
+                .await
+        ));
+        let request: BatchRequest = serde_json::from_value(serde_json::json!({
+            "projects": [allowed, outside],
+            "prompt": "Hello",
+        }))
+        .unwrap();
+        assert!(is_refused(backend.start_batch(request).await));
+        assert!(backend.list_batches().await.unwrap().is_empty());
+        assert!(is_refused(backend.get_git_info(outside.clone()).await));
+        assert!(is_refused(
+            backend.validate_directory(outside.clone()).await
+        ));
+        assert!(is_refused(
+            backend.get_canonical_path(outside.clone()).await
+        ));
+        assert!(is_refused(backend.get_parent_directory(outside).await));
+
+        let allowed = allowed.to_string_lossy().to_string();
+        assert!(backend.validate_directory(allowed.clone()).await.unwrap());
+        assert!(
+            backend
+                .get_parent_directory(allowed)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_policy_guards_file_commands() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let allowed = temp.path().join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        let backend = create_test_backend()
+            .with_path_policy(PathPolicy::restricted([allowed.clone()]).with_read_only(true));
+
+        let inside = allowed.join("notes.txt").to_string_lossy().to_string();
+        let outside = temp.path().join("other.txt").to_string_lossy().to_string();
+        assert!(backend.read_file_content(inside.clone()).await.is_ok());
+
+        let err = backend.read_file_content(outside).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+        let err = backend
+            .write_file_content(inside, "text".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+            Some(PathPermissionReason::ReadOnly)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_file_commands_use_the_checked_path() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let allowed = temp.path().join("allowed");
+        std::fs::create_dir_all(allowed.join("real")).unwrap();
+        std::fs::write(allowed.join("real/notes.txt"), "notes").unwrap();
+        std::os::unix::fs::symlink(allowed.join("real"), allowed.join("link")).unwrap();
+        let backend =
+            create_test_backend().with_path_policy(PathPolicy::restricted([allowed.clone()]));
+
+        let path = allowed.join("link/../link/notes.txt");
+        let content = backend
+            .read_file_content(path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        let resolved = allowed.canonicalize().unwrap().join("real/notes.txt");
+        assert_eq!(content.path, resolved.to_string_lossy());
+        assert_eq!(content.content.as_deref(), Some("notes"));
+    }
+
+    #[tokio::test]
+    async fn test_data_dirs_keep_projects_apart() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let alice = create_test_backend().with_data_dir(temp.path().join("alice"));
+        let bob = create_test_backend().with_data_dir(temp.path().join("bob"));
+        let project = temp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+
+        let id = rpc::ProjectHasher::hash_path(&project.to_string_lossy()).unwrap();
+        alice
+            .get_enriched_project(id.clone(), project.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert!(
+            temp.path()
+                .join("alice/projects")
+                .join(&id)
+                .join("project.json")
+                .exists()
+        );
+        assert_eq!(alice.list_enriched_projects().await.unwrap().len(), 1);
+        assert!(bob.list_enriched_projects().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_projects_register_only_their_own_allowed_root() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let allowed = temp.path().join("allowed");
+        let project = allowed.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let backend = create_test_backend()
+            .with_data_dir(temp.path().join("data"))
+            .with_path_policy(PathPolicy::restricted([allowed.clone()]));
+        let id = |path: &Path| rpc::ProjectHasher::hash_path(&path.to_string_lossy()).unwrap();
+
+        // Another directory's id, or a made up one, can't claim a root
+        for sha256 in [id(&allowed), "c".repeat(64)] {
+            assert!(
+                backend
+                    .get_enriched_project(sha256, project.to_string_lossy().to_string())
+                    .await
+                    .is_err()
+            );
+        }
+        // Nor can a directory outside the policy, even under its own id
+        let err = backend
+            .get_enriched_project(id(Path::new("/")), "/".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+        assert!(backend.list_enriched_projects().await.unwrap().is_empty());
+        assert!(
+            backend
+                .list_directory_contents("/".to_string())
+                .await
+                .is_err()
+        );
+
+        backend
+            .get_enriched_project(id(&project), project.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(backend.list_enriched_projects().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_terminal_stays_in_project() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let backend = create_test_backend().with_data_dir(temp.path().join("data"));
+        let project = temp.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        let id = rpc::ProjectHasher::hash_path(&project.to_string_lossy()).unwrap();
+        backend
+            .get_enriched_project(id.clone(), project.to_string_lossy().to_string())
+            .await
+            .unwrap();
+
+        let outside = temp.path().to_string_lossy().to_string();
+        for dir in ["..", "src/../..", outside.as_str()] {
+            let err = backend
+                .spawn_terminal(id.clone(), Some(dir.to_string()), None, 80, 24)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+                Some(PathPermissionReason::OutsideAllowedRoots),
+                "{dir}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_denied_directories_are_off_limits_to_every_command() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let denied = project.join("other-user");
+        std::fs::create_dir_all(&denied).unwrap();
+        let backend = create_test_backend()
+            .with_data_dir(temp.path().join("data"))
+            .with_path_policy(
+                PathPolicy::restricted([project.clone()]).with_denied([denied.clone()]),
+            );
+        let id = rpc::ProjectHasher::hash_path(&project.to_string_lossy()).unwrap();
+        backend
+            .get_enriched_project(id.clone(), project.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        let denied = denied.to_string_lossy().to_string();
+
+        assert!(is_refused(
+            backend
+                .initialize_session(
+                    "session".to_string(),
+                    denied.clone(),
+                    DEFAULT_SESSION_MODEL.to_string(),
+                    None,
+                    None,
+                    None,
+                )
+                .await
+        ));
+        assert!(is_refused(
+            backend
+                .execute_confirmed_command(JobSpec {
+                    command: "true".to_string(),
+                    working_directory: denied.clone(),
+                    env: HashMap::new(),
+                    timeout_secs: None,
+                })
+                .await
+        ));
+        assert!(is_refused(
+            backend
+                .spawn_terminal(id, Some("other-user".to_string()), None, 80, 24)
+                .await
+        ));
+        assert!(is_refused(backend.list_directory_contents(denied).await));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_prompt_unknown_session() {
+        let backend = create_test_backend();
+        let err = backend.cancel_prompt("missing").await.unwrap_err();
+        assert_eq!(err.to_string(), "Session not found: missing");
+    }
 }
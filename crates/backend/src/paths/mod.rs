@@ -0,0 +1,117 @@
+//! Where the app keeps its data.
+//!
+//! Projects, conversation logs, the credential vault, hooks and logs all live
+//! under one data directory, `~/.gemini-cli-desktop` unless it is moved with
+//! `GEMINI_CLI_DESKTOP_DATA_DIR` or [`set_data_dir`]. A backend serving
+//! someone else, such as one user of a shared web server, is given a
+//! [`DataDir`] of its own.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides the data directory for every frontend.
+pub const DATA_DIR_ENV: &str = "GEMINI_CLI_DESKTOP_DATA_DIR";
+const DATA_DIR_NAME: &str = ".gemini-cli-desktop";
+
+static DATA_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` as the data directory for the rest of the process. Only the
+/// first call has any effect; returns whether this one did.
+pub fn set_data_dir(dir: PathBuf) -> bool {
+    DATA_DIR_OVERRIDE.set(dir).is_ok()
+}
+
+/// The data directory: the one given to [`set_data_dir`], else
+/// `GEMINI_CLI_DESKTOP_DATA_DIR`, else `~/.gemini-cli-desktop`. `None` when
+/// there is no home directory to put it in.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = DATA_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok();
+    resolve_data_dir(std::env::var_os(DATA_DIR_ENV), home)
+}
+
+fn resolve_data_dir(configured: Option<OsString>, home: Option<String>) -> Option<PathBuf> {
+    if let Some(dir) = configured.filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let home = home.filter(|home| !home.is_empty())?;
+    Some(Path::new(&home).join(DATA_DIR_NAME))
+}
+
+/// [`data_dir`], falling back to `.gemini-cli-desktop` in the current
+/// directory.
+pub fn data_dir_or_local() -> PathBuf {
+    data_dir().unwrap_or_else(|| Path::new(".").join(DATA_DIR_NAME))
+}
+
+/// The data directory a backend uses: a fixed one, or else the process-wide
+/// [`data_dir`], looked up each time so it follows the environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataDir(Option<PathBuf>);
+
+impl DataDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self(Some(dir.into()))
+    }
+
+    /// The directory; `None` when it follows the process-wide one and there
+    /// is no home directory to put that in.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.0.clone().or_else(data_dir)
+    }
+
+    /// [`DataDir::path`], falling back like [`data_dir_or_local`].
+    pub fn path_or_local(&self) -> PathBuf {
+        self.0.clone().unwrap_or_else(data_dir_or_local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir_under_home() {
+        assert_eq!(
+            resolve_data_dir(None, Some("/home/test".to_string())),
+            Some(Path::new("/home/test").join(".gemini-cli-desktop"))
+        );
+    }
+
+    #[test]
+    fn test_configured_data_dir_wins() {
+        assert_eq!(
+            resolve_data_dir(
+                Some(OsString::from("/srv/gemini")),
+                Some("/home/test".to_string())
+            ),
+            Some(PathBuf::from("/srv/gemini"))
+        );
+        // An empty setting counts as unset
+        assert_eq!(
+            resolve_data_dir(Some(OsString::new()), Some("/home/test".to_string())),
+            Some(Path::new("/home/test").join(".gemini-cli-desktop"))
+        );
+    }
+
+    #[test]
+    fn test_no_data_dir_without_home() {
+        assert_eq!(resolve_data_dir(None, None), None);
+        assert_eq!(resolve_data_dir(None, Some(String::new())), None);
+    }
+
+    #[test]
+    fn test_fixed_data_dir() {
+        let dir = DataDir::new("/srv/gemini/users/alice");
+        assert_eq!(dir.path(), Some(PathBuf::from("/srv/gemini/users/alice")));
+        assert_eq!(
+            dir.path_or_local(),
+            PathBuf::from("/srv/gemini/users/alice")
+        );
+    }
+}
@@ -1,13 +1,17 @@
+use crate::paths::DataDir;
+use crate::redact::Redactor;
 use anyhow::{Context, Result};
 use chrono::{DateTime, FixedOffset, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectListItem {
     pub id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,7 +28,7 @@ pub struct ProjectListItem {
     pub log_count: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectsResponse {
     pub items: Vec<ProjectListItem>,
     pub total: u32,
@@ -45,7 +49,61 @@ pub struct ProjectMetadata {
     pub updated_at: Option<DateTime<FixedOffset>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Per-conversation metadata stored in a project's `conversations.json`, keyed
+/// by the conversation's log file name.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ConversationMetadata {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Per-project settings for what sessions persist to the project's history,
+/// stored in `history.json` next to `project.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ProjectHistorySettings {
+    /// When false, sessions in this project write no RPC log at all.
+    #[serde(default = "default_persist_rpc_log")]
+    pub persist_rpc_log: bool,
+    /// Extra regexes redacted from the RPC log on top of the built-in detectors.
+    #[serde(default)]
+    pub redaction_patterns: Vec<String>,
+}
+
+fn default_persist_rpc_log() -> bool {
+    true
+}
+
+impl Default for ProjectHistorySettings {
+    fn default() -> Self {
+        Self {
+            persist_rpc_log: true,
+            redaction_patterns: Vec::new(),
+        }
+    }
+}
+
+impl ProjectHistorySettings {
+    /// Redactor for this project's RPC log. Patterns are validated when saved,
+    /// but a hand-edited file may still contain invalid ones; those are skipped.
+    pub fn redactor(&self) -> Redactor {
+        let valid: Vec<&String> = self
+            .redaction_patterns
+            .iter()
+            .filter(|pattern| match Redactor::with_user_patterns(&[pattern]) {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("Ignoring redaction pattern: {e:#}");
+                    false
+                }
+            })
+            .collect();
+        Redactor::with_user_patterns(&valid).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectMetadataView {
     pub path: String,
     pub sha256: String,
@@ -56,7 +114,7 @@ pub struct ProjectMetadataView {
     pub updated_at: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EnrichedProject {
     pub sha256: String,
     pub root_path: PathBuf,
@@ -78,25 +136,18 @@ impl TouchThrottle {
     }
 }
 
-fn home_projects_root() -> Option<PathBuf> {
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| "".to_string()));
-    if home.is_empty() {
-        return None;
-    }
-    let path = Path::new(&home)
-        .join(".gemini-cli-desktop")
-        .join("projects");
+fn home_projects_root(data_dir: &DataDir) -> Option<PathBuf> {
+    let path = data_dir.path()?.join("projects");
     // Ensure proper path normalization for the platform
     Some(path.components().collect::<PathBuf>())
 }
 
-fn projects_root_dir() -> Option<PathBuf> {
-    home_projects_root()
+fn projects_root_dir(data_dir: &DataDir) -> Option<PathBuf> {
+    home_projects_root(data_dir)
 }
 
-fn project_json_path(sha256: &str) -> Option<PathBuf> {
-    projects_root_dir().map(|root| root.join(sha256).join("project.json"))
+fn project_json_path(data_dir: &DataDir, sha256: &str) -> Option<PathBuf> {
+    projects_root_dir(data_dir).map(|root| root.join(sha256).join("project.json"))
 }
 
 fn now_fixed_offset() -> DateTime<FixedOffset> {
@@ -138,8 +189,8 @@ fn parse_millis_from_log_name(name: &str) -> Option<u64> {
     ts_part.parse::<u64>().ok()
 }
 
-fn read_project_metadata(root_sha: &str) -> Result<ProjectMetadata> {
-    let Some(path) = project_json_path(root_sha) else {
+fn read_project_metadata(data_dir: &DataDir, root_sha: &str) -> Result<ProjectMetadata> {
+    let Some(path) = project_json_path(data_dir, root_sha) else {
         anyhow::bail!("Project not found");
     };
     if !path.exists() {
@@ -150,8 +201,13 @@ fn read_project_metadata(root_sha: &str) -> Result<ProjectMetadata> {
         .context("Failed to parse project metadata JSON")
 }
 
-fn write_project_metadata(sha256: &str, meta: &ProjectMetadata) -> Result<()> {
-    let Some(json_path) = project_json_path(sha256) else {
+/// Root directory of a known project.
+pub fn project_root(data_dir: &DataDir, sha256: &str) -> Result<PathBuf> {
+    Ok(read_project_metadata(data_dir, sha256)?.path)
+}
+
+fn write_project_metadata(data_dir: &DataDir, sha256: &str, meta: &ProjectMetadata) -> Result<()> {
+    let Some(json_path) = project_json_path(data_dir, sha256) else {
         anyhow::bail!("Project not found");
     };
     if let Some(dir) = json_path.parent() {
@@ -166,6 +222,121 @@ fn write_project_metadata(sha256: &str, meta: &ProjectMetadata) -> Result<()> {
     Ok(())
 }
 
+fn conversations_json_path(data_dir: &DataDir, sha256: &str) -> Option<PathBuf> {
+    projects_root_dir(data_dir).map(|root| root.join(sha256).join("conversations.json"))
+}
+
+/// Read all stored conversation metadata for a project. Missing or unreadable
+/// files yield an empty map.
+pub fn read_conversations_metadata(
+    data_dir: &DataDir,
+    sha256: &str,
+) -> HashMap<String, ConversationMetadata> {
+    conversations_json_path(data_dir, sha256)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_conversations_metadata(
+    data_dir: &DataDir,
+    sha256: &str,
+    conversations: &HashMap<String, ConversationMetadata>,
+) -> Result<()> {
+    let Some(json_path) = conversations_json_path(data_dir, sha256) else {
+        anyhow::bail!("Project not found");
+    };
+    if let Some(dir) = json_path.parent() {
+        fs::create_dir_all(dir).context("Failed to create project metadata directory")?;
+    }
+    let tmp_path = json_path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(conversations)
+        .context("Failed to serialize conversation metadata")?;
+    fs::write(&tmp_path, content.as_bytes())
+        .context("Failed to write temporary conversation metadata file")?;
+    fs::rename(&tmp_path, &json_path).context("Failed to rename conversation metadata file")?;
+    Ok(())
+}
+
+/// Stored title for a conversation, if one has been generated.
+pub fn conversation_title(data_dir: &DataDir, sha256: &str, log_filename: &str) -> Option<String> {
+    read_conversations_metadata(data_dir, sha256)
+        .remove(log_filename)
+        .and_then(|meta| meta.title)
+}
+
+pub fn set_conversation_title(
+    data_dir: &DataDir,
+    sha256: &str,
+    log_filename: &str,
+    title: &str,
+) -> Result<()> {
+    let mut conversations = read_conversations_metadata(data_dir, sha256);
+    let entry = conversations.entry(log_filename.to_string()).or_default();
+    entry.title = Some(title.to_string());
+    entry.updated_at = Some(now_fixed_offset());
+    write_conversations_metadata(data_dir, sha256, &conversations)
+}
+
+pub fn remove_conversation_metadata(
+    data_dir: &DataDir,
+    sha256: &str,
+    log_filename: &str,
+) -> Result<()> {
+    let mut conversations = read_conversations_metadata(data_dir, sha256);
+    if conversations.remove(log_filename).is_some() {
+        write_conversations_metadata(data_dir, sha256, &conversations)?;
+    }
+    Ok(())
+}
+
+fn history_json_path(data_dir: &DataDir, sha256: &str) -> Option<PathBuf> {
+    project_file_path(data_dir, sha256, "history.json")
+}
+
+/// A file in a project's metadata directory.
+pub(crate) fn project_file_path(
+    data_dir: &DataDir,
+    sha256: &str,
+    file_name: &str,
+) -> Option<PathBuf> {
+    // Project ids come from the frontend; only accept hashes so the path stays in the projects root.
+    if sha256.is_empty() || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    projects_root_dir(data_dir).map(|root| root.join(sha256).join(file_name))
+}
+
+/// History settings for a project; defaults when none have been saved.
+pub fn read_history_settings(data_dir: &DataDir, sha256: &str) -> ProjectHistorySettings {
+    history_json_path(data_dir, sha256)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_history_settings(
+    data_dir: &DataDir,
+    sha256: &str,
+    settings: &ProjectHistorySettings,
+) -> Result<()> {
+    Redactor::with_user_patterns(&settings.redaction_patterns)?;
+
+    let Some(json_path) = history_json_path(data_dir, sha256) else {
+        anyhow::bail!("Project not found");
+    };
+    if let Some(dir) = json_path.parent() {
+        fs::create_dir_all(dir).context("Failed to create project metadata directory")?;
+    }
+    let tmp_path = json_path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(settings)
+        .context("Failed to serialize project history settings")?;
+    fs::write(&tmp_path, content.as_bytes())
+        .context("Failed to write temporary project history settings file")?;
+    fs::rename(&tmp_path, &json_path).context("Failed to rename project history settings file")?;
+    Ok(())
+}
+
 fn to_view(meta: &ProjectMetadata, canonical_root: &Path, sha256: &str) -> ProjectMetadataView {
     let friendly = meta
         .friendly_name
@@ -183,10 +354,11 @@ fn to_view(meta: &ProjectMetadata, canonical_root: &Path, sha256: &str) -> Proje
 }
 
 pub fn ensure_project_metadata(
+    data_dir: &DataDir,
     sha256: &str,
     external_root_canonical: Option<&Path>,
 ) -> Result<ProjectMetadata> {
-    match read_project_metadata(sha256) {
+    match read_project_metadata(data_dir, sha256) {
         Ok(meta) => Ok(meta),
         Err(e) => {
             if let Some(ext) = external_root_canonical {
@@ -198,8 +370,8 @@ pub fn ensure_project_metadata(
                     first_used: Some(now),
                     updated_at: Some(now),
                 };
-                write_project_metadata(sha256, &meta)?;
-                eprintln!("info: created project.json for {sha256}");
+                write_project_metadata(data_dir, sha256, &meta)?;
+                info!("created project.json for {sha256}");
                 Ok(meta)
             } else {
                 Err(e)
@@ -208,8 +380,12 @@ pub fn ensure_project_metadata(
     }
 }
 
-pub fn maybe_touch_updated_at(sha256: &str, throttle: &TouchThrottle) -> Result<()> {
-    let mut meta = match read_project_metadata(sha256) {
+pub fn maybe_touch_updated_at(
+    data_dir: &DataDir,
+    sha256: &str,
+    throttle: &TouchThrottle,
+) -> Result<()> {
+    let mut meta = match read_project_metadata(data_dir, sha256) {
         Ok(m) => m,
         Err(_) => return Ok(()),
     };
@@ -227,24 +403,25 @@ pub fn maybe_touch_updated_at(sha256: &str, throttle: &TouchThrottle) -> Result<
     drop(guard);
 
     meta.updated_at = Some(now_fixed_offset());
-    write_project_metadata(sha256, &meta)?;
-    eprintln!("debug: touched updated_at for {sha256}");
+    write_project_metadata(data_dir, sha256, &meta)?;
+    debug!("touched updated_at for {sha256}");
     Ok(())
 }
 
 pub fn make_enriched_project(
+    data_dir: &DataDir,
     sha256: &str,
     external_root: Option<&Path>,
     should_create_if_missing: bool,
 ) -> EnrichedProject {
-    let meta_opt = read_project_metadata(sha256).ok();
+    let meta_opt = read_project_metadata(data_dir, sha256).ok();
 
     let display_root = if let Some(ref meta) = meta_opt {
         meta.path.clone()
     } else if let Some(er) = external_root {
         er.to_path_buf()
     } else {
-        projects_root_dir()
+        projects_root_dir(data_dir)
             .unwrap_or_else(|| PathBuf::from("."))
             .join(sha256)
     };
@@ -252,12 +429,14 @@ pub fn make_enriched_project(
     let meta = if let Some(meta) = meta_opt {
         meta
     } else if should_create_if_missing {
-        ensure_project_metadata(sha256, external_root).unwrap_or_else(|_| ProjectMetadata {
-            path: display_root.clone(),
-            sha256: Some(sha256.to_string()),
-            friendly_name: Some(derive_friendly_name_from_path(&display_root)),
-            first_used: None,
-            updated_at: None,
+        ensure_project_metadata(data_dir, sha256, external_root).unwrap_or_else(|_| {
+            ProjectMetadata {
+                path: display_root.clone(),
+                sha256: Some(sha256.to_string()),
+                friendly_name: Some(derive_friendly_name_from_path(&display_root)),
+                first_used: None,
+                updated_at: None,
+            }
         })
     } else {
         ProjectMetadata {
@@ -276,8 +455,8 @@ pub fn make_enriched_project(
     }
 }
 
-pub fn list_projects(limit: u32, offset: u32) -> Result<ProjectsResponse> {
-    let Some(root) = home_projects_root() else {
+pub fn list_projects(data_dir: &DataDir, limit: u32, offset: u32) -> Result<ProjectsResponse> {
+    let Some(root) = home_projects_root(data_dir) else {
         return Ok(ProjectsResponse {
             items: vec![],
             total: 0,
@@ -415,8 +594,25 @@ pub fn list_projects(limit: u32, offset: u32) -> Result<ProjectsResponse> {
     })
 }
 
-pub fn list_enriched_projects() -> Result<Vec<EnrichedProject>> {
-    let Some(root) = home_projects_root() else {
+pub fn list_enriched_projects(data_dir: &DataDir) -> Result<Vec<EnrichedProject>> {
+    Ok(project_ids(data_dir)?
+        .into_iter()
+        .map(|sha256| make_enriched_project(data_dir, &sha256, None, false))
+        .collect())
+}
+
+/// Root directories of all known projects.
+pub fn project_roots(data_dir: &DataDir) -> Vec<PathBuf> {
+    project_ids(data_dir)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|sha256| project_root(data_dir, sha256).ok())
+        .collect()
+}
+
+/// Ids of the projects with a metadata directory, sorted.
+fn project_ids(data_dir: &DataDir) -> Result<Vec<String>> {
+    let Some(root) = home_projects_root(data_dir) else {
         return Ok(vec![]);
     };
     if !root.exists() || !root.is_dir() {
@@ -440,24 +636,25 @@ pub fn list_enriched_projects() -> Result<Vec<EnrichedProject>> {
         }
     }
     all_ids.sort();
-
-    let mut results = Vec::new();
-    for sha256 in all_ids {
-        results.push(make_enriched_project(&sha256, None, false));
-    }
-    Ok(results)
+    Ok(all_ids)
 }
 
 pub async fn get_enriched_project(
+    data_dir: &DataDir,
     sha256: String,
     external_root_path: String,
 ) -> Result<EnrichedProject> {
     let external_root = Path::new(&external_root_path);
-    Ok(make_enriched_project(&sha256, Some(external_root), true))
+    Ok(make_enriched_project(
+        data_dir,
+        &sha256,
+        Some(external_root),
+        true,
+    ))
 }
 
-pub async fn delete_project(project_id: &str) -> Result<()> {
-    let Some(root) = home_projects_root() else {
+pub async fn delete_project(data_dir: &DataDir, project_id: &str) -> Result<()> {
+    let Some(root) = home_projects_root(data_dir) else {
         anyhow::bail!("Projects root directory not found");
     };
     let project_path = root.join(project_id);
@@ -660,7 +857,7 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", "/test/home");
 
-        let result = home_projects_root();
+        let result = home_projects_root(&DataDir::default());
         assert!(result.is_some());
         let path = result.unwrap();
         assert_eq!(path, Path::new("/test/home/.gemini-cli-desktop/projects"));
@@ -672,7 +869,7 @@ mod tests {
         env_guard.remove("HOME");
         env_guard.set("USERPROFILE", "C:\\Users\\test");
 
-        let result = home_projects_root();
+        let result = home_projects_root(&DataDir::default());
         assert!(result.is_some());
         let path = result.unwrap();
         // Build expected path using the same method as the function under test
@@ -688,7 +885,7 @@ mod tests {
         env_guard.remove("HOME");
         env_guard.remove("USERPROFILE");
 
-        let result = home_projects_root();
+        let result = home_projects_root(&DataDir::default());
         // On Windows, there might be other environment variables that provide a home directory
         // The function falls back to an empty string, so result could be Some or None
         // We just verify it doesn't panic
@@ -700,7 +897,7 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", "/test/home");
 
-        let result = projects_root_dir();
+        let result = projects_root_dir(&DataDir::default());
         assert!(result.is_some());
     }
 
@@ -709,7 +906,7 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", "/test/home");
 
-        let result = project_json_path("abcd1234");
+        let result = project_json_path(&DataDir::default(), "abcd1234");
         assert!(result.is_some());
         let path = result.unwrap();
         assert_eq!(
@@ -724,7 +921,7 @@ mod tests {
         env_guard.remove("HOME");
         env_guard.remove("USERPROFILE");
 
-        let result = project_json_path("abcd1234");
+        let result = project_json_path(&DataDir::default(), "abcd1234");
         assert!(result.is_none());
     }
 
@@ -734,7 +931,7 @@ mod tests {
         env_guard.remove("HOME");
         env_guard.remove("USERPROFILE");
 
-        let result = read_project_metadata("test");
+        let result = read_project_metadata(&DataDir::default(), "test");
         assert!(result.is_err());
         assert!(
             result
@@ -750,7 +947,7 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
-        let result = read_project_metadata("nonexistent");
+        let result = read_project_metadata(&DataDir::default(), "nonexistent");
         assert!(result.is_err());
         assert!(
             result
@@ -787,7 +984,7 @@ mod tests {
         let content = serde_json::to_string_pretty(&metadata).unwrap();
         fs::write(&json_path, content).unwrap();
 
-        let result = read_project_metadata(&valid_sha).unwrap();
+        let result = read_project_metadata(&DataDir::default(), &valid_sha).unwrap();
         assert_eq!(result.path, test_path);
         assert_eq!(result.sha256, Some(valid_sha));
         assert_eq!(result.friendly_name, Some("test-project".to_string()));
@@ -810,7 +1007,7 @@ mod tests {
         let json_path = projects_dir.join("project.json");
         fs::write(&json_path, "invalid json").unwrap();
 
-        let result = read_project_metadata(&valid_sha);
+        let result = read_project_metadata(&DataDir::default(), &valid_sha);
         assert!(result.is_err());
         let error = result.unwrap_err();
         assert!(error.to_string().contains("JSON") || error.to_string().contains("serde"));
@@ -823,7 +1020,7 @@ mod tests {
         env_guard.remove("USERPROFILE");
 
         let metadata = ProjectMetadata::default();
-        let result = write_project_metadata("test", &metadata);
+        let result = write_project_metadata(&DataDir::default(), "test", &metadata);
         assert!(result.is_err());
         assert!(
             result
@@ -847,7 +1044,7 @@ mod tests {
             updated_at: None,
         };
 
-        let result = write_project_metadata("abcd1234", &metadata);
+        let result = write_project_metadata(&DataDir::default(), "abcd1234", &metadata);
         assert!(result.is_ok());
 
         // Verify the file was created
@@ -864,6 +1061,92 @@ mod tests {
         assert_eq!(read_metadata.sha256, metadata.sha256);
     }
 
+    #[test]
+    fn test_conversation_title_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut env_guard = EnvGuard::new();
+        env_guard.set("HOME", temp_dir.path().to_str().unwrap());
+
+        assert!(conversation_title(&DataDir::default(), "abcd1234", "rpc-log-1.log").is_none());
+
+        set_conversation_title(
+            &DataDir::default(),
+            "abcd1234",
+            "rpc-log-1.log",
+            "Fix the parser",
+        )
+        .unwrap();
+        set_conversation_title(
+            &DataDir::default(),
+            "abcd1234",
+            "rpc-log-2.log",
+            "Add tests",
+        )
+        .unwrap();
+        set_conversation_title(
+            &DataDir::default(),
+            "abcd1234",
+            "rpc-log-1.log",
+            "Fix the lexer",
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversation_title(&DataDir::default(), "abcd1234", "rpc-log-1.log").as_deref(),
+            Some("Fix the lexer")
+        );
+        assert_eq!(
+            read_conversations_metadata(&DataDir::default(), "abcd1234").len(),
+            2
+        );
+
+        remove_conversation_metadata(&DataDir::default(), "abcd1234", "rpc-log-1.log").unwrap();
+        assert!(conversation_title(&DataDir::default(), "abcd1234", "rpc-log-1.log").is_none());
+        assert_eq!(
+            conversation_title(&DataDir::default(), "abcd1234", "rpc-log-2.log").as_deref(),
+            Some("Add tests")
+        );
+    }
+
+    #[test]
+    fn test_history_settings_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut env_guard = EnvGuard::new();
+        env_guard.set("HOME", temp_dir.path().to_str().unwrap());
+
+        assert_eq!(
+            read_history_settings(&DataDir::default(), "abcd1234"),
+            ProjectHistorySettings::default()
+        );
+
+        let settings = ProjectHistorySettings {
+            persist_rpc_log: false,
+            redaction_patterns: vec![r"ACME-\d+".to_string()],
+        };
+        write_history_settings(&DataDir::default(), "abcd1234", &settings).unwrap();
+        assert_eq!(
+            read_history_settings(&DataDir::default(), "abcd1234"),
+            settings
+        );
+        assert!(
+            settings
+                .redactor()
+                .redact("ACME-42")
+                .starts_with("[REDACTED:custom-1#")
+        );
+
+        let invalid = ProjectHistorySettings {
+            redaction_patterns: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(write_history_settings(&DataDir::default(), "abcd1234", &invalid).is_err());
+        assert!(write_history_settings(&DataDir::default(), "../escape", &settings).is_err());
+        assert_eq!(
+            read_history_settings(&DataDir::default(), "abcd1234"),
+            settings
+        );
+    }
+
     #[test]
     fn test_to_view() {
         let metadata = ProjectMetadata {
@@ -932,7 +1215,7 @@ mod tests {
         let content = serde_json::to_string_pretty(&metadata).unwrap();
         fs::write(&json_path, content).unwrap();
 
-        let result = ensure_project_metadata(&valid_sha, None).unwrap();
+        let result = ensure_project_metadata(&DataDir::default(), &valid_sha, None).unwrap();
         assert_eq!(result.path, PathBuf::from("/existing/path"));
         assert_eq!(result.sha256, Some(valid_sha));
     }
@@ -945,7 +1228,8 @@ mod tests {
 
         let valid_sha = "f".repeat(64); // Use 64-character hex string
         let external_root = Path::new("/new/project");
-        let result = ensure_project_metadata(&valid_sha, Some(external_root)).unwrap();
+        let result =
+            ensure_project_metadata(&DataDir::default(), &valid_sha, Some(external_root)).unwrap();
 
         assert_eq!(result.path, PathBuf::from("/new/project"));
         assert_eq!(result.sha256, Some(valid_sha));
@@ -960,7 +1244,7 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
-        let result = ensure_project_metadata("nonexistent", None);
+        let result = ensure_project_metadata(&DataDir::default(), "nonexistent", None);
         assert!(result.is_err());
         assert!(
             result
@@ -977,7 +1261,7 @@ mod tests {
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
         let throttle = TouchThrottle::new(Duration::from_millis(100));
-        let result = maybe_touch_updated_at("nonexistent", &throttle);
+        let result = maybe_touch_updated_at(&DataDir::default(), "nonexistent", &throttle);
         assert!(result.is_ok()); // Should not fail for nonexistent projects
     }
 
@@ -996,16 +1280,16 @@ mod tests {
             updated_at: None,
         };
 
-        write_project_metadata("test", &metadata).unwrap();
+        write_project_metadata(&DataDir::default(), "test", &metadata).unwrap();
 
         let throttle = TouchThrottle::new(Duration::from_secs(1));
 
         // First touch should succeed
-        let result1 = maybe_touch_updated_at("test", &throttle);
+        let result1 = maybe_touch_updated_at(&DataDir::default(), "test", &throttle);
         assert!(result1.is_ok());
 
         // Immediate second touch should be throttled (no error, just skipped)
-        let result2 = maybe_touch_updated_at("test", &throttle);
+        let result2 = maybe_touch_updated_at(&DataDir::default(), "test", &throttle);
         assert!(result2.is_ok());
     }
 
@@ -1026,9 +1310,9 @@ mod tests {
             updated_at: None,
         };
 
-        write_project_metadata(&valid_sha, &metadata).unwrap();
+        write_project_metadata(&DataDir::default(), &valid_sha, &metadata).unwrap();
 
-        let result = make_enriched_project(&valid_sha, None, false);
+        let result = make_enriched_project(&DataDir::default(), &valid_sha, None, false);
         assert_eq!(result.sha256, valid_sha);
         assert_eq!(result.root_path, test_path);
         assert_eq!(result.metadata.friendly_name, "existing-project");
@@ -1041,7 +1325,12 @@ mod tests {
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
         let external_root = Path::new("/external/root");
-        let result = make_enriched_project("newproject", Some(external_root), false);
+        let result = make_enriched_project(
+            &DataDir::default(),
+            "newproject",
+            Some(external_root),
+            false,
+        );
 
         assert_eq!(result.sha256, "newproject");
         assert_eq!(result.root_path, PathBuf::from("/external/root"));
@@ -1054,7 +1343,8 @@ mod tests {
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
         let external_root = Path::new("/new/project");
-        let result = make_enriched_project("newsha", Some(external_root), true);
+        let result =
+            make_enriched_project(&DataDir::default(), "newsha", Some(external_root), true);
 
         assert_eq!(result.sha256, "newsha");
         assert_eq!(result.root_path, PathBuf::from("/new/project"));
@@ -1067,7 +1357,7 @@ mod tests {
         env_guard.remove("HOME");
         env_guard.remove("USERPROFILE");
 
-        let result = list_projects(10, 0).unwrap();
+        let result = list_projects(&DataDir::default(), 10, 0).unwrap();
         assert_eq!(result.items.len(), 0);
         assert_eq!(result.total, 0);
         assert_eq!(result.limit, 10);
@@ -1084,7 +1374,7 @@ mod tests {
         let projects_dir = temp_dir.path().join(".gemini-cli-desktop/projects");
         fs::create_dir_all(&projects_dir).unwrap();
 
-        let result = list_projects(10, 0).unwrap();
+        let result = list_projects(&DataDir::default(), 10, 0).unwrap();
         assert_eq!(result.items.len(), 0);
         assert_eq!(result.total, 0);
     }
@@ -1111,7 +1401,7 @@ mod tests {
         let invalid_dir = projects_dir.join("invalid");
         fs::create_dir_all(&invalid_dir).unwrap();
 
-        let result = list_projects(10, 0).unwrap();
+        let result = list_projects(&DataDir::default(), 10, 0).unwrap();
         assert_eq!(result.items.len(), 1);
         assert_eq!(result.total, 1);
         assert_eq!(result.items[0].id, valid_sha);
@@ -1140,14 +1430,14 @@ mod tests {
         }
 
         // Test first page
-        let result = list_projects(2, 0).unwrap();
+        let result = list_projects(&DataDir::default(), 2, 0).unwrap();
         assert_eq!(result.items.len(), 2);
         assert_eq!(result.total, 3);
         assert_eq!(result.limit, 2);
         assert_eq!(result.offset, 0);
 
         // Test second page
-        let result = list_projects(2, 2).unwrap();
+        let result = list_projects(&DataDir::default(), 2, 2).unwrap();
         assert_eq!(result.items.len(), 1);
         assert_eq!(result.total, 3);
         assert_eq!(result.limit, 2);
@@ -1163,7 +1453,7 @@ mod tests {
         let projects_dir = temp_dir.path().join(".gemini-cli-desktop/projects");
         fs::create_dir_all(&projects_dir).unwrap();
 
-        let result = list_enriched_projects().unwrap();
+        let result = list_enriched_projects(&DataDir::default()).unwrap();
         assert_eq!(result.len(), 0);
     }
 
@@ -1183,7 +1473,7 @@ mod tests {
         let log_file = project_dir.join("rpc-log-1640995200000.log");
         fs::write(&log_file, "{}").unwrap();
 
-        let result = list_enriched_projects().unwrap();
+        let result = list_enriched_projects(&DataDir::default()).unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].sha256, sha);
     }
@@ -1194,10 +1484,13 @@ mod tests {
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
-        let result =
-            get_enriched_project("testsha256".to_string(), "/test/external/root".to_string())
-                .await
-                .unwrap();
+        let result = get_enriched_project(
+            &DataDir::default(),
+            "testsha256".to_string(),
+            "/test/external/root".to_string(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(result.sha256, "testsha256");
         assert_eq!(result.root_path, PathBuf::from("/test/external/root"));
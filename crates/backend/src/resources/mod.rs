@@ -0,0 +1,199 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Resource usage of a session's process tree (the CLI wrapper and everything
+/// it spawned). Fields are `None` where the platform does not expose them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct ProcessResources {
+    pub cpu_percent: Option<f32>,
+    pub memory_rss_bytes: Option<u64>,
+    pub child_process_count: Option<u32>,
+}
+
+/// Samples resource usage and remembers the previous CPU reading of each root
+/// process, so CPU% reflects the time between two samples.
+#[derive(Default)]
+pub struct ResourceSampler {
+    previous: HashMap<u32, (u64, Instant)>,
+}
+
+impl ResourceSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sample(&mut self, pid: u32) -> ProcessResources {
+        let Some(tree) = platform::snapshot_tree(pid) else {
+            self.previous.remove(&pid);
+            return ProcessResources::default();
+        };
+
+        let now = Instant::now();
+        let cpu_percent =
+            self.previous
+                .insert(pid, (tree.cpu_ticks, now))
+                .and_then(|(prev_ticks, prev_at)| {
+                    let elapsed = now.duration_since(prev_at).as_secs_f64();
+                    (elapsed > 0.0).then(|| {
+                        let used = tree.cpu_ticks.saturating_sub(prev_ticks) as f64
+                            / platform::CLOCK_TICKS_PER_SEC;
+                        (used / elapsed * 100.0) as f32
+                    })
+                });
+
+        ProcessResources {
+            cpu_percent,
+            memory_rss_bytes: Some(tree.rss_bytes),
+            child_process_count: Some(tree.descendants),
+        }
+    }
+
+    /// Forget samples for processes that are no longer tracked.
+    pub fn retain(&mut self, live_pids: &[u32]) {
+        self.previous.retain(|pid, _| live_pids.contains(pid));
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct TreeSnapshot {
+    cpu_ticks: u64,
+    rss_bytes: u64,
+    descendants: u32,
+}
+
+/// Parse `/proc/<pid>/stat` into (ppid, utime + stime). The command name is
+/// wrapped in parentheses and may itself contain spaces or parentheses.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(stat: &str) -> Option<(u32, u64)> {
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    let fields: Vec<&str> = after_comm.split_whitespace().collect();
+    // Fields after comm start at index 3 (state), so ppid is [1], utime [11], stime [12].
+    let ppid = fields.get(1)?.parse().ok()?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((ppid, utime + stime))
+}
+
+/// Parse the `VmRSS` line of `/proc/<pid>/status` into bytes.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{TreeSnapshot, parse_stat, parse_vm_rss};
+    use std::collections::HashMap;
+
+    /// `USER_HZ`, which is 100 on every mainstream Linux configuration.
+    pub const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+    pub fn snapshot_tree(root: u32) -> Option<TreeSnapshot> {
+        let root_stat = std::fs::read_to_string(format!("/proc/{root}/stat")).ok()?;
+        let (_, root_ticks) = parse_stat(&root_stat)?;
+
+        let mut children: HashMap<u32, Vec<(u32, u64)>> = HashMap::new();
+        for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            if let Ok(stat) = std::fs::read_to_string(entry.path().join("stat"))
+                && let Some((ppid, ticks)) = parse_stat(&stat)
+            {
+                children.entry(ppid).or_default().push((pid, ticks));
+            }
+        }
+
+        let rss = |pid: u32| {
+            std::fs::read_to_string(format!("/proc/{pid}/status"))
+                .ok()
+                .and_then(|status| parse_vm_rss(&status))
+                .unwrap_or(0)
+        };
+
+        let mut snapshot = TreeSnapshot {
+            cpu_ticks: root_ticks,
+            rss_bytes: rss(root),
+            descendants: 0,
+        };
+        let mut stack = vec![root];
+        while let Some(parent) = stack.pop() {
+            for &(pid, ticks) in children.get(&parent).into_iter().flatten() {
+                snapshot.cpu_ticks += ticks;
+                snapshot.rss_bytes += rss(pid);
+                snapshot.descendants += 1;
+                stack.push(pid);
+            }
+        }
+        Some(snapshot)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::TreeSnapshot;
+
+    pub const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+    pub fn snapshot_tree(_root: u32) -> Option<TreeSnapshot> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat_with_spaces_in_name() {
+        let stat = "1234 (node (worker) x) S 42 1234 1234 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 11 0 100 0 0";
+        assert_eq!(parse_stat(stat), Some((42, 300)));
+    }
+
+    #[test]
+    fn test_parse_stat_invalid() {
+        assert_eq!(parse_stat("garbage"), None);
+        assert_eq!(parse_stat("1 (sh) S"), None);
+    }
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tnode\nVmPeak:\t 2048 kB\nVmRSS:\t   1500 kB\nThreads:\t11\n";
+        assert_eq!(parse_vm_rss(status), Some(1500 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tnode\n"), None);
+    }
+
+    #[test]
+    fn test_sample_unknown_pid_is_empty() {
+        let mut sampler = ResourceSampler::new();
+        assert_eq!(sampler.sample(u32::MAX), ProcessResources::default());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sample_current_process() {
+        let mut sampler = ResourceSampler::new();
+        let pid = std::process::id();
+
+        let first = sampler.sample(pid);
+        assert!(first.memory_rss_bytes.unwrap() > 0);
+        assert!(first.cpu_percent.is_none());
+
+        let second = sampler.sample(pid);
+        assert!(second.cpu_percent.is_some());
+
+        sampler.retain(&[]);
+        assert!(sampler.sample(pid).cpu_percent.is_none());
+    }
+}
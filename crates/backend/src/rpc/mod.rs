@@ -1,3 +1,5 @@
+use crate::paths::DataDir;
+use crate::redact::Redactor;
 use anyhow::{Context, Result};
 use chrono::{SecondsFormat, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
@@ -49,6 +51,11 @@ pub struct JsonRpcError {
 
 pub trait RpcLogger: Send + Sync {
     fn log_rpc(&self, message: &str) -> Result<(), std::io::Error>;
+
+    /// Location of the history log this logger writes to, if any.
+    fn log_path(&self) -> Option<&std::path::Path> {
+        None
+    }
 }
 
 pub struct ProjectHasher;
@@ -64,16 +71,31 @@ impl ProjectHasher {
         let hash = format!("{:x}", hasher.finalize());
         Ok(hash)
     }
+
+    /// Hash of the canonical path, or of the path as given when it cannot be
+    /// canonicalized. This is the directory name used under `projects/`.
+    pub fn hash_path_or_raw(path: &str) -> String {
+        Self::hash_path(path).unwrap_or_else(|_| {
+            let mut hasher = Sha256::new();
+            hasher.update(path.as_bytes());
+            format!("{:x}", hasher.finalize())
+        })
+    }
 }
 
 pub struct FileRpcLogger {
     writer: Arc<Mutex<BufWriter<File>>>,
     file_path: std::path::PathBuf,
     backend_name: String,
+    redactor: Redactor,
 }
 
 impl FileRpcLogger {
-    pub fn new(working_directory: Option<&str>, backend_name: Option<&str>) -> Result<Self> {
+    pub fn new(
+        data_dir: &DataDir,
+        working_directory: Option<&str>,
+        backend_name: Option<&str>,
+    ) -> Result<Self> {
         let project_dir = working_directory.map(|s| s.to_string()).unwrap_or_else(|| {
             std::env::current_dir()
                 .unwrap_or_else(|_| std::path::PathBuf::from("."))
@@ -82,20 +104,10 @@ impl FileRpcLogger {
         });
 
         // If path doesn't exist or cannot be canonicalized, fall back to provided string to produce a stable hash
-        let project_hash = match ProjectHasher::hash_path(&project_dir) {
-            Ok(h) => h,
-            Err(_) => {
-                let mut hasher = Sha256::new();
-                hasher.update(project_dir.as_bytes());
-                format!("{:x}", hasher.finalize())
-            }
-        };
-
-        let home_dir = std::env::var("HOME")
-            .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
+        let project_hash = ProjectHasher::hash_path_or_raw(&project_dir);
 
-        let log_dir = std::path::Path::new(&home_dir)
-            .join(".gemini-cli-desktop")
+        let log_dir = data_dir
+            .path_or_local()
             .join("projects")
             .join(&project_hash);
 
@@ -103,6 +115,7 @@ impl FileRpcLogger {
 
         // Note: ensure_project_metadata will be called from projects module
         let _ = crate::projects::ensure_project_metadata(
+            data_dir,
             &project_hash,
             Some(std::path::Path::new(&project_dir)),
         );
@@ -128,9 +141,16 @@ impl FileRpcLogger {
             writer,
             file_path,
             backend_name,
+            redactor: Redactor::default(),
         })
     }
 
+    /// Replace the built-in redaction applied to every logged message.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
     pub fn cleanup_old_logs(&self) -> Result<(), std::io::Error> {
         let parent_dir = self.file_path.parent().unwrap();
         let cutoff_time = std::time::SystemTime::now()
@@ -161,6 +181,7 @@ impl FileRpcLogger {
 impl RpcLogger for FileRpcLogger {
     fn log_rpc(&self, message: &str) -> Result<(), std::io::Error> {
         let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let message = self.redactor.redact_json_line(message);
         let log_line = format!("[{timestamp}] [{}] {message}\n", self.backend_name);
 
         if let Ok(mut writer) = self.writer.lock() {
@@ -170,6 +191,10 @@ impl RpcLogger for FileRpcLogger {
 
         Ok(())
     }
+
+    fn log_path(&self) -> Option<&std::path::Path> {
+        Some(&self.file_path)
+    }
 }
 
 pub struct NoOpRpcLogger;
@@ -351,13 +376,36 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None);
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        );
         assert!(logger.is_ok());
 
         let logger = logger.unwrap();
         assert!(logger.file_path.exists());
     }
 
+    #[test]
+    fn test_rpc_logger_log_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut env_guard = EnvGuard::new();
+        env_guard.set("HOME", temp_dir.path().to_str().unwrap());
+
+        let working_dir = temp_dir.path().join("test_project");
+        fs::create_dir_all(&working_dir).unwrap();
+
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(logger.log_path(), Some(logger.file_path.as_path()));
+        assert!(NoOpRpcLogger.log_path().is_none());
+    }
+
     #[test]
     fn test_file_rpc_logger_new_without_working_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -365,7 +413,7 @@ mod tests {
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
         let _current_dir = std::env::current_dir().unwrap();
-        let logger = FileRpcLogger::new(None, None);
+        let logger = FileRpcLogger::new(&DataDir::default(), None, None);
         assert!(logger.is_ok());
     }
 
@@ -378,7 +426,12 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None).unwrap();
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
 
         let test_message = "test RPC message";
         let result = logger.log_rpc(test_message);
@@ -390,6 +443,37 @@ mod tests {
         assert!(content.contains("[2")); // Should contain timestamp
     }
 
+    #[test]
+    fn test_file_rpc_logger_redacts_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut env_guard = EnvGuard::new();
+        env_guard.set("HOME", temp_dir.path().to_str().unwrap());
+
+        let working_dir = temp_dir.path().join("test_project");
+        fs::create_dir_all(&working_dir).unwrap();
+
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap()
+        .with_redactor(Redactor::with_user_patterns(&["ACME-[0-9]{4}"]).unwrap());
+
+        let message = r#"{"jsonrpc":"2.0","id":5,"result":{"output":"GITHUB_TOKEN=ghp_abcdefghijklmnopqrstuvwx\nid ACME-1234"}}"#;
+        logger.log_rpc(message).unwrap();
+
+        let content = fs::read_to_string(&logger.file_path).unwrap();
+        assert!(!content.contains("ghp_abcdefghijklmnopqrstuvwx"));
+        assert!(!content.contains("ACME-1234"));
+        assert!(content.contains("[REDACTED:custom-1#"));
+
+        // The logged message must stay valid JSON so history parsing keeps working.
+        let json = content[content.find('{').unwrap()..].trim_end();
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["id"], 5);
+    }
+
     #[test]
     fn test_file_rpc_logger_log_multiple_messages() {
         let temp_dir = TempDir::new().unwrap();
@@ -399,7 +483,12 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None).unwrap();
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
 
         let messages = vec!["message 1", "message 2", "message 3"];
         for message in &messages {
@@ -426,7 +515,12 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None).unwrap();
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
         let log_dir = logger.file_path.parent().unwrap();
 
         // Create some old log files (simulate old files by setting modified time in the past)
@@ -459,7 +553,12 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None).unwrap();
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
 
         // Remove all files to create empty directory scenario
         let log_dir = logger.file_path.parent().unwrap();
@@ -487,7 +586,11 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        if let Ok(file_logger) = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None) {
+        if let Ok(file_logger) = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        ) {
             let file_logger: Box<dyn RpcLogger> = Box::new(file_logger);
             assert!(file_logger.log_rpc("test").is_ok());
         }
@@ -503,7 +606,11 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None);
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        );
         assert!(logger.is_ok());
     }
 
@@ -516,7 +623,11 @@ mod tests {
         let working_dir = std::env::current_dir().unwrap();
 
         // Should use "." as fallback home directory
-        let logger = FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None);
+        let logger = FileRpcLogger::new(
+            &DataDir::default(),
+            Some(working_dir.to_str().unwrap()),
+            None,
+        );
         assert!(logger.is_ok());
     }
 
@@ -562,8 +673,14 @@ mod tests {
         let working_dir = temp_dir.path().join("test_project");
         fs::create_dir_all(&working_dir).unwrap();
 
-        let logger =
-            Arc::new(FileRpcLogger::new(Some(working_dir.to_str().unwrap()), None).unwrap());
+        let logger = Arc::new(
+            FileRpcLogger::new(
+                &DataDir::default(),
+                Some(working_dir.to_str().unwrap()),
+                None,
+            )
+            .unwrap(),
+        );
         let mut handles = vec![];
 
         // Spawn multiple threads to log concurrently
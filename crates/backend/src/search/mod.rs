@@ -1,11 +1,13 @@
+use crate::paths::DataDir;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RecentChat {
     pub id: String,
     pub title: String,
@@ -17,7 +19,7 @@ pub struct RecentChat {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConversationHistoryEntry {
     pub id: String,
     pub role: String, // "user" or "assistant"
@@ -27,7 +29,7 @@ pub struct ConversationHistoryEntry {
     pub metadata: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailedConversation {
     pub chat: RecentChat,
     pub messages: Vec<ConversationHistoryEntry>,
@@ -36,14 +38,14 @@ pub struct DetailedConversation {
     pub tool_calls_count: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     pub chat: RecentChat,
     pub matches: Vec<MessageMatch>,
     pub relevance_score: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MessageMatch {
     pub content_snippet: String,
     pub line_number: u32,
@@ -55,7 +57,7 @@ pub struct MessageMatch {
     pub timestamp_iso: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct SearchFilters {
     pub date_range: Option<(String, String)>,
     pub project_hash: Option<String>,
@@ -256,13 +258,8 @@ fn count_messages_in_log(log_path: &Path) -> u32 {
     user_count + assistant_count
 }
 
-pub async fn get_recent_chats() -> Result<Vec<RecentChat>> {
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
-
-    let projects_dir = Path::new(&home)
-        .join(".gemini-cli-desktop")
-        .join("projects");
+pub async fn get_recent_chats(data_dir: &DataDir) -> Result<Vec<RecentChat>> {
+    let projects_dir = data_dir.path_or_local().join("projects");
 
     let mut all_chats = Vec::new();
 
@@ -300,7 +297,12 @@ pub async fn get_recent_chats() -> Result<Vec<RecentChat>> {
 
                             all_chats.push(RecentChat {
                                 id: format!("{project_hash}/{filename}"),
-                                title: enhanced_title,
+                                title: crate::projects::conversation_title(
+                                    data_dir,
+                                    &project_hash,
+                                    &filename,
+                                )
+                                .unwrap_or(enhanced_title),
                                 started_at_iso: datetime.to_rfc3339(),
                                 message_count,
                                 summary,
@@ -322,6 +324,7 @@ pub async fn get_recent_chats() -> Result<Vec<RecentChat>> {
 }
 
 pub async fn search_chats(
+    data_dir: &DataDir,
     query: String,
     filters: Option<SearchFilters>,
 ) -> Result<Vec<SearchResult>> {
@@ -330,12 +333,7 @@ pub async fn search_chats(
         return Ok(vec![]);
     }
 
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
-
-    let projects_dir = Path::new(&home)
-        .join(".gemini-cli-desktop")
-        .join("projects");
+    let projects_dir = data_dir.path_or_local().join("projects");
     let mut results = Vec::new();
 
     let query_lower = query.to_lowercase();
@@ -644,7 +642,12 @@ pub async fn search_chats(
                             }
 
                             if !matches.is_empty() {
-                                let title = generate_title_from_messages(&log_path);
+                                let title = crate::projects::conversation_title(
+                                    data_dir,
+                                    &project_hash,
+                                    &filename,
+                                )
+                                .unwrap_or_else(|| generate_title_from_messages(&log_path));
                                 let message_count = count_messages_in_log(&log_path);
 
                                 let datetime = DateTime::<Local>::from(
@@ -682,14 +685,11 @@ pub async fn search_chats(
     Ok(results)
 }
 
-pub async fn get_project_discussions(project_id: &str) -> Result<Vec<RecentChat>> {
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
-
-    let project_dir = Path::new(&home)
-        .join(".gemini-cli-desktop")
-        .join("projects")
-        .join(project_id);
+pub async fn get_project_discussions(
+    data_dir: &DataDir,
+    project_id: &str,
+) -> Result<Vec<RecentChat>> {
+    let project_dir = data_dir.path_or_local().join("projects").join(project_id);
 
     let mut chats = Vec::new();
 
@@ -715,7 +715,8 @@ pub async fn get_project_discussions(project_id: &str) -> Result<Vec<RecentChat>
 
                 chats.push(RecentChat {
                     id: format!("{project_id}/{filename}"),
-                    title: enhanced_title,
+                    title: crate::projects::conversation_title(data_dir, project_id, &filename)
+                        .unwrap_or(enhanced_title),
                     started_at_iso: datetime.to_rfc3339(),
                     message_count,
                     summary,
@@ -731,7 +732,10 @@ pub async fn get_project_discussions(project_id: &str) -> Result<Vec<RecentChat>
     Ok(chats)
 }
 
-pub async fn get_detailed_conversation(chat_id: &str) -> Result<DetailedConversation> {
+pub async fn get_detailed_conversation(
+    data_dir: &DataDir,
+    chat_id: &str,
+) -> Result<DetailedConversation> {
     let parts: Vec<&str> = chat_id.split('/').collect();
     if parts.len() != 2 {
         anyhow::bail!("Invalid chat ID format");
@@ -740,11 +744,8 @@ pub async fn get_detailed_conversation(chat_id: &str) -> Result<DetailedConversa
     let project_hash = parts[0];
     let filename = parts[1];
 
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
-
-    let log_path = Path::new(&home)
-        .join(".gemini-cli-desktop")
+    let log_path = data_dir
+        .path_or_local()
         .join("projects")
         .join(project_hash)
         .join(filename);
@@ -763,6 +764,8 @@ pub async fn get_detailed_conversation(chat_id: &str) -> Result<DetailedConversa
 
     let (title, summary, tags, tool_calls_count, last_activity) =
         generate_enhanced_chat_info(&log_path);
+    let title =
+        crate::projects::conversation_title(data_dir, project_hash, filename).unwrap_or(title);
     let message_count = count_messages_in_log(&log_path);
 
     let chat = RecentChat {
@@ -1027,8 +1030,12 @@ pub async fn get_detailed_conversation(chat_id: &str) -> Result<DetailedConversa
     })
 }
 
-pub async fn export_conversation_history(chat_id: &str, format: &str) -> Result<String> {
-    let detailed = get_detailed_conversation(chat_id).await?;
+pub async fn export_conversation_history(
+    data_dir: &DataDir,
+    chat_id: &str,
+    format: &str,
+) -> Result<String> {
+    let detailed = get_detailed_conversation(data_dir, chat_id).await?;
 
     match format.to_lowercase().as_str() {
         "json" => serde_json::to_string_pretty(&detailed)
@@ -1071,7 +1078,7 @@ pub async fn export_conversation_history(chat_id: &str, format: &str) -> Result<
     }
 }
 
-pub async fn delete_conversation(chat_id: &str) -> Result<()> {
+pub async fn delete_conversation(data_dir: &DataDir, chat_id: &str) -> Result<()> {
     let parts: Vec<&str> = chat_id.split('/').collect();
     if parts.len() != 2 {
         anyhow::bail!("Invalid chat ID format");
@@ -1080,11 +1087,8 @@ pub async fn delete_conversation(chat_id: &str) -> Result<()> {
     let project_hash = parts[0];
     let filename = parts[1];
 
-    let home = std::env::var("HOME")
-        .unwrap_or_else(|_| std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string()));
-
-    let log_path = Path::new(&home)
-        .join(".gemini-cli-desktop")
+    let log_path = data_dir
+        .path_or_local()
         .join("projects")
         .join(project_hash)
         .join(filename);
@@ -1095,7 +1099,9 @@ pub async fn delete_conversation(chat_id: &str) -> Result<()> {
     }
 
     std::fs::remove_file(&log_path)
-        .with_context(|| format!("Failed to delete chat log file: {:?}", log_path))
+        .with_context(|| format!("Failed to delete chat log file: {:?}", log_path))?;
+    let _ = crate::projects::remove_conversation_metadata(data_dir, project_hash, filename);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1311,7 +1317,7 @@ mod tests {
         env_guard.set("HOME", ".");
 
         // Should not fail, but may return empty results if no projects directory exists
-        let result = get_recent_chats().await;
+        let result = get_recent_chats(&DataDir::default()).await;
         assert!(result.is_ok());
     }
 
@@ -1324,7 +1330,7 @@ mod tests {
         // Create projects directory but leave it empty
         let _projects_dir = test_dir_manager.create_projects_structure().unwrap();
 
-        let result = get_recent_chats().await.unwrap();
+        let result = get_recent_chats(&DataDir::default()).await.unwrap();
         assert_eq!(result.len(), 0);
     }
 
@@ -1344,13 +1350,52 @@ mod tests {
             )
             .unwrap();
 
-        let result = get_recent_chats().await.unwrap();
+        let result = get_recent_chats(&DataDir::default()).await.unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].title, "Test message");
         assert_eq!(result[0].message_count, 1);
         assert!(result[0].id.contains(&valid_project_hash));
     }
 
+    #[tokio::test]
+    async fn test_stored_title_overrides_first_prompt() {
+        let test_dir_manager = TestDirManager::new().unwrap();
+        let mut env_guard = EnvGuard::new();
+        env_guard.set("HOME", test_dir_manager.path().to_string_lossy());
+
+        let valid_project_hash = "d".repeat(64);
+        test_dir_manager
+            .create_log_file(
+                &valid_project_hash,
+                1640995200000,
+                r#"{"method":"session/prompt","params":{"prompt":[{"text":"Test message"}]}}"#,
+            )
+            .unwrap();
+        crate::projects::set_conversation_title(
+            &DataDir::default(),
+            &valid_project_hash,
+            "rpc-log-1640995200000.log",
+            "Generated Title",
+        )
+        .unwrap();
+
+        let recent = get_recent_chats(&DataDir::default()).await.unwrap();
+        assert_eq!(recent[0].title, "Generated Title");
+
+        let detailed = get_detailed_conversation(&DataDir::default(), &recent[0].id)
+            .await
+            .unwrap();
+        assert_eq!(detailed.chat.title, "Generated Title");
+
+        delete_conversation(&DataDir::default(), &recent[0].id)
+            .await
+            .unwrap();
+        assert!(
+            crate::projects::read_conversations_metadata(&DataDir::default(), &valid_project_hash)
+                .is_empty()
+        );
+    }
+
     #[tokio::test]
     async fn test_get_recent_chats_sorts_by_date() {
         let test_dir_manager = TestDirManager::new().unwrap();
@@ -1368,7 +1413,7 @@ mod tests {
             .create_log_file(&valid_project_hash, 1640995200000, content)
             .unwrap();
 
-        let result = get_recent_chats().await.unwrap();
+        let result = get_recent_chats(&DataDir::default()).await.unwrap();
         assert_eq!(result.len(), 2);
         // Newer chat should be first
         assert!(result[0].id.contains("1640995200000"));
@@ -1392,7 +1437,7 @@ mod tests {
                 .unwrap();
         }
 
-        let result = get_recent_chats().await.unwrap();
+        let result = get_recent_chats(&DataDir::default()).await.unwrap();
         assert_eq!(result.len(), 20); // Should be limited to 20
     }
 
@@ -1409,7 +1454,9 @@ mod tests {
             .create_log_file(&valid_project_hash, 1640995200000, content)
             .unwrap();
 
-        let result = search_chats("".to_string(), None).await.unwrap();
+        let result = search_chats(&DataDir::default(), "".to_string(), None)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 0); // Empty query should return no results
     }
 
@@ -1428,7 +1475,9 @@ Another line with different content"#;
             .create_log_file(&valid_project_hash, 1640995200000, content)
             .unwrap();
 
-        let result = search_chats("search term".to_string(), None).await.unwrap();
+        let result = search_chats(&DataDir::default(), "search term".to_string(), None)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].matches.len(), 1);
         assert!(result[0].matches[0].content_snippet.contains("search term"));
@@ -1449,7 +1498,9 @@ Another line with different content"#;
             .create_log_file(&valid_project_hash, 1640995200000, content)
             .unwrap();
 
-        let result = search_chats("search term".to_string(), None).await.unwrap();
+        let result = search_chats(&DataDir::default(), "search term".to_string(), None)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].matches.len(), 1);
     }
@@ -1480,9 +1531,13 @@ Another line with different content"#;
             ..Default::default()
         };
 
-        let result = search_chats("search term".to_string(), Some(filters))
-            .await
-            .unwrap();
+        let result = search_chats(
+            &DataDir::default(),
+            "search term".to_string(),
+            Some(filters),
+        )
+        .await
+        .unwrap();
         assert_eq!(result.len(), 1);
         assert!(result[0].chat.id.contains(&project1_hash));
     }
@@ -1512,9 +1567,13 @@ Another line with different content"#;
             ..Default::default()
         };
 
-        let result = search_chats("search term".to_string(), Some(filters))
-            .await
-            .unwrap();
+        let result = search_chats(
+            &DataDir::default(),
+            "search term".to_string(),
+            Some(filters),
+        )
+        .await
+        .unwrap();
         assert_eq!(result.len(), 2);
     }
 
@@ -1539,7 +1598,9 @@ Another line with different content"#;
         let content2 = "This contains match\nAnother line with match";
         fs::write(&log2, content2).unwrap();
 
-        let result = search_chats("match".to_string(), None).await.unwrap();
+        let result = search_chats(&DataDir::default(), "match".to_string(), None)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 2);
         // Result with 2 matches should be first (higher relevance score)
         assert_eq!(result[0].matches.len(), 2);
@@ -1562,7 +1623,9 @@ Another line with different content"#;
         let long_line = format!("{}search term{}", "a".repeat(100), "b".repeat(150));
         fs::write(&log_file, &long_line).unwrap();
 
-        let result = search_chats("search term".to_string(), None).await.unwrap();
+        let result = search_chats(&DataDir::default(), "search term".to_string(), None)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].matches.len(), 1);
         assert!(result[0].matches[0].content_snippet.ends_with("..."));
@@ -1575,7 +1638,9 @@ Another line with different content"#;
         let mut env_guard = EnvGuard::new();
         env_guard.set("HOME", temp_dir.path().to_str().unwrap());
 
-        let result = get_project_discussions("nonexistent").await.unwrap();
+        let result = get_project_discussions(&DataDir::default(), "nonexistent")
+            .await
+            .unwrap();
         assert_eq!(result.len(), 0);
     }
 
@@ -1598,7 +1663,9 @@ Another line with different content"#;
         fs::write(&log1, content).unwrap();
         fs::write(&log2, content).unwrap();
 
-        let result = get_project_discussions(&valid_project_hash).await.unwrap();
+        let result = get_project_discussions(&DataDir::default(), &valid_project_hash)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 2);
         // Should be sorted by date descending
         assert!(result[0].id.contains("1640995200000"));
@@ -1627,7 +1694,9 @@ Another line with different content"#;
         let invalid_log = project_dir.join("rpc-log-invalid.log");
         fs::write(&invalid_log, "invalid").unwrap();
 
-        let result = get_project_discussions(&valid_project_hash).await.unwrap();
+        let result = get_project_discussions(&DataDir::default(), &valid_project_hash)
+            .await
+            .unwrap();
         assert_eq!(result.len(), 1); // Only the valid log should be included
     }
 }
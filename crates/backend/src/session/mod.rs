@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process::Stdio;
@@ -6,6 +7,7 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 use tokio::sync::mpsc;
 use tokio::time::{Duration, sleep};
+use tracing::{debug, error, info, trace, warn};
 
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
@@ -13,165 +15,145 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
-/// RAII guard that automatically clears environment variables when dropped.
-/// This ensures credentials don't persist in the process environment after a session ends.
-#[derive(Debug)]
-struct EnvVarGuard {
-    var_name: String,
+/// Credentials for a session's CLI process. They are only set on the child
+/// process, never on this one, so sessions running side by side (for
+/// different users, too) can't pick up each other's keys.
+#[derive(Debug, Default)]
+struct SessionEnvironment {
+    vars: Vec<(String, String)>,
 }
 
-impl EnvVarGuard {
-    fn new(var_name: impl Into<String>, value: impl AsRef<str>) -> Self {
-        let var_name = var_name.into();
-        // SAFETY: We're setting an environment variable that will be read by child processes.
-        // This is safe because:
-        // 1. We're the only code setting this specific variable
-        // 2. The child process inherits but doesn't modify parent env
-        // 3. The EnvVarGuard ensures cleanup on drop
-        // 4. Multiple sessions use different variable names based on provider
-        unsafe {
-            std::env::set_var(&var_name, value.as_ref());
+impl SessionEnvironment {
+    fn set(&mut self, var_name: &str, value: impl AsRef<str>) {
+        if var_name.ends_with("_API_KEY") {
+            redact::register_secret(value.as_ref());
         }
-        Self { var_name }
+        self.vars
+            .push((var_name.to_string(), value.as_ref().to_string()));
     }
-}
-
-impl Drop for EnvVarGuard {
-    fn drop(&mut self) {
-        // SAFETY: Removing the variable we previously set. Safe for same reasons as setting.
-        unsafe {
-            std::env::remove_var(&self.var_name);
-        }
-        println!(
-            "🔒 [CLEANUP] Cleared environment variable: {}",
-            self.var_name
-        );
-    }
-}
-
-/// Manages environment variables for a session with automatic cleanup
-#[derive(Debug)]
-pub(crate) struct SessionEnvironment {
-    _guards: Vec<EnvVarGuard>,
-}
 
-impl SessionEnvironment {
     fn setup_llxprt(config: &LLxprtConfig) -> Result<Self> {
-        let mut guards = Vec::new();
+        let mut environment = Self::default();
 
-        println!(
-            "🔧 [HANDSHAKE] Setting up LLxprt Code environment for provider: {}",
+        debug!(
+            "[HANDSHAKE] Setting up LLxprt Code environment for provider: {}",
             config.provider
         );
-        println!("🔧 [HANDSHAKE] Using API key (security delegated to CLI)");
+        debug!(
+            "[HANDSHAKE] Using API key {} (security delegated to CLI)",
+            mask_api_key(&config.api_key)
+        );
 
         match config.provider.as_str() {
             "anthropic" => {
-                guards.push(EnvVarGuard::new("ANTHROPIC_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set ANTHROPIC_API_KEY");
+                environment.set("ANTHROPIC_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set ANTHROPIC_API_KEY");
             }
             "openai" | "openrouter" => {
-                guards.push(EnvVarGuard::new("OPENAI_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set OPENAI_API_KEY");
+                environment.set("OPENAI_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set OPENAI_API_KEY");
 
                 if let Some(url) = &config.base_url
                     && !url.trim().is_empty()
                 {
-                    guards.push(EnvVarGuard::new("OPENAI_BASE_URL", url));
-                    println!("🔧 [HANDSHAKE] Set OPENAI_BASE_URL");
+                    environment.set("OPENAI_BASE_URL", url);
+                    debug!("[HANDSHAKE] Set OPENAI_BASE_URL");
                 }
             }
             "gemini" | "google" => {
-                guards.push(EnvVarGuard::new("GEMINI_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set GEMINI_API_KEY");
+                environment.set("GEMINI_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set GEMINI_API_KEY");
             }
             "qwen" => {
-                guards.push(EnvVarGuard::new("QWEN_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set QWEN_API_KEY");
+                environment.set("QWEN_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set QWEN_API_KEY");
             }
             "groq" => {
-                guards.push(EnvVarGuard::new("GROQ_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set GROQ_API_KEY");
+                environment.set("GROQ_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set GROQ_API_KEY");
             }
             "together" => {
-                guards.push(EnvVarGuard::new("TOGETHER_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set TOGETHER_API_KEY");
+                environment.set("TOGETHER_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set TOGETHER_API_KEY");
             }
             "xai" => {
-                guards.push(EnvVarGuard::new("X_API_KEY", &config.api_key));
-                println!("🔧 [HANDSHAKE] Set X_API_KEY");
+                environment.set("X_API_KEY", &config.api_key);
+                debug!("[HANDSHAKE] Set X_API_KEY");
             }
             other => {
                 // For custom providers, use OPENAI_API_KEY and OPENAI_BASE_URL
-                guards.push(EnvVarGuard::new("OPENAI_API_KEY", &config.api_key));
-                println!(
-                    "🔧 [HANDSHAKE] Set OPENAI_API_KEY for custom provider '{}'",
+                environment.set("OPENAI_API_KEY", &config.api_key);
+                debug!(
+                    "[HANDSHAKE] Set OPENAI_API_KEY for custom provider '{}'",
                     other
                 );
 
                 if let Some(url) = &config.base_url
                     && !url.trim().is_empty()
                 {
-                    guards.push(EnvVarGuard::new("OPENAI_BASE_URL", url));
-                    println!("🔧 [HANDSHAKE] Set OPENAI_BASE_URL");
+                    environment.set("OPENAI_BASE_URL", url);
+                    debug!("[HANDSHAKE] Set OPENAI_BASE_URL");
                 }
             }
         }
 
-        Ok(Self { _guards: guards })
+        Ok(environment)
     }
 
     fn setup_qwen(config: &QwenConfig) -> Result<Self> {
-        let mut guards = Vec::new();
+        let mut environment = Self::default();
 
-        println!("🔧 [HANDSHAKE] Setting up Qwen Code environment");
-        println!("🔧 [HANDSHAKE] Using API key (security delegated to CLI)");
+        debug!("[HANDSHAKE] Setting up Qwen Code environment");
+        debug!(
+            "[HANDSHAKE] Using API key {} (security delegated to CLI)",
+            mask_api_key(&config.api_key)
+        );
 
-        guards.push(EnvVarGuard::new("OPENAI_API_KEY", &config.api_key));
-        guards.push(EnvVarGuard::new("OPENAI_BASE_URL", &config.base_url));
-        guards.push(EnvVarGuard::new("OPENAI_MODEL", &config.model));
+        environment.set("OPENAI_API_KEY", &config.api_key);
+        environment.set("OPENAI_BASE_URL", &config.base_url);
+        environment.set("OPENAI_MODEL", &config.model);
 
-        println!("🔧 [HANDSHAKE] Set OPENAI_BASE_URL");
-        println!("🔧 [HANDSHAKE] Set OPENAI_MODEL: {}", config.model);
+        debug!("[HANDSHAKE] Set OPENAI_BASE_URL");
+        debug!("[HANDSHAKE] Set OPENAI_MODEL: {}", config.model);
 
-        Ok(Self { _guards: guards })
+        Ok(environment)
     }
 
     fn setup_gemini(auth: &GeminiAuthConfig) -> Result<Self> {
-        let mut guards = Vec::new();
+        let mut environment = Self::default();
 
         match auth.method.as_str() {
             "gemini-api-key" => {
                 if let Some(api_key) = &auth.api_key {
-                    guards.push(EnvVarGuard::new("GEMINI_API_KEY", api_key));
-                    println!("🔧 [HANDSHAKE] Set GEMINI_API_KEY");
+                    environment.set("GEMINI_API_KEY", api_key);
+                    debug!("[HANDSHAKE] Set GEMINI_API_KEY");
                 } else {
-                    println!("⚠️ [HANDSHAKE] No API key provided for gemini-api-key auth method");
+                    warn!("[HANDSHAKE] No API key provided for gemini-api-key auth method");
                 }
             }
             "vertex-ai" => {
                 if let Some(project) = &auth.vertex_project {
-                    guards.push(EnvVarGuard::new("GOOGLE_CLOUD_PROJECT", project));
-                    println!("🔧 [HANDSHAKE] Set GOOGLE_CLOUD_PROJECT: {}", project);
+                    environment.set("GOOGLE_CLOUD_PROJECT", project);
+                    debug!("[HANDSHAKE] Set GOOGLE_CLOUD_PROJECT: {}", project);
                 }
                 if let Some(location) = &auth.vertex_location {
-                    guards.push(EnvVarGuard::new("GOOGLE_CLOUD_LOCATION", location));
-                    println!("🔧 [HANDSHAKE] Set GOOGLE_CLOUD_LOCATION: {}", location);
+                    environment.set("GOOGLE_CLOUD_LOCATION", location);
+                    debug!("[HANDSHAKE] Set GOOGLE_CLOUD_LOCATION: {}", location);
                 }
             }
             _ => {
-                println!(
-                    "🔧 [HANDSHAKE] Using auth method: {} (no env vars needed)",
+                debug!(
+                    "[HANDSHAKE] Using auth method: {} (no env vars needed)",
                     auth.method
                 );
             }
         }
 
-        Ok(Self { _guards: guards })
+        Ok(environment)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct QwenConfig {
     pub api_key: String,
     pub base_url: String,
@@ -179,7 +161,7 @@ pub struct QwenConfig {
     pub yolo: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeminiAuthConfig {
     pub method: String, // "oauth-personal", "gemini-api-key", "vertex-ai", or "cloud-shell"
     pub api_key: Option<String>,
@@ -188,7 +170,7 @@ pub struct GeminiAuthConfig {
     pub yolo: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LLxprtConfig {
     pub provider: String, // "openai", "anthropic", "gemini", "qwen", "openrouter", etc.
     pub api_key: String,
@@ -199,16 +181,86 @@ pub struct LLxprtConfig {
 use crate::acp::{
     AuthenticateParams, ClientCapabilities, ContentBlock, FileSystemCapabilities, InitializeParams,
     InitializeResult, SessionNewParams, SessionNewResult, SessionPromptResult,
-    SessionRequestPermissionParams, SessionUpdate, SessionUpdateParams,
+    SessionRequestPermissionParams, SessionUpdate, SessionUpdateParams, ToolCallStatus,
 };
 use crate::cli::StreamAssistantMessageChunkParams;
 use crate::events::{
-    CliIoPayload, CliIoType, EventEmitter, GeminiOutputPayload, GeminiThoughtPayload,
-    InternalEvent, SessionProgressPayload, SessionProgressStage,
+    AppEvent, ChunkCoalescer, CliIoPayload, CliIoType, EventEmitter, GeminiOutputPayload,
+    GeminiThoughtPayload, InternalEvent, PermissionRequestPayload, SessionProgressPayload,
+    SessionProgressStage,
+};
+use crate::hooks::SessionHooks;
+use crate::paths::DataDir;
+use crate::projects;
+use crate::redact::{self, mask_api_key};
+use crate::resources::{ProcessResources, ResourceSampler};
+use crate::rpc::{
+    FileRpcLogger, JsonRpcRequest, JsonRpcResponse, NoOpRpcLogger, ProjectHasher, RpcLogger,
 };
-use crate::rpc::{FileRpcLogger, JsonRpcRequest, JsonRpcResponse, NoOpRpcLogger, RpcLogger};
 use anyhow::{Context, Result};
 
+/// Backend selection and credentials a session was started with, kept so helper
+/// sessions (such as title generation) can launch the same CLI.
+#[derive(Debug, Clone)]
+pub struct SessionLaunchConfig {
+    pub model: String,
+    pub backend_config: Option<QwenConfig>,
+    pub gemini_auth: Option<GeminiAuthConfig>,
+    pub llxprt_config: Option<LLxprtConfig>,
+    /// Set on the CLI process on top of the app's environment.
+    pub env: HashMap<String, String>,
+}
+
+impl SessionLaunchConfig {
+    /// The CLI this config launches, as named in `ProcessStatus::backend_type`.
+    pub fn backend_type(&self) -> &'static str {
+        if self.llxprt_config.is_some() {
+            "llxprt"
+        } else if self.backend_config.is_some() {
+            "qwen"
+        } else {
+            "gemini"
+        }
+    }
+}
+
+/// What a session is doing within the current prompt turn.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnState {
+    #[default]
+    Idle,
+    /// A prompt was sent and the agent has not produced output yet.
+    Prompting,
+    /// The agent is streaming message or thought chunks.
+    Streaming,
+    RunningTool,
+    AwaitingPermission,
+}
+
+/// Turn state plus the time (unix seconds) of the last traffic in either direction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionActivity {
+    pub turn_state: TurnState,
+    pub last_activity: u64,
+}
+
+impl SessionActivity {
+    fn record(&mut self, state: Option<TurnState>) {
+        if let Some(state) = state {
+            self.turn_state = state;
+        }
+        self.last_activity = unix_now_secs();
+    }
+}
+
+fn unix_now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 pub struct PersistentSession {
     pub conversation_id: String,
     pub acp_session_id: Option<String>,
@@ -216,22 +268,32 @@ pub struct PersistentSession {
     pub created_at: u64,
     pub is_alive: bool,
     pub stdin: Option<ChildStdin>,
-    pub message_sender: Option<mpsc::UnboundedSender<String>>,
+    pub message_sender: Option<mpsc::Sender<String>>,
     pub rpc_logger: Arc<dyn RpcLogger>,
     pub child: Option<Child>,
     pub working_directory: String,
     pub backend_type: String,
-    /// Environment variable guards that automatically clean up on drop
-    pub(crate) _environment: Option<SessionEnvironment>,
+    pub launch_config: Option<SessionLaunchConfig>,
+    pub activity: SessionActivity,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Status of one session process, as listed by `get_process_statuses` and
+/// pushed in `process-status-changed` events.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ProcessStatus {
     pub conversation_id: String,
     pub pid: Option<u32>,
     pub created_at: u64,
     pub is_alive: bool,
     pub backend_type: String,
+    #[serde(default)]
+    pub uptime_secs: u64,
+    #[serde(flatten, default)]
+    pub resources: ProcessResources,
+    #[serde(default)]
+    pub turn_state: TurnState,
+    #[serde(default)]
+    pub last_activity: u64,
 }
 
 impl From<&PersistentSession> for ProcessStatus {
@@ -242,38 +304,51 @@ impl From<&PersistentSession> for ProcessStatus {
             created_at: session.created_at,
             is_alive: session.is_alive,
             backend_type: session.backend_type.clone(),
+            uptime_secs: if session.is_alive {
+                unix_now_secs().saturating_sub(session.created_at)
+            } else {
+                0
+            },
+            resources: ProcessResources::default(),
+            turn_state: session.activity.turn_state,
+            last_activity: session.activity.last_activity,
         }
     }
 }
 
 pub type ProcessMap = Arc<Mutex<HashMap<String, PersistentSession>>>;
 
+/// How often [`StatusMonitor`] pushes `process-status-changed` by default.
+pub const STATUS_MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct SessionManager {
     processes: ProcessMap,
+    resource_sampler: Arc<Mutex<ResourceSampler>>,
 }
 
 impl SessionManager {
     pub fn new() -> Self {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
+            resource_sampler: Arc::new(Mutex::new(ResourceSampler::new())),
         }
     }
 
-    pub fn get_process_statuses(&self) -> Result<Vec<ProcessStatus>> {
-        let processes = self
-            .processes
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Failed to lock processes mutex"))?;
+    /// Snapshot every session with live resource usage, without logging.
+    pub fn collect_process_statuses(&self) -> Result<Vec<ProcessStatus>> {
+        collect_statuses(&self.processes, &self.resource_sampler)
+    }
 
-        let statuses: Vec<ProcessStatus> = processes.values().map(ProcessStatus::from).collect();
+    pub fn get_process_statuses(&self) -> Result<Vec<ProcessStatus>> {
+        let statuses = self.collect_process_statuses()?;
 
-        println!(
-            "📊 [STATUS-CHECK] Current process statuses ({} sessions):",
+        debug!(
+            "[STATUS-CHECK] Current process statuses ({} sessions):",
             statuses.len()
         );
         for status in &statuses {
-            println!(
-                "📊 [STATUS-CHECK]   - {}: {} {} (PID: {:?}, created: {})",
+            debug!(
+                "[STATUS-CHECK]   - {}: {} {} (PID: {:?}, created: {})",
                 status.conversation_id,
                 if status.is_alive {
                     "ACTIVE"
@@ -357,6 +432,82 @@ impl SessionManager {
     pub(crate) fn get_processes(&self) -> &ProcessMap {
         &self.processes
     }
+
+    /// Periodically emits `process-status-changed` while sessions are alive.
+    /// The returned monitor does not borrow the manager, so it can be spawned
+    /// on whichever runtime the frontend uses.
+    pub fn status_monitor<E: EventEmitter>(
+        &self,
+        emitter: E,
+        interval: Duration,
+    ) -> StatusMonitor<E> {
+        StatusMonitor {
+            processes: self.processes.clone(),
+            resource_sampler: self.resource_sampler.clone(),
+            emitter,
+            interval,
+        }
+    }
+}
+
+fn collect_statuses(
+    processes: &ProcessMap,
+    resource_sampler: &Mutex<ResourceSampler>,
+) -> Result<Vec<ProcessStatus>> {
+    let mut statuses: Vec<ProcessStatus> = {
+        let processes = processes
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock processes mutex"))?;
+        processes.values().map(ProcessStatus::from).collect()
+    };
+
+    let mut sampler = resource_sampler
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to lock resource sampler"))?;
+    let mut live_pids = Vec::new();
+    for status in &mut statuses {
+        if let (true, Some(pid)) = (status.is_alive, status.pid) {
+            status.resources = sampler.sample(pid);
+            live_pids.push(pid);
+        }
+    }
+    sampler.retain(&live_pids);
+
+    Ok(statuses)
+}
+
+/// Background task pushing session statuses (resources and turn state) to the
+/// frontend. See [`SessionManager::status_monitor`].
+pub struct StatusMonitor<E: EventEmitter> {
+    processes: ProcessMap,
+    resource_sampler: Arc<Mutex<ResourceSampler>>,
+    emitter: E,
+    interval: Duration,
+}
+
+impl<E: EventEmitter> StatusMonitor<E> {
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut had_alive_sessions = false;
+
+        loop {
+            ticker.tick().await;
+            let Ok(statuses) = collect_statuses(&self.processes, &self.resource_sampler) else {
+                continue;
+            };
+
+            // Keep quiet while nothing is running, but send one final update
+            // after the last session goes away so the UI doesn't show stale data.
+            let has_alive_sessions = statuses.iter().any(|s| s.is_alive);
+            if has_alive_sessions || had_alive_sessions {
+                let _ = self
+                    .emitter
+                    .emit(AppEvent::ProcessStatusChanged { payload: statuses });
+            }
+            had_alive_sessions = has_alive_sessions;
+        }
+    }
 }
 
 impl Default for SessionManager {
@@ -376,7 +527,7 @@ async fn send_jsonrpc_request<E: EventEmitter>(
 ) -> Result<Option<JsonRpcResponse>> {
     let request_json = serde_json::to_string(request).context("Failed to serialize request")?;
 
-    println!("🔍 RAW INPUT TO GEMINI CLI: {request_json}");
+    trace!("RAW INPUT TO GEMINI CLI: {request_json}");
     let _ = rpc_logger.log_rpc(&request_json);
 
     // Send request
@@ -390,40 +541,40 @@ async fn send_jsonrpc_request<E: EventEmitter>(
         .context("Failed to write newline")?;
     stdin.flush().await.context("Failed to flush")?;
 
-    let _ = emitter.emit(
-        &format!("cli-io-{session_id}"),
-        CliIoPayload {
+    let _ = emitter.emit(AppEvent::CliIo {
+        session_id: session_id.to_string(),
+        payload: CliIoPayload {
             io_type: CliIoType::Input,
             data: request_json,
         },
-    );
+    });
 
     // Read response - keep reading lines until we get valid JSON
-    println!("⏳ Waiting for response from CLI...");
+    trace!("Waiting for response from CLI...");
     let mut line = String::new();
     let trimmed_line = loop {
         line.clear();
         if let Err(e) = reader.read_line(&mut line).await {
             anyhow::bail!("Failed to read response: {e}");
         }
-        println!("Read line from CLI: '{}'", line.trim());
+        trace!("Read line from CLI: '{}'", line.trim());
 
         let trimmed = line.trim();
-        println!("🔍 RAW OUTPUT FROM GEMINI CLI: {trimmed}");
+        trace!("RAW OUTPUT FROM GEMINI CLI: {trimmed}");
 
         let _ = rpc_logger.log_rpc(trimmed);
 
-        let _ = emitter.emit(
-            &format!("cli-io-{session_id}"),
-            CliIoPayload {
+        let _ = emitter.emit(AppEvent::CliIo {
+            session_id: session_id.to_string(),
+            payload: CliIoPayload {
                 io_type: CliIoType::Output,
                 data: trimmed.to_string(),
             },
-        );
+        });
 
         // Skip non-JSON lines like "Data collection is disabled."
         if trimmed.is_empty() || (!trimmed.starts_with('{') && !trimmed.starts_with('[')) {
-            println!("🔍 Skipping non-JSON line: {trimmed}");
+            trace!("Skipping non-JSON line: {trimmed}");
             continue;
         }
 
@@ -432,7 +583,7 @@ async fn send_jsonrpc_request<E: EventEmitter>(
             // Valid JSON found
             break trimmed.to_string();
         } else {
-            println!("🔍 Line is not valid JSON, continuing: {trimmed}");
+            trace!("Line is not valid JSON, continuing: {trimmed}");
             continue;
         }
     };
@@ -447,6 +598,12 @@ async fn send_jsonrpc_request<E: EventEmitter>(
     Ok(Some(response))
 }
 
+/// Events a session can queue before reading from the CLI waits for them to
+/// be emitted.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+/// Messages a session can queue before senders wait for the CLI's stdin.
+pub const MESSAGE_CHANNEL_CAPACITY: usize = 64;
+
 /// Parameters for initializing a session
 pub struct SessionParams {
     pub session_id: String,
@@ -455,13 +612,37 @@ pub struct SessionParams {
     pub backend_config: Option<QwenConfig>,
     pub gemini_auth: Option<GeminiAuthConfig>,
     pub llxprt_config: Option<LLxprtConfig>,
+    /// Write the session's RPC traffic to the project history logs. Helper
+    /// sessions turn this off so they never show up as conversations.
+    pub record_history: bool,
+    /// How long streamed text may wait to be merged with the chunks after
+    /// it; zero emits every chunk as it arrives.
+    pub chunk_window: Duration,
+    /// Where the session's history, project settings and hooks are.
+    pub data_dir: DataDir,
+    /// Set on the CLI process and hook commands on top of the app's
+    /// environment. The session's credentials take precedence.
+    pub env: HashMap<String, String>,
+}
+
+impl SessionParams {
+    pub fn launch_config(&self) -> SessionLaunchConfig {
+        SessionLaunchConfig {
+            model: self.model.clone(),
+            backend_config: self.backend_config.clone(),
+            gemini_auth: self.gemini_auth.clone(),
+            llxprt_config: self.llxprt_config.clone(),
+            env: self.env.clone(),
+        }
+    }
 }
 
 pub async fn initialize_session<E: EventEmitter + 'static>(
     params: SessionParams,
     emitter: E,
     session_manager: &SessionManager,
-) -> Result<(mpsc::UnboundedSender<String>, Arc<dyn RpcLogger>)> {
+) -> Result<(mpsc::Sender<String>, Arc<dyn RpcLogger>)> {
+    let launch_config = params.launch_config();
     let SessionParams {
         session_id,
         working_directory,
@@ -469,6 +650,10 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
         backend_config,
         gemini_auth,
         llxprt_config,
+        record_history,
+        chunk_window,
+        data_dir,
+        env,
     } = params;
     let (backend_type, cli_name) = if llxprt_config.is_some() {
         ("llxprt", "LLxprt Code")
@@ -479,76 +664,100 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
     };
 
     // Create event forwarding system early so we can use it for progress events
-    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<InternalEvent>();
+    let (event_tx, mut event_rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
     let _session_id_for_events = session_id.clone();
     let emitter_for_events = emitter.clone();
+    // Helper sessions don't show up as conversations, so they don't run hooks
+    let hooks = if record_history {
+        SessionHooks::load(&session_id, &working_directory, &data_dir).with_env(env.clone())
+    } else {
+        SessionHooks::new(session_id.clone(), working_directory.clone(), Vec::new())
+    };
+    let mut hooks_for_events = hooks.clone();
 
     // Start event forwarding task
     tokio::spawn(async move {
-        while let Some(internal_event) = event_rx.recv().await {
-            println!("📨 Processing internal_event: {internal_event:?}");
-            match internal_event {
+        let mut coalescer = ChunkCoalescer::new(chunk_window);
+        loop {
+            let internal_event = match coalescer.deadline() {
+                Some(deadline) => tokio::select! {
+                    internal_event = event_rx.recv() => internal_event,
+                    () = tokio::time::sleep_until(deadline) => {
+                        for event in coalescer.flush() {
+                            let _ = emitter_for_events.emit(event);
+                        }
+                        continue;
+                    }
+                },
+                None => event_rx.recv().await,
+            };
+            let Some(internal_event) = internal_event else {
+                break;
+            };
+            trace!("Processing internal_event: {internal_event:?}");
+            let event = match internal_event {
                 InternalEvent::CliIo {
                     session_id,
                     payload,
-                } => {
-                    let _ = emitter_for_events.emit(&format!("cli-io-{session_id}"), payload);
-                }
+                } => AppEvent::CliIo {
+                    session_id,
+                    payload,
+                },
                 InternalEvent::GeminiOutput {
                     session_id,
                     payload,
-                } => {
-                    let _ =
-                        emitter_for_events.emit(&format!("ai-output-{session_id}"), payload.text);
-                }
+                } => AppEvent::AiOutput {
+                    session_id,
+                    payload: payload.text,
+                },
                 InternalEvent::GeminiThought {
                     session_id,
                     payload,
-                } => {
-                    let _ = emitter_for_events
-                        .emit(&format!("ai-thought-{session_id}"), payload.thought);
-                }
+                } => AppEvent::AiThought {
+                    session_id,
+                    payload: payload.thought,
+                },
                 #[allow(deprecated)]
                 InternalEvent::ToolCall { .. } => {
                     // No-op: Use AcpSessionUpdate instead
+                    continue;
                 }
                 #[allow(deprecated)]
                 InternalEvent::ToolCallUpdate { .. } => {
                     // No-op: Use AcpSessionUpdate instead
+                    continue;
                 }
                 #[allow(deprecated)]
                 InternalEvent::ToolCallConfirmation { .. } => {
                     // No-op: Use AcpPermissionRequest instead
+                    continue;
                 }
-                InternalEvent::GeminiTurnFinished { session_id } => {
-                    let _ =
-                        emitter_for_events.emit(&format!("ai-turn-finished-{session_id}"), true);
-                }
+                InternalEvent::GeminiTurnFinished { session_id } => AppEvent::AiTurnFinished {
+                    session_id,
+                    payload: true,
+                },
                 InternalEvent::Error {
                     session_id,
                     payload,
-                } => {
-                    let _ =
-                        emitter_for_events.emit(&format!("ai-error-{session_id}"), payload.error);
-                }
+                } => AppEvent::AiError {
+                    session_id,
+                    payload: payload.error,
+                },
                 InternalEvent::SessionProgress {
                     session_id,
                     payload,
-                } => {
-                    let _ =
-                        emitter_for_events.emit(&format!("session-progress-{session_id}"), payload);
-                }
+                } => AppEvent::SessionProgress {
+                    session_id,
+                    payload,
+                },
                 // Pure ACP events - emit directly with new event names
                 InternalEvent::AcpSessionUpdate { session_id, update } => {
-                    println!(
-                        "🔧 [EDIT-DEBUG] Emitting acp-session-update-{session_id} event: {update:?}"
+                    trace!(
+                        "[EDIT-DEBUG] Emitting acp-session-update-{session_id} event: {update:?}"
                     );
-                    let emit_result = emitter_for_events
-                        .emit(&format!("acp-session-update-{session_id}"), update);
-                    if emit_result.is_err() {
-                        println!(
-                            "🔧 [EDIT-DEBUG] Failed to emit acp-session-update event: {emit_result:?}"
-                        );
+                    AppEvent::AcpSessionUpdate {
+                        session_id,
+                        payload: update,
                     }
                 }
                 InternalEvent::AcpPermissionRequest {
@@ -556,69 +765,85 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                     request_id,
                     request,
                 } => {
-                    println!(
-                        "🔧 [REQUEST-DEBUG] Emitting acp-permission-request-{session_id} event: {request:?}"
-                    );
-                    let emit_result = emitter_for_events.emit(
-                        &format!("acp-permission-request-{session_id}"),
-                        serde_json::json!({
-                            "request_id": request_id,
-                            "request": request
-                        }),
+                    debug!(
+                        "[REQUEST-DEBUG] Emitting acp-permission-request-{session_id} event: {request:?}"
                     );
-                    if emit_result.is_err() {
-                        println!(
-                            "🔧 [REQUEST-DEBUG] Failed to emit acp-permission-request event: {emit_result:?}"
-                        );
+                    AppEvent::AcpPermissionRequest {
+                        session_id,
+                        payload: PermissionRequestPayload {
+                            request_id,
+                            request,
+                        },
                     }
                 }
+            };
+            for event in coalescer.push(event, tokio::time::Instant::now()) {
+                hooks_for_events.observe(&event);
+                if let Err(e) = emitter_for_events.emit(event) {
+                    trace!("Failed to emit session event: {e:?}");
+                }
             }
         }
+        // The session's senders are gone, so nothing more will be merged in
+        for event in coalescer.flush() {
+            let _ = emitter_for_events.emit(event);
+        }
     });
-    println!("🚀 [HANDSHAKE] Starting {cli_name} session initialization for: {session_id}");
-    println!("🚀 [HANDSHAKE] Working directory: {working_directory}");
-    println!("🚀 [HANDSHAKE] Model: {model}");
-    println!(
-        "🚀 [HANDSHAKE] Backend config present: {}",
+    info!("[HANDSHAKE] Starting {cli_name} session initialization for: {session_id}");
+    info!("[HANDSHAKE] Working directory: {working_directory}");
+    info!("[HANDSHAKE] Model: {model}");
+    info!(
+        "[HANDSHAKE] Backend config present: {}",
         backend_config.is_some()
     );
-    println!(
-        "🚀 [HANDSHAKE] Gemini auth config present: {}",
+    info!(
+        "[HANDSHAKE] Gemini auth config present: {}",
         gemini_auth.is_some()
     );
     if let Some(auth) = &gemini_auth {
-        println!("🚀 [HANDSHAKE] Auth method: {}", auth.method);
+        info!("[HANDSHAKE] Auth method: {}", auth.method);
     }
 
     // Emit starting progress
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::Starting,
-            message: format!("Starting {} session initialization", cli_name),
-            progress_percent: Some(5),
-            details: Some(format!("Working directory: {}", working_directory)),
-        },
-    });
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::Starting,
+                message: format!("Starting {} session initialization", cli_name),
+                progress_percent: Some(5),
+                details: Some(format!("Working directory: {}", working_directory)),
+            },
+        })
+        .await;
 
-    let rpc_logger: Arc<dyn RpcLogger> =
-        match FileRpcLogger::new(Some(&working_directory), Some(cli_name)) {
+    let history_settings = projects::read_history_settings(
+        &data_dir,
+        &ProjectHasher::hash_path_or_raw(&working_directory),
+    );
+    let rpc_logger: Arc<dyn RpcLogger> = if !record_history {
+        debug!("[HANDSHAKE] RPC logging disabled for session: {session_id}");
+        Arc::new(NoOpRpcLogger)
+    } else if !history_settings.persist_rpc_log {
+        debug!("[HANDSHAKE] RPC logging disabled by project settings for session: {session_id}");
+        Arc::new(NoOpRpcLogger)
+    } else {
+        match FileRpcLogger::new(&data_dir, Some(&working_directory), Some(cli_name)) {
             Ok(logger) => {
-                println!("📝 [HANDSHAKE] RPC logging enabled for session: {session_id}");
+                debug!("[HANDSHAKE] RPC logging enabled for session: {session_id}");
                 let _ = logger.cleanup_old_logs();
-                Arc::new(logger)
+                Arc::new(logger.with_redactor(history_settings.redactor()))
             }
             Err(e) => {
-                println!(
-                    "⚠️ [HANDSHAKE] Failed to create RPC logger for session {session_id}: {e}"
-                );
+                warn!("[HANDSHAKE] Failed to create RPC logger for session {session_id}: {e}");
                 Arc::new(NoOpRpcLogger)
             }
-        };
+        }
+    };
 
-    let (message_tx, message_rx) = mpsc::unbounded_channel::<String>();
+    let (message_tx, message_rx) = mpsc::channel::<String>(MESSAGE_CHANNEL_CAPACITY);
 
-    // Setup environment variables with automatic cleanup
+    // Credentials for the CLI process
     let session_env = {
         if let Some(config) = &llxprt_config {
             Some(SessionEnvironment::setup_llxprt(config)?)
@@ -650,13 +875,13 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
 
             let llxprt_args = if has_base_url {
                 let base_url = config.base_url.as_ref().unwrap();
-                println!("🔧 [HANDSHAKE] Using base URL (validated)");
+                debug!("[HANDSHAKE] Using base URL (validated)");
                 format!(
                     "llxprt --experimental-acp --provider {} --model {} --baseurl {}",
                     llxprt_provider, config.model, base_url
                 )
             } else {
-                println!("🔧 [HANDSHAKE] No base URL specified, using provider defaults");
+                debug!("[HANDSHAKE] No base URL specified, using provider defaults");
                 format!(
                     "llxprt --experimental-acp --provider {} --model {}",
                     llxprt_provider, config.model
@@ -665,8 +890,8 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
 
             #[cfg(windows)]
             {
-                println!(
-                    "🔧 [HANDSHAKE] Creating Windows LLxprt command: cmd.exe /C {}",
+                debug!(
+                    "[HANDSHAKE] Creating Windows LLxprt command: cmd.exe /C {}",
                     llxprt_args
                 );
                 let mut c = Command::new("cmd.exe");
@@ -677,8 +902,8 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             }
             #[cfg(not(windows))]
             {
-                println!(
-                    "🔧 [HANDSHAKE] Creating Unix LLxprt command: sh -lc '{}'",
+                debug!(
+                    "[HANDSHAKE] Creating Unix LLxprt command: sh -lc '{}'",
                     llxprt_args
                 );
                 let mut c = Command::new("sh");
@@ -701,7 +926,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                 } else {
                     "cmd.exe /C qwen --experimental-acp"
                 };
-                println!("🔧 [HANDSHAKE] Creating Windows Qwen command: {command_display}");
+                debug!("[HANDSHAKE] Creating Windows Qwen command: {command_display}");
                 let mut c = Command::new("cmd.exe");
                 c.args(args);
                 #[cfg(windows)]
@@ -715,8 +940,8 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                 } else {
                     "qwen --experimental-acp".to_string()
                 };
-                println!(
-                    "🔧 [HANDSHAKE] Creating Unix Qwen command: sh -lc '{}'",
+                debug!(
+                    "[HANDSHAKE] Creating Unix Qwen command: sh -lc '{}'",
                     qwen_command
                 );
                 let mut c = Command::new("sh");
@@ -742,7 +967,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                 } else {
                     format!("cmd.exe /C {gemini_path} --model {model} --experimental-acp")
                 };
-                println!("🔧 [HANDSHAKE] Creating Windows Gemini command: {command_display}");
+                debug!("[HANDSHAKE] Creating Windows Gemini command: {command_display}");
 
                 let mut c = Command::new("cmd.exe");
                 c.args(args);
@@ -760,8 +985,8 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                 } else {
                     format!("gemini --model {model} --experimental-acp")
                 };
-                println!(
-                    "🔧 [HANDSHAKE] Creating Unix Gemini command: sh -lc '{}'",
+                debug!(
+                    "[HANDSHAKE] Creating Unix Gemini command: sh -lc '{}'",
                     gemini_command
                 );
                 let mut c = Command::new("sh");
@@ -774,23 +999,29 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
     cmd.stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    cmd.envs(&env);
+    if let Some(session_env) = &session_env {
+        cmd.envs(session_env.vars.iter().map(|(name, value)| (name, value)));
+    }
 
     if !working_directory.is_empty() {
-        println!("🗂️ [HANDSHAKE] Setting working directory to: {working_directory}");
+        debug!("[HANDSHAKE] Setting working directory to: {working_directory}");
         cmd.current_dir(&working_directory);
     }
 
     // Pre-flight check: Test if CLI is available
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::ValidatingCli,
-            message: format!("Validating {} CLI availability", cli_name),
-            progress_percent: Some(15),
-            details: Some("Testing CLI installation and connectivity".to_string()),
-        },
-    });
-    println!("🔍 [PRECHECK] Testing CLI availability...");
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::ValidatingCli,
+                message: format!("Validating {} CLI availability", cli_name),
+                progress_percent: Some(15),
+                details: Some("Testing CLI installation and connectivity".to_string()),
+            },
+        })
+        .await;
+    debug!("[PRECHECK] Testing CLI availability...");
 
     let needs_cli_check = backend_type == "gemini" || backend_type == "llxprt";
 
@@ -805,6 +1036,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             {
                 std::process::Command::new("cmd.exe")
                     .args(["/C", cli_name_test, "--version"])
+                    .envs(&env)
                     .creation_flags(CREATE_NO_WINDOW)
                     .output()
             }
@@ -812,25 +1044,27 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             {
                 std::process::Command::new(cli_name_test)
                     .arg("--version")
+                    .envs(&env)
                     .output()
             }
         } else {
             std::process::Command::new(cli_name_test)
                 .arg("--version")
+                .envs(&env)
                 .output()
         };
 
         match test_result {
             Ok(output) => {
                 if output.status.success() {
-                    println!(
-                        "✅ [PRECHECK] {} CLI is available and responding",
+                    info!(
+                        "[PRECHECK] {} CLI is available and responding",
                         cli_name_test
                     );
                 } else {
                     let stderr = String::from_utf8_lossy(&output.stderr);
-                    println!(
-                        "❌ [PRECHECK] {} CLI returned error: {}",
+                    error!(
+                        "[PRECHECK] {} CLI returned error: {}",
                         cli_name_test, stderr
                     );
                     anyhow::bail!(
@@ -842,7 +1076,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
                 }
             }
             Err(e) => {
-                println!("❌ [PRECHECK] Cannot execute {} CLI: {}", cli_name_test, e);
+                error!("[PRECHECK] Cannot execute {} CLI: {}", cli_name_test, e);
                 let install_cmd = if backend_type == "llxprt" {
                     "npm install -g llxprt"
                 } else {
@@ -859,21 +1093,23 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             }
         }
     } else {
-        println!("🔍 [PRECHECK] Skipping CLI check for Qwen (uses API directly)");
+        debug!("[PRECHECK] Skipping CLI check for Qwen (uses API directly)");
     }
 
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::SpawningProcess,
-            message: format!("Spawning {} process", cli_name),
-            progress_percent: Some(25),
-            details: Some("Starting CLI subprocess with configured parameters".to_string()),
-        },
-    });
-    println!("🔄 [HANDSHAKE] Spawning CLI process...");
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::SpawningProcess,
+                message: format!("Spawning {} process", cli_name),
+                progress_percent: Some(25),
+                details: Some("Starting CLI subprocess with configured parameters".to_string()),
+            },
+        })
+        .await;
+    debug!("[HANDSHAKE] Spawning CLI process...");
     let mut child = cmd.spawn().map_err(|e| {
-        println!("❌ [HANDSHAKE] Failed to spawn {} process: {e}", cli_name);
+        error!("[HANDSHAKE] Failed to spawn {} process: {e}", cli_name);
         #[cfg(windows)]
         {
             anyhow::anyhow!(
@@ -890,10 +1126,10 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
         }
     })?;
 
-    println!("✅ [HANDSHAKE] CLI process spawned successfully");
+    info!("[HANDSHAKE] CLI process spawned successfully");
 
     let pid = child.id();
-    println!("🔗 [HANDSHAKE] CLI process PID: {pid:?}");
+    debug!("[HANDSHAKE] CLI process PID: {pid:?}");
 
     let mut stdin = child
         .stdin
@@ -920,33 +1156,35 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             match stderr_reader.read_line(&mut line).await {
                 Ok(0) => break,
                 Ok(_) => {
-                    println!("🔍 STDERR from CLI: {}", line.trim());
-                    let _ = emitter_for_stderr.emit(
-                        &format!("cli-io-{}", session_id_for_stderr),
-                        CliIoPayload {
+                    debug!("STDERR from CLI: {}", line.trim());
+                    let _ = emitter_for_stderr.emit(AppEvent::CliIo {
+                        session_id: session_id_for_stderr.clone(),
+                        payload: CliIoPayload {
                             io_type: CliIoType::Error,
                             data: line.clone(),
                         },
-                    );
+                    });
                     line.clear();
                 }
                 Err(_) => break,
             }
         }
     });
-    println!("📡 [HANDSHAKE] Set up stdin/stdout/stderr communication channels");
+    debug!("[HANDSHAKE] Set up stdin/stdout/stderr communication channels");
 
     // Step 1: Initialize
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::Initializing,
-            message: "Initializing ACP protocol".to_string(),
-            progress_percent: Some(40),
-            details: Some("Establishing communication protocol with CLI".to_string()),
-        },
-    });
-    println!("🤝 [HANDSHAKE] Step 1/3: Sending initialize request");
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::Initializing,
+                message: "Initializing ACP protocol".to_string(),
+                progress_percent: Some(40),
+                details: Some("Establishing communication protocol with CLI".to_string()),
+            },
+        })
+        .await;
+    debug!("[HANDSHAKE] Step 1/3: Sending initialize request");
     let init_params = InitializeParams {
         protocol_version: 1,
         client_capabilities: ClientCapabilities {
@@ -956,7 +1194,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             },
         },
     };
-    println!("🤝 [HANDSHAKE] Initialize params: protocol_version=1");
+    debug!("[HANDSHAKE] Initialize params: protocol_version=1");
 
     let init_request = JsonRpcRequest {
         jsonrpc: "2.0".to_string(),
@@ -988,14 +1226,14 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
         )
         .await
         .map_err(|e| {
-            println!("❌ [HANDSHAKE] Initialize request failed: {e}");
+            error!("[HANDSHAKE] Initialize request failed: {e}");
             e
         });
 
         // `None` indicates that we haven't gotten any JSON response from Gemini yet.
         match init_response_result {
             Ok(None) => {
-                println!("No response received yet; sending again");
+                debug!("No response received yet; sending again");
                 sleep(Duration::from_secs(2)).await;
             }
             Ok(Some(res)) => {
@@ -1010,24 +1248,26 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
         serde_json::from_value(init_response.result.unwrap_or_default())
             .context("Failed to parse init result")?;
 
-    println!("✅ [HANDSHAKE] Step 1/3: Initialize completed successfully for: {session_id}");
+    info!("[HANDSHAKE] Step 1/3: Initialize completed successfully for: {session_id}");
 
     // Step 2: Create new session
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::CreatingSession,
-            message: "Creating ACP session".to_string(),
-            progress_percent: Some(80),
-            details: Some("Establishing working directory and session context".to_string()),
-        },
-    });
-    println!("📁 [HANDSHAKE] Step 2/3: Creating new ACP session");
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::CreatingSession,
+                message: "Creating ACP session".to_string(),
+                progress_percent: Some(80),
+                details: Some("Establishing working directory and session context".to_string()),
+            },
+        })
+        .await;
+    debug!("[HANDSHAKE] Step 2/3: Creating new ACP session");
     let session_params = SessionNewParams {
         cwd: working_directory.clone(),
         mcp_servers: vec![], // No MCP servers for now
     };
-    println!("📁 [HANDSHAKE] Session params: cwd={working_directory}, mcp_servers=[]");
+    debug!("[HANDSHAKE] Session params: cwd={working_directory}, mcp_servers=[]");
 
     let session_request = JsonRpcRequest {
         jsonrpc: "2.0".to_string(),
@@ -1050,33 +1290,36 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
     if let Err(e) = session_response {
         let msg = e.to_string();
         if msg.contains("Authentication required") {
-            println!("⚠️ [HANDSHAKE] Session creation request failed - needs auth");
+            warn!("[HANDSHAKE] Session creation request failed - needs auth");
             // Step 3: Authenticate - choose method based on configuration
-            let _ = event_tx.send(InternalEvent::SessionProgress {
-                session_id: session_id.clone(),
-                payload: SessionProgressPayload {
-                    stage: SessionProgressStage::Authenticating,
-                    message: "Authenticating with AI service".to_string(),
-                    progress_percent: Some(65),
-                    details: Some(
-                        "Verifying credentials and establishing authenticated session".to_string(),
-                    ),
-                },
-            });
-            println!("🔐 [HANDSHAKE] Step 3/3: Determining authentication method");
+            let _ = event_tx
+                .send(InternalEvent::SessionProgress {
+                    session_id: session_id.clone(),
+                    payload: SessionProgressPayload {
+                        stage: SessionProgressStage::Authenticating,
+                        message: "Authenticating with AI service".to_string(),
+                        progress_percent: Some(65),
+                        details: Some(
+                            "Verifying credentials and establishing authenticated session"
+                                .to_string(),
+                        ),
+                    },
+                })
+                .await;
+            debug!("[HANDSHAKE] Step 3/3: Determining authentication method");
             let auth_method_id = if let Some(auth) = &gemini_auth {
-                println!("🔐 [HANDSHAKE] Using provided auth method: {}", auth.method);
+                debug!("[HANDSHAKE] Using provided auth method: {}", auth.method);
                 auth.method.clone()
             } else if llxprt_config.is_some() {
-                println!("🔐 [HANDSHAKE] Using gemini-api-key auth for LLxprt backend");
+                debug!("[HANDSHAKE] Using gemini-api-key auth for LLxprt backend");
                 // LLxprt uses API key auth
                 "gemini-api-key".to_string()
             } else if backend_config.is_some() {
-                println!("🔐 [HANDSHAKE] Using gemini-api-key auth for Qwen backend");
+                debug!("[HANDSHAKE] Using gemini-api-key auth for Qwen backend");
                 // Qwen uses API key auth
                 "gemini-api-key".to_string()
             } else {
-                println!("🔐 [HANDSHAKE] Using default oauth-personal auth method");
+                debug!("[HANDSHAKE] Using default oauth-personal auth method");
                 // Default to OAuth for Gemini if no config provided
                 "oauth-personal".to_string()
             };
@@ -1084,7 +1327,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             let auth_params = AuthenticateParams {
                 method_id: auth_method_id.clone(),
             };
-            println!("🔐 [HANDSHAKE] Sending authenticate request with method: {auth_method_id}");
+            debug!("[HANDSHAKE] Sending authenticate request with method: {auth_method_id}");
 
             let auth_request = JsonRpcRequest {
                 jsonrpc: "2.0".to_string(),
@@ -1104,13 +1347,11 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             )
             .await
             .map_err(|e| {
-                println!("❌ [HANDSHAKE] Authentication request failed: {e}");
+                error!("[HANDSHAKE] Authentication request failed: {e}");
                 e
             })?;
 
-            println!(
-                "✅ [HANDSHAKE] Step 3/3: Authentication completed successfully for: {session_id}"
-            );
+            info!("[HANDSHAKE] Step 3/3: Authentication completed successfully for: {session_id}");
 
             session_response = send_jsonrpc_request(
                 &session_request,
@@ -1122,7 +1363,7 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             )
             .await;
         } else {
-            println!("❌ [HANDSHAKE] Session creation request failed: {msg}");
+            error!("[HANDSHAKE] Session creation request failed: {msg}");
             return Err(e);
         }
     };
@@ -1138,16 +1379,16 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
         );
     };
 
-    println!(
-        "✅ [HANDSHAKE] Step 3/3: ACP session created successfully with ID: {}",
+    info!(
+        "[HANDSHAKE] Step 3/3: ACP session created successfully with ID: {}",
         session_result.session_id
     );
 
     {
-        println!("💾 [HANDSHAKE] Storing session in process manager");
+        debug!("[HANDSHAKE] Storing session in process manager");
         let processes = session_manager.get_processes();
         let mut processes = processes.lock().map_err(|_| {
-            println!("❌ [HANDSHAKE] Failed to lock processes mutex");
+            error!("[HANDSHAKE] Failed to lock processes mutex");
             anyhow::anyhow!("Session initialization failed: Failed to lock processes")
         })?;
 
@@ -1166,16 +1407,20 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
             child: Some(child),
             working_directory: working_directory.clone(),
             backend_type: backend_type.to_string(),
-            _environment: session_env,
+            launch_config: Some(launch_config),
+            activity: SessionActivity {
+                turn_state: TurnState::Idle,
+                last_activity: unix_now_secs(),
+            },
         };
 
         processes.insert(session_id.clone(), persistent_session);
-        println!(
-            "💾 [HANDSHAKE] {} session stored successfully - marking as ALIVE",
+        debug!(
+            "[HANDSHAKE] {} session stored successfully - marking as ALIVE",
             backend_type.to_uppercase()
         );
-        println!(
-            "💾 [HANDSHAKE] Session details: conversation_id={}, backend={}, acp_session_id={}, pid={:?}, is_alive=true",
+        debug!(
+            "[HANDSHAKE] Session details: conversation_id={}, backend={}, acp_session_id={}, pid={:?}, is_alive=true",
             session_id,
             backend_type.to_uppercase(),
             session_result.session_id,
@@ -1184,23 +1429,25 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
     }
 
     // Emit session ready progress
-    let _ = event_tx.send(InternalEvent::SessionProgress {
-        session_id: session_id.clone(),
-        payload: SessionProgressPayload {
-            stage: SessionProgressStage::Ready,
-            message: "Session ready".to_string(),
-            progress_percent: Some(100),
-            details: Some(format!(
-                "Session {} is now active and ready for use",
-                session_result.session_id
-            )),
-        },
-    });
+    let _ = event_tx
+        .send(InternalEvent::SessionProgress {
+            session_id: session_id.clone(),
+            payload: SessionProgressPayload {
+                stage: SessionProgressStage::Ready,
+                message: "Session ready".to_string(),
+                progress_percent: Some(100),
+                details: Some(format!(
+                    "Session {} is now active and ready for use",
+                    session_result.session_id
+                )),
+            },
+        })
+        .await;
 
     // Emit real-time status change - session became active
     if let Ok(statuses) = session_manager.get_process_statuses() {
-        println!("📡 [STATUS-WS] Emitting process status change after session became active");
-        let _ = emitter.emit("process-status-changed", &statuses);
+        debug!("[STATUS-WS] Emitting process status change after session became active");
+        let _ = emitter.emit(AppEvent::ProcessStatusChanged { payload: statuses });
     }
 
     let session_id_clone = session_id.clone();
@@ -1209,16 +1456,17 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
     tokio::spawn(async move {
         // Ensure the I/O loop does not block forever if the CLI becomes silent.
         // The internal handler itself reads line-by-line and will exit on EOF.
-        println!("🔄 [HANDSHAKE] Starting I/O handler task for session: {session_id_clone}");
+        debug!("[HANDSHAKE] Starting I/O handler task for session: {session_id_clone}");
         handle_session_io_internal(
             session_id_clone,
             reader,
             message_rx,
             processes_clone,
             event_tx,
+            hooks,
         )
         .await;
-        println!("💀 [HANDSHAKE] I/O handler task exited for session!");
+        warn!("[HANDSHAKE] I/O handler task exited for session!");
     });
 
     Ok((message_tx, rpc_logger))
@@ -1227,15 +1475,16 @@ pub async fn initialize_session<E: EventEmitter + 'static>(
 async fn handle_session_io_internal(
     session_id: String,
     mut reader: AsyncBufReader<ChildStdout>,
-    mut message_rx: mpsc::UnboundedReceiver<String>,
+    mut message_rx: mpsc::Receiver<String>,
     processes: ProcessMap,
-    event_tx: mpsc::UnboundedSender<InternalEvent>,
+    event_tx: mpsc::Sender<InternalEvent>,
+    hooks: SessionHooks,
 ) {
-    println!("🔄 [IO-HANDLER] Starting I/O handler loop for session: {session_id}");
+    trace!("[IO-HANDLER] Starting I/O handler loop for session: {session_id}");
     let mut line_buffer = String::new();
 
     loop {
-        println!("🔄 [IO-HANDLER] Waiting for message or CLI output for session: {session_id}");
+        trace!("[IO-HANDLER] Waiting for message or CLI output for session: {session_id}");
         tokio::select! {
             message = message_rx.recv() => {
                 if let Some(message_json) = message {
@@ -1257,37 +1506,39 @@ async fn handle_session_io_internal(
                         }
 
                         if let Err(e) = stdin.write_all(message_json.as_bytes()).await {
-                            eprintln!("Failed to write to stdin: {e}");
+                            error!("Failed to write to stdin: {e}");
                             break;
                         }
                         if let Err(e) = stdin.write_all(if cfg!(windows) { b"\r\n" } else { b"\n" }).await {
-                            eprintln!("Failed to write newline: {e}");
+                            error!("Failed to write newline: {e}");
                             break;
                         }
                         if let Err(e) = stdin.flush().await {
-                            eprintln!("Failed to flush stdin: {e}");
+                            error!("Failed to flush stdin: {e}");
                             break;
                         }
 
 
 
+                        let message_json_for_state = message_json.clone();
                         let _ = event_tx.send(InternalEvent::CliIo {
                             session_id: session_id.clone(),
                             payload: CliIoPayload {
                                 io_type: CliIoType::Input,
                                 data: message_json,
                             },
-                        });
+                        }).await;
 
                         {
                             let mut processes_guard = processes.lock().unwrap();
                             if let Some(session) = processes_guard.get_mut(&session_id) {
                                 session.stdin = Some(stdin);
+                                session.activity.record(outgoing_turn_state(&message_json_for_state));
                             }
                         }
                     }
                 } else {
-                    println!("Message receiver closed for session: {session_id}");
+                    debug!("Message receiver closed for session: {session_id}");
                     break;
                 }
             }
@@ -1295,18 +1546,19 @@ async fn handle_session_io_internal(
             result = reader.read_line(&mut line_buffer) => {
                 match result {
                     Ok(0) => {
-                        println!("💀 [SESSION-LIFECYCLE] CLI process closed (EOF) for session: {session_id}");
-                        println!("💀 [SESSION-LIFECYCLE] This will cause session to become INACTIVE");
+                        warn!("[SESSION-LIFECYCLE] CLI process closed (EOF) for session: {session_id}");
+                        warn!("[SESSION-LIFECYCLE] This will cause session to become INACTIVE");
                         break;
                     }
                     Ok(bytes_read) => {
-                        println!("📥 [SESSION-LIFECYCLE] Read {bytes_read} bytes from CLI for session: {session_id}");
+                        trace!("[SESSION-LIFECYCLE] Read {bytes_read} bytes from CLI for session: {session_id}");
                         let line = line_buffer.trim().to_string();
 
-                        if let Ok(processes_guard) = processes.lock()
-                            && let Some(session) = processes_guard.get(&session_id)
+                        if let Ok(mut processes_guard) = processes.lock()
+                            && let Some(session) = processes_guard.get_mut(&session_id)
                         {
                             let _ = session.rpc_logger.log_rpc(&line);
+                            session.activity.record(incoming_turn_state(&line));
                         }
 
                         let _ = event_tx.send(InternalEvent::CliIo {
@@ -1315,10 +1567,10 @@ async fn handle_session_io_internal(
                                 io_type: CliIoType::Output,
                                 data: line.clone(),
                             },
-                        });
+                        }).await;
 
                         let line_preview = line.chars().take(100).collect::<String>();
-                        println!("🔧 [EDIT-DEBUG] Processing CLI output line: {line_preview}");
+                        trace!("[EDIT-DEBUG] Processing CLI output line: {line_preview}");
 
                         handle_cli_output_line(
                             &session_id,
@@ -1327,13 +1579,13 @@ async fn handle_session_io_internal(
                             &processes,
                         ).await;
 
-                        println!("🔧 [EDIT-DEBUG] Finished processing CLI line");
+                        trace!("[EDIT-DEBUG] Finished processing CLI line");
 
                         line_buffer.clear();
                     }
                     Err(e) => {
-                        println!("💀 [SESSION-LIFECYCLE] Error reading from CLI for session {session_id}: {e}");
-                        println!("💀 [SESSION-LIFECYCLE] This will cause session to become INACTIVE");
+                        warn!("[SESSION-LIFECYCLE] Error reading from CLI for session {session_id}: {e}");
+                        warn!("[SESSION-LIFECYCLE] This will cause session to become INACTIVE");
                         break;
                     }
                 }
@@ -1341,24 +1593,77 @@ async fn handle_session_io_internal(
         }
     }
 
-    {
-        println!(
-            "💀 [SESSION-LIFECYCLE] I/O handler exiting, marking session as INACTIVE: {session_id}"
-        );
+    // A session that was killed is already marked inactive
+    let crashed = {
+        warn!("[SESSION-LIFECYCLE] I/O handler exiting, marking session as INACTIVE: {session_id}");
         let mut processes_guard = processes.lock().unwrap();
         if let Some(session) = processes_guard.get_mut(&session_id) {
-            println!("💀 [SESSION-LIFECYCLE] Setting is_alive=false for session: {session_id}");
+            warn!("[SESSION-LIFECYCLE] Setting is_alive=false for session: {session_id}");
+            let crashed = session.is_alive;
             session.is_alive = false;
             session.stdin = None;
             session.message_sender = None;
+            crashed
         } else {
-            println!(
-                "⚠️ [SESSION-LIFECYCLE] Session {session_id} not found in processes map when trying to mark inactive"
+            warn!(
+                "[SESSION-LIFECYCLE] Session {session_id} not found in processes map when trying to mark inactive"
             );
+            false
         }
+    };
+    if crashed {
+        hooks.session_crashed();
+    }
+
+    info!("[SESSION-LIFECYCLE] Session I/O handler finished for: {session_id}");
+}
+
+/// Turn state implied by a message we send to the CLI.
+fn outgoing_turn_state(message_json: &str) -> Option<TurnState> {
+    let value = serde_json::from_str::<serde_json::Value>(message_json).ok()?;
+    match value.get("method").and_then(|m| m.as_str()) {
+        Some("session/prompt") => Some(TurnState::Prompting),
+        Some(_) => None,
+        // A response to a permission request hands control back to the agent.
+        None if value.get("result").is_some() => Some(TurnState::Streaming),
+        None => None,
     }
+}
 
-    println!("🛑 [SESSION-LIFECYCLE] Session I/O handler finished for: {session_id}");
+/// Turn state implied by a line received from the CLI.
+fn incoming_turn_state(line: &str) -> Option<TurnState> {
+    let value = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    match value.get("method").and_then(|m| m.as_str()) {
+        Some("streamAssistantMessageChunk") => Some(TurnState::Streaming),
+        Some("session/request_permission") => Some(TurnState::AwaitingPermission),
+        Some("session/update") => {
+            let params = serde_json::from_value::<SessionUpdateParams>(
+                value.get("params").cloned().unwrap_or_default(),
+            )
+            .ok()?;
+            match params.update {
+                SessionUpdate::AgentMessageChunk { .. }
+                | SessionUpdate::AgentThoughtChunk { .. } => Some(TurnState::Streaming),
+                SessionUpdate::ToolCall { status, .. }
+                | SessionUpdate::ToolCallUpdate { status, .. } => match status {
+                    ToolCallStatus::Pending | ToolCallStatus::InProgress => {
+                        Some(TurnState::RunningTool)
+                    }
+                    ToolCallStatus::Completed | ToolCallStatus::Failed => {
+                        Some(TurnState::Streaming)
+                    }
+                },
+            }
+        }
+        Some(_) => None,
+        None => {
+            let finished = value
+                .get("result")
+                .and_then(|r| r.get("stopReason"))
+                .is_some();
+            (finished || value.get("error").is_some()).then_some(TurnState::Idle)
+        }
+    }
 }
 
 pub async fn send_response_to_cli(
@@ -1389,21 +1694,21 @@ pub async fn send_response_to_cli(
             .get_mut(session_id)
             .and_then(|s| s.message_sender.clone())
     } {
-        let _ = sender.send(response_json);
+        let _ = sender.send(response_json).await;
     }
 }
 
 async fn handle_cli_output_line(
     session_id: &str,
     line: &str,
-    event_tx: &mpsc::UnboundedSender<InternalEvent>,
+    event_tx: &mpsc::Sender<InternalEvent>,
     _processes: &ProcessMap,
 ) {
-    println!("🔧 [EDIT-DEBUG] handle_cli_output_line called for session: {session_id}");
-    println!("🔧 [EDIT-DEBUG] Line content: {line}");
+    trace!("[EDIT-DEBUG] handle_cli_output_line called for session: {session_id}");
+    trace!("[EDIT-DEBUG] Line content: {line}");
 
     if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line) {
-        println!("🔧 [EDIT-DEBUG] Successfully parsed JSON from line");
+        trace!("[EDIT-DEBUG] Successfully parsed JSON from line");
         if let Some(method) = json_value.get("method").and_then(|m| m.as_str()) {
             match method {
                 "streamAssistantMessageChunk" => {
@@ -1411,16 +1716,20 @@ async fn handle_cli_output_line(
                         json_value.get("params").cloned().unwrap_or_default(),
                     ) {
                         if let Some(thought) = params.chunk.thought {
-                            let _ = event_tx.send(InternalEvent::GeminiThought {
-                                session_id: session_id.to_string(),
-                                payload: GeminiThoughtPayload { thought },
-                            });
+                            let _ = event_tx
+                                .send(InternalEvent::GeminiThought {
+                                    session_id: session_id.to_string(),
+                                    payload: GeminiThoughtPayload { thought },
+                                })
+                                .await;
                         }
                         if let Some(text) = params.chunk.text {
-                            let _ = event_tx.send(InternalEvent::GeminiOutput {
-                                session_id: session_id.to_string(),
-                                payload: GeminiOutputPayload { text },
-                            });
+                            let _ = event_tx
+                                .send(InternalEvent::GeminiOutput {
+                                    session_id: session_id.to_string(),
+                                    payload: GeminiOutputPayload { text },
+                                })
+                                .await;
                         }
                     }
                 }
@@ -1432,28 +1741,32 @@ async fn handle_cli_output_line(
                             SessionUpdate::AgentMessageChunk { content } => {
                                 match content {
                                     ContentBlock::Text { text } => {
-                                        let _ = event_tx.send(InternalEvent::GeminiOutput {
-                                            session_id: session_id.to_string(),
-                                            payload: GeminiOutputPayload { text },
-                                        });
+                                        let _ = event_tx
+                                            .send(InternalEvent::GeminiOutput {
+                                                session_id: session_id.to_string(),
+                                                payload: GeminiOutputPayload { text },
+                                            })
+                                            .await;
                                     }
                                     _ => {
                                         // Handle other content types as needed
-                                        println!("Received non-text content block: {content:?}");
+                                        trace!("Received non-text content block: {content:?}");
                                     }
                                 }
                             }
                             SessionUpdate::AgentThoughtChunk { content } => {
                                 match content {
                                     ContentBlock::Text { text } => {
-                                        let _ = event_tx.send(InternalEvent::GeminiThought {
-                                            session_id: session_id.to_string(),
-                                            payload: GeminiThoughtPayload { thought: text },
-                                        });
+                                        let _ = event_tx
+                                            .send(InternalEvent::GeminiThought {
+                                                session_id: session_id.to_string(),
+                                                payload: GeminiThoughtPayload { thought: text },
+                                            })
+                                            .await;
                                     }
                                     _ => {
                                         // Handle other content types as needed
-                                        println!(
+                                        debug!(
                                             "Received non-text thought content block: {content:?}"
                                         );
                                     }
@@ -1469,32 +1782,34 @@ async fn handle_cli_output_line(
                                 server_name,
                                 tool_name,
                             } => {
-                                println!(
-                                    "🔧 [EDIT-DEBUG] Backend received ToolCall from CLI: tool_call_id={tool_call_id}, status={status:?}, title={title}"
+                                trace!(
+                                    "[EDIT-DEBUG] Backend received ToolCall from CLI: tool_call_id={tool_call_id}, status={status:?}, title={title}"
                                 );
 
                                 // Emit pure ACP SessionUpdate event - no legacy conversion
-                                let emit_result = event_tx.send(InternalEvent::AcpSessionUpdate {
-                                    session_id: session_id.to_string(),
-                                    update: SessionUpdate::ToolCall {
-                                        tool_call_id: tool_call_id.clone(),
-                                        status: status.clone(),
-                                        title: title.clone(),
-                                        content: content.clone(),
-                                        locations: locations.clone(),
-                                        kind: kind.clone(),
-                                        server_name: server_name.clone(),
-                                        tool_name: tool_name.clone(),
-                                    },
-                                });
+                                let emit_result = event_tx
+                                    .send(InternalEvent::AcpSessionUpdate {
+                                        session_id: session_id.to_string(),
+                                        update: SessionUpdate::ToolCall {
+                                            tool_call_id: tool_call_id.clone(),
+                                            status: status.clone(),
+                                            title: title.clone(),
+                                            content: content.clone(),
+                                            locations: locations.clone(),
+                                            kind: kind.clone(),
+                                            server_name: server_name.clone(),
+                                            tool_name: tool_name.clone(),
+                                        },
+                                    })
+                                    .await;
 
                                 if emit_result.is_err() {
-                                    println!(
-                                        "🔧 [EDIT-DEBUG] Failed to send ToolCall event: {emit_result:?}"
+                                    trace!(
+                                        "[EDIT-DEBUG] Failed to send ToolCall event: {emit_result:?}"
                                     );
                                 } else {
-                                    println!(
-                                        "🔧 [EDIT-DEBUG] Successfully sent ToolCall event for: {tool_call_id}"
+                                    trace!(
+                                        "[EDIT-DEBUG] Successfully sent ToolCall event for: {tool_call_id}"
                                     );
                                 }
                             }
@@ -1505,39 +1820,41 @@ async fn handle_cli_output_line(
                                 server_name,
                                 tool_name,
                             } => {
-                                println!(
-                                    "🔧 [EDIT-DEBUG] Backend received ToolCallUpdate from CLI: tool_call_id={tool_call_id}, status={status:?}"
+                                trace!(
+                                    "[EDIT-DEBUG] Backend received ToolCallUpdate from CLI: tool_call_id={tool_call_id}, status={status:?}"
                                 );
-                                println!(
-                                    "🔧 [EDIT-DEBUG] ToolCallUpdate has content: {} items",
+                                trace!(
+                                    "[EDIT-DEBUG] ToolCallUpdate has content: {} items",
                                     content.len()
                                 );
 
                                 // Emit pure ACP SessionUpdate event - no legacy conversion
-                                let _ = event_tx.send(InternalEvent::AcpSessionUpdate {
-                                    session_id: session_id.to_string(),
-                                    update: SessionUpdate::ToolCallUpdate {
-                                        tool_call_id: tool_call_id.clone(),
-                                        status: status.clone(),
-                                        content: content.clone(),
-                                        server_name: server_name.clone(),
-                                        tool_name: tool_name.clone(),
-                                    },
-                                });
-                                println!(
-                                    "🔧 [EDIT-DEBUG] Sent AcpSessionUpdate event for ToolCallUpdate: {tool_call_id}"
+                                let _ = event_tx
+                                    .send(InternalEvent::AcpSessionUpdate {
+                                        session_id: session_id.to_string(),
+                                        update: SessionUpdate::ToolCallUpdate {
+                                            tool_call_id: tool_call_id.clone(),
+                                            status: status.clone(),
+                                            content: content.clone(),
+                                            server_name: server_name.clone(),
+                                            tool_name: tool_name.clone(),
+                                        },
+                                    })
+                                    .await;
+                                trace!(
+                                    "[EDIT-DEBUG] Sent AcpSessionUpdate event for ToolCallUpdate: {tool_call_id}"
                                 );
                             }
                         }
                     }
                 }
                 "session/request_permission" => {
-                    println!("🔔 BACKEND: Received session/request_permission from CLI");
-                    println!("🔔 BACKEND: JSON value: {json_value:?}");
+                    debug!("BACKEND: Received session/request_permission from CLI");
+                    trace!("BACKEND: JSON value: {json_value:?}");
                     // First try to parse and log what fails
                     let params_value = json_value.get("params").cloned().unwrap_or_default();
-                    println!(
-                        "🔔 BACKEND: Trying to parse params: {}",
+                    debug!(
+                        "BACKEND: Trying to parse params: {}",
                         serde_json::to_string_pretty(&params_value)
                             .unwrap_or("failed to stringify".to_string())
                     );
@@ -1546,27 +1863,27 @@ async fn handle_cli_output_line(
                         serde_json::from_value::<SessionRequestPermissionParams>(params_value)
                         && let Some(id) = json_value.get("id").and_then(|i| i.as_u64())
                     {
-                        println!("🔔 BACKEND: Successfully parsed permission request with id={id}");
-                        println!(
-                            "🔔 BACKEND: Tool call ID in request: {}",
+                        debug!("BACKEND: Successfully parsed permission request with id={id}");
+                        debug!(
+                            "BACKEND: Tool call ID in request: {}",
                             params.tool_call.tool_call_id
                         );
                         // Emit pure ACP permission request - no legacy conversion
-                        let _ = event_tx.send(InternalEvent::AcpPermissionRequest {
-                            session_id: session_id.to_string(),
-                            request_id: id,
-                            request: params,
-                        });
-                        println!(
-                            "🔔 BACKEND: Sent InternalEvent::AcpPermissionRequest to event_tx"
-                        );
+                        let _ = event_tx
+                            .send(InternalEvent::AcpPermissionRequest {
+                                session_id: session_id.to_string(),
+                                request_id: id,
+                                request: params,
+                            })
+                            .await;
+                        debug!("BACKEND: Sent InternalEvent::AcpPermissionRequest to event_tx");
                     } else {
                         // Try to get the specific parsing error
                         let parse_result = serde_json::from_value::<SessionRequestPermissionParams>(
                             json_value.get("params").cloned().unwrap_or_default(),
                         );
-                        println!(
-                            "❌ BACKEND: Failed to parse session/request_permission params: {:?}",
+                        error!(
+                            "BACKEND: Failed to parse session/request_permission params: {:?}",
                             parse_result.err()
                         );
                     }
@@ -1579,9 +1896,11 @@ async fn handle_cli_output_line(
                 json_value.get("result").cloned().unwrap_or_default(),
             ) && result.stop_reason == "end_turn"
             {
-                let _ = event_tx.send(InternalEvent::GeminiTurnFinished {
-                    session_id: session_id.to_string(),
-                });
+                let _ = event_tx
+                    .send(InternalEvent::GeminiTurnFinished {
+                        session_id: session_id.to_string(),
+                    })
+                    .await;
             }
         }
 
@@ -1594,6 +1913,7 @@ async fn handle_cli_output_line(
 mod tests {
     use super::*;
     // use crate::events::MockEventEmitter; // Unused import removed
+    use crate::events::DEFAULT_CHUNK_WINDOW;
     use serde_json::json;
     // use std::sync::atomic::{AtomicU32, Ordering}; // Unused imports removed
     use std::sync::Arc;
@@ -1614,7 +1934,8 @@ mod tests {
             child: None,
             working_directory: ".".to_string(),
             backend_type: "gemini".to_string(),
-            _environment: None,
+            launch_config: None,
+            activity: SessionActivity::default(),
         };
 
         assert_eq!(session.conversation_id, "test-id");
@@ -1634,6 +1955,14 @@ mod tests {
             created_at: 1640995200,
             is_alive: true,
             backend_type: "gemini".to_string(),
+            uptime_secs: 42,
+            resources: ProcessResources {
+                cpu_percent: Some(12.5),
+                memory_rss_bytes: Some(1024),
+                child_process_count: Some(2),
+            },
+            turn_state: TurnState::RunningTool,
+            last_activity: 1640995242,
         };
 
         let json = serde_json::to_string(&status).unwrap();
@@ -1643,6 +1972,57 @@ mod tests {
         assert_eq!(status.pid, deserialized.pid);
         assert_eq!(status.created_at, deserialized.created_at);
         assert_eq!(status.is_alive, deserialized.is_alive);
+        assert_eq!(status.resources, deserialized.resources);
+        assert_eq!(deserialized.turn_state, TurnState::RunningTool);
+
+        // Resource fields are flattened for the frontend
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(value["memory_rss_bytes"], 1024);
+        assert_eq!(value["turn_state"], "running_tool");
+    }
+
+    #[test]
+    fn test_turn_state_transitions() {
+        assert_eq!(
+            outgoing_turn_state(
+                r#"{"jsonrpc":"2.0","id":1000,"method":"session/prompt","params":{}}"#
+            ),
+            Some(TurnState::Prompting)
+        );
+        assert_eq!(
+            outgoing_turn_state(
+                r#"{"jsonrpc":"2.0","id":5,"result":{"outcome":{"outcome":"cancelled"}}}"#
+            ),
+            Some(TurnState::Streaming)
+        );
+
+        let chunk = r#"{"jsonrpc":"2.0","method":"session/update","params":{"sessionId":"s","update":{"sessionUpdate":"agent_message_chunk","content":{"type":"text","text":"hi"}}}}"#;
+        assert_eq!(incoming_turn_state(chunk), Some(TurnState::Streaming));
+
+        let tool = r#"{"jsonrpc":"2.0","method":"session/update","params":{"sessionId":"s","update":{"sessionUpdate":"tool_call_update","toolCallId":"t","status":"in_progress","content":[]}}}"#;
+        assert_eq!(incoming_turn_state(tool), Some(TurnState::RunningTool));
+
+        let permission =
+            r#"{"jsonrpc":"2.0","id":7,"method":"session/request_permission","params":{}}"#;
+        assert_eq!(
+            incoming_turn_state(permission),
+            Some(TurnState::AwaitingPermission)
+        );
+
+        let finished = r#"{"jsonrpc":"2.0","id":1000,"result":{"stopReason":"end_turn"}}"#;
+        assert_eq!(incoming_turn_state(finished), Some(TurnState::Idle));
+
+        assert_eq!(incoming_turn_state("Data collection is disabled."), None);
+    }
+
+    #[test]
+    fn test_process_status_deserializes_without_new_fields() {
+        let status: ProcessStatus = serde_json::from_str(
+            r#"{"conversation_id":"a","pid":null,"created_at":1,"is_alive":false,"backend_type":"qwen"}"#,
+        )
+        .unwrap();
+        assert_eq!(status.turn_state, TurnState::Idle);
+        assert_eq!(status.resources, ProcessResources::default());
     }
 
     #[test]
@@ -1659,7 +2039,8 @@ mod tests {
             child: None,
             working_directory: ".".to_string(),
             backend_type: "gemini".to_string(),
-            _environment: None,
+            launch_config: None,
+            activity: SessionActivity::default(),
         };
 
         let status = ProcessStatus::from(&session);
@@ -1667,6 +2048,41 @@ mod tests {
         assert_eq!(status.pid, Some(9876));
         assert_eq!(status.created_at, 1640995300);
         assert!(!status.is_alive);
+        assert_eq!(status.uptime_secs, 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_collect_process_statuses_samples_live_sessions() {
+        let manager = SessionManager::new();
+        manager.get_processes().lock().unwrap().insert(
+            "live".to_string(),
+            PersistentSession {
+                conversation_id: "live".to_string(),
+                acp_session_id: None,
+                pid: Some(std::process::id()),
+                created_at: unix_now_secs() - 10,
+                is_alive: true,
+                stdin: None,
+                message_sender: None,
+                rpc_logger: Arc::new(NoOpRpcLogger),
+                child: None,
+                working_directory: ".".to_string(),
+                backend_type: "gemini".to_string(),
+                launch_config: None,
+                activity: SessionActivity {
+                    turn_state: TurnState::Streaming,
+                    last_activity: 1,
+                },
+            },
+        );
+
+        let statuses = manager.collect_process_statuses().unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].uptime_secs >= 10);
+        assert!(statuses[0].resources.memory_rss_bytes.unwrap() > 0);
+        assert_eq!(statuses[0].turn_state, TurnState::Streaming);
+        assert_eq!(statuses[0].last_activity, 1);
     }
 
     #[test]
@@ -1704,7 +2120,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         }
@@ -1746,7 +2163,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         }
@@ -1786,7 +2204,7 @@ mod tests {
     #[tokio::test]
     async fn test_send_response_to_cli_with_session() {
         let processes: ProcessMap = Arc::new(Mutex::new(HashMap::new()));
-        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let (tx, mut rx) = mpsc::channel::<String>(MESSAGE_CHANNEL_CAPACITY);
 
         // Add session with message sender
         {
@@ -1805,7 +2223,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         }
@@ -1831,7 +2250,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_cli_output_line_invalid_json() {
-        let (tx, _rx) = mpsc::unbounded_channel::<InternalEvent>();
+        let (tx, _rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
 
         // Should not panic on invalid JSON
         let processes = Arc::new(Mutex::new(HashMap::new()));
@@ -1840,7 +2259,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_cli_output_line_stream_assistant_message_chunk() {
-        let (tx, mut rx) = mpsc::unbounded_channel::<InternalEvent>();
+        let (tx, mut rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
 
         let input = json!({
             "method": "streamAssistantMessageChunk",
@@ -1893,7 +2312,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_cli_output_line_end_turn_response() {
-        let (tx, mut rx) = mpsc::unbounded_channel::<InternalEvent>();
+        let (tx, mut rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
 
         let input = json!({
             "jsonrpc": "2.0",
@@ -1923,7 +2342,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_cli_output_line_non_end_turn_response() {
-        let (tx, mut rx) = mpsc::unbounded_channel::<InternalEvent>();
+        let (tx, mut rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
 
         let input = json!({
             "jsonrpc": "2.0",
@@ -1947,7 +2366,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_cli_output_line_unknown_method() {
-        let (tx, _rx) = mpsc::unbounded_channel::<InternalEvent>();
+        let (tx, _rx) = mpsc::channel::<InternalEvent>(EVENT_CHANNEL_CAPACITY);
 
         let input = json!({
             "method": "unknownMethod",
@@ -2027,6 +2446,10 @@ mod tests {
                 backend_config: None,
                 gemini_auth: None,
                 llxprt_config: None,
+                record_history: true,
+                chunk_window: DEFAULT_CHUNK_WINDOW,
+                data_dir: DataDir::default(),
+                env: HashMap::new(),
             },
             emitter.clone(),
             &session_manager,
@@ -2044,7 +2467,7 @@ mod tests {
 
                 // Test that we can send a message (will be queued)
                 let test_message = "test message";
-                let send_result = sender.send(test_message.to_string());
+                let send_result = sender.send(test_message.to_string()).await;
                 assert!(send_result.is_ok());
             }
             Err(e) => {
@@ -2089,7 +2512,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         }
@@ -2117,7 +2541,7 @@ mod tests {
         use tokio::sync::mpsc;
 
         let processes: ProcessMap = Arc::new(Mutex::new(HashMap::new()));
-        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let (tx, mut rx) = mpsc::channel::<String>(MESSAGE_CHANNEL_CAPACITY);
 
         // Set up a mock session with message sender
         {
@@ -2136,7 +2560,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         }
@@ -2199,7 +2624,8 @@ mod tests {
                             child: None,
                             working_directory: ".".to_string(),
                             backend_type: "gemini".to_string(),
-                            _environment: None,
+                            launch_config: None,
+                            activity: SessionActivity::default(),
                         },
                     );
                 }
@@ -2252,7 +2678,8 @@ mod tests {
                     child: None,
                     working_directory: ".".to_string(),
                     backend_type: "gemini".to_string(),
-                    _environment: None,
+                    launch_config: None,
+                    activity: SessionActivity::default(),
                 },
             );
         });
@@ -2333,7 +2760,8 @@ mod tests {
                         child: None,
                         working_directory: ".".to_string(),
                         backend_type: "gemini".to_string(),
-                        _environment: None,
+                        launch_config: None,
+                        activity: SessionActivity::default(),
                     },
                 );
             }
@@ -2408,42 +2836,33 @@ mod tests {
 
     // ==================== SECURITY TESTS ====================
 
-    #[test]
-    #[serial_test::serial]
-    fn test_environment_cleanup_on_drop() {
-        // Set up a unique test var to avoid conflicts
-        let test_var = format!("TEST_API_KEY_{}", std::process::id());
-
-        // Ensure it's not set initially
-        unsafe {
-            std::env::remove_var(&test_var);
-        }
-        assert!(std::env::var(&test_var).is_err());
-
-        {
-            let _guard = EnvVarGuard::new(&test_var, "secret_value");
-            // Variable should be set within this scope
-            assert_eq!(std::env::var(&test_var).unwrap(), "secret_value");
-        } // guard dropped here
+    /// The value `env` sets for `var_name`, if any.
+    fn env_var<'a>(env: &'a SessionEnvironment, var_name: &str) -> Option<&'a str> {
+        env.vars
+            .iter()
+            .find(|(name, _)| name == var_name)
+            .map(|(_, value)| value.as_str())
+    }
 
-        // Give a moment for cleanup
-        std::thread::sleep(std::time::Duration::from_millis(10));
+    #[test]
+    fn test_session_environment_leaves_process_environment_alone() {
+        let config = LLxprtConfig {
+            provider: "groq".to_string(),
+            api_key: "gsk-test-key-12345".to_string(),
+            model: "llama-3.3-70b".to_string(),
+            base_url: None,
+        };
 
-        // Variable should be cleared after drop
-        assert!(
-            std::env::var(&test_var).is_err(),
-            "Environment variable should be cleared after guard drops"
+        let env = SessionEnvironment::setup_llxprt(&config).unwrap();
+        assert_eq!(env_var(&env, "GROQ_API_KEY"), Some("gsk-test-key-12345"));
+        assert_ne!(
+            std::env::var("GROQ_API_KEY").ok().as_deref(),
+            Some("gsk-test-key-12345")
         );
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_session_environment_llxprt_anthropic() {
-        let test_var = "ANTHROPIC_API_KEY";
-        unsafe {
-            std::env::remove_var(test_var);
-        }
-
         let config = LLxprtConfig {
             provider: "anthropic".to_string(),
             api_key: "sk-ant-test-key-12345".to_string(),
@@ -2451,31 +2870,18 @@ mod tests {
             base_url: None,
         };
 
-        {
-            let _env = SessionEnvironment::setup_llxprt(&config).unwrap();
-            assert_eq!(std::env::var(test_var).unwrap(), "sk-ant-test-key-12345");
-        }
-
-        // Give cleanup time to run
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        assert!(
-            std::env::var(test_var).is_err(),
-            "API key should be cleared"
+        let env = SessionEnvironment::setup_llxprt(&config).unwrap();
+        assert_eq!(
+            env.vars,
+            vec![(
+                "ANTHROPIC_API_KEY".to_string(),
+                "sk-ant-test-key-12345".to_string()
+            )]
         );
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_session_environment_llxprt_openrouter_with_base_url() {
-        let key_var = "OPENAI_API_KEY";
-        let url_var = "OPENAI_BASE_URL";
-        unsafe {
-            std::env::remove_var(key_var);
-        }
-        unsafe {
-            std::env::remove_var(url_var);
-        }
-
         let config = LLxprtConfig {
             provider: "openrouter".to_string(),
             api_key: "sk-or-test".to_string(),
@@ -2483,36 +2889,16 @@ mod tests {
             base_url: Some("https://openrouter.ai/api/v1".to_string()),
         };
 
-        {
-            let _env = SessionEnvironment::setup_llxprt(&config).unwrap();
-            assert_eq!(std::env::var(key_var).unwrap(), "sk-or-test");
-            assert_eq!(
-                std::env::var(url_var).unwrap(),
-                "https://openrouter.ai/api/v1"
-            );
-        }
-
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        assert!(std::env::var(key_var).is_err());
-        assert!(std::env::var(url_var).is_err());
+        let env = SessionEnvironment::setup_llxprt(&config).unwrap();
+        assert_eq!(env_var(&env, "OPENAI_API_KEY"), Some("sk-or-test"));
+        assert_eq!(
+            env_var(&env, "OPENAI_BASE_URL"),
+            Some("https://openrouter.ai/api/v1")
+        );
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_session_environment_qwen() {
-        let key_var = "OPENAI_API_KEY";
-        let url_var = "OPENAI_BASE_URL";
-        let model_var = "OPENAI_MODEL";
-        unsafe {
-            std::env::remove_var(key_var);
-        }
-        unsafe {
-            std::env::remove_var(url_var);
-        }
-        unsafe {
-            std::env::remove_var(model_var);
-        }
-
         let config = QwenConfig {
             api_key: "qwen-test-key".to_string(),
             base_url: "https://dashscope.aliyuncs.com/compatible-mode/v1".to_string(),
@@ -2520,30 +2906,17 @@ mod tests {
             yolo: None,
         };
 
-        {
-            let _env = SessionEnvironment::setup_qwen(&config).unwrap();
-            assert_eq!(std::env::var(key_var).unwrap(), "qwen-test-key");
-            assert_eq!(
-                std::env::var(url_var).unwrap(),
-                "https://dashscope.aliyuncs.com/compatible-mode/v1"
-            );
-            assert_eq!(std::env::var(model_var).unwrap(), "qwen-max");
-        }
-
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        assert!(std::env::var(key_var).is_err());
-        assert!(std::env::var(url_var).is_err());
-        assert!(std::env::var(model_var).is_err());
+        let env = SessionEnvironment::setup_qwen(&config).unwrap();
+        assert_eq!(env_var(&env, "OPENAI_API_KEY"), Some("qwen-test-key"));
+        assert_eq!(
+            env_var(&env, "OPENAI_BASE_URL"),
+            Some("https://dashscope.aliyuncs.com/compatible-mode/v1")
+        );
+        assert_eq!(env_var(&env, "OPENAI_MODEL"), Some("qwen-max"));
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_session_environment_gemini_api_key() {
-        let test_var = "GEMINI_API_KEY";
-        unsafe {
-            std::env::remove_var(test_var);
-        }
-
         let auth = GeminiAuthConfig {
             method: "gemini-api-key".to_string(),
             api_key: Some("gemini-test-key".to_string()),
@@ -2552,27 +2925,12 @@ mod tests {
             yolo: None,
         };
 
-        {
-            let _env = SessionEnvironment::setup_gemini(&auth).unwrap();
-            assert_eq!(std::env::var(test_var).unwrap(), "gemini-test-key");
-        }
-
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        assert!(std::env::var(test_var).is_err());
+        let env = SessionEnvironment::setup_gemini(&auth).unwrap();
+        assert_eq!(env_var(&env, "GEMINI_API_KEY"), Some("gemini-test-key"));
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_session_environment_gemini_vertex_ai() {
-        let project_var = "GOOGLE_CLOUD_PROJECT";
-        let location_var = "GOOGLE_CLOUD_LOCATION";
-        unsafe {
-            std::env::remove_var(project_var);
-        }
-        unsafe {
-            std::env::remove_var(location_var);
-        }
-
         let auth = GeminiAuthConfig {
             method: "vertex-ai".to_string(),
             api_key: None,
@@ -2581,15 +2939,9 @@ mod tests {
             yolo: None,
         };
 
-        {
-            let _env = SessionEnvironment::setup_gemini(&auth).unwrap();
-            assert_eq!(std::env::var(project_var).unwrap(), "test-project");
-            assert_eq!(std::env::var(location_var).unwrap(), "us-central1");
-        }
-
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        assert!(std::env::var(project_var).is_err());
-        assert!(std::env::var(location_var).is_err());
+        let env = SessionEnvironment::setup_gemini(&auth).unwrap();
+        assert_eq!(env_var(&env, "GOOGLE_CLOUD_PROJECT"), Some("test-project"));
+        assert_eq!(env_var(&env, "GOOGLE_CLOUD_LOCATION"), Some("us-central1"));
     }
 
     #[test]
@@ -2621,11 +2973,9 @@ mod tests {
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_multiple_sessions_environment_isolation() {
-        // Test that multiple sessions can coexist with different env vars
         let config1 = LLxprtConfig {
-            provider: "anthropic".to_string(),
+            provider: "openai".to_string(),
             api_key: "key1".to_string(),
             model: "model1".to_string(),
             base_url: None,
@@ -2638,14 +2988,11 @@ mod tests {
             base_url: None,
         };
 
-        let _env1 = SessionEnvironment::setup_llxprt(&config1).unwrap();
-        let _env2 = SessionEnvironment::setup_llxprt(&config2).unwrap();
-
-        // Both should be set (though they might override each other for some vars)
-        // This mainly tests that the setup doesn't fail
-        assert!(
-            std::env::var("ANTHROPIC_API_KEY").is_ok() || std::env::var("OPENAI_API_KEY").is_ok()
-        );
+        // Sessions using the same variable each keep their own value
+        let env1 = SessionEnvironment::setup_llxprt(&config1).unwrap();
+        let env2 = SessionEnvironment::setup_llxprt(&config2).unwrap();
+        assert_eq!(env_var(&env1, "OPENAI_API_KEY"), Some("key1"));
+        assert_eq!(env_var(&env2, "OPENAI_API_KEY"), Some("key2"));
     }
 
     #[test]
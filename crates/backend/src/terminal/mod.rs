@@ -0,0 +1,514 @@
+use crate::events::{AppEvent, EventEmitter};
+use crate::jobs::take_utf8_prefix;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use portable_pty::{ChildKiller, CommandBuilder, MasterPty, PtySize, native_pty_system};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Bytes of output kept per terminal and replayed to clients that reconnect.
+pub const SCROLLBACK_LIMIT: usize = 512 * 1024;
+
+/// How many output events a slow subscriber may fall behind before it starts
+/// missing them.
+const SUBSCRIBER_BUFFER: usize = 1024;
+
+pub const DEFAULT_COLS: u16 = 80;
+pub const DEFAULT_ROWS: u16 = 24;
+
+/// A terminal to open. `working_directory` has already been resolved against
+/// the project root by the caller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalSpec {
+    pub project_id: String,
+    pub working_directory: String,
+    /// Program to run instead of the user's default shell.
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "default_cols")]
+    pub cols: u16,
+    #[serde(default = "default_rows")]
+    pub rows: u16,
+}
+
+fn default_cols() -> u16 {
+    DEFAULT_COLS
+}
+
+fn default_rows() -> u16 {
+    DEFAULT_ROWS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TerminalInfo {
+    pub id: String,
+    pub project_id: String,
+    pub working_directory: String,
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    pub created_at: DateTime<Utc>,
+    pub alive: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// Payload of the `terminal-output-{terminal_id}` events and of the messages
+/// sent to terminal subscribers. `exit` is always the last one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TerminalEvent {
+    Output { data: String },
+    Exit { code: Option<u32> },
+}
+
+/// Output history of a terminal plus the live feed that continues it.
+struct Screen {
+    scrollback: VecDeque<u8>,
+    live: broadcast::Sender<TerminalEvent>,
+}
+
+impl Screen {
+    fn push(&mut self, event: TerminalEvent) {
+        if let TerminalEvent::Output { data } = &event {
+            self.scrollback.extend(data.as_bytes());
+            let excess = self.scrollback.len().saturating_sub(SCROLLBACK_LIMIT);
+            if excess > 0 {
+                self.scrollback.drain(..excess);
+                // Don't start on the middle of a character
+                while self
+                    .scrollback
+                    .front()
+                    .is_some_and(|byte| byte & 0xC0 == 0x80)
+                {
+                    self.scrollback.pop_front();
+                }
+            }
+        }
+        // No subscribers is fine; the output is still in the scrollback.
+        let _ = self.live.send(event);
+    }
+
+    fn snapshot(&self) -> String {
+        let (a, b) = self.scrollback.as_slices();
+        let mut bytes = Vec::with_capacity(a.len() + b.len());
+        bytes.extend_from_slice(a);
+        bytes.extend_from_slice(b);
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+struct Terminal {
+    info: TerminalInfo,
+    master: Box<dyn MasterPty + Send>,
+    /// Locked on its own so a blocked write doesn't hold up the manager.
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    screen: Arc<Mutex<Screen>>,
+}
+
+/// Owns the PTY sessions opened by the frontend. Terminals stay alive while
+/// no client is attached, so a reconnecting client can pick up where it left
+/// off with [`TerminalManager::subscribe`].
+#[derive(Clone, Default)]
+pub struct TerminalManager {
+    terminals: Arc<Mutex<HashMap<String, Terminal>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl TerminalManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a PTY and start the shell in it. Output is emitted as
+    /// `terminal-output-{terminal_id}` events and sent to subscribers.
+    pub fn spawn<E: EventEmitter + 'static>(
+        &self,
+        spec: TerminalSpec,
+        emitter: E,
+    ) -> Result<TerminalInfo> {
+        let working_directory = PathBuf::from(&spec.working_directory);
+        if !working_directory.is_dir() {
+            anyhow::bail!(
+                "Working directory does not exist: {}",
+                spec.working_directory
+            );
+        }
+
+        let pair = native_pty_system()
+            .openpty(pty_size(spec.cols, spec.rows))
+            .context("Failed to open PTY")?;
+
+        let mut cmd = match &spec.shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new_default_prog(),
+        };
+        cmd.cwd(&working_directory);
+        cmd.env("TERM", "xterm-256color");
+        for (key, value) in &spec.env {
+            cmd.env(key, value);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .context("Failed to start shell in PTY")?;
+        // Only the child should hold the slave side, or reads never see EOF.
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .context("Failed to read from PTY")?;
+        let writer = pair
+            .master
+            .take_writer()
+            .context("Failed to write to PTY")?;
+
+        let id = format!("term-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let info = TerminalInfo {
+            id: id.clone(),
+            project_id: spec.project_id.clone(),
+            working_directory: spec.working_directory.clone(),
+            pid: child.process_id(),
+            cols: spec.cols,
+            rows: spec.rows,
+            created_at: Utc::now(),
+            alive: true,
+            exit_code: None,
+        };
+        debug!(
+            "Opened terminal {id} for project {} (pid {:?})",
+            spec.project_id, info.pid
+        );
+
+        let screen = Arc::new(Mutex::new(Screen {
+            scrollback: VecDeque::new(),
+            live: broadcast::channel(SUBSCRIBER_BUFFER).0,
+        }));
+        self.lock()?.insert(
+            id.clone(),
+            Terminal {
+                info: info.clone(),
+                master: pair.master,
+                writer: Arc::new(Mutex::new(writer)),
+                killer: child.clone_killer(),
+                screen: Arc::clone(&screen),
+            },
+        );
+
+        // PTY reads are blocking, so each terminal gets its own thread.
+        let terminals = Arc::clone(&self.terminals);
+        std::thread::Builder::new()
+            .name(format!("pty-{id}"))
+            .spawn(move || {
+                let publish = |event: TerminalEvent| {
+                    let app_event = AppEvent::TerminalOutput {
+                        terminal_id: id.clone(),
+                        payload: event.clone(),
+                    };
+                    if let Err(e) = emitter.emit(app_event) {
+                        warn!("Failed to emit terminal event for {id}: {e}");
+                    }
+                    if let Ok(mut screen) = screen.lock() {
+                        screen.push(event);
+                    }
+                };
+
+                let mut buf = [0u8; 8192];
+                let mut pending = Vec::new();
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => {
+                            pending.extend_from_slice(&buf[..n]);
+                            let data = take_utf8_prefix(&mut pending);
+                            if !data.is_empty() {
+                                publish(TerminalEvent::Output { data });
+                            }
+                        }
+                    }
+                }
+
+                let code = child.wait().ok().map(|status| status.exit_code());
+                debug!("Terminal {id} exited with {code:?}");
+                // Publish the exit while `alive` can't be seen flipped, so a
+                // subscriber gets it from either its snapshot or the live
+                // feed but not both. `subscribe` locks in the same order.
+                let mut terminals = terminals.lock();
+                if let Ok(terminals) = &mut terminals
+                    && let Some(terminal) = terminals.get_mut(&id)
+                {
+                    terminal.info.alive = false;
+                    terminal.info.exit_code = code;
+                }
+                publish(TerminalEvent::Exit { code });
+                drop(terminals);
+            })
+            .context("Failed to start PTY reader thread")?;
+
+        Ok(info)
+    }
+
+    /// Terminals of `project_id`, or of every project, oldest first.
+    pub fn list(&self, project_id: Option<&str>) -> Result<Vec<TerminalInfo>> {
+        let mut terminals: Vec<TerminalInfo> = self
+            .lock()?
+            .values()
+            .filter(|t| project_id.is_none_or(|p| t.info.project_id == p))
+            .map(|t| t.info.clone())
+            .collect();
+        terminals.sort_by_key(|info| info.created_at);
+        Ok(terminals)
+    }
+
+    /// Send keyboard input to the terminal.
+    pub fn write(&self, terminal_id: &str, data: &[u8]) -> Result<()> {
+        let writer = {
+            let terminals = self.lock()?;
+            let terminal = get(&terminals, terminal_id)?;
+            if !terminal.info.alive {
+                anyhow::bail!("Terminal has exited: {terminal_id}");
+            }
+            Arc::clone(&terminal.writer)
+        };
+        let mut writer = writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock terminal writer"))?;
+        writer
+            .write_all(data)
+            .and_then(|()| writer.flush())
+            .context("Failed to write to terminal")
+    }
+
+    pub fn resize(&self, terminal_id: &str, cols: u16, rows: u16) -> Result<()> {
+        let mut terminals = self.lock()?;
+        let terminal = get_mut(&mut terminals, terminal_id)?;
+        terminal
+            .master
+            .resize(pty_size(cols, rows))
+            .context("Failed to resize terminal")?;
+        terminal.info.cols = cols;
+        terminal.info.rows = rows;
+        Ok(())
+    }
+
+    /// Kill the shell if it is still running and forget the terminal.
+    pub fn kill(&self, terminal_id: &str) -> Result<()> {
+        let mut terminal = self
+            .lock()?
+            .remove(terminal_id)
+            .with_context(|| format!("Terminal not found: {terminal_id}"))?;
+        if terminal.info.alive {
+            terminal
+                .killer
+                .kill()
+                .context("Failed to kill terminal process")?;
+        }
+        debug!("Closed terminal {terminal_id}");
+        Ok(())
+    }
+
+    /// Output kept for the terminal, for clients that reattach.
+    pub fn scrollback(&self, terminal_id: &str) -> Result<String> {
+        let screen = Arc::clone(&get(&*self.lock()?, terminal_id)?.screen);
+        let screen = screen
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock terminal screen"))?;
+        Ok(screen.snapshot())
+    }
+
+    /// The terminal's scrollback and a feed of everything printed after it.
+    pub fn subscribe(
+        &self,
+        terminal_id: &str,
+    ) -> Result<(TerminalInfo, String, broadcast::Receiver<TerminalEvent>)> {
+        let terminals = self.lock()?;
+        let terminal = get(&terminals, terminal_id)?;
+        // Holding the screen lock keeps output from landing between the
+        // snapshot and the subscription.
+        let screen = terminal
+            .screen
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock terminal screen"))?;
+        Ok((
+            terminal.info.clone(),
+            screen.snapshot(),
+            screen.live.subscribe(),
+        ))
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, Terminal>>> {
+        self.terminals
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock terminals mutex"))
+    }
+}
+
+fn get<'a>(terminals: &'a HashMap<String, Terminal>, terminal_id: &str) -> Result<&'a Terminal> {
+    terminals
+        .get(terminal_id)
+        .with_context(|| format!("Terminal not found: {terminal_id}"))
+}
+
+fn get_mut<'a>(
+    terminals: &'a mut HashMap<String, Terminal>,
+    terminal_id: &str,
+) -> Result<&'a mut Terminal> {
+    terminals
+        .get_mut(terminal_id)
+        .with_context(|| format!("Terminal not found: {terminal_id}"))
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::MockEventEmitter;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    fn spec(dir: &TempDir, shell: &str) -> TerminalSpec {
+        TerminalSpec {
+            project_id: "project".to_string(),
+            working_directory: dir.path().to_string_lossy().to_string(),
+            shell: Some(shell.to_string()),
+            env: HashMap::new(),
+            cols: DEFAULT_COLS,
+            rows: DEFAULT_ROWS,
+        }
+    }
+
+    fn wait_until(mut done: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if done() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_screen_trims_scrollback() {
+        let mut screen = Screen {
+            scrollback: VecDeque::new(),
+            live: broadcast::channel(4).0,
+        };
+        screen.push(TerminalEvent::Output {
+            data: "a".repeat(SCROLLBACK_LIMIT),
+        });
+        screen.push(TerminalEvent::Output {
+            data: "tail".to_string(),
+        });
+        let snapshot = screen.snapshot();
+        assert_eq!(snapshot.len(), SCROLLBACK_LIMIT);
+        assert!(snapshot.ends_with("aaatail"));
+    }
+
+    #[test]
+    fn test_screen_trims_scrollback_to_a_char_boundary() {
+        let mut screen = Screen {
+            scrollback: VecDeque::new(),
+            live: broadcast::channel(4).0,
+        };
+        screen.push(TerminalEvent::Output {
+            data: "é".repeat(SCROLLBACK_LIMIT / 2),
+        });
+        // One byte over the limit, which cuts the first `é` in half
+        screen.push(TerminalEvent::Output {
+            data: "a".to_string(),
+        });
+        let snapshot = screen.snapshot();
+        assert!(!snapshot.contains(char::REPLACEMENT_CHARACTER));
+        assert!(snapshot.starts_with('é'));
+        assert_eq!(snapshot.len(), SCROLLBACK_LIMIT - 1);
+    }
+
+    #[test]
+    fn test_spawn_rejects_missing_directory() {
+        let manager = TerminalManager::new();
+        let mut spec = spec(&TempDir::new().unwrap(), "sh");
+        spec.working_directory = "/definitely/not/a/real/dir".to_string();
+        assert!(manager.spawn(spec, MockEventEmitter::new()).is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_terminal_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let manager = TerminalManager::new();
+        let emitter = MockEventEmitter::new();
+        let info = manager.spawn(spec(&dir, "sh"), emitter.clone()).unwrap();
+        assert!(info.alive);
+        assert_eq!(manager.list(Some("project")).unwrap().len(), 1);
+        assert!(manager.list(Some("other")).unwrap().is_empty());
+
+        manager.resize(&info.id, 100, 30).unwrap();
+        manager
+            .write(
+                &info.id,
+                b"echo \"cols=$(tput cols 2>/dev/null || echo 100) $PWD\"\n",
+            )
+            .unwrap();
+        let expected = format!("cols=100 {}", dir.path().canonicalize().unwrap().display());
+        assert!(wait_until(|| {
+            manager
+                .subscribe(&info.id)
+                .map(|(_, scrollback, _)| scrollback.contains(&expected))
+                .unwrap_or(false)
+        }));
+
+        // A new subscriber gets the scrollback and then live output.
+        let (_, scrollback, mut live) = manager.subscribe(&info.id).unwrap();
+        assert!(scrollback.contains(&expected));
+        assert!(manager.scrollback(&info.id).unwrap().contains(&expected));
+        manager.write(&info.id, b"exit 7\n").unwrap();
+        assert!(wait_until(|| {
+            matches!(live.try_recv(), Ok(TerminalEvent::Exit { code: Some(7) }))
+        }));
+
+        let finished = &manager.list(None).unwrap()[0];
+        assert!(!finished.alive);
+        assert_eq!(finished.exit_code, Some(7));
+        assert!(manager.write(&info.id, b"ls\n").is_err());
+        assert!(emitter.has_event(&format!("terminal-output-{}", info.id)));
+
+        manager.kill(&info.id).unwrap();
+        assert!(manager.list(None).unwrap().is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_kill_running_terminal() {
+        let dir = TempDir::new().unwrap();
+        let manager = TerminalManager::new();
+        let emitter = MockEventEmitter::new();
+        let info = manager.spawn(spec(&dir, "sh"), emitter.clone()).unwrap();
+        let (_, _, mut live) = manager.subscribe(&info.id).unwrap();
+
+        manager.kill(&info.id).unwrap();
+        assert!(manager.write(&info.id, b"ls\n").is_err());
+        assert!(wait_until(|| {
+            matches!(live.try_recv(), Ok(TerminalEvent::Exit { .. }))
+        }));
+    }
+}
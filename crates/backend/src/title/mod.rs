@@ -0,0 +1,189 @@
+use crate::headless::{HeadlessPermissions, HeadlessSession};
+use crate::projects;
+use crate::session::SessionParams;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+/// Longest title we accept from the model before falling back to the heuristic.
+pub const MAX_TITLE_CHARS: usize = 60;
+
+const HEURISTIC_MAX_WORDS: usize = 6;
+
+/// How long title generation waits for the agent before using the local fallback.
+const TITLE_GENERATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A title request ready to send to the agent, holding everything it needs so
+/// it can run without the backend that prepared it.
+pub struct TitleGeneration {
+    pub(crate) message: String,
+    pub(crate) params: SessionParams,
+    /// History log of the conversation to store the title in, if any.
+    pub(crate) log_path: Option<PathBuf>,
+}
+
+impl TitleGeneration {
+    /// Ask the agent for a title, falling back to one derived from the message
+    /// locally, and store it with the conversation.
+    pub async fn run(self) -> String {
+        let Self {
+            message,
+            params,
+            log_path,
+        } = self;
+        let data_dir = params.data_dir.clone();
+        let generated = async {
+            let mut session =
+                HeadlessSession::start(params, HeadlessPermissions::RejectAll).await?;
+            let turn = session
+                .prompt(&title_prompt(&message), TITLE_GENERATION_TIMEOUT)
+                .await;
+            let _ = session.shutdown();
+            anyhow::Ok(clean_generated_title(&turn?.text))
+        }
+        .await;
+
+        let final_title = match generated {
+            Ok(Some(title)) => title,
+            Ok(None) => {
+                warn!("[TITLE] Agent returned no usable title, using local fallback");
+                heuristic_title(&message)
+            }
+            Err(e) => {
+                warn!("[TITLE] Title generation failed, using local fallback: {e:#}");
+                heuristic_title(&message)
+            }
+        };
+
+        if let Some(log_path) = log_path
+            && let (Some(project_hash), Some(log_filename)) = (
+                log_path
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string()),
+                log_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string()),
+            )
+            && let Err(e) = projects::set_conversation_title(
+                &data_dir,
+                &project_hash,
+                &log_filename,
+                &final_title,
+            )
+        {
+            warn!("[TITLE] Failed to store conversation title: {e:#}");
+        }
+
+        final_title
+    }
+}
+
+/// Prompt asking the agent for a conversation title.
+pub fn title_prompt(message: &str) -> String {
+    format!(
+        "Generate a short, concise title (3-6 words) for a conversation that starts with this user message: \"{}\". Do not use any tools. Only return the title, nothing else.",
+        message.chars().take(200).collect::<String>()
+    )
+}
+
+/// Extract a usable title from the agent's reply, or `None` if the reply does
+/// not look like a title.
+pub fn clean_generated_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).rfind(|l| !l.is_empty())?;
+
+    let line = line.trim_start_matches('#').trim();
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_end_matches('.')
+        .trim_matches(|c| matches!(c, '"' | '\'' | '*' | '`' | '_'))
+        .trim();
+
+    if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+/// Build a title locally from the user's message, used when no agent is
+/// available or it fails to answer.
+pub fn heuristic_title(message: &str) -> String {
+    // Earlier messages are joined with " | "; the first one carries the intent.
+    let first = message.split(" | ").next().unwrap_or(message);
+    let first_line = first
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with("```"))
+        .unwrap_or("");
+    let sentence = first_line
+        .split_terminator(['.', '?', '!'])
+        .next()
+        .unwrap_or(first_line);
+
+    let words: Vec<&str> = sentence
+        .split_whitespace()
+        .filter(|w| !w.starts_with('@'))
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '-' && c != '_'))
+        .filter(|w| !w.is_empty())
+        .take(HEURISTIC_MAX_WORDS)
+        .collect();
+
+    if words.is_empty() {
+        return "New Conversation".to_string();
+    }
+
+    let mut title = words.join(" ");
+    if let Some(first) = title.chars().next() {
+        title = first.to_uppercase().chain(title.chars().skip(1)).collect();
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        title = title.chars().take(MAX_TITLE_CHARS).collect();
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_prompt_truncates_message() {
+        let prompt = title_prompt(&"x".repeat(500));
+        assert!(prompt.contains(&"x".repeat(200)));
+        assert!(!prompt.contains(&"x".repeat(201)));
+    }
+
+    #[test]
+    fn test_clean_generated_title() {
+        assert_eq!(
+            clean_generated_title("Sure!\n\n\"Fix Login Redirect Bug\"\n").as_deref(),
+            Some("Fix Login Redirect Bug")
+        );
+        assert_eq!(
+            clean_generated_title("## Title: **Refactor Parser**.").as_deref(),
+            Some("Refactor Parser")
+        );
+        assert_eq!(clean_generated_title("   \n  "), None);
+        assert_eq!(clean_generated_title(&"word ".repeat(30)), None);
+    }
+
+    #[test]
+    fn test_heuristic_title() {
+        assert_eq!(
+            heuristic_title("please fix the failing tests in @src/lib.rs. They broke yesterday"),
+            "Please fix the failing tests in"
+        );
+        assert_eq!(
+            heuristic_title("how do I add a route? | also add docs"),
+            "How do I add a route"
+        );
+        assert_eq!(heuristic_title("```\ncode\n```"), "Code");
+        assert_eq!(heuristic_title("   "), "New Conversation");
+        assert_eq!(heuristic_title("@README.md"), "New Conversation");
+    }
+}
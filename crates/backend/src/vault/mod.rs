@@ -0,0 +1,746 @@
+use crate::paths::DataDir;
+use crate::redact::{self, mask_api_key};
+use crate::session::{GeminiAuthConfig, LLxprtConfig, QwenConfig};
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const VAULT_VERSION: u32 = 1;
+const VAULT_FILE_NAME: &str = "vault.json";
+/// Binds the ciphertext to this file format so it can't be replayed elsewhere.
+const ASSOCIATED_DATA: &[u8] = b"gemini-cli-desktop-vault-v1";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "gemini-cli-desktop";
+#[cfg(feature = "keyring")]
+const KEYRING_USER: &str = "vault-key";
+
+/// `vault.json` in the data directory.
+pub fn default_vault_path(data_dir: &DataDir) -> Option<PathBuf> {
+    data_dir.path().map(|dir| dir.join(VAULT_FILE_NAME))
+}
+
+/// Where the vault's encryption key comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// Derived from a user passphrase with Argon2id.
+    Passphrase,
+    /// A random key stored in the OS keyring.
+    Keyring,
+}
+
+/// Backend configuration stored in a profile, in the same shape
+/// `start_session` accepts.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "backend", content = "config", rename_all = "snake_case")]
+pub enum ProfileConfig {
+    Gemini(GeminiAuthConfig),
+    Qwen(QwenConfig),
+    Llxprt(LLxprtConfig),
+}
+
+impl ProfileConfig {
+    fn api_key(&self) -> Option<&str> {
+        match self {
+            Self::Gemini(auth) => auth.api_key.as_deref(),
+            Self::Qwen(config) => Some(&config.api_key),
+            Self::Llxprt(config) => Some(&config.api_key),
+        }
+    }
+
+    fn api_key_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Gemini(auth) => auth.api_key.as_mut(),
+            Self::Qwen(config) => Some(&mut config.api_key),
+            Self::Llxprt(config) => Some(&mut config.api_key),
+        }
+    }
+
+    /// Model the profile pins, if its backend has one.
+    pub fn model(&self) -> Option<&str> {
+        match self {
+            Self::Gemini(_) => None,
+            Self::Qwen(config) => Some(&config.model),
+            Self::Llxprt(config) => Some(&config.model),
+        }
+    }
+
+    /// Split into the optional configs `initialize_session` takes.
+    pub fn into_session_configs(
+        self,
+    ) -> (
+        Option<QwenConfig>,
+        Option<GeminiAuthConfig>,
+        Option<LLxprtConfig>,
+    ) {
+        match self {
+            Self::Gemini(auth) => (None, Some(auth), None),
+            Self::Qwen(config) => (Some(config), None, None),
+            Self::Llxprt(config) => (None, None, Some(config)),
+        }
+    }
+
+    /// Copy of this config that is safe to hand to the frontend.
+    fn masked(&self) -> Self {
+        let mut masked = self.clone();
+        if let Some(key) = masked.api_key_mut()
+            && !key.is_empty()
+        {
+            *key = mask_api_key(key);
+        }
+        masked
+    }
+}
+
+/// A named set of provider credentials.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProviderProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ProfileConfig,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProviderProfile {
+    /// The profile with its API key masked, as returned by the listing APIs.
+    pub fn summary(&self) -> ProviderProfile {
+        ProviderProfile {
+            config: self.config.masked(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Input for creating or updating a profile. On update, an empty API key
+/// keeps the stored one, so the frontend never needs to send it back.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileInput {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ProfileConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+    pub key_source: Option<KeySource>,
+    pub keyring_available: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfHeader {
+    salt: String,
+    #[serde(flatten)]
+    params: KdfParams,
+}
+
+/// On-disk layout. Only `ciphertext` holds profile data.
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    key_source: KeySource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<KdfHeader>,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct Unlocked {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    key_source: KeySource,
+    kdf: Option<KdfHeader>,
+    profiles: Vec<ProviderProfile>,
+}
+
+/// Encrypted store of provider profiles. Profiles are only readable while the
+/// vault is unlocked; the key is dropped (and zeroed) on `lock`.
+pub struct Vault {
+    path: PathBuf,
+    kdf_params: KdfParams,
+    unlocked: Option<Unlocked>,
+}
+
+impl Vault {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            kdf_params: KdfParams::default(),
+            unlocked: None,
+        }
+    }
+
+    /// Argon2 cost used when creating a vault. Existing vaults keep the cost
+    /// they were created with.
+    pub fn with_kdf_params(mut self, params: KdfParams) -> Self {
+        self.kdf_params = params;
+        self
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        let key_source = self
+            .unlocked
+            .as_ref()
+            .map(|u| u.key_source)
+            .or_else(|| self.read_file().ok().flatten().map(|file| file.key_source));
+        VaultStatus {
+            exists: self.path.exists(),
+            unlocked: self.unlocked.is_some(),
+            key_source,
+            keyring_available: cfg!(feature = "keyring"),
+        }
+    }
+
+    /// Create an empty vault protected by `passphrase`, or by a key in the OS
+    /// keyring when no passphrase is given.
+    pub fn create(&mut self, passphrase: Option<&str>) -> Result<()> {
+        if self.path.exists() {
+            anyhow::bail!("A vault already exists at {}", self.path.display());
+        }
+
+        let (key, key_source, kdf) = match passphrase {
+            Some(passphrase) => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let kdf = KdfHeader {
+                    salt: BASE64.encode(salt),
+                    params: self.kdf_params,
+                };
+                (
+                    derive_key(passphrase, &kdf)?,
+                    KeySource::Passphrase,
+                    Some(kdf),
+                )
+            }
+            None => {
+                let mut key = Zeroizing::new([0u8; KEY_LEN]);
+                OsRng.fill_bytes(key.as_mut());
+                keyring::store_key(&key)?;
+                (key, KeySource::Keyring, None)
+            }
+        };
+
+        self.unlocked = Some(Unlocked {
+            key,
+            key_source,
+            kdf,
+            profiles: Vec::new(),
+        });
+        self.save()
+    }
+
+    /// Decrypt the vault. `passphrase` is required for passphrase vaults and
+    /// ignored for keyring vaults.
+    pub fn unlock(&mut self, passphrase: Option<&str>) -> Result<()> {
+        let file = self.read_file()?.context("No vault has been created yet")?;
+        if file.version != VAULT_VERSION {
+            anyhow::bail!("Unsupported vault version {}", file.version);
+        }
+
+        let key = match file.key_source {
+            KeySource::Passphrase => {
+                let passphrase = passphrase.context("This vault needs a passphrase to unlock")?;
+                let kdf = file
+                    .kdf
+                    .as_ref()
+                    .context("Vault is missing its KDF header")?;
+                derive_key(passphrase, kdf)?
+            }
+            KeySource::Keyring => keyring::load_key()?,
+        };
+
+        let nonce = BASE64
+            .decode(&file.nonce)
+            .context("Vault nonce is not valid base64")?;
+        if nonce.len() != 24 {
+            anyhow::bail!("Vault nonce has the wrong length");
+        }
+        let ciphertext = BASE64
+            .decode(&file.ciphertext)
+            .context("Vault ciphertext is not valid base64")?;
+
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: ASSOCIATED_DATA,
+                    },
+                )
+                .map_err(|_| match file.key_source {
+                    KeySource::Passphrase => anyhow::anyhow!("Incorrect vault passphrase"),
+                    KeySource::Keyring => {
+                        anyhow::anyhow!("The keyring key does not match this vault")
+                    }
+                })?,
+        );
+        let profiles: Vec<ProviderProfile> =
+            serde_json::from_slice(&plaintext).context("Failed to parse vault contents")?;
+
+        for profile in &profiles {
+            if let Some(key) = profile.config.api_key() {
+                redact::register_secret(key);
+            }
+        }
+
+        self.unlocked = Some(Unlocked {
+            key,
+            key_source: file.key_source,
+            kdf: file.kdf,
+            profiles,
+        });
+        Ok(())
+    }
+
+    pub fn lock(&mut self) {
+        self.unlocked = None;
+    }
+
+    pub fn list_profiles(&self) -> Result<Vec<ProviderProfile>> {
+        Ok(self
+            .unlocked()?
+            .profiles
+            .iter()
+            .map(ProviderProfile::summary)
+            .collect())
+    }
+
+    /// Full profile including its API key, for starting sessions. Never send
+    /// the result to the frontend.
+    pub fn profile(&self, id: &str) -> Result<ProviderProfile> {
+        self.unlocked()?
+            .profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .cloned()
+            .with_context(|| format!("Profile not found: {id}"))
+    }
+
+    pub fn create_profile(&mut self, input: ProfileInput) -> Result<ProviderProfile> {
+        let now = Utc::now();
+        let profile = ProviderProfile {
+            id: new_profile_id(),
+            name: validate_name(input.name)?,
+            config: input.config,
+            created_at: now,
+            updated_at: now,
+        };
+        if let Some(key) = profile.config.api_key() {
+            redact::register_secret(key);
+        }
+
+        self.unlocked_mut()?.profiles.push(profile.clone());
+        self.save()?;
+        Ok(profile.summary())
+    }
+
+    pub fn update_profile(&mut self, id: &str, input: ProfileInput) -> Result<ProviderProfile> {
+        let name = validate_name(input.name)?;
+        let unlocked = self.unlocked_mut()?;
+        let profile = unlocked
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.id == id)
+            .with_context(|| format!("Profile not found: {id}"))?;
+
+        let mut config = input.config;
+        if let Some(new_key) = config.api_key_mut()
+            && new_key.is_empty()
+            && let Some(old_key) = profile.config.api_key()
+        {
+            *new_key = old_key.to_string();
+        }
+        if let Some(key) = config.api_key() {
+            redact::register_secret(key);
+        }
+
+        profile.name = name;
+        profile.config = config;
+        profile.updated_at = Utc::now();
+        let summary = profile.summary();
+
+        self.save()?;
+        Ok(summary)
+    }
+
+    pub fn delete_profile(&mut self, id: &str) -> Result<()> {
+        let unlocked = self.unlocked_mut()?;
+        let before = unlocked.profiles.len();
+        unlocked.profiles.retain(|profile| profile.id != id);
+        if unlocked.profiles.len() == before {
+            anyhow::bail!("Profile not found: {id}");
+        }
+        self.save()
+    }
+
+    fn unlocked(&self) -> Result<&Unlocked> {
+        self.unlocked.as_ref().context("The vault is locked")
+    }
+
+    fn unlocked_mut(&mut self) -> Result<&mut Unlocked> {
+        self.unlocked.as_mut().context("The vault is locked")
+    }
+
+    fn read_file(&self) -> Result<Option<VaultFile>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&self.path).context("Failed to read vault file")?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .context("Failed to parse vault file")
+    }
+
+    /// Re-encrypt everything under a fresh nonce and atomically replace the file.
+    fn save(&self) -> Result<()> {
+        let unlocked = self.unlocked()?;
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&unlocked.profiles).context("Failed to serialize profiles")?,
+        );
+
+        let cipher = XChaCha20Poly1305::new(unlocked.key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: ASSOCIATED_DATA,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt vault"))?;
+
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            key_source: unlocked.key_source,
+            kdf: unlocked.kdf.clone(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create vault directory")?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(&file).context("Failed to serialize vault")?;
+        write_private(&tmp_path, content.as_bytes())?;
+        std::fs::rename(&tmp_path, &self.path).context("Failed to replace vault file")?;
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfHeader) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+    let salt = BASE64
+        .decode(&kdf.salt)
+        .context("Vault salt is not valid base64")?;
+    let params = Params::new(
+        kdf.params.m_cost,
+        kdf.params.t_cost,
+        kdf.params.p_cost,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid vault KDF parameters: {e}"))?;
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Failed to derive vault key: {e}"))?;
+    Ok(key)
+}
+
+fn validate_name(name: String) -> Result<String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        anyhow::bail!("Profile name cannot be empty");
+    }
+    Ok(name)
+}
+
+fn new_profile_id() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Write a file readable only by the current user where the platform
+/// supports it.
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .context("Failed to create temporary vault file")?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .context("Failed to write temporary vault file")
+}
+
+#[cfg(feature = "keyring")]
+mod keyring {
+    use super::{BASE64, KEY_LEN, KEYRING_SERVICE, KEYRING_USER};
+    use anyhow::{Context, Result};
+    use base64::Engine;
+    use zeroize::Zeroizing;
+
+    fn entry() -> Result<::keyring::Entry> {
+        ::keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Failed to open OS keyring")
+    }
+
+    pub fn store_key(key: &[u8; KEY_LEN]) -> Result<()> {
+        entry()?
+            .set_password(&BASE64.encode(key))
+            .context("Failed to store vault key in OS keyring")
+    }
+
+    pub fn load_key() -> Result<Zeroizing<[u8; KEY_LEN]>> {
+        let encoded = Zeroizing::new(
+            entry()?
+                .get_password()
+                .context("Failed to read vault key from OS keyring")?,
+        );
+        let decoded = Zeroizing::new(
+            BASE64
+                .decode(encoded.as_bytes())
+                .context("Vault key in OS keyring is corrupt")?,
+        );
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        if decoded.len() != KEY_LEN {
+            anyhow::bail!("Vault key in OS keyring has the wrong length");
+        }
+        key.copy_from_slice(&decoded);
+        Ok(key)
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+mod keyring {
+    use super::KEY_LEN;
+    use anyhow::Result;
+    use zeroize::Zeroizing;
+
+    const UNAVAILABLE: &str =
+        "OS keyring support is not available in this build; use a passphrase instead";
+
+    pub fn store_key(_key: &[u8; KEY_LEN]) -> Result<()> {
+        anyhow::bail!(UNAVAILABLE)
+    }
+
+    pub fn load_key() -> Result<Zeroizing<[u8; KEY_LEN]>> {
+        anyhow::bail!(UNAVAILABLE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Cheap Argon2 settings so tests don't spend seconds deriving keys.
+    const TEST_KDF: KdfParams = KdfParams {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn test_vault(dir: &TempDir) -> Vault {
+        Vault::new(dir.path().join("vault.json")).with_kdf_params(TEST_KDF)
+    }
+
+    fn qwen_input(name: &str, api_key: &str) -> ProfileInput {
+        ProfileInput {
+            name: name.to_string(),
+            config: ProfileConfig::Qwen(QwenConfig {
+                api_key: api_key.to_string(),
+                base_url: "https://api.example.com/v1".to_string(),
+                model: "qwen3-coder".to_string(),
+                yolo: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_create_and_unlock_with_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        assert!(!vault.status().exists);
+
+        vault.create(Some("correct horse")).unwrap();
+        let created = vault
+            .create_profile(qwen_input("Work", "sk-work-1234567890abcdef"))
+            .unwrap();
+        vault.lock();
+        assert!(vault.list_profiles().is_err());
+
+        let status = vault.status();
+        assert!(status.exists && !status.unlocked);
+        assert_eq!(status.key_source, Some(KeySource::Passphrase));
+
+        assert!(vault.unlock(Some("wrong horse")).is_err());
+        assert!(vault.unlock(None).is_err());
+        vault.unlock(Some("correct horse")).unwrap();
+
+        let profile = vault.profile(&created.id).unwrap();
+        assert_eq!(profile.name, "Work");
+        assert_eq!(profile.config.api_key(), Some("sk-work-1234567890abcdef"));
+    }
+
+    #[test]
+    fn test_file_does_not_contain_secrets() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        vault.create(Some("pass")).unwrap();
+        vault
+            .create_profile(qwen_input("Personal", "sk-personal-secret-value"))
+            .unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("vault.json")).unwrap();
+        assert!(!content.contains("sk-personal-secret-value"));
+        assert!(!content.contains("Personal"));
+        assert!(content.contains("\"key_source\": \"passphrase\""));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("vault.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_listing_masks_keys() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        vault.create(Some("pass")).unwrap();
+        let summary = vault
+            .create_profile(qwen_input("Work", "sk-work-1234567890abcdef"))
+            .unwrap();
+
+        assert_eq!(summary.config.api_key(), Some("sk-w…cdef"));
+        let listed = vault.list_profiles().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].config.api_key(), Some("sk-w…cdef"));
+    }
+
+    #[test]
+    fn test_update_keeps_key_when_blank() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        vault.create(Some("pass")).unwrap();
+        let created = vault
+            .create_profile(qwen_input("Work", "sk-work-1234567890abcdef"))
+            .unwrap();
+
+        let updated = vault
+            .update_profile(&created.id, qwen_input("Work (renamed)", ""))
+            .unwrap();
+        assert_eq!(updated.name, "Work (renamed)");
+        assert_eq!(
+            vault.profile(&created.id).unwrap().config.api_key(),
+            Some("sk-work-1234567890abcdef")
+        );
+
+        vault
+            .update_profile(&created.id, qwen_input("Work", "sk-rotated-0987654321"))
+            .unwrap();
+        assert_eq!(
+            vault.profile(&created.id).unwrap().config.api_key(),
+            Some("sk-rotated-0987654321")
+        );
+    }
+
+    #[test]
+    fn test_delete_profile_and_errors() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        assert!(vault.unlock(Some("pass")).is_err());
+        vault.create(Some("pass")).unwrap();
+        assert!(vault.create(Some("pass")).is_err());
+        assert!(vault.create_profile(qwen_input("  ", "key")).is_err());
+
+        let created = vault.create_profile(qwen_input("Work", "key")).unwrap();
+        vault.delete_profile(&created.id).unwrap();
+        assert!(vault.delete_profile(&created.id).is_err());
+        assert!(vault.profile(&created.id).is_err());
+    }
+
+    #[test]
+    fn test_profile_config_into_session_configs() {
+        let config = ProfileConfig::Llxprt(LLxprtConfig {
+            provider: "anthropic".to_string(),
+            api_key: "key".to_string(),
+            model: "claude".to_string(),
+            base_url: None,
+        });
+        assert_eq!(config.model(), Some("claude"));
+        let (qwen, gemini, llxprt) = config.into_session_configs();
+        assert!(qwen.is_none() && gemini.is_none());
+        assert_eq!(llxprt.unwrap().provider, "anthropic");
+
+        let json = serde_json::to_value(ProfileInput {
+            name: "Gemini".to_string(),
+            config: ProfileConfig::Gemini(GeminiAuthConfig {
+                method: "oauth-personal".to_string(),
+                api_key: None,
+                vertex_project: None,
+                vertex_location: None,
+                yolo: None,
+            }),
+        })
+        .unwrap();
+        assert_eq!(json["backend"], "gemini");
+        assert_eq!(json["config"]["method"], "oauth-personal");
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    #[test]
+    fn test_keyring_unavailable_without_feature() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = test_vault(&dir);
+        assert!(!vault.status().keyring_available);
+        assert!(vault.create(None).is_err());
+        assert!(!vault.status().exists);
+    }
+}
@@ -0,0 +1,824 @@
+use anyhow::{Context, Result};
+use backend::acp::{
+    PermissionOptionKind, SessionRequestPermissionParams, SessionUpdate, ToolCallKind,
+    ToolCallStatus,
+};
+use backend::{
+    AppEvent, ChannelEventEmitter, GeminiAuthConfig, GeminiBackend, LogConfig,
+    PermissionRequestPayload, SearchFilters, logging,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+
+/// Terminal client for Gemini CLI Desktop.
+///
+/// Sessions record their history in the same `~/.gemini-cli-desktop`
+/// directory as the desktop and web apps, so conversations started here show
+/// up there and vice versa.
+#[derive(Parser)]
+#[command(name = "gemini-cli-desktop-cli", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start a session and chat with the agent
+    Chat(ChatArgs),
+    /// List known projects
+    Projects {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Browse, search and export saved conversations
+    #[command(subcommand)]
+    History(HistoryCommand),
+}
+
+#[derive(Args)]
+struct ChatArgs {
+    /// Prompt to send. Without one, the prompt is read from stdin when it is
+    /// piped; otherwise an interactive chat starts.
+    prompt: Vec<String>,
+    /// Project directory the session runs in
+    #[arg(short = 'C', long, default_value = ".")]
+    dir: PathBuf,
+    #[arg(short, long)]
+    model: Option<String>,
+    /// Provider profile from the credential vault, instead of the Gemini
+    /// CLI's own login
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_PROFILE")]
+    profile: Option<String>,
+    /// Gemini CLI auth method when no profile is given. Defaults to
+    /// `gemini-api-key` when GEMINI_API_KEY is set and `oauth-personal`
+    /// otherwise.
+    #[arg(long)]
+    auth: Option<String>,
+    /// How to answer permission prompts. Defaults to `ask` on a terminal and
+    /// `reject` otherwise; `ask` needs a terminal on stdin.
+    #[arg(long, value_enum)]
+    permissions: Option<PermissionPolicy>,
+    /// Always allow tool calls of this kind, whatever the policy (repeatable)
+    #[arg(long = "allow", value_enum)]
+    allow: Vec<ToolKind>,
+    /// Print the agent's thoughts to stderr
+    #[arg(long)]
+    thoughts: bool,
+    /// Give up on a turn after this many seconds
+    #[arg(long)]
+    timeout: Option<u64>,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// List recent conversations
+    Recent {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Search all conversations
+    Search {
+        query: String,
+        /// Only search this project (its SHA-256 id)
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        max_results: Option<u32>,
+        #[arg(long)]
+        case_sensitive: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export one conversation
+    Export {
+        chat_id: String,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum PermissionPolicy {
+    /// Prompt on the terminal
+    Ask,
+    /// Allow every tool call once
+    Allow,
+    /// Reject every tool call
+    Reject,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ToolKind {
+    Read,
+    Edit,
+    Execute,
+    Search,
+    Fetch,
+    Other,
+}
+
+impl ToolKind {
+    fn matches(self, kind: &ToolCallKind) -> bool {
+        matches!(
+            (self, kind),
+            (ToolKind::Read, ToolCallKind::Read)
+                | (ToolKind::Edit, ToolCallKind::Edit)
+                | (ToolKind::Execute, ToolCallKind::Execute)
+                | (ToolKind::Search, ToolCallKind::Search)
+                | (ToolKind::Fetch, ToolCallKind::Fetch)
+                | (ToolKind::Other, ToolCallKind::Other)
+        )
+    }
+}
+
+impl PermissionPolicy {
+    /// The policy for `--permissions`. Without a terminal there is nobody to
+    /// ask, so the default falls back to `reject` and an explicit `ask` is an
+    /// error.
+    fn resolve(requested: Option<Self>, interactive: bool) -> Result<Self> {
+        match requested {
+            Some(Self::Ask) if !interactive => {
+                anyhow::bail!("--permissions ask needs a terminal on stdin; use allow or reject")
+            }
+            Some(policy) => Ok(policy),
+            None if interactive => Ok(Self::Ask),
+            None => Ok(Self::Reject),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Name the backend's exporter knows the format by.
+    fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "markdown",
+        }
+    }
+}
+
+type Backend = GeminiBackend<ChannelEventEmitter>;
+type Events = mpsc::UnboundedReceiver<AppEvent>;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Stdout carries the agent's output, so logs only go to the shared log files.
+    let _log_guard = logging::init(LogConfig {
+        stdout: false,
+        ..LogConfig::default()
+    })
+    .inspect_err(|e| eprintln!("Failed to initialize logging: {e:#}"))
+    .ok();
+
+    let (emitter, events) = ChannelEventEmitter::new();
+    let backend = GeminiBackend::new(emitter);
+
+    let result = match cli.command {
+        Command::Chat(args) => chat(&backend, events, args).await,
+        Command::Projects { json } => projects(&backend, json).await,
+        Command::History(command) => history(&backend, command).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// =====================================
+// Chat
+// =====================================
+
+async fn chat(backend: &Backend, events: Events, args: ChatArgs) -> Result<()> {
+    let working_directory = args
+        .dir
+        .canonicalize()
+        .with_context(|| format!("Project directory not found: {}", args.dir.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let stdin_is_terminal = std::io::stdin().is_terminal();
+    let prompt = if !args.prompt.is_empty() {
+        Some(args.prompt.join(" "))
+    } else if !stdin_is_terminal {
+        let mut prompt = String::new();
+        std::io::stdin()
+            .read_to_string(&mut prompt)
+            .context("Failed to read prompt from stdin")?;
+        Some(prompt)
+    } else {
+        None
+    };
+    let policy = PermissionPolicy::resolve(args.permissions, stdin_is_terminal)?;
+
+    let session_id = format!(
+        "cli-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    );
+    start_session(backend, &session_id, working_directory, &args).await?;
+
+    let mut chat = Chat {
+        backend,
+        events,
+        session_id: session_id.clone(),
+        policy,
+        allow: args.allow,
+        thoughts: args.thoughts,
+        timeout: args.timeout.map(Duration::from_secs),
+    };
+    let result = match prompt {
+        Some(prompt) => chat.turn(&prompt).await,
+        None => chat.repl().await,
+    };
+
+    if let Err(e) = backend.kill_process(&session_id) {
+        debug!("Failed to stop session {session_id}: {e:#}");
+    }
+    result
+}
+
+async fn start_session(
+    backend: &Backend,
+    session_id: &str,
+    working_directory: String,
+    args: &ChatArgs,
+) -> Result<()> {
+    if let Some(profile_id) = &args.profile {
+        let passphrase = std::env::var("GEMINI_CLI_DESKTOP_VAULT_PASSPHRASE").ok();
+        backend
+            .unlock_vault(passphrase)
+            .await
+            .context("Failed to unlock the credential vault")?;
+        return backend
+            .initialize_session_with_profile(
+                session_id.to_string(),
+                working_directory,
+                args.model.clone(),
+                profile_id,
+            )
+            .await;
+    }
+
+    let api_key = std::env::var("GEMINI_API_KEY").ok();
+    let method = args.auth.clone().unwrap_or_else(|| {
+        if api_key.is_some() {
+            "gemini-api-key".to_string()
+        } else {
+            "oauth-personal".to_string()
+        }
+    });
+    backend
+        .initialize_session(
+            session_id.to_string(),
+            working_directory,
+            args.model
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            None,
+            Some(GeminiAuthConfig {
+                method,
+                api_key,
+                vertex_project: std::env::var("GOOGLE_CLOUD_PROJECT").ok(),
+                vertex_location: std::env::var("GOOGLE_CLOUD_LOCATION").ok(),
+                yolo: None,
+            }),
+            None,
+        )
+        .await
+}
+
+struct Chat<'a> {
+    backend: &'a Backend,
+    events: Events,
+    session_id: String,
+    policy: PermissionPolicy,
+    allow: Vec<ToolKind>,
+    thoughts: bool,
+    timeout: Option<Duration>,
+}
+
+impl Chat<'_> {
+    async fn repl(&mut self) -> Result<()> {
+        eprintln!("Type a message and press Enter. /exit or Ctrl-D quits.");
+        loop {
+            eprint!("> ");
+            let _ = std::io::stderr().flush();
+            let Some(line) = read_line().await? else {
+                eprintln!();
+                return Ok(());
+            };
+            let line = line.trim();
+            match line {
+                "" => continue,
+                "/exit" | "/quit" => return Ok(()),
+                _ => {
+                    if let Err(e) = self.turn(line).await {
+                        eprintln!("error: {e:#}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send one prompt and stream the reply until the turn finishes.
+    async fn turn(&mut self, prompt: &str) -> Result<()> {
+        self.backend
+            .send_message(self.session_id.clone(), prompt.to_string(), String::new())
+            .await?;
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.stream_reply())
+                .await
+                .context("Timed out waiting for the agent to finish its turn")?,
+            None => self.stream_reply().await,
+        }
+    }
+
+    async fn stream_reply(&mut self) -> Result<()> {
+        let mut stdout = std::io::stdout();
+        let mut ends_with_newline = true;
+        while let Some(event) = self.events.recv().await {
+            match event {
+                AppEvent::AiOutput {
+                    session_id,
+                    payload: text,
+                } if session_id == self.session_id && !text.is_empty() => {
+                    let _ = stdout.write_all(text.as_bytes());
+                    let _ = stdout.flush();
+                    ends_with_newline = text.ends_with('\n');
+                }
+                AppEvent::AiThought {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id && self.thoughts => eprint!("{payload}"),
+                AppEvent::AiTurnFinished { session_id, .. } if session_id == self.session_id => {
+                    if !ends_with_newline {
+                        println!();
+                    }
+                    return Ok(());
+                }
+                AppEvent::AiError {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => {
+                    anyhow::bail!("Agent reported an error: {payload}");
+                }
+                AppEvent::AcpSessionUpdate {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => match payload {
+                    SessionUpdate::ToolCall { title, .. } => eprintln!("[tool] {title}"),
+                    SessionUpdate::ToolCallUpdate {
+                        tool_call_id,
+                        status: ToolCallStatus::Failed,
+                        ..
+                    } => eprintln!("[tool] {tool_call_id} failed"),
+                    _ => {}
+                },
+                AppEvent::AcpPermissionRequest {
+                    session_id,
+                    payload,
+                } if session_id == self.session_id => {
+                    self.answer_permission(payload).await?;
+                }
+                _ => {}
+            }
+        }
+
+        anyhow::bail!("Session ended before the turn finished")
+    }
+
+    async fn answer_permission(&self, payload: PermissionRequestPayload) -> Result<()> {
+        let PermissionRequestPayload {
+            request_id,
+            request,
+        } = payload;
+        let request_id = u32::try_from(request_id).context("Permission request id out of range")?;
+
+        let allowed = self
+            .allow
+            .iter()
+            .any(|kind| kind.matches(&request.tool_call.kind));
+        let option_id = match (allowed, self.policy) {
+            (true, _) | (false, PermissionPolicy::Allow) => option_of_kind(&request, |kind| {
+                matches!(kind, PermissionOptionKind::AllowOnce)
+            }),
+            (false, PermissionPolicy::Reject) => option_of_kind(&request, |kind| {
+                matches!(kind, PermissionOptionKind::RejectOnce)
+            }),
+            (false, PermissionPolicy::Ask) => ask_permission(&request).await?,
+        };
+
+        let option_id = option_id.unwrap_or_else(|| "cancel".to_string());
+        eprintln!("[permission] {}: {option_id}", request.tool_call.title);
+        self.backend
+            .handle_tool_confirmation(
+                request.session_id.clone(),
+                request_id,
+                request.tool_call.tool_call_id.clone(),
+                option_id,
+            )
+            .await
+    }
+}
+
+fn option_of_kind(
+    request: &SessionRequestPermissionParams,
+    wanted: impl Fn(&PermissionOptionKind) -> bool,
+) -> Option<String> {
+    request
+        .options
+        .iter()
+        .find(|option| wanted(&option.kind))
+        .map(|option| option.option_id.clone())
+}
+
+/// Let the user pick one of the request's options. `None` cancels the tool call.
+async fn ask_permission(request: &SessionRequestPermissionParams) -> Result<Option<String>> {
+    eprintln!("\nPermission requested: {}", request.tool_call.title);
+    for (i, option) in request.options.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, option.name);
+    }
+    loop {
+        eprint!("Choose 1-{} (empty cancels): ", request.options.len());
+        let _ = std::io::stderr().flush();
+        let Some(answer) = read_line().await? else {
+            return Ok(None);
+        };
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        if let Some(option) = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| request.options.get(i))
+        {
+            return Ok(Some(option.option_id.clone()));
+        }
+    }
+}
+
+/// Read one line from stdin without blocking the runtime. `None` at EOF.
+async fn read_line() -> Result<Option<String>> {
+    tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut line)
+            .context("Failed to read from stdin")?;
+        Ok((read > 0).then_some(line))
+    })
+    .await
+    .context("Stdin reader panicked")?
+}
+
+// =====================================
+// Projects and history
+// =====================================
+
+async fn projects(backend: &Backend, json: bool) -> Result<()> {
+    let projects = backend.list_enriched_projects().await?;
+    if json {
+        return print_json(&projects);
+    }
+    for project in projects {
+        println!(
+            "{}  {}  {}",
+            &project.sha256[..12.min(project.sha256.len())],
+            project.metadata.friendly_name,
+            project.root_path.display()
+        );
+    }
+    Ok(())
+}
+
+async fn history(backend: &Backend, command: HistoryCommand) -> Result<()> {
+    match command {
+        HistoryCommand::Recent { json } => {
+            let chats = backend.get_recent_chats().await?;
+            if json {
+                return print_json(&chats);
+            }
+            for chat in chats {
+                println!("{}  {}  {}", chat.id, chat.started_at_iso, chat.title);
+            }
+        }
+        HistoryCommand::Search {
+            query,
+            project,
+            max_results,
+            case_sensitive,
+            json,
+        } => {
+            let filters = SearchFilters {
+                date_range: None,
+                project_hash: project,
+                max_results,
+                case_sensitive: Some(case_sensitive),
+                include_thinking: None,
+            };
+            let results = backend.search_chats(query, Some(filters)).await?;
+            if json {
+                return print_json(&results);
+            }
+            for result in results {
+                println!("{}  {}", result.chat.id, result.chat.title);
+                for m in result.matches {
+                    println!("    [{}] {}", m.role, m.content_snippet.trim());
+                }
+            }
+        }
+        HistoryCommand::Export {
+            chat_id,
+            format,
+            output,
+        } => {
+            let exported = backend
+                .export_conversation_history(&chat_id, format.as_str())
+                .await?;
+            match output {
+                Some(path) => std::fs::write(&path, exported)
+                    .with_context(|| format!("Failed to write {}", path.display()))?,
+                None => print!("{exported}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).context("Failed to serialize output")?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("gemini-cli-desktop-cli").chain(args.iter().copied()))
+    }
+
+    fn chat_args(args: &[&str]) -> ChatArgs {
+        match parse(args).unwrap().command {
+            Command::Chat(args) => args,
+            _ => panic!("Expected the chat command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_chat_args() {
+        let args = chat_args(&[
+            "chat",
+            "-C",
+            "/tmp/project",
+            "--model",
+            "gemini-2.5-pro",
+            "--permissions",
+            "allow",
+            "--allow",
+            "read",
+            "--allow",
+            "execute",
+            "--timeout",
+            "30",
+            "explain",
+            "this",
+        ]);
+        assert_eq!(args.prompt, ["explain", "this"]);
+        assert_eq!(args.dir, PathBuf::from("/tmp/project"));
+        assert_eq!(args.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(args.permissions, Some(PermissionPolicy::Allow));
+        assert_eq!(args.allow, [ToolKind::Read, ToolKind::Execute]);
+        assert_eq!(args.timeout, Some(30));
+        assert!(!args.thoughts);
+    }
+
+    #[test]
+    fn test_chat_defaults() {
+        let args = chat_args(&["chat"]);
+        assert!(args.prompt.is_empty());
+        assert_eq!(args.dir, PathBuf::from("."));
+        assert_eq!(args.permissions, None);
+        assert!(args.allow.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        assert!(parse(&["chat", "--permissions", "sometimes"]).is_err());
+        assert!(parse(&["chat", "--allow", "everything"]).is_err());
+        assert!(parse(&["history", "export", "a/b.log", "--format", "html"]).is_err());
+    }
+
+    #[test]
+    fn test_permission_policy() {
+        use PermissionPolicy::*;
+        assert_eq!(PermissionPolicy::resolve(None, true).unwrap(), Ask);
+        assert_eq!(PermissionPolicy::resolve(None, false).unwrap(), Reject);
+        assert_eq!(PermissionPolicy::resolve(Some(Ask), true).unwrap(), Ask);
+        assert_eq!(
+            PermissionPolicy::resolve(Some(Allow), false).unwrap(),
+            Allow
+        );
+        assert_eq!(
+            PermissionPolicy::resolve(Some(Reject), true).unwrap(),
+            Reject
+        );
+        // An explicit `ask` is never silently turned into `reject`
+        assert!(PermissionPolicy::resolve(Some(Ask), false).is_err());
+    }
+
+    #[test]
+    fn test_tool_kind_matches() {
+        assert!(ToolKind::Edit.matches(&ToolCallKind::Edit));
+        assert!(!ToolKind::Edit.matches(&ToolCallKind::Execute));
+        assert!(ToolKind::Other.matches(&ToolCallKind::Other));
+    }
+
+    #[test]
+    fn test_history_export_args() {
+        let Command::History(HistoryCommand::Export {
+            chat_id,
+            format,
+            output,
+        }) = parse(&["history", "export", "abc/rpc-log-1.log"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected history export");
+        };
+        assert_eq!(chat_id, "abc/rpc-log-1.log");
+        assert_eq!(format, ExportFormat::Markdown);
+        assert_eq!(output, None);
+
+        let Command::History(HistoryCommand::Export { format, output, .. }) = parse(&[
+            "history",
+            "export",
+            "abc/rpc-log-1.log",
+            "--format",
+            "json",
+            "-o",
+            "chat.json",
+        ])
+        .unwrap()
+        .command
+        else {
+            panic!("Expected history export");
+        };
+        assert_eq!(format, ExportFormat::Json);
+        assert_eq!(output, Some(PathBuf::from("chat.json")));
+    }
+
+    /// A data directory under the system temp dir holding one conversation.
+    struct History {
+        dir: PathBuf,
+        chat_id: String,
+    }
+
+    impl History {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "gemini-cli-desktop-cli-{name}-{}",
+                std::process::id()
+            ));
+            let project = dir.join("projects").join("abc123");
+            std::fs::create_dir_all(&project).unwrap();
+            let prompt = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "session/prompt",
+                "params": {
+                    "sessionId": "s1",
+                    "prompt": [{ "type": "text", "text": "What is in main.rs?" }]
+                }
+            });
+            let reply = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "session/update",
+                "params": {
+                    "sessionId": "s1",
+                    "update": {
+                        "sessionUpdate": "agent_message_chunk",
+                        "content": { "type": "text", "text": "A main function." }
+                    }
+                }
+            });
+            std::fs::write(
+                project.join("rpc-log-1700000000000.log"),
+                format!(
+                    "[2023-11-14T22:13:20.000Z] [Gemini] {prompt}\n[2023-11-14T22:13:21.000Z] [Gemini] {reply}\n"
+                ),
+            )
+            .unwrap();
+            Self {
+                dir,
+                chat_id: "abc123/rpc-log-1700000000000.log".to_string(),
+            }
+        }
+
+        fn backend(&self) -> Backend {
+            GeminiBackend::new(ChannelEventEmitter::new().0).with_data_dir(self.dir.clone())
+        }
+
+        async fn export(&self, format: ExportFormat) -> String {
+            let output = self.dir.join("export");
+            history(
+                &self.backend(),
+                HistoryCommand::Export {
+                    chat_id: self.chat_id.clone(),
+                    format,
+                    output: Some(output.clone()),
+                },
+            )
+            .await
+            .unwrap();
+            std::fs::read_to_string(output).unwrap()
+        }
+    }
+
+    impl Drop for History {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_export_markdown() {
+        let history = History::new("markdown");
+        let markdown = history.export(ExportFormat::Markdown).await;
+        assert!(markdown.starts_with("# "), "{markdown}");
+        assert!(markdown.contains("**Messages:** "), "{markdown}");
+        assert!(markdown.contains("## Conversation"), "{markdown}");
+        assert!(
+            markdown.contains("**User:** What is in main.rs?\n\n**Assistant:** A main function."),
+            "{markdown}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_export_json() {
+        let history = History::new("json");
+        let json: serde_json::Value =
+            serde_json::from_str(&history.export(ExportFormat::Json).await).unwrap();
+        assert_eq!(json["chat"]["id"], history.chat_id.as_str());
+        let messages: Vec<(&str, &str)> = json["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| (m["role"].as_str().unwrap(), m["content"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("user", "What is in main.rs?"),
+                ("assistant", "A main function.")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_export_unknown_chat() {
+        let history = History::new("missing");
+        let err = history
+            .backend()
+            .export_conversation_history("abc123/rpc-log-1.log", ExportFormat::Json.as_str())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Chat log file not found");
+    }
+}
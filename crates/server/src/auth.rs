@@ -0,0 +1,482 @@
+//! Authentication for the `/api` routes.
+//!
+//! The server is unlocked with one secret: the password in
+//! `GEMINI_CLI_DESKTOP_PASSWORD`, or else an access token generated on first
+//! start, printed once and kept in `server-token` in the data directory.
+//! Scripts send it as `Authorization: Bearer <secret>`. Browsers log in once
+//! at `/login` and then use a session cookie, which also covers the WebSocket
+//! and Server-Sent Events streams since those can't set headers.
+//!
+//! Cookies are sent on cross-site requests too, so state-changing requests
+//! authenticated by one must also echo the session's CSRF token, which login
+//! puts in a cookie the frontend can read, in `X-CSRF-Token`.
+//!
+//! A server with users in its config file has no shared secret: browsers and
+//! scripts log in with a user name and password, and every request acts as
+//! the user it was logged in as.
+
+use crate::config::UserConfig;
+use crate::origin::TrustedOrigin;
+use anyhow::{Context, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use rocket::http::{ContentType, Cookie, CookieJar, Method, SameSite, Status};
+use rocket::request::{self, FromRequest};
+use rocket::serde::json::Json;
+use rocket::{Request, State, catch, get, post};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Password to log in with instead of a generated token.
+pub const PASSWORD_ENV: &str = "GEMINI_CLI_DESKTOP_PASSWORD";
+const TOKEN_FILE_NAME: &str = "server-token";
+const SESSION_COOKIE: &str = "gcd_session";
+/// Readable by the frontend, which echoes it in [`CSRF_HEADER`].
+const CSRF_COOKIE: &str = "gcd_csrf";
+const CSRF_HEADER: &str = "X-CSRF-Token";
+/// How long a browser stays logged in.
+const SESSION_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Slows down guessing the secret.
+const LOGIN_FAILURE_DELAY: Duration = Duration::from_secs(1);
+
+static LOGIN_PAGE: &str = include_str!("login.html");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    Token,
+    Password,
+    Users,
+}
+
+/// A logged in browser, and the user it logged in as on a shared server.
+struct BrowserSession {
+    expires: Instant,
+    csrf_token: String,
+    user: Option<String>,
+}
+
+/// How a request proved who it is.
+enum Credential {
+    Bearer,
+    Session {
+        csrf_token: String,
+        user: Option<String>,
+    },
+}
+
+struct Account {
+    password_hash: String,
+    admin: bool,
+}
+
+/// What logins are checked against.
+enum Secrets {
+    Shared(String),
+    Accounts {
+        accounts: HashMap<String, Account>,
+        /// Verified for unknown user names, so they take as long to refuse
+        /// as wrong passwords.
+        dummy_hash: String,
+    },
+}
+
+pub struct Auth {
+    method: AuthMethod,
+    secrets: Secrets,
+    /// Logged in browsers by session id.
+    sessions: Mutex<HashMap<String, BrowserSession>>,
+    /// Only send the login cookies over HTTPS.
+    secure_cookies: bool,
+}
+
+impl Auth {
+    pub fn new(method: AuthMethod, secret: String) -> Result<Self> {
+        anyhow::ensure!(
+            !secret.trim().is_empty(),
+            "The server secret can't be empty"
+        );
+        Ok(Self {
+            method,
+            secrets: Secrets::Shared(secret),
+            sessions: Mutex::new(HashMap::new()),
+            secure_cookies: false,
+        })
+    }
+
+    /// Log in as one of `users`, whose password hashes have been validated.
+    pub fn for_users(users: &[UserConfig]) -> Result<Self> {
+        let salt = SaltString::generate(&mut OsRng);
+        let dummy_hash = argon2::Argon2::default()
+            .hash_password(random_hex(16).as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Failed to hash a password: {e}"))?
+            .to_string();
+        let accounts = users
+            .iter()
+            .map(|user| {
+                let account = Account {
+                    password_hash: user.password_hash.clone(),
+                    admin: user.admin,
+                };
+                (user.name.clone(), account)
+            })
+            .collect();
+        info!("Logging in with the {} configured users", users.len());
+        Ok(Self {
+            method: AuthMethod::Users,
+            secrets: Secrets::Accounts {
+                accounts,
+                dummy_hash,
+            },
+            sessions: Mutex::new(HashMap::new()),
+            secure_cookies: false,
+        })
+    }
+
+    /// The password from `GEMINI_CLI_DESKTOP_PASSWORD`, or else the access
+    /// token in the user's data directory, generated and printed if there
+    /// isn't one yet. `server_url` is where the login link points.
+    pub fn from_env(server_url: &str) -> Result<Self> {
+        if let Ok(password) = std::env::var(PASSWORD_ENV) {
+            info!("Logging in with the password from {PASSWORD_ENV}");
+            return Self::new(AuthMethod::Password, password)
+                .with_context(|| format!("Invalid {PASSWORD_ENV}"));
+        }
+        let path = token_path().context("No data directory for the access token")?;
+        Self::new(AuthMethod::Token, load_or_create_token(&path, server_url)?)
+    }
+
+    /// Mark the login cookies `Secure`, for a server running under TLS.
+    pub fn with_secure_cookies(mut self, secure: bool) -> Self {
+        self.secure_cookies = secure;
+        self
+    }
+
+    pub fn method(&self) -> AuthMethod {
+        self.method
+    }
+
+    fn check_secret(&self, secret: &str) -> bool {
+        match &self.secrets {
+            Secrets::Shared(shared) => constant_time_eq(secret.as_bytes(), shared.as_bytes()),
+            Secrets::Accounts { .. } => false,
+        }
+    }
+
+    /// Check a login and return the user it is for, `None` on a
+    /// single-user server.
+    async fn check_login(&self, username: Option<&str>, secret: &str) -> Option<Option<String>> {
+        let Secrets::Accounts {
+            accounts,
+            dummy_hash,
+        } = &self.secrets
+        else {
+            return self.check_secret(secret.trim()).then_some(None);
+        };
+        let account = username.and_then(|name| accounts.get(name));
+        let hash = account.map_or(dummy_hash, |account| &account.password_hash);
+        // Argon2 is slow on purpose, so keep it off the async workers
+        let (hash, secret) = (hash.clone(), secret.to_string());
+        let verified = tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&hash).is_ok_and(|hash| {
+                argon2::Argon2::default()
+                    .verify_password(secret.as_bytes(), &hash)
+                    .is_ok()
+            })
+        })
+        .await
+        .unwrap_or(false);
+        (verified && account.is_some()).then(|| username.map(str::to_string))
+    }
+
+    /// Whether `user` may see every user's sessions. Everyone can on a
+    /// single-user server.
+    fn is_admin(&self, user: Option<&str>) -> bool {
+        match (&self.secrets, user) {
+            (Secrets::Shared(_), _) => true,
+            (Secrets::Accounts { accounts, .. }, Some(user)) => {
+                accounts.get(user).is_some_and(|account| account.admin)
+            }
+            (Secrets::Accounts { .. }, None) => false,
+        }
+    }
+
+    /// A new session's id and CSRF token.
+    fn start_session(&self, user: Option<String>) -> (String, String) {
+        let session_id = random_hex(32);
+        let csrf_token = random_hex(32);
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires > now);
+        sessions.insert(
+            session_id.clone(),
+            BrowserSession {
+                expires: now + SESSION_LIFETIME,
+                csrf_token: csrf_token.clone(),
+                user,
+            },
+        );
+        (session_id, csrf_token)
+    }
+
+    /// The CSRF token and user of a live session.
+    fn live_session(&self, session_id: &str) -> Option<(String, Option<String>)> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .filter(|session| session.expires > Instant::now())
+            .map(|session| (session.csrf_token.clone(), session.user.clone()))
+    }
+
+    fn end_session(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+
+    fn authenticate(&self, request: &Request<'_>) -> Option<Credential> {
+        if let Some(header) = request.headers().get_one("Authorization") {
+            return header
+                .strip_prefix("Bearer ")
+                .is_some_and(|secret| self.check_secret(secret.trim()))
+                .then_some(Credential::Bearer);
+        }
+        let session_id = request.cookies().get(SESSION_COOKIE)?.value().to_string();
+        let (csrf_token, user) = self.live_session(&session_id)?;
+        Some(Credential::Session { csrf_token, user })
+    }
+}
+
+/// `server-token` in the data directory.
+fn token_path() -> Option<PathBuf> {
+    backend::paths::data_dir().map(|dir| dir.join(TOKEN_FILE_NAME))
+}
+
+fn load_or_create_token(path: &Path, server_url: &str) -> Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(token) if !token.trim().is_empty() => {
+            info!("Using the access token in {}", path.display());
+            return Ok(token.trim().to_string());
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read access token {}", path.display()));
+        }
+    }
+
+    let token = random_hex(24);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    write_private(path, token.as_bytes())
+        .with_context(|| format!("Failed to save access token to {}", path.display()))?;
+    // Printed rather than logged so it only ends up on this terminal, and
+    // apart from the URL so it doesn't end up in browser history or logs
+    println!();
+    println!("  Access token: {token}");
+    println!("  Log in at {server_url}/login");
+    println!("  (saved to {}; it won't be shown again)", path.display());
+    println!();
+    Ok(token)
+}
+
+/// Write a file readable only by the current user where the platform
+/// supports it.
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Compare secrets without leaking how much of them matched through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Request guard for routes that need a logged in browser or the bearer
+/// secret, from a trusted origin.
+pub struct Authenticated {
+    /// The user on a shared server.
+    pub user: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authenticated {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        if let request::Outcome::Error(error) = request.guard::<TrustedOrigin>().await {
+            return request::Outcome::Error(error);
+        }
+        let Some(auth) = request.rocket().state::<Auth>() else {
+            return request::Outcome::Error((Status::InternalServerError, ()));
+        };
+        match auth.authenticate(request) {
+            None => request::Outcome::Error((Status::Unauthorized, ())),
+            Some(Credential::Session { csrf_token, .. })
+                if !is_safe_method(request.method())
+                    && !request.headers().get_one(CSRF_HEADER).is_some_and(|token| {
+                        constant_time_eq(token.as_bytes(), csrf_token.as_bytes())
+                    }) =>
+            {
+                request::Outcome::Error((Status::Forbidden, ()))
+            }
+            Some(Credential::Bearer) => request::Outcome::Success(Self { user: None }),
+            Some(Credential::Session { user, .. }) => request::Outcome::Success(Self { user }),
+        }
+    }
+}
+
+/// Request guard for routes only admins may use.
+pub struct Admin;
+
+/// Marks requests refused by [`Admin`], for the 403 catcher.
+pub struct NotAdmin(pub bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let authenticated = rocket::outcome::try_outcome!(request.guard::<Authenticated>().await);
+        let Some(auth) = request.rocket().state::<Auth>() else {
+            return request::Outcome::Error((Status::InternalServerError, ()));
+        };
+        if auth.is_admin(authenticated.user.as_deref()) {
+            request::Outcome::Success(Self)
+        } else {
+            request.local_cache(|| NotAdmin(true));
+            request::Outcome::Error((Status::Forbidden, ()))
+        }
+    }
+}
+
+fn is_safe_method(method: Method) -> bool {
+    matches!(method, Method::Get | Method::Head | Method::Options)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LoginRequest {
+    /// Only used on a shared server.
+    username: Option<String>,
+    secret: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UserInfo {
+    name: String,
+    admin: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AuthStatus {
+    authenticated: bool,
+    method: AuthMethod,
+    /// Who is logged in on a shared server.
+    user: Option<UserInfo>,
+}
+
+#[get("/login")]
+pub fn login_page() -> (ContentType, &'static str) {
+    (ContentType::HTML, LOGIN_PAGE)
+}
+
+#[get("/auth/status")]
+pub fn auth_status(
+    _origin: TrustedOrigin,
+    request_auth: Option<Authenticated>,
+    auth: &State<Auth>,
+) -> Json<AuthStatus> {
+    let user = request_auth
+        .as_ref()
+        .and_then(|request_auth| request_auth.user.clone())
+        .map(|name| UserInfo {
+            admin: auth.is_admin(Some(&name)),
+            name,
+        });
+    Json(AuthStatus {
+        authenticated: request_auth.is_some(),
+        method: auth.method(),
+        user,
+    })
+}
+
+#[post("/auth/login", data = "<request>")]
+pub async fn login(
+    _origin: TrustedOrigin,
+    request: Json<LoginRequest>,
+    auth: &State<Auth>,
+    cookies: &CookieJar<'_>,
+) -> Status {
+    let username = request.username.as_deref().map(str::trim);
+    let Some(user) = auth.check_login(username, &request.secret).await else {
+        match username {
+            Some(username) if auth.method() == AuthMethod::Users => {
+                warn!("Rejected a login as {username}")
+            }
+            _ => warn!("Rejected a login with the wrong {:?}", auth.method()),
+        }
+        tokio::time::sleep(LOGIN_FAILURE_DELAY).await;
+        return Status::Unauthorized;
+    };
+    if let Some(user) = &user {
+        info!("{user} logged in");
+    }
+    let (session_id, csrf_token) = auth.start_session(user);
+    let max_age = rocket::time::Duration::seconds(SESSION_LIFETIME.as_secs() as i64);
+    cookies.add(
+        Cookie::build((SESSION_COOKIE, session_id))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .secure(auth.secure_cookies)
+            .max_age(max_age),
+    );
+    cookies.add(
+        Cookie::build((CSRF_COOKIE, csrf_token))
+            .path("/")
+            .same_site(SameSite::Strict)
+            .secure(auth.secure_cookies)
+            .max_age(max_age),
+    );
+    Status::NoContent
+}
+
+#[post("/auth/logout")]
+pub fn logout(_origin: TrustedOrigin, auth: &State<Auth>, cookies: &CookieJar<'_>) -> Status {
+    if let Some(cookie) = cookies.get(SESSION_COOKIE) {
+        auth.end_session(cookie.value());
+    }
+    cookies.remove(Cookie::build(SESSION_COOKIE).path("/"));
+    cookies.remove(Cookie::build(CSRF_COOKIE).path("/"));
+    Status::NoContent
+}
+
+#[catch(401)]
+pub fn unauthorized() -> (Status, (ContentType, &'static str)) {
+    (
+        Status::Unauthorized,
+        (ContentType::JSON, "{\"error\":\"Authentication required\"}"),
+    )
+}
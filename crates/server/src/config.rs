@@ -0,0 +1,643 @@
+//! Startup configuration for the web server.
+//!
+//! Every setting can come from a command-line flag, an environment variable
+//! or a TOML file given with `--config`, in that order of precedence. A file
+//! looks like:
+//!
+//! ```toml
+//! address = "127.0.0.1"
+//! port = 1858
+//! data_dir = "/srv/gemini-cli-desktop"
+//! log_level = "info,backend::session=debug"
+//! allowed_origins = ["https://dev.example.com"]
+//! allowed_hosts = ["devbox.example.com"]
+//! backends = ["gemini", "qwen"]
+//! allowed_paths = ["/srv/repos"]
+//! read_only = false
+//!
+//! [tls]
+//! cert = "cert.pem"
+//! key = "key.pem"
+//!
+//! [[users]]
+//! name = "alice"
+//! password_hash = "$argon2id$v=19$m=19456,t=2,p=1$..."
+//! admin = true
+//! home = "/home/alice"
+//!
+//! [users.env]
+//! GEMINI_API_KEY = "..."
+//! ```
+//!
+//! Relative paths in the file are resolved against the file's directory.
+//!
+//! Users can only be set in the file. With any, the server asks for a user
+//! name and password and keeps each user's projects, conversations, vault,
+//! sessions and events apart; `gemini-cli-desktop-web hash-password` prints
+//! the hash of a password read from standard input. Each user needs a `home`
+//! or `allowed_paths` to confine their file access to, and never gets at
+//! other users' data directories.
+
+use anyhow::{Context, Result, bail};
+use argon2::password_hash::{PasswordHash, PasswordHasher, SaltString};
+use backend::PathPolicy;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, IsTerminal, Write};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+/// Separates paths in environment variables, as in `PATH`.
+const PATH_LIST_DELIMITER: char = if cfg!(windows) { ';' } else { ':' };
+pub const DEFAULT_PORT: u16 = 1858;
+
+/// Web server for Gemini CLI Desktop.
+///
+/// Serves the web UI and its API. Settings can be given as flags, as the
+/// environment variables shown, or in a TOML file passed with --config; flags
+/// win over the environment, which wins over the file.
+#[derive(Parser)]
+#[command(name = "gemini-cli-desktop-web", version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML file to read settings from
+    #[arg(short, long, env = "GEMINI_CLI_DESKTOP_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on [default: 0.0.0.0]
+    #[arg(short, long, env = "GEMINI_CLI_DESKTOP_ADDRESS")]
+    address: Option<IpAddr>,
+    /// Port to listen on [default: 1858]
+    #[arg(short, long, env = "GEMINI_CLI_DESKTOP_PORT")]
+    port: Option<u16>,
+    /// PEM certificate chain; serves HTTPS and WSS instead of HTTP and WS
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_TLS_CERT", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key for --tls-cert
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    /// Where projects, conversations, the vault and logs are kept
+    /// [default: ~/.gemini-cli-desktop]
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Log level or filter, e.g. `debug` or `info,backend::session=trace`
+    /// [default: info]
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_LOG")]
+    log_level: Option<String>,
+    /// Origin allowed to call the API from another site, e.g.
+    /// `https://dev.example.com` (repeatable, comma-separated in the
+    /// environment)
+    #[arg(
+        long = "allowed-origin",
+        env = "GEMINI_CLI_DESKTOP_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    allowed_origins: Vec<String>,
+    /// Host name browsers may reach the server by, besides localhost, IP
+    /// addresses and the allowed origins' hosts (repeatable, comma-separated
+    /// in the environment)
+    #[arg(
+        long = "allowed-host",
+        env = "GEMINI_CLI_DESKTOP_ALLOWED_HOSTS",
+        value_delimiter = ','
+    )]
+    allowed_hosts: Vec<String>,
+    /// Agent CLI sessions may use (repeatable, comma-separated in the
+    /// environment) [default: all]
+    #[arg(
+        long = "backend",
+        env = "GEMINI_CLI_DESKTOP_BACKENDS",
+        value_enum,
+        value_delimiter = ','
+    )]
+    backends: Vec<CliBackend>,
+    /// Directory the file commands may use besides the project roots
+    /// (repeatable, separated like PATH in the environment) [default: the
+    /// home directory]
+    #[arg(
+        long = "allow-path",
+        env = "GEMINI_CLI_DESKTOP_ALLOWED_PATHS",
+        value_delimiter = PATH_LIST_DELIMITER
+    )]
+    allowed_paths: Vec<PathBuf>,
+    /// Let the file commands use any path
+    #[arg(
+        long,
+        env = "GEMINI_CLI_DESKTOP_UNRESTRICTED_PATHS",
+        conflicts_with = "allowed_paths"
+    )]
+    unrestricted_paths: bool,
+    /// Refuse every file write and diff apply
+    #[arg(long, env = "GEMINI_CLI_DESKTOP_READ_ONLY")]
+    read_only: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Read a password from standard input and print its hash, for the
+    /// `password_hash` of a user in the config file
+    HashPassword,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CliBackend {
+    Gemini,
+    Qwen,
+    Llxprt,
+}
+
+impl CliBackend {
+    /// The name the backend crate uses for this CLI.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gemini => "gemini",
+            Self::Qwen => "qwen",
+            Self::Llxprt => "llxprt",
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    address: Option<IpAddr>,
+    port: Option<u16>,
+    tls: Option<FileTls>,
+    data_dir: Option<PathBuf>,
+    log_level: Option<String>,
+    allowed_origins: Option<Vec<String>>,
+    allowed_hosts: Option<Vec<String>>,
+    backends: Option<Vec<CliBackend>>,
+    allowed_paths: Option<Vec<PathBuf>>,
+    unrestricted_paths: Option<bool>,
+    read_only: Option<bool>,
+    #[serde(default)]
+    users: Vec<FileUser>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileUser {
+    name: String,
+    password_hash: String,
+    #[serde(default)]
+    admin: bool,
+    home: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    allowed_paths: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTls {
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub address: IpAddr,
+    pub port: u16,
+    pub tls: Option<TlsConfig>,
+    /// `None` keeps the backend's default.
+    pub data_dir: Option<PathBuf>,
+    /// `None` keeps the default filter.
+    pub log_level: Option<String>,
+    /// Normalized `scheme://host[:port]` origins.
+    pub allowed_origins: Vec<String>,
+    /// Lowercase host names, without ports.
+    pub allowed_hosts: Vec<String>,
+    pub backends: Vec<CliBackend>,
+    /// Directories the file commands may use besides the project roots;
+    /// `None` allows every path.
+    pub allowed_paths: Option<Vec<PathBuf>>,
+    pub read_only: bool,
+    /// Accounts of a shared server; empty for a single-user one.
+    pub users: Vec<UserConfig>,
+}
+
+/// An account on a shared server.
+#[derive(Debug, Clone)]
+pub struct UserConfig {
+    pub name: String,
+    /// Argon2 hash in PHC string format.
+    pub password_hash: String,
+    /// Can see every user's sessions.
+    pub admin: bool,
+    /// `HOME` for the user's CLIs, terminals and jobs.
+    pub home: Option<PathBuf>,
+    /// Where the user's projects, conversations and vault are kept.
+    pub data_dir: PathBuf,
+    /// `None` falls back to the home directory; one of the two is required.
+    pub allowed_paths: Option<Vec<PathBuf>>,
+    /// Extra environment for the user's CLIs, terminals and jobs.
+    pub env: BTreeMap<String, String>,
+}
+
+impl ServerConfig {
+    /// Read the configuration from the command line, the environment and the
+    /// config file. Exits with usage information on `--help`, `--version` and
+    /// malformed flags, and after running a subcommand.
+    pub fn load() -> Result<Self> {
+        let args = Args::parse();
+        if let Some(Command::HashPassword) = args.command {
+            hash_password()?;
+            std::process::exit(0);
+        }
+        Self::from_args(args)
+    }
+
+    fn from_args(args: Args) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => read_file(path)?,
+            None => FileConfig::default(),
+        };
+        let file_dir = args
+            .config
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let from_file = |path: PathBuf| file_dir.join(path);
+
+        let tls = match (args.tls_cert, args.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsConfig { cert, key }),
+            _ => file.tls.map(|tls| TlsConfig {
+                cert: from_file(tls.cert),
+                key: from_file(tls.key),
+            }),
+        };
+        let allowed_origins = if args.allowed_origins.is_empty() {
+            file.allowed_origins.unwrap_or_default()
+        } else {
+            args.allowed_origins
+        };
+        let allowed_hosts = if args.allowed_hosts.is_empty() {
+            file.allowed_hosts.unwrap_or_default()
+        } else {
+            args.allowed_hosts
+        };
+        let backends = if args.backends.is_empty() {
+            file.backends
+                .unwrap_or_else(|| CliBackend::value_variants().to_vec())
+        } else {
+            args.backends
+        };
+
+        let allowed_paths = if args.unrestricted_paths {
+            None
+        } else if !args.allowed_paths.is_empty() {
+            Some(args.allowed_paths)
+        } else if file.unrestricted_paths == Some(true) {
+            None
+        } else {
+            match file.allowed_paths {
+                Some(paths) => Some(paths.into_iter().map(from_file).collect()),
+                None => Some(default_allowed_paths()),
+            }
+        };
+
+        let data_dir = args.data_dir.or(file.data_dir.map(&from_file));
+        let users_dir = data_dir
+            .clone()
+            .or_else(backend::paths::data_dir)
+            .map(|dir| dir.join("users"));
+        let users = file
+            .users
+            .into_iter()
+            .map(|user| {
+                let data_dir = match (user.data_dir, &users_dir) {
+                    (Some(dir), _) => from_file(dir),
+                    (None, Some(users_dir)) => users_dir.join(&user.name),
+                    (None, None) => bail!("No data directory for user {}", user.name),
+                };
+                Ok(UserConfig {
+                    home: user.home.map(&from_file),
+                    allowed_paths: user
+                        .allowed_paths
+                        .map(|paths| paths.into_iter().map(&from_file).collect()),
+                    name: user.name,
+                    password_hash: user.password_hash,
+                    admin: user.admin,
+                    data_dir,
+                    env: user.env,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let config = Self {
+            address: args.address.or(file.address).unwrap_or(DEFAULT_ADDRESS),
+            port: args.port.or(file.port).unwrap_or(DEFAULT_PORT),
+            tls,
+            data_dir,
+            log_level: args.log_level.or(file.log_level),
+            allowed_origins: allowed_origins
+                .iter()
+                .map(|origin| normalize_origin(origin))
+                .collect::<Result<_>>()?,
+            allowed_hosts: allowed_hosts
+                .iter()
+                .map(|host| normalize_host(host))
+                .collect::<Result<_>>()?,
+            backends,
+            allowed_paths,
+            read_only: args.read_only || file.read_only.unwrap_or(false),
+            users,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.port == 0 {
+            bail!("Port must be between 1 and 65535");
+        }
+        if let Some(tls) = &self.tls {
+            for (name, path) in [("certificate", &tls.cert), ("key", &tls.key)] {
+                if !path.is_file() {
+                    bail!("TLS {name} {} is not a readable file", path.display());
+                }
+            }
+        }
+        if let Some(dir) = &self.data_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Can't create data directory {}", dir.display()))?;
+        }
+        if let Some(filter) = &self.log_level {
+            backend::logging::check_filter(filter)?;
+        }
+        if self.backends.is_empty() {
+            bail!("At least one backend must be enabled");
+        }
+        for path in self.allowed_paths.iter().flatten() {
+            if !path.is_dir() {
+                bail!("Allowed path {} is not a directory", path.display());
+            }
+        }
+        let mut names = HashSet::new();
+        for user in &self.users {
+            user.validate()?;
+            if !names.insert(&user.name) {
+                bail!("User {} is configured twice", user.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Where this machine reaches the server, for messages printed at
+    /// startup.
+    pub fn local_url(&self) -> String {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let host = match self.address {
+            address if address.is_unspecified() || address.is_loopback() => "localhost".to_string(),
+            IpAddr::V6(address) => format!("[{address}]"),
+            address => address.to_string(),
+        };
+        format!("{scheme}://{host}:{}", self.port)
+    }
+
+    /// The file access policy for the backend.
+    pub fn path_policy(&self) -> PathPolicy {
+        let policy = match &self.allowed_paths {
+            Some(paths) => PathPolicy::restricted(paths.iter().cloned()),
+            None => PathPolicy::unrestricted(),
+        };
+        policy.with_read_only(self.read_only)
+    }
+
+    /// Rocket's settings for the address, port and TLS, on top of its own
+    /// defaults.
+    pub fn figment(&self) -> rocket::figment::Figment {
+        let mut figment = rocket::Config::figment()
+            .merge(("address", self.address))
+            .merge(("port", self.port));
+        if let Some(tls) = &self.tls {
+            figment = figment
+                .merge(("tls.certs", &tls.cert))
+                .merge(("tls.key", &tls.key));
+        }
+        figment
+    }
+}
+
+impl UserConfig {
+    fn validate(&self) -> Result<()> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            && !self.name.starts_with('.');
+        if !valid_name {
+            bail!(
+                "Invalid user name `{}`; use letters, digits, `_`, `-` and `.`",
+                self.name
+            );
+        }
+        PasswordHash::new(&self.password_hash).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid password hash for user {}: {e}; create one with `hash-password`",
+                self.name
+            )
+        })?;
+        // The server's own paths would include the other users' data
+        if self.home.is_none() && self.allowed_paths.is_none() {
+            bail!(
+                "User {} needs a `home` or `allowed_paths` to limit their file access to",
+                self.name
+            );
+        }
+        for path in self.home.iter().chain(self.allowed_paths.iter().flatten()) {
+            if !path.is_dir() {
+                bail!(
+                    "{} of user {} is not a directory",
+                    path.display(),
+                    self.name
+                );
+            }
+        }
+        std::fs::create_dir_all(&self.data_dir).with_context(|| {
+            format!(
+                "Can't create data directory {} for user {}",
+                self.data_dir.display(),
+                self.name
+            )
+        })
+    }
+
+    /// The file access policy for the user's backend: their allowed paths,
+    /// else their home directory, without the other users' data directories.
+    pub fn path_policy(&self, server: &ServerConfig) -> PathPolicy {
+        let roots = match (&self.allowed_paths, &self.home) {
+            (Some(paths), _) => paths.clone(),
+            (None, Some(home)) => vec![home.clone()],
+            (None, None) => Vec::new(),
+        };
+        let others = server
+            .users
+            .iter()
+            .filter(|user| user.name != self.name)
+            .map(|user| user.data_dir.clone());
+        PathPolicy::restricted(roots)
+            .with_denied(others)
+            .with_read_only(server.read_only)
+    }
+
+    /// The environment for the user's processes: `HOME` pointing at their
+    /// home directory, unless their `env` sets it.
+    pub fn process_env(&self) -> BTreeMap<String, String> {
+        let mut env = BTreeMap::new();
+        if let Some(home) = &self.home {
+            let home = home.to_string_lossy().to_string();
+            if cfg!(windows) {
+                env.insert("USERPROFILE".to_string(), home.clone());
+            }
+            env.insert("HOME".to_string(), home);
+        }
+        env.extend(self.env.clone());
+        env
+    }
+}
+
+/// Print the Argon2 hash of a password read from standard input.
+fn hash_password() -> Result<()> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        std::io::stderr().flush()?;
+    }
+    let mut password = String::new();
+    stdin
+        .lock()
+        .read_line(&mut password)
+        .context("Failed to read the password")?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        bail!("The password can't be empty");
+    }
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    let hash = argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash the password: {e}"))?;
+    println!("{hash}");
+    Ok(())
+}
+
+/// The home directory, so projects anywhere in it can be opened.
+fn default_allowed_paths() -> Vec<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .into_iter()
+        .collect()
+}
+
+fn read_file(path: &Path) -> Result<FileConfig> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// `scheme://host[:port]` in lowercase, without a trailing slash.
+fn normalize_origin(origin: &str) -> Result<String> {
+    let normalized = origin.trim().trim_end_matches('/').to_ascii_lowercase();
+    let valid = normalized.split_once("://").is_some_and(|(scheme, host)| {
+        matches!(scheme, "http" | "https")
+            && !host.is_empty()
+            && !host.contains(['/', '?', '#', '@'])
+    });
+    if !valid {
+        bail!("Invalid allowed origin `{origin}`; expected e.g. `https://example.com:8080`");
+    }
+    Ok(normalized)
+}
+
+/// A host name in lowercase, refusing anything with a scheme, port or path.
+fn normalize_host(host: &str) -> Result<String> {
+    let normalized = host.trim().to_ascii_lowercase();
+    if normalized.is_empty() || normalized.contains([':', '/', '@', '?', '#']) {
+        bail!("Invalid allowed host `{host}`; expected a host name like `devbox.example.com`");
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::{Access, DataDir, PathPermissionError, PathPermissionReason};
+
+    fn load(dir: &Path, users: &str) -> Result<ServerConfig> {
+        let file = dir.join("config.toml");
+        let config = format!(
+            "data_dir = \"data\"\nallowed_paths = [\".\"]\n{users}",
+            users = users.replace(
+                "$HASH",
+                "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA"
+            )
+        );
+        std::fs::write(&file, config).unwrap();
+        ServerConfig::from_args(Args::parse_from([
+            "gemini-cli-desktop-web",
+            "--config",
+            file.to_str().unwrap(),
+        ]))
+    }
+
+    #[test]
+    fn test_users_need_confined_file_access() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let err = load(
+            temp.path(),
+            "[[users]]\nname = \"alice\"\npassword_hash = \"$HASH\"\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("alice"), "{err:#}");
+    }
+
+    #[test]
+    fn test_users_cannot_read_each_others_data() {
+        let temp = tempfile::TempDir::new().unwrap();
+        // Alice's home holds the whole data directory, Bob's included
+        let config = load(
+            temp.path(),
+            "[[users]]\nname = \"alice\"\npassword_hash = \"$HASH\"\nhome = \".\"\n\
+             [[users]]\nname = \"bob\"\npassword_hash = \"$HASH\"\nhome = \".\"\n",
+        )
+        .unwrap();
+        let [alice, bob] = [&config.users[0], &config.users[1]];
+        let bob_file = bob.data_dir.join("vault.json");
+        std::fs::write(&bob_file, "{}").unwrap();
+
+        let policy = alice.path_policy(&config);
+        let data_dir = DataDir::new(alice.data_dir.clone());
+        let err = policy
+            .check(&data_dir, bob_file.to_str().unwrap(), Access::Read)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PathPermissionError>().map(|e| e.reason),
+            Some(PathPermissionReason::OutsideAllowedRoots)
+        );
+        assert!(
+            policy
+                .check(&data_dir, alice.data_dir.to_str().unwrap(), Access::Read)
+                .is_ok()
+        );
+        assert!(
+            bob.path_policy(&config)
+                .check(&data_dir, bob_file.to_str().unwrap(), Access::Read)
+                .is_ok()
+        );
+    }
+}
@@ -1,28 +1,55 @@
+mod auth;
+mod config;
+mod openapi;
+mod origin;
+mod users;
+
 use anyhow::{Context, Error as AnyhowError};
 use include_dir::{Dir, include_dir};
+use rocket::futures::{SinkExt, StreamExt};
 use rocket::{
-    Request, Response, Shutdown, State, delete, get,
+    Request, Response, Route, Shutdown, State, catchers, delete,
+    fairing::AdHoc,
+    get,
     http::{ContentType, Status},
-    post,
-    response::{self, Responder},
+    post, put,
+    request::{self, FromRequest},
+    response::{
+        self, Responder,
+        stream::{Event, EventStream},
+    },
     routes,
     serde::json::Json,
 };
-use rocket_ws::{Message, Stream, WebSocket};
-use serde::{Deserialize, Serialize};
+use rocket_ws::frame::{CloseCode, CloseFrame};
+use rocket_ws::{Channel, Message, WebSocket};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::mpsc;
 use std::sync::{
     Arc,
     atomic::{AtomicU64, Ordering},
 };
 use std::{io::Cursor, path::PathBuf};
-use tokio::sync::{Mutex, mpsc as tokio_mpsc};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{Mutex, broadcast, mpsc as tokio_mpsc};
+use tracing::{debug, error, info};
 
 // Import backend functionality
 use backend::{
-    DetailedConversation, DirEntry, EnrichedProject, EventEmitter, FileContent, GeminiBackend,
-    GitInfo, ProcessStatus, RecentChat, SearchFilters, SearchResult,
+    AppEvent, BatchReport, BatchRequest, DetailedConversation, DiffApplyResult, DiffPreview,
+    DirEntry, EnrichedProject, EventEmitter, FileContent, GeminiBackend, GitInfo, JobInfo, JobSpec,
+    LogConfig, PathPermissionError, ProcessStatus, ProfileInput, ProjectHistorySettings,
+    ProviderProfile, RecentChat, RecordingEventEmitter, Replay, STATUS_MONITOR_INTERVAL,
+    SearchFilters, SearchResult, StatusMonitor, TerminalEvent, TerminalInfo, VaultStatus,
 };
+use backend::{acp::error_codes, logging, terminal};
+
+use auth::{Admin, Auth, Authenticated};
+use config::{ServerConfig, UserConfig};
+use origin::OriginPolicy;
+use users::{UserState, Users};
 
 static FRONTEND_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../frontend/dist");
 
@@ -30,10 +57,367 @@ static FRONTEND_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../frontend/dist
 // WebSocket Connection Management
 // =====================================
 
-/// Manages active WebSocket connections for event broadcasting
+/// Recent events kept for clients that reconnect with `?since=<sequence>`.
+const EVENT_HISTORY_LIMIT: usize = 2048;
+/// Recent events kept per session, so a busy session can't push a quiet
+/// session's events out of the replay window.
+const SESSION_HISTORY_LIMIT: usize = 512;
+/// Sessions with their own history; the least recently active one goes first.
+const MAX_SESSION_HISTORIES: usize = 32;
+/// Messages queued for one event stream client. A client that falls further
+/// behind is disconnected, and catches up from the history when it reconnects.
+const CONNECTION_QUEUE_CAPACITY: usize = 1024;
+/// JSON-RPC commands from one WebSocket client waiting to run; more are
+/// answered with an error.
+const COMMAND_QUEUE_CAPACITY: usize = 32;
+
+/// A serialized event on its way to the clients.
+#[derive(Clone)]
+pub struct BufferedEvent {
+    sequence: u64,
+    family: &'static str,
+    session_id: Option<String>,
+    message: Arc<str>,
+}
+
+/// What a WebSocket connection wants to receive: `global` (everything),
+/// `session:<session_id>` or `family:<event>` (e.g. `family:process-status-changed`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Topic {
+    Global,
+    Session(String),
+    Family(String),
+}
+
+impl TryFrom<String> for Topic {
+    type Error = String;
+
+    fn try_from(topic: String) -> Result<Self, Self::Error> {
+        match topic.split_once(':') {
+            None if topic == "global" => Ok(Self::Global),
+            Some(("session", id)) if !id.is_empty() => Ok(Self::Session(id.to_string())),
+            Some(("family", family)) if !family.is_empty() => Ok(Self::Family(family.to_string())),
+            _ => Err(format!("Unknown topic '{topic}'")),
+        }
+    }
+}
+
+impl Topic {
+    fn matches(&self, event: &BufferedEvent) -> bool {
+        match self {
+            Self::Global => true,
+            Self::Session(id) => event.session_id.as_deref() == Some(id.as_str()),
+            Self::Family(family) => event.family == family,
+        }
+    }
+
+    /// Parse a comma-separated topic list, skipping topics that don't parse.
+    fn parse_list(topics: &str) -> HashSet<Self> {
+        topics
+            .split(',')
+            .filter(|topic| !topic.is_empty())
+            .filter_map(|topic| {
+                Self::try_from(topic.to_string())
+                    .inspect_err(|e| debug!("Ignoring WebSocket topic: {e}"))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+/// Messages a client sends over the event WebSocket.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventClientMessage {
+    Subscribe { topics: Vec<Topic> },
+    Unsubscribe { topics: Vec<Topic> },
+}
+
+/// A JSON-RPC 2.0 command sent over the event WebSocket. `params` are the
+/// body of the matching HTTP route (e.g. `send_message` takes the body of
+/// `POST /api/send-message`).
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    id: serde_json::Value,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: serde_json::Value,
+    #[serde(flatten)]
+    outcome: RpcOutcome,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RpcOutcome {
+    Result(serde_json::Value),
+    Error(RpcError),
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcResponse {
+    fn error(id: serde_json::Value, code: i32, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            outcome: RpcOutcome::Error(RpcError { code, message }),
+        }
+    }
+}
+
+impl RpcRequest {
+    /// Run the command and build its response.
+    async fn handle(self, backend: &Mutex<Backend>) -> RpcResponse {
+        if self.jsonrpc != "2.0" {
+            return RpcResponse::error(
+                self.id,
+                error_codes::INVALID_REQUEST,
+                "Only JSON-RPC 2.0 is supported".to_string(),
+            );
+        }
+
+        let result = match self.method.as_str() {
+            "start_session" => Self::run(self.params, backend, StartSessionRequest::run).await,
+            "send_message" => Self::run(self.params, backend, SendMessageRequest::run).await,
+            "tool_confirmation" => {
+                Self::run(self.params, backend, ToolConfirmationRequest::run).await
+            }
+            "cancel_prompt" => Self::run(self.params, backend, CancelPromptRequest::run).await,
+            "kill_process" => Self::run(self.params, backend, KillProcessRequest::run).await,
+            method => Err(RpcError {
+                code: error_codes::METHOD_NOT_FOUND,
+                message: format!("Unknown method '{method}'"),
+            }),
+        };
+
+        RpcResponse {
+            jsonrpc: "2.0",
+            id: self.id,
+            outcome: match result {
+                Ok(()) => RpcOutcome::Result(serde_json::Value::Null),
+                Err(error) => RpcOutcome::Error(error),
+            },
+        }
+    }
+
+    async fn run<P: DeserializeOwned>(
+        params: serde_json::Value,
+        backend: &Mutex<Backend>,
+        command: impl AsyncFnOnce(P, &Backend) -> anyhow::Result<()>,
+    ) -> Result<(), RpcError> {
+        let params = serde_json::from_value(params).map_err(|e| RpcError {
+            code: error_codes::INVALID_PARAMS,
+            message: e.to_string(),
+        })?;
+        let backend = backend.lock().await;
+        command(params, &backend).await.map_err(|e| RpcError {
+            code: error_codes::INTERNAL_ERROR,
+            message: format!("{e:#}"),
+        })
+    }
+}
+
+/// A text frame from a client: a subscription change, a command, or a
+/// command too malformed to run, answered with an error right away.
+enum ClientMessage {
+    Subscription(EventClientMessage),
+    Command(RpcRequest),
+    Rejected(RpcResponse),
+}
+
+impl ClientMessage {
+    fn parse(text: &str) -> serde_json::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(text)?;
+        if value.get("jsonrpc").is_none() {
+            return Ok(Self::Subscription(serde_json::from_value(value)?));
+        }
+
+        let id = value.get("id").cloned().unwrap_or_default();
+        Ok(match serde_json::from_value(value) {
+            Ok(request) => Self::Command(request),
+            Err(e) => Self::Rejected(RpcResponse::error(
+                id,
+                error_codes::INVALID_REQUEST,
+                e.to_string(),
+            )),
+        })
+    }
+}
+
+/// A message on its way to one client. Events carry their sequence number,
+/// which the SSE stream sends as the event id.
+pub struct Outgoing {
+    sequence: Option<u64>,
+    message: Arc<str>,
+}
+
+/// A WebSocket or SSE client of the event stream.
+struct Connection {
+    id: u64,
+    sender: tokio_mpsc::Sender<Outgoing>,
+    topics: HashSet<Topic>,
+}
+
+impl Connection {
+    fn wants(&self, event: &BufferedEvent) -> bool {
+        self.topics.iter().any(|topic| topic.matches(event))
+    }
+
+    /// Queue `outgoing` without waiting. False if the client is gone or its
+    /// queue is full, either way a connection to drop.
+    fn offer(&self, outgoing: Outgoing) -> bool {
+        self.sender.try_send(outgoing).is_ok()
+    }
+}
+
+/// Bounded buffer of serialized events, oldest first.
+#[derive(Default)]
+struct EventRing {
+    events: VecDeque<BufferedEvent>,
+    /// Sequence of the newest event pushed out of the buffer.
+    dropped_through: Option<u64>,
+}
+
+impl EventRing {
+    fn push(&mut self, event: BufferedEvent, limit: usize) {
+        if self.events.len() >= limit
+            && let Some(dropped) = self.events.pop_front()
+        {
+            self.dropped_through = Some(dropped.sequence);
+        }
+        self.events.push_back(event);
+    }
+
+    /// Whether every event after `since` is still in the buffer.
+    fn covers(&self, since: u64) -> bool {
+        self.dropped_through.is_none_or(|dropped| dropped <= since)
+    }
+
+    fn after(&self, since: u64) -> impl Iterator<Item = &BufferedEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.sequence > since)
+    }
+}
+
+/// Sent before a replay that can't include everything the client missed.
+#[derive(Serialize)]
+struct GapMarker {
+    event: &'static str,
+    payload: GapPayload,
+}
+
+#[derive(Serialize)]
+struct GapPayload {
+    /// The sequence the client asked to resume after.
+    since: u64,
+    /// Oldest sequence still available, if any.
+    first_available: Option<u64>,
+    /// Sessions whose events after `since` are all part of the replay anyway.
+    complete_sessions: Vec<String>,
+}
+
+/// Recent events, globally and per session.
+#[derive(Default)]
+struct EventHistory {
+    global: EventRing,
+    sessions: HashMap<String, EventRing>,
+    latest: Option<u64>,
+}
+
+impl EventHistory {
+    fn push(&mut self, event: BufferedEvent) {
+        self.latest = Some(event.sequence);
+        if let Some(session_id) = event.session_id.as_deref() {
+            if !self.sessions.contains_key(session_id)
+                && self.sessions.len() >= MAX_SESSION_HISTORIES
+                && let Some(stale) = self
+                    .sessions
+                    .iter()
+                    .min_by_key(|(_, ring)| ring.events.back().map(|event| event.sequence))
+                    .map(|(id, _)| id.clone())
+            {
+                self.sessions.remove(&stale);
+            }
+            self.sessions
+                .entry(session_id.to_string())
+                .or_default()
+                .push(event.clone(), SESSION_HISTORY_LIMIT);
+        }
+        self.global.push(event, EVENT_HISTORY_LIMIT);
+    }
+
+    /// Messages to send a client that last saw `since`, oldest first, preceded
+    /// by a gap marker if part of what it missed is no longer buffered. Only
+    /// events `wanted` returns true for are replayed.
+    fn replay(&self, since: u64, wanted: impl Fn(&BufferedEvent) -> bool) -> Vec<Outgoing> {
+        let mut missed: BTreeMap<u64, &Arc<str>> = BTreeMap::new();
+        for ring in std::iter::once(&self.global).chain(self.sessions.values()) {
+            missed.extend(
+                ring.after(since)
+                    .filter(|event| wanted(event))
+                    .map(|event| (event.sequence, &event.message)),
+            );
+        }
+
+        // A sequence we never handed out means the server restarted since.
+        let restarted = self.latest.is_none_or(|latest| since > latest);
+        let mut messages = Vec::with_capacity(missed.len() + 1);
+        if restarted || !self.global.covers(since) {
+            let mut complete_sessions: Vec<String> = if restarted {
+                Vec::new()
+            } else {
+                self.sessions
+                    .iter()
+                    .filter(|(_, ring)| ring.covers(since))
+                    .map(|(id, _)| id.clone())
+                    .collect()
+            };
+            complete_sessions.sort();
+            let marker = GapMarker {
+                event: "gap",
+                payload: GapPayload {
+                    since,
+                    first_available: missed.keys().next().copied(),
+                    complete_sessions,
+                },
+            };
+            match serde_json::to_string(&marker) {
+                Ok(message) => messages.push(Outgoing {
+                    sequence: None,
+                    message: message.into(),
+                }),
+                Err(e) => error!("Failed to serialize gap marker: {e}"),
+            }
+        }
+        messages.extend(missed.into_iter().map(|(sequence, message)| Outgoing {
+            sequence: Some(sequence),
+            message: message.clone(),
+        }));
+        messages
+    }
+}
+
+/// Manages active WebSocket and SSE connections and routes events to the ones
+/// subscribed to them
 #[derive(Clone)]
 pub struct WebSocketManager {
-    connections: Arc<Mutex<Vec<tokio_mpsc::UnboundedSender<String>>>>,
+    connections: Arc<Mutex<Vec<Connection>>>,
+    /// Only locked while `connections` is held, so a replay and the live
+    /// stream never overlap or leave a hole.
+    history: Arc<Mutex<EventHistory>>,
     connection_counter: Arc<AtomicU64>,
 }
 
@@ -47,48 +431,109 @@ impl Default for WebSocketManager {
     fn default() -> Self {
         Self {
             connections: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(Mutex::new(EventHistory::default())),
             connection_counter: Arc::new(AtomicU64::new(0)),
         }
     }
 }
 
 impl WebSocketManager {
-    /// Register a new WebSocket connection
-    pub async fn add_connection(&self, sender: tokio_mpsc::UnboundedSender<String>) -> u64 {
+    /// Register a new WebSocket or SSE connection subscribed to `topics`.
+    /// Returns its id and the buffered events after `since` it is subscribed
+    /// to, which the caller sends before anything that arrives on `sender`;
+    /// they may not fit in its queue.
+    pub async fn add_connection(
+        &self,
+        sender: tokio_mpsc::Sender<Outgoing>,
+        topics: HashSet<Topic>,
+        since: Option<u64>,
+    ) -> (u64, Vec<Outgoing>) {
         let connection_id = self.connection_counter.fetch_add(1, Ordering::SeqCst);
+        let connection = Connection {
+            id: connection_id,
+            sender,
+            topics,
+        };
         let mut connections = self.connections.lock().await;
-        connections.push(sender);
-        println!(
-            "📡 WebSocket connection added (ID: {}). Total connections: {}",
+        let replay = match since {
+            Some(since) => {
+                let replay = self
+                    .history
+                    .lock()
+                    .await
+                    .replay(since, |event| connection.wants(event));
+                debug!(
+                    "Replaying {} events after {since} to connection {connection_id}",
+                    replay.len()
+                );
+                replay
+            }
+            None => Vec::new(),
+        };
+        connections.push(connection);
+        debug!(
+            "WebSocket connection added (ID: {}). Total connections: {}",
             connection_id,
             connections.len()
         );
-        connection_id
+        (connection_id, replay)
+    }
+
+    /// Add topics to (`subscribe`) or remove them from a connection's
+    /// subscriptions
+    pub async fn update_subscriptions(
+        &self,
+        connection_id: u64,
+        topics: Vec<Topic>,
+        subscribe: bool,
+    ) {
+        let mut connections = self.connections.lock().await;
+        let Some(connection) = connections.iter_mut().find(|conn| conn.id == connection_id) else {
+            return;
+        };
+        for topic in topics {
+            if subscribe {
+                connection.topics.insert(topic);
+            } else {
+                connection.topics.remove(&topic);
+            }
+        }
+        debug!(
+            "WebSocket connection {connection_id} is subscribed to {:?}",
+            connection.topics
+        );
     }
 
     /// Remove a specific WebSocket connection
-    pub async fn remove_connection(&self, sender: &tokio_mpsc::UnboundedSender<String>) {
+    pub async fn remove_connection(&self, connection_id: u64) {
         let mut connections = self.connections.lock().await;
-        if let Some(pos) = connections
-            .iter()
-            .position(|conn| std::ptr::eq(conn, sender))
-        {
+        if let Some(pos) = connections.iter().position(|conn| conn.id == connection_id) {
             connections.remove(pos);
-            println!(
-                "📡 WebSocket connection removed. Total connections: {}",
+            debug!(
+                "WebSocket connection removed. Total connections: {}",
                 connections.len()
             );
         }
     }
 
-    /// Broadcast an event message to all connected clients
-    pub async fn broadcast(&self, message: String) -> anyhow::Result<()> {
+    /// Record an event and send it to every client subscribed to it
+    pub async fn broadcast(&self, event: BufferedEvent) -> anyhow::Result<()> {
         let mut connections = self.connections.lock().await;
+        self.history.lock().await.push(event.clone());
         let mut failed_indices = Vec::new();
 
-        // Send to all connections, tracking failures
-        for (i, sender) in connections.iter().enumerate() {
-            if sender.send(message.clone()).is_err() {
+        // Send to subscribed connections, tracking failures. SSE streams
+        // can't clean up after themselves, so closed ones are dropped here
+        // even if they aren't subscribed to this event. So are clients too
+        // far behind to take another event, rather than queueing without end.
+        for (i, connection) in connections.iter().enumerate() {
+            let outgoing = || Outgoing {
+                sequence: Some(event.sequence),
+                message: event.message.clone(),
+            };
+            if connection.sender.is_closed()
+                || connection.wants(&event) && !connection.offer(outgoing())
+            {
                 failed_indices.push(i);
             }
         }
@@ -99,8 +544,8 @@ impl WebSocketManager {
         }
 
         if !failed_indices.is_empty() {
-            println!(
-                "📡 Removed {} dead WebSocket connections. Active: {}",
+            debug!(
+                "Removed {} closed or lagging event stream connections. Active: {}",
                 failed_indices.len(),
                 connections.len()
             );
@@ -109,16 +554,36 @@ impl WebSocketManager {
         Ok(())
     }
 
+    /// Send a message to a single connection, if it is still open
+    pub async fn send_to(&self, connection_id: u64, message: String) {
+        let mut connections = self.connections.lock().await;
+        if let Some(pos) = connections.iter().position(|conn| conn.id == connection_id)
+            && !connections[pos].offer(Outgoing {
+                sequence: None,
+                message: message.into(),
+            })
+        {
+            connections.remove(pos);
+        }
+    }
+
     /// Get the number of active connections
     pub async fn connection_count(&self) -> usize {
         self.connections.lock().await.len()
     }
 
+    /// Wait until at least one client is connected
+    pub async fn wait_for_client(&self) {
+        while self.connection_count().await == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
     /// Close all WebSocket connections gracefully
     pub async fn close_all_connections(&self) {
         let mut connections = self.connections.lock().await;
-        println!(
-            "📡 Closing {} WebSocket connections for graceful shutdown",
+        debug!(
+            "Closing {} WebSocket connections for graceful shutdown",
             connections.len()
         );
         connections.clear();
@@ -127,9 +592,9 @@ impl WebSocketManager {
 
 /// WebSocket event message format with sequence number for ordering
 #[derive(Serialize)]
-struct WebSocketEvent<T> {
+struct WebSocketEvent {
     event: String,
-    payload: T,
+    payload: serde_json::Value,
     sequence: u64,
 }
 
@@ -137,17 +602,26 @@ struct WebSocketEvent<T> {
 // WebSockets EventEmitter Implementation
 // =====================================
 
+/// What the broadcast worker sends: an event for every subscribed
+/// connection, or a reply for the one connection that sent a command.
+enum Outbound {
+    Event(BufferedEvent),
+    Reply { connection_id: u64, message: String },
+}
+
 /// WebSocket-based event emitter that implements EventEmitter
 #[derive(Clone)]
 pub struct WebSocketsEventEmitter {
     sequence_counter: Arc<AtomicU64>,
-    event_sender: mpsc::Sender<String>,
+    /// Locked while a sequence number is taken and the event is queued, so
+    /// events reach the broadcast worker in sequence order.
+    event_sender: Arc<std::sync::Mutex<mpsc::Sender<Outbound>>>,
 }
 
 impl WebSocketsEventEmitter {
     pub fn new(ws_manager: WebSocketManager) -> Self {
         // Create synchronous channel for ordered event processing
-        let (event_sender, event_receiver) = mpsc::channel::<String>();
+        let (event_sender, event_receiver) = mpsc::channel::<Outbound>();
 
         // Spawn async worker task to bridge sync channel to async WebSocket broadcast
         let ws_manager_worker = ws_manager.clone();
@@ -156,9 +630,17 @@ impl WebSocketsEventEmitter {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async move {
                 // Process events in order from synchronous channel
-                while let Ok(message) = event_receiver.recv() {
-                    if let Err(e) = ws_manager_worker.broadcast(message).await {
-                        eprintln!("❌ Failed to broadcast WebSocket event: {e}");
+                while let Ok(outbound) = event_receiver.recv() {
+                    match outbound {
+                        Outbound::Event(event) => {
+                            if let Err(e) = ws_manager_worker.broadcast(event).await {
+                                error!("Failed to broadcast WebSocket event: {e}");
+                            }
+                        }
+                        Outbound::Reply {
+                            connection_id,
+                            message,
+                        } => ws_manager_worker.send_to(connection_id, message).await,
                     }
                 }
             });
@@ -166,19 +648,43 @@ impl WebSocketsEventEmitter {
 
         Self {
             sequence_counter: Arc::new(AtomicU64::new(0)),
-            event_sender,
+            event_sender: Arc::new(std::sync::Mutex::new(event_sender)),
         }
     }
+
+    /// Queue a message for one connection behind every event emitted so far,
+    /// so a command's reply arrives after the events the command caused.
+    fn reply(&self, connection_id: u64, response: &RpcResponse) -> anyhow::Result<()> {
+        let message =
+            serde_json::to_string(response).context("Failed to serialize JSON-RPC response")?;
+        self.event_sender
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock WebSocket event sender mutex"))?
+            .send(Outbound::Reply {
+                connection_id,
+                message,
+            })
+            .context("Failed to send message to WebSocket channel")
+    }
 }
 
 impl EventEmitter for WebSocketsEventEmitter {
-    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> anyhow::Result<()> {
+    fn emit(&self, event: AppEvent) -> anyhow::Result<()> {
+        let family = event.family();
+        let session_id = event.session_id().map(str::to_string);
+        let (event, payload) = event.to_wire()?;
+
+        let event_sender = self
+            .event_sender
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to lock WebSocket event sender mutex"))?;
+
         // Get next sequence number for ordering
         let sequence = self.sequence_counter.fetch_add(1, Ordering::SeqCst);
 
         // Create WebSocket event message with sequence number for ordering
         let ws_event = WebSocketEvent {
-            event: event.to_string(),
+            event,
             payload,
             sequence,
         };
@@ -188,8 +694,14 @@ impl EventEmitter for WebSocketsEventEmitter {
             .context("Failed to serialize WebSocket event to JSON")?;
 
         // Send synchronously to ordered channel - this maintains perfect ordering
-        self.event_sender
-            .send(message)
+        let event = BufferedEvent {
+            sequence,
+            family,
+            session_id,
+            message: message.into(),
+        };
+        event_sender
+            .send(Outbound::Event(event))
             .context("Failed to send message to WebSocket channel")?;
 
         Ok(())
@@ -200,16 +712,42 @@ impl EventEmitter for WebSocketsEventEmitter {
 // Application State
 // =====================================
 
-struct AppState {
-    backend: Arc<Mutex<GeminiBackend<WebSocketsEventEmitter>>>,
+pub struct AppState {
+    backend: Arc<Mutex<Backend>>,
     ws_manager: WebSocketManager,
+    emitter: WebSocketsEventEmitter,
+}
+
+impl AppState {
+    /// A backend with its own event stream, for `user` on a shared server.
+    fn new(config: &ServerConfig, user: Option<&UserConfig>) -> (Self, StatusMonitor<Emitter>) {
+        let ws_manager = WebSocketManager::new();
+        let emitter = WebSocketsEventEmitter::new(ws_manager.clone());
+        let backend = match user {
+            None => GeminiBackend::new(RecordingEventEmitter::from_env(emitter.clone()))
+                .with_path_policy(config.path_policy()),
+            // One recording can't tell the users' events apart
+            Some(user) => GeminiBackend::new(RecordingEventEmitter::new(emitter.clone()))
+                .with_path_policy(user.path_policy(config))
+                .with_data_dir(user.data_dir.clone())
+                .with_process_env(user.process_env()),
+        }
+        .with_enabled_backends(config.backends.iter().map(|backend| backend.as_str()));
+        let status_monitor = backend.status_monitor(STATUS_MONITOR_INTERVAL);
+        let state = Self {
+            backend: Arc::new(Mutex::new(backend)),
+            ws_manager,
+            emitter,
+        };
+        (state, status_monitor)
+    }
 }
 
 // =====================================
 // Request/Response Types
 // =====================================
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct StartSessionRequest {
     session_id: String,
@@ -218,9 +756,11 @@ struct StartSessionRequest {
     backend_config: Option<backend::session::QwenConfig>,
     gemini_auth: Option<backend::session::GeminiAuthConfig>,
     llxprt_config: Option<backend::session::LLxprtConfig>,
+    /// Vault profile to take credentials from instead of the raw configs above.
+    profile_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct SendMessageRequest {
     session_id: String,
@@ -229,15 +769,16 @@ struct SendMessageRequest {
     model: Option<String>,
     backend_config: Option<backend::session::QwenConfig>,
     gemini_auth: Option<backend::session::GeminiAuthConfig>,
+    profile_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct KillProcessRequest {
     conversation_id: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ToolConfirmationRequest {
     session_id: String,
@@ -248,86 +789,228 @@ struct ToolConfirmationRequest {
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct CancelPromptRequest {
+    session_id: String,
+}
+
+type Emitter = RecordingEventEmitter<WebSocketsEventEmitter>;
+type Backend = GeminiBackend<Emitter>;
+
+// The session commands can be sent over HTTP or the event WebSocket, so they
+// run here rather than in the route handlers.
+
+impl StartSessionRequest {
+    async fn run(self, backend: &Backend) -> anyhow::Result<()> {
+        // If working_directory is provided, initialize a session with that directory
+        let Some(working_directory) = self.working_directory else {
+            return Ok(());
+        };
+        if let Some(profile_id) = self.profile_id {
+            return backend
+                .initialize_session_with_profile(
+                    self.session_id,
+                    working_directory,
+                    self.model,
+                    &profile_id,
+                )
+                .await
+                .context("Failed to initialize session");
+        }
+
+        let model = self
+            .model
+            .unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
+        backend
+            .initialize_session(
+                self.session_id,
+                working_directory,
+                model,
+                self.backend_config,
+                self.gemini_auth,
+                self.llxprt_config,
+            )
+            .await
+            .context("Failed to initialize session")
+    }
+}
+
+impl SendMessageRequest {
+    async fn run(self, backend: &Backend) -> anyhow::Result<()> {
+        // Check if session exists, if not and we have backend config, initialize it first
+        let session_exists = backend
+            .get_process_statuses()
+            .unwrap_or_default()
+            .iter()
+            .any(|status| status.conversation_id == self.session_id && status.is_alive);
+
+        if !session_exists && let Some(profile_id) = &self.profile_id {
+            backend
+                .initialize_session_with_profile(
+                    self.session_id.clone(),
+                    ".".to_string(),
+                    self.model.clone(),
+                    profile_id,
+                )
+                .await
+                .context("Failed to initialize session")?;
+        } else if !session_exists && self.backend_config.is_some() {
+            debug!("YOLO-DEBUG: send_message creating new session for backend_config");
+            if let Some(ref auth) = self.gemini_auth {
+                debug!("YOLO-DEBUG: send_message gemini_auth: {auth:?}");
+            } else {
+                debug!("YOLO-DEBUG: send_message NO gemini_auth provided!");
+            }
+            let model = self
+                .model
+                .unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
+            // Initialize session with minimal working directory (current directory)
+            backend
+                .initialize_session(
+                    self.session_id.clone(),
+                    ".".to_string(),
+                    model,
+                    self.backend_config,
+                    self.gemini_auth,
+                    None, // llxprt_config not available in send_message
+                )
+                .await
+                .context("Failed to initialize session")?;
+        }
+
+        backend
+            .send_message(self.session_id, self.message, self.conversation_history)
+            .await
+            .context("Failed to send message")
+    }
+}
+
+impl KillProcessRequest {
+    async fn run(self, backend: &Backend) -> anyhow::Result<()> {
+        backend
+            .kill_process(&self.conversation_id)
+            .context("Failed to kill process")
+    }
+}
+
+impl ToolConfirmationRequest {
+    async fn run(self, backend: &Backend) -> anyhow::Result<()> {
+        backend
+            .handle_tool_confirmation(
+                self.session_id,
+                self.request_id,
+                self.tool_call_id,
+                self.outcome,
+            )
+            .await
+            .context("Failed to send tool call confirmation response")
+    }
+}
+
+impl CancelPromptRequest {
+    async fn run(self, backend: &Backend) -> anyhow::Result<()> {
+        backend
+            .cancel_prompt(&self.session_id)
+            .await
+            .context("Failed to cancel prompt")
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 struct ExecuteCommandRequest {
     command: String,
+    working_directory: String,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    timeout_secs: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct GenerateTitleRequest {
     message: String,
     model: Option<String>,
+    session_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ValidateDirectoryRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct IsHomeDirectoryRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ListDirectoryRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ListFilesRecursiveRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct GetParentDirectoryRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct GetGitInfoRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ReadFileContentRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ReadBinaryFileAsBase64Request {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct CanonicalPathRequest {
     path: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ReadFileContentWithOptionsRequest {
     path: String,
     force_text: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct WriteFileContentRequest {
     path: String,
     content: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct DiffRequest {
+    path: String,
+    old_text: String,
+    new_text: String,
+    context_lines: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct DeleteProjectRequest {
     project_id: String,
@@ -337,12 +1020,25 @@ struct DeleteProjectRequest {
 pub struct AnyhowResponder(pub AnyhowError);
 
 impl<'r> Responder<'r, 'static> for AnyhowResponder {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        // Refused file access is the client's doing, so it gets a 403 that
+        // says why instead of a 500
+        if let Some(denied) = self.0.downcast_ref::<PathPermissionError>() {
+            info!("{denied}");
+            let body = serde_json::json!({
+                "error": denied.to_string(),
+                "code": "permission_denied",
+                "path": denied.path,
+                "reason": denied.reason,
+            });
+            return (Status::Forbidden, Json(body)).respond_to(request);
+        }
+
         // Use :#? for full error chain formatting
         let error_message = format!("{{\"error\":\"{:#}\"}}", self.0);
 
         // Log the full error chain for debugging
-        eprintln!("Error occurred: {:#}", self.0);
+        error!("Error occurred: {:#}", self.0);
 
         Response::build()
             .status(Status::InternalServerError)
@@ -387,9 +1083,10 @@ fn index(path: PathBuf) -> Result<(ContentType, &'static [u8]), Status> {
 
 #[get("/projects?<limit>&<offset>")]
 async fn list_projects(
+    _auth: Authenticated,
     limit: Option<u32>,
     offset: Option<u32>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<serde_json::Value>> {
     let lim = limit.unwrap_or(25);
     let off = offset.unwrap_or(0);
@@ -407,7 +1104,10 @@ async fn list_projects(
 }
 
 #[get("/projects-enriched")]
-async fn list_enriched_projects(state: &State<AppState>) -> AppResult<Json<Vec<EnrichedProject>>> {
+async fn list_enriched_projects(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<EnrichedProject>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
@@ -419,7 +1119,8 @@ async fn list_enriched_projects(state: &State<AppState>) -> AppResult<Json<Vec<E
 
 #[get("/project?<sha256>&<external_root_path>")]
 async fn get_enriched_project_http(
-    state: &State<AppState>,
+    _auth: Authenticated,
+    state: UserState<'_>,
     sha256: String,
     external_root_path: String,
 ) -> AppResult<Json<EnrichedProject>> {
@@ -434,8 +1135,9 @@ async fn get_enriched_project_http(
 
 #[get("/projects/<project_id>/discussions")]
 async fn get_project_discussions(
+    _auth: Authenticated,
     project_id: &str,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<Vec<RecentChat>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -446,8 +1148,40 @@ async fn get_project_discussions(
     ))
 }
 
+#[get("/projects/<project_id>/history-settings")]
+async fn get_project_history_settings(
+    _auth: Authenticated,
+    project_id: &str,
+    state: UserState<'_>,
+) -> AppResult<Json<ProjectHistorySettings>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .get_project_history_settings(project_id)
+            .await
+            .context("Failed to get project history settings")?,
+    ))
+}
+
+#[post("/projects/<project_id>/history-settings", data = "<settings>")]
+async fn set_project_history_settings(
+    _auth: Authenticated,
+    project_id: &str,
+    settings: Json<ProjectHistorySettings>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .set_project_history_settings(project_id, settings.into_inner())
+        .await
+        .context("Failed to update project history settings")?)
+}
+
 #[get("/recent-chats")]
-async fn get_recent_chats(state: &State<AppState>) -> AppResult<Json<Vec<RecentChat>>> {
+async fn get_recent_chats(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<RecentChat>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
@@ -457,7 +1191,7 @@ async fn get_recent_chats(state: &State<AppState>) -> AppResult<Json<Vec<RecentC
     ))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SearchChatsRequest {
     query: String,
     filters: Option<SearchFilters>,
@@ -465,8 +1199,9 @@ struct SearchChatsRequest {
 
 #[post("/search-chats", data = "<request>")]
 async fn search_chats(
+    _auth: Authenticated,
     request: Json<SearchChatsRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<Vec<SearchResult>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -479,8 +1214,9 @@ async fn search_chats(
 
 #[get("/conversations/<chat_id>")]
 async fn get_detailed_conversation(
+    _auth: Authenticated,
     chat_id: String,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<DetailedConversation>> {
     let decoded_chat_id = urlencoding::decode(&chat_id)
         .map_err(|e| AnyhowError::msg(format!("Failed to decode chat ID: {}", e)))?;
@@ -494,16 +1230,17 @@ async fn get_detailed_conversation(
     ))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct ExportConversationRequest {
     format: String,
 }
 
 #[post("/conversations/<chat_id>/export", data = "<request>")]
 async fn export_conversation_history(
+    _auth: Authenticated,
     chat_id: String,
     request: Json<ExportConversationRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<String> {
     let backend = state.backend.lock().await;
     backend
@@ -514,7 +1251,11 @@ async fn export_conversation_history(
 }
 
 #[delete("/conversations/<chat_id>")]
-async fn delete_conversation(chat_id: String, state: &State<AppState>) -> AppResult<()> {
+async fn delete_conversation(
+    _auth: Authenticated,
+    chat_id: String,
+    state: UserState<'_>,
+) -> AppResult<()> {
     let decoded_chat_id = urlencoding::decode(&chat_id)
         .map_err(|e| AnyhowError::msg(format!("Failed to decode chat ID: {}", e)))?;
 
@@ -525,10 +1266,119 @@ async fn delete_conversation(chat_id: String, state: &State<AppState>) -> AppRes
         .context("Failed to delete conversation")?)
 }
 
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct VaultPassphraseRequest {
+    /// Omitted to use the OS keyring instead of a passphrase.
+    passphrase: Option<String>,
+}
+
+#[get("/vault/status")]
+async fn vault_status(_auth: Authenticated, state: UserState<'_>) -> AppResult<Json<VaultStatus>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .vault_status()
+            .await
+            .context("Failed to get vault status")?,
+    ))
+}
+
+#[post("/vault/create", data = "<request>")]
+async fn create_vault(
+    _auth: Authenticated,
+    request: Json<VaultPassphraseRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .create_vault(request.into_inner().passphrase)
+        .await
+        .context("Failed to create vault")?)
+}
+
+#[post("/vault/unlock", data = "<request>")]
+async fn unlock_vault(
+    _auth: Authenticated,
+    request: Json<VaultPassphraseRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .unlock_vault(request.into_inner().passphrase)
+        .await
+        .context("Failed to unlock vault")?)
+}
+
+#[post("/vault/lock")]
+async fn lock_vault(_auth: Authenticated, state: UserState<'_>) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend.lock_vault().await.context("Failed to lock vault")?)
+}
+
+#[get("/vault/profiles")]
+async fn list_profiles(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<ProviderProfile>>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .list_profiles()
+            .await
+            .context("Failed to list profiles")?,
+    ))
+}
+
+#[post("/vault/profiles", data = "<input>")]
+async fn create_profile(
+    _auth: Authenticated,
+    input: Json<ProfileInput>,
+    state: UserState<'_>,
+) -> AppResult<Json<ProviderProfile>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .create_profile(input.into_inner())
+            .await
+            .context("Failed to create profile")?,
+    ))
+}
+
+#[put("/vault/profiles/<profile_id>", data = "<input>")]
+async fn update_profile(
+    _auth: Authenticated,
+    profile_id: String,
+    input: Json<ProfileInput>,
+    state: UserState<'_>,
+) -> AppResult<Json<ProviderProfile>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .update_profile(profile_id, input.into_inner())
+            .await
+            .context("Failed to update profile")?,
+    ))
+}
+
+#[delete("/vault/profiles/<profile_id>")]
+async fn delete_profile(
+    _auth: Authenticated,
+    profile_id: String,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .delete_profile(profile_id)
+        .await
+        .context("Failed to delete profile")?)
+}
+
 #[post("/delete-project", data = "<request>")]
 async fn delete_project(
+    _auth: Authenticated,
     request: Json<DeleteProjectRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<()> {
     let backend = state.backend.lock().await;
     Ok(backend
@@ -538,7 +1388,7 @@ async fn delete_project(
 }
 
 #[get("/check-cli-installed")]
-async fn check_cli_installed(state: &State<AppState>) -> AppResult<Json<bool>> {
+async fn check_cli_installed(_auth: Authenticated, state: UserState<'_>) -> AppResult<Json<bool>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
@@ -550,144 +1400,424 @@ async fn check_cli_installed(state: &State<AppState>) -> AppResult<Json<bool>> {
 
 #[post("/start-session", data = "<request>")]
 async fn start_session(
+    _auth: Authenticated,
     request: Json<StartSessionRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<()> {
-    let req = request.into_inner();
     let backend = state.backend.lock().await;
+    Ok(request.into_inner().run(&backend).await?)
+}
 
-    // If working_directory is provided, initialize a session with that directory
-    if let Some(working_directory) = req.working_directory {
-        let model = req
-            .model
-            .unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
+#[post("/send-message", data = "<request>")]
+async fn send_message(
+    _auth: Authenticated,
+    request: Json<SendMessageRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(request.into_inner().run(&backend).await?)
+}
+
+#[get("/process-statuses")]
+async fn get_process_statuses(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<ProcessStatus>>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
         backend
-            .initialize_session(
-                req.session_id,
-                working_directory,
-                model,
-                req.backend_config,
-                req.gemini_auth.clone(),
-                req.llxprt_config,
-            )
+            .get_process_statuses()
+            .context("Failed to get process statuses")?,
+    ))
+}
+
+/// A session and the user it belongs to, for admins.
+#[derive(Serialize, JsonSchema)]
+struct UserProcessStatus {
+    /// `None` on a single-user server.
+    user: Option<String>,
+    #[serde(flatten)]
+    status: ProcessStatus,
+}
+
+/// Every user's sessions.
+#[get("/admin/sessions")]
+async fn list_all_sessions(
+    _admin: Admin,
+    users: &State<Users>,
+) -> AppResult<Json<Vec<UserProcessStatus>>> {
+    let mut sessions = Vec::new();
+    for (user, state) in users.iter() {
+        let statuses = state
+            .backend
+            .lock()
             .await
-            .context("Failed to initialize session")?;
+            .get_process_statuses()
+            .context("Failed to get process statuses")?;
+        sessions.extend(statuses.into_iter().map(|status| UserProcessStatus {
+            user: user.map(str::to_string),
+            status,
+        }));
     }
-    Ok(())
+    Ok(Json(sessions))
 }
 
-#[post("/send-message", data = "<request>")]
-async fn send_message(request: Json<SendMessageRequest>, state: &State<AppState>) -> AppResult<()> {
+#[post("/kill-process", data = "<request>")]
+async fn kill_process(
+    _auth: Authenticated,
+    request: Json<KillProcessRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(request.into_inner().run(&backend).await?)
+}
+
+#[post("/tool-confirmation", data = "<request>")]
+async fn send_tool_call_confirmation_response(
+    _auth: Authenticated,
+    request: Json<ToolConfirmationRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(request.into_inner().run(&backend).await?)
+}
+
+#[post("/execute-command", data = "<request>")]
+async fn execute_confirmed_command(
+    _auth: Authenticated,
+    request: Json<ExecuteCommandRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<JobInfo>> {
     let req = request.into_inner();
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .execute_confirmed_command(JobSpec {
+                command: req.command,
+                working_directory: req.working_directory,
+                env: req.env,
+                timeout_secs: req.timeout_secs,
+            })
+            .await
+            .context("Failed to execute confirmed command")?,
+    ))
+}
 
+#[get("/jobs")]
+async fn list_jobs(_auth: Authenticated, state: UserState<'_>) -> AppResult<Json<Vec<JobInfo>>> {
     let backend = state.backend.lock().await;
+    Ok(Json(
+        backend.list_jobs().await.context("Failed to list jobs")?,
+    ))
+}
 
-    // Check if session exists, if not and we have backend config, initialize it first
-    let session_exists = backend
-        .get_process_statuses()
-        .unwrap_or_default()
-        .iter()
-        .any(|status| status.conversation_id == req.session_id && status.is_alive);
+#[post("/jobs/<job_id>/cancel")]
+async fn cancel_job(_auth: Authenticated, job_id: String, state: UserState<'_>) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .cancel_job(&job_id)
+        .await
+        .context("Failed to cancel job")?)
+}
 
-    if !session_exists && req.backend_config.is_some() {
-        println!("🚀 YOLO-DEBUG: send_message creating new session for backend_config");
-        if let Some(ref auth) = req.gemini_auth {
-            println!("🚀 YOLO-DEBUG: send_message gemini_auth: {auth:?}");
-        } else {
-            println!("🚀 YOLO-DEBUG: send_message NO gemini_auth provided!");
-        }
-        let model = req
-            .model
-            .unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
-        // Initialize session with minimal working directory (current directory)
+#[post("/jobs/<job_id>/kill")]
+async fn kill_job(_auth: Authenticated, job_id: String, state: UserState<'_>) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .kill_job(&job_id)
+        .await
+        .context("Failed to kill job")?)
+}
+
+#[post("/batches", data = "<request>")]
+async fn start_batch(
+    _auth: Authenticated,
+    request: Json<BatchRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<BatchReport>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
         backend
-            .initialize_session(
-                req.session_id.clone(),
-                ".".to_string(),
-                model,
-                req.backend_config,
-                req.gemini_auth,
-                None, // llxprt_config not available in send_message
-            )
+            .start_batch(request.into_inner())
             .await
-            .context("Failed to initialize session")?;
-    }
+            .context("Failed to start batch")?,
+    ))
+}
+
+#[get("/batches")]
+async fn list_batches(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<BatchReport>>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .list_batches()
+            .await
+            .context("Failed to list batches")?,
+    ))
+}
+
+#[get("/batches/<batch_id>")]
+async fn get_batch(
+    _auth: Authenticated,
+    batch_id: String,
+    state: UserState<'_>,
+) -> AppResult<Json<BatchReport>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .get_batch(&batch_id)
+            .await
+            .context("Failed to get batch")?,
+    ))
+}
 
+#[post("/batches/<batch_id>/cancel")]
+async fn cancel_batch(
+    _auth: Authenticated,
+    batch_id: String,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
     Ok(backend
-        .send_message(req.session_id, req.message, req.conversation_history)
+        .cancel_batch(&batch_id)
         .await
-        .context("Failed to send message")?)
+        .context("Failed to cancel batch")?)
 }
 
-#[get("/process-statuses")]
-async fn get_process_statuses(state: &State<AppState>) -> AppResult<Json<Vec<ProcessStatus>>> {
+#[get("/batches/<batch_id>/export?<format>")]
+async fn export_batch(
+    _auth: Authenticated,
+    batch_id: String,
+    format: Option<String>,
+    state: UserState<'_>,
+) -> AppResult<String> {
+    let backend = state.backend.lock().await;
+    backend
+        .export_batch(&batch_id, format.as_deref().unwrap_or("markdown"))
+        .await
+        .context("Failed to export batch")
+        .map_err(AnyhowResponder)
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct SpawnTerminalRequest {
+    project_id: String,
+    working_directory: Option<String>,
+    shell: Option<String>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TerminalInputRequest {
+    data: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TerminalResizeRequest {
+    cols: u16,
+    rows: u16,
+}
+
+/// Messages a client sends over a terminal WebSocket.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TerminalClientMessage {
+    Input { data: String },
+    Resize { cols: u16, rows: u16 },
+}
+
+#[post("/terminals", data = "<request>")]
+async fn spawn_terminal(
+    _auth: Authenticated,
+    request: Json<SpawnTerminalRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<TerminalInfo>> {
+    let req = request.into_inner();
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
-            .get_process_statuses()
-            .context("Failed to get process statuses")?,
+            .spawn_terminal(
+                req.project_id,
+                req.working_directory,
+                req.shell,
+                req.cols.unwrap_or(terminal::DEFAULT_COLS),
+                req.rows.unwrap_or(terminal::DEFAULT_ROWS),
+            )
+            .await
+            .context("Failed to open terminal")?,
     ))
 }
 
-#[post("/kill-process", data = "<request>")]
-async fn kill_process(request: Json<KillProcessRequest>, state: &State<AppState>) -> AppResult<()> {
+#[get("/terminals?<project_id>")]
+async fn list_terminals(
+    _auth: Authenticated,
+    project_id: Option<String>,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<TerminalInfo>>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .list_terminals(project_id.as_deref())
+            .await
+            .context("Failed to list terminals")?,
+    ))
+}
+
+#[post("/terminals/<terminal_id>/input", data = "<request>")]
+async fn write_terminal(
+    _auth: Authenticated,
+    terminal_id: String,
+    request: Json<TerminalInputRequest>,
+    state: UserState<'_>,
+) -> AppResult<()> {
     let backend = state.backend.lock().await;
     Ok(backend
-        .kill_process(&request.conversation_id)
-        .context("Failed to kill process")?)
+        .write_terminal(&terminal_id, &request.data)
+        .await
+        .context("Failed to write to terminal")?)
 }
 
-#[post("/tool-confirmation", data = "<request>")]
-async fn send_tool_call_confirmation_response(
-    request: Json<ToolConfirmationRequest>,
-    state: &State<AppState>,
+#[post("/terminals/<terminal_id>/resize", data = "<request>")]
+async fn resize_terminal(
+    _auth: Authenticated,
+    terminal_id: String,
+    request: Json<TerminalResizeRequest>,
+    state: UserState<'_>,
 ) -> AppResult<()> {
-    let req = request.into_inner();
     let backend = state.backend.lock().await;
     Ok(backend
-        .handle_tool_confirmation(
-            req.session_id,
-            req.request_id,
-            req.tool_call_id,
-            req.outcome,
-        )
+        .resize_terminal(&terminal_id, request.cols, request.rows)
         .await
-        .context("Failed to send tool call confirmation response")?)
+        .context("Failed to resize terminal")?)
 }
 
-#[post("/execute-command", data = "<request>")]
-async fn execute_confirmed_command(
-    request: Json<ExecuteCommandRequest>,
-    state: &State<AppState>,
+#[get("/terminals/<terminal_id>/scrollback")]
+async fn terminal_scrollback(
+    _auth: Authenticated,
+    terminal_id: String,
+    state: UserState<'_>,
 ) -> AppResult<Json<String>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
-            .execute_confirmed_command(request.command.clone())
+            .terminal_scrollback(&terminal_id)
             .await
-            .context("Failed to execute confirmed command")?,
+            .context("Failed to read terminal scrollback")?,
     ))
 }
 
+#[delete("/terminals/<terminal_id>")]
+async fn kill_terminal(
+    _auth: Authenticated,
+    terminal_id: String,
+    state: UserState<'_>,
+) -> AppResult<()> {
+    let backend = state.backend.lock().await;
+    Ok(backend
+        .kill_terminal(&terminal_id)
+        .await
+        .context("Failed to kill terminal")?)
+}
+
+/// Bidirectional channel for one terminal. The client first receives the
+/// scrollback as an `output` message, then live `output`/`exit` messages, and
+/// sends `input` and `resize` messages back.
+#[get("/terminals/<terminal_id>/ws")]
+async fn terminal_websocket(
+    _auth: Authenticated,
+    terminal_id: String,
+    ws: WebSocket,
+    state: UserState<'_>,
+    mut shutdown: Shutdown,
+) -> AppResult<Channel<'static>> {
+    let terminals = state.backend.lock().await.terminals().clone();
+    let (info, scrollback, mut live) = terminals
+        .subscribe(&terminal_id)
+        .context("Failed to attach to terminal")?;
+
+    Ok(ws.channel(move |mut stream| {
+        Box::pin(async move {
+            debug!("Terminal WebSocket attached to {terminal_id}");
+            let mut backlog = vec![TerminalEvent::Output { data: scrollback }];
+            if !info.alive {
+                backlog.push(TerminalEvent::Exit {
+                    code: info.exit_code,
+                });
+            }
+            for event in backlog {
+                stream.send(terminal_message(&event)).await?;
+            }
+
+            loop {
+                tokio::select! {
+                    event = live.recv() => match event {
+                        Ok(event) => {
+                            stream.send(terminal_message(&event)).await?;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            debug!("Terminal WebSocket for {terminal_id} skipped {skipped} events");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Text(text))) => {
+                            let result = match serde_json::from_str(&text) {
+                                Ok(TerminalClientMessage::Input { data }) => {
+                                    terminals.write(&terminal_id, data.as_bytes())
+                                }
+                                Ok(TerminalClientMessage::Resize { cols, rows }) => {
+                                    terminals.resize(&terminal_id, cols, rows)
+                                }
+                                Err(e) => Err(e.into()),
+                            };
+                            if let Err(e) = result {
+                                debug!("Terminal WebSocket message for {terminal_id} failed: {e:#}");
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => break,
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e),
+                    },
+                    _ = &mut shutdown => break,
+                }
+            }
+
+            debug!("Terminal WebSocket detached from {terminal_id}");
+            Ok(())
+        })
+    }))
+}
+
+fn terminal_message(event: &TerminalEvent) -> Message {
+    Message::text(serde_json::to_string(event).unwrap_or_default())
+}
+
 #[post("/generate-title", data = "<request>")]
 async fn generate_conversation_title(
+    _auth: Authenticated,
     request: Json<GenerateTitleRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<String>> {
     let req = request.into_inner();
-    let backend = state.backend.lock().await;
-    Ok(Json(
-        backend
-            .generate_conversation_title(req.message, req.model)
-            .await
-            .context("Failed to generate conversation title")?,
-    ))
+    // The agent can take a while, so don't hold up the user's other requests
+    let generation = state
+        .backend
+        .lock()
+        .await
+        .prepare_title_generation(req.message, req.model, req.session_id)
+        .context("Failed to generate conversation title")?;
+    Ok(Json(generation.run().await))
 }
 
 #[post("/validate-directory", data = "<request>")]
 async fn validate_directory(
+    _auth: Authenticated,
     request: Json<ValidateDirectoryRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<bool>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -700,8 +1830,9 @@ async fn validate_directory(
 
 #[post("/is-home-directory", data = "<request>")]
 async fn is_home_directory(
+    _auth: Authenticated,
     request: Json<IsHomeDirectoryRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<bool>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -713,7 +1844,7 @@ async fn is_home_directory(
 }
 
 #[get("/get-home-directory")]
-async fn get_home_directory(state: &State<AppState>) -> AppResult<Json<String>> {
+async fn get_home_directory(_auth: Authenticated, state: UserState<'_>) -> AppResult<Json<String>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
@@ -725,8 +1856,9 @@ async fn get_home_directory(state: &State<AppState>) -> AppResult<Json<String>>
 
 #[post("/get-parent-directory", data = "<request>")]
 async fn get_parent_directory(
+    _auth: Authenticated,
     request: Json<GetParentDirectoryRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<Option<String>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -739,8 +1871,9 @@ async fn get_parent_directory(
 
 #[post("/list-directory", data = "<request>")]
 async fn list_directory_contents(
+    _auth: Authenticated,
     request: Json<ListDirectoryRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<Vec<DirEntry>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -753,8 +1886,9 @@ async fn list_directory_contents(
 
 #[post("/list-files-recursive", data = "<request>")]
 async fn list_files_recursive(
+    _auth: Authenticated,
     request: Json<ListFilesRecursiveRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> Json<Vec<DirEntry>> {
     let backend = state.backend.lock().await;
     let contents = backend
@@ -765,7 +1899,10 @@ async fn list_files_recursive(
 }
 
 #[get("/list-volumes")]
-async fn list_volumes(state: &State<AppState>) -> AppResult<Json<Vec<DirEntry>>> {
+async fn list_volumes(
+    _auth: Authenticated,
+    state: UserState<'_>,
+) -> AppResult<Json<Vec<DirEntry>>> {
     let backend = state.backend.lock().await;
     Ok(Json(
         backend
@@ -777,8 +1914,9 @@ async fn list_volumes(state: &State<AppState>) -> AppResult<Json<Vec<DirEntry>>>
 
 #[post("/get-git-info", data = "<request>")]
 async fn get_git_info(
+    _auth: Authenticated,
     request: Json<GetGitInfoRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> Result<Json<Option<GitInfo>>, Status> {
     let backend = state.backend.lock().await;
     match backend.get_git_info(request.path.clone()).await {
@@ -789,8 +1927,9 @@ async fn get_git_info(
 
 #[post("/read-file-content", data = "<request>")]
 async fn read_file_content(
+    _auth: Authenticated,
     request: Json<ReadFileContentRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<FileContent>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -803,8 +1942,9 @@ async fn read_file_content(
 
 #[post("/read-binary-file-as-base64", data = "<request>")]
 async fn read_binary_file_as_base64(
+    _auth: Authenticated,
     request: Json<ReadBinaryFileAsBase64Request>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<String>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -817,8 +1957,9 @@ async fn read_binary_file_as_base64(
 
 #[post("/get-canonical-path", data = "<request>")]
 async fn get_canonical_path(
+    _auth: Authenticated,
     request: Json<CanonicalPathRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<String>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -831,8 +1972,9 @@ async fn get_canonical_path(
 
 #[post("/read-file-content-with-options", data = "<request>")]
 async fn read_file_content_with_options(
+    _auth: Authenticated,
     request: Json<ReadFileContentWithOptionsRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<FileContent>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -845,8 +1987,9 @@ async fn read_file_content_with_options(
 
 #[post("/write-file-content", data = "<request>")]
 async fn write_file_content(
+    _auth: Authenticated,
     request: Json<WriteFileContentRequest>,
-    state: &State<AppState>,
+    state: UserState<'_>,
 ) -> AppResult<Json<FileContent>> {
     let backend = state.backend.lock().await;
     Ok(Json(
@@ -857,108 +2000,482 @@ async fn write_file_content(
     ))
 }
 
+#[post("/preview-diff", data = "<request>")]
+async fn preview_diff(
+    _auth: Authenticated,
+    request: Json<DiffRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<DiffPreview>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .preview_diff(
+                request.path.clone(),
+                request.old_text.clone(),
+                request.new_text.clone(),
+                request.context_lines,
+            )
+            .await
+            .context("Failed to preview diff")?,
+    ))
+}
+
+#[post("/apply-diff", data = "<request>")]
+async fn apply_diff(
+    _auth: Authenticated,
+    request: Json<DiffRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<DiffApplyResult>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .apply_diff(
+                request.path.clone(),
+                request.old_text.clone(),
+                request.new_text.clone(),
+            )
+            .await
+            .context("Failed to apply diff")?,
+    ))
+}
+
+#[post("/revert-diff", data = "<request>")]
+async fn revert_diff(
+    _auth: Authenticated,
+    request: Json<DiffRequest>,
+    state: UserState<'_>,
+) -> AppResult<Json<DiffApplyResult>> {
+    let backend = state.backend.lock().await;
+    Ok(Json(
+        backend
+            .revert_diff(
+                request.path.clone(),
+                request.old_text.clone(),
+                request.new_text.clone(),
+            )
+            .await
+            .context("Failed to revert diff")?,
+    ))
+}
+
 // =====================================
 // WebSocket Route Handler
 // =====================================
 
-#[get("/ws")]
+/// Live event stream. A client that reconnects passes the last `sequence` it
+/// saw as `since` to get the events it missed replayed first. A client that
+/// falls too far behind is closed with code 1013 (try again later) and
+/// catches up that way.
+///
+/// Clients can also send JSON-RPC 2.0 commands (`start_session`,
+/// `send_message`, `tool_confirmation`, `cancel_prompt`, `kill_process`) on
+/// the socket. They run one at a time in the order received, and each
+/// response is queued behind the events emitted before it, so a client sees
+/// the events a command caused before its response.
+///
+/// Without `topics` the connection receives every event, as it always has.
+/// With `topics` (a comma-separated list such as
+/// `session:abc,family:process-status-changed`, possibly empty) it receives
+/// only the events matching one of them. Either way the client can change its
+/// subscriptions later by sending `{"type":"subscribe","topics":[...]}` or
+/// `{"type":"unsubscribe","topics":[...]}`.
+#[get("/ws?<since>&<topics>")]
 fn websocket_handler(
+    _auth: Authenticated,
     ws: WebSocket,
-    state: &State<AppState>,
+    since: Option<u64>,
+    topics: Option<&str>,
+    state: UserState<'_>,
     mut shutdown: Shutdown,
-) -> Stream!['static] {
+) -> Channel<'static> {
     let ws_manager = state.ws_manager.clone();
+    let backend = state.backend.clone();
+    let emitter = state.emitter.clone();
+    let topics = topics.map_or_else(|| HashSet::from([Topic::Global]), Topic::parse_list);
+
+    ws.channel(move |mut stream| {
+        Box::pin(async move {
+            // Create a channel for this WebSocket connection to receive backend events
+            let (tx, mut rx) = tokio_mpsc::channel::<Outgoing>(CONNECTION_QUEUE_CAPACITY);
+
+            // Register this connection with the manager
+            let (connection_id, replay) = ws_manager.add_connection(tx, topics, since).await;
+            debug!("New WebSocket connection established (ID: {connection_id})");
+            for outgoing in replay {
+                if let Err(e) = stream.send(Message::text(&*outgoing.message)).await {
+                    ws_manager.remove_connection(connection_id).await;
+                    return Err(e);
+                }
+            }
 
-    Stream! { ws =>
-        // We don't have any use for the `WebSocket` instance right now.
-        let _ = ws;
+            // Run commands one at a time, without holding up event forwarding
+            let (command_tx, mut command_rx) =
+                tokio_mpsc::channel::<RpcRequest>(COMMAND_QUEUE_CAPACITY);
+            let command_emitter = emitter.clone();
+            tokio::spawn(async move {
+                while let Some(request) = command_rx.recv().await {
+                    let response = request.handle(&backend).await;
+                    if let Err(e) = command_emitter.reply(connection_id, &response) {
+                        error!("Failed to queue JSON-RPC response: {e:#}");
+                    }
+                }
+            });
+
+            // Event forwarding loop with graceful shutdown support
+            let result = loop {
+                tokio::select! {
+                    // Handle incoming backend messages
+                    msg = rx.recv() => match msg {
+                        Some(outgoing) => {
+                            if let Err(e) = stream.send(Message::text(&*outgoing.message)).await {
+                                break Err(e);
+                            }
+                        }
+                        // The manager dropped the connection, because the
+                        // client fell behind or the server is shutting down.
+                        // Either way the client should reconnect with `since`.
+                        None => {
+                            let close = CloseFrame {
+                                code: CloseCode::Again,
+                                reason: "Event stream fell behind".into(),
+                            };
+                            let _ = stream.send(Message::Close(Some(close))).await;
+                            break Ok(());
+                        }
+                    },
+                    // Handle subscription changes and commands from the client
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Text(text))) => match ClientMessage::parse(&text) {
+                            Ok(ClientMessage::Subscription(EventClientMessage::Subscribe { topics })) => {
+                                ws_manager.update_subscriptions(connection_id, topics, true).await;
+                            }
+                            Ok(ClientMessage::Subscription(EventClientMessage::Unsubscribe { topics })) => {
+                                ws_manager.update_subscriptions(connection_id, topics, false).await;
+                            }
+                            Ok(ClientMessage::Command(request)) => {
+                                if let Err(TrySendError::Full(request)) = command_tx.try_send(request) {
+                                    let response = RpcResponse::error(
+                                        request.id,
+                                        error_codes::INTERNAL_ERROR,
+                                        "Too many commands waiting to run".to_string(),
+                                    );
+                                    if let Err(e) = emitter.reply(connection_id, &response) {
+                                        error!("Failed to queue JSON-RPC response: {e:#}");
+                                    }
+                                }
+                            }
+                            Ok(ClientMessage::Rejected(response)) => {
+                                if let Err(e) = emitter.reply(connection_id, &response) {
+                                    error!("Failed to queue JSON-RPC response: {e:#}");
+                                }
+                            }
+                            Err(e) => {
+                                debug!("Invalid message on WebSocket connection {connection_id}: {e}");
+                            }
+                        },
+                        Some(Ok(Message::Close(_))) | None => break Ok(()),
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => break Err(e),
+                    },
+                    // Handle server shutdown
+                    _ = &mut shutdown => {
+                        debug!("WebSocket connection (ID: {connection_id}) received shutdown signal");
+                        break Ok(());
+                    }
+                }
+            };
+
+            // Clean up connection when the stream ends
+            ws_manager.remove_connection(connection_id).await;
+            debug!("WebSocket connection terminated (ID: {connection_id})");
+            result
+        })
+    })
+}
 
-        // Create a channel for this WebSocket connection to receive backend events
-        let (tx, mut rx) = tokio_mpsc::unbounded_channel::<String>();
+/// The `Last-Event-ID` header an `EventSource` sends when it reconnects.
+struct LastEventId(Option<u64>);
 
-        // Register this connection with the manager
-        let connection_id = ws_manager.add_connection(tx.clone()).await;
-        println!("📡 New WebSocket connection established (ID: {})", connection_id);
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = std::convert::Infallible;
 
-        // Event forwarding loop with graceful shutdown support
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let id = request
+            .headers()
+            .get_one("Last-Event-ID")
+            .and_then(|id| id.trim().parse().ok());
+        request::Outcome::Success(Self(id))
+    }
+}
+
+/// The event stream as Server-Sent Events, for clients that can't use
+/// WebSockets. Takes the same `since` and `topics` as `/ws` and sends the same
+/// messages, with each event's `sequence` as its SSE id; a reconnecting
+/// `EventSource` resumes from its `Last-Event-ID`, which takes precedence over
+/// `since`.
+#[get("/events?<since>&<topics>")]
+fn event_stream(
+    _auth: Authenticated,
+    since: Option<u64>,
+    topics: Option<&str>,
+    last_event_id: LastEventId,
+    state: UserState<'_>,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let ws_manager = state.ws_manager.clone();
+    let topics = topics.map_or_else(|| HashSet::from([Topic::Global]), Topic::parse_list);
+    let since = last_event_id.0.or(since);
+
+    EventStream! {
+        let (tx, mut rx) = tokio_mpsc::channel::<Outgoing>(CONNECTION_QUEUE_CAPACITY);
+        let (connection_id, replay) = ws_manager.add_connection(tx, topics, since).await;
+        debug!("New SSE connection established (ID: {connection_id})");
+        for outgoing in replay {
+            yield sse_event(outgoing);
+        }
+
+        // Ends when the manager drops a client that fell behind, and the
+        // `EventSource` reconnects from its `Last-Event-ID`
         loop {
             tokio::select! {
-                // Handle incoming backend messages
-                msg = rx.recv() => {
-                    match msg {
-                        Some(backend_msg) => yield Message::text(backend_msg),
-                        None => break, // Channel closed
+                outgoing = rx.recv() => match outgoing {
+                    Some(outgoing) => {
+                        yield sse_event(outgoing);
                     }
-                }
-                // Handle server shutdown
-                _ = &mut shutdown => {
-                    println!("📡 WebSocket connection (ID: {connection_id}) received shutdown signal");
-                    break;
-                }
+                    None => break,
+                },
+                _ = &mut shutdown => break,
             }
         }
 
-        // Clean up connection when the stream ends
-        ws_manager.remove_connection(&tx).await;
-        println!("📡 WebSocket connection terminated (ID: {})", connection_id);
+        // Not reached when the client goes away: that drops the stream, and
+        // its connection is removed on the next broadcast
+        ws_manager.remove_connection(connection_id).await;
+    }
+}
+
+/// An event stream message as an SSE event, with its sequence as the id.
+fn sse_event(outgoing: Outgoing) -> Event {
+    let event = Event::data(outgoing.message.to_string());
+    match outgoing.sequence {
+        Some(sequence) => event.id(sequence.to_string()),
+        None => event,
     }
 }
 
+/// Every `/api` route, as documented in [`openapi::openapi`].
+fn api_routes() -> Vec<Route> {
+    routes![
+        auth::login,
+        auth::logout,
+        auth::auth_status,
+        openapi::openapi_json,
+        openapi::docs,
+        websocket_handler,
+        event_stream,
+        check_cli_installed,
+        start_session,
+        send_message,
+        get_process_statuses,
+        list_all_sessions,
+        kill_process,
+        send_tool_call_confirmation_response,
+        execute_confirmed_command,
+        list_jobs,
+        cancel_job,
+        kill_job,
+        start_batch,
+        list_batches,
+        get_batch,
+        cancel_batch,
+        export_batch,
+        spawn_terminal,
+        list_terminals,
+        write_terminal,
+        resize_terminal,
+        terminal_scrollback,
+        kill_terminal,
+        terminal_websocket,
+        generate_conversation_title,
+        validate_directory,
+        is_home_directory,
+        get_home_directory,
+        get_parent_directory,
+        list_directory_contents,
+        list_files_recursive,
+        list_volumes,
+        get_git_info,
+        get_recent_chats,
+        search_chats,
+        list_projects,
+        list_enriched_projects,
+        get_enriched_project_http,
+        get_project_discussions,
+        get_project_history_settings,
+        set_project_history_settings,
+        get_detailed_conversation,
+        export_conversation_history,
+        delete_conversation,
+        delete_project,
+        vault_status,
+        create_vault,
+        unlock_vault,
+        lock_vault,
+        list_profiles,
+        create_profile,
+        update_profile,
+        delete_profile,
+        read_file_content,
+        read_binary_file_as_base64,
+        get_canonical_path,
+        read_file_content_with_options,
+        write_file_content,
+        preview_diff,
+        apply_diff,
+        revert_diff,
+    ]
+}
+
 #[rocket::launch]
 fn rocket() -> _ {
-    // Create WebSocket manager and backend with WebSockets event emitter
-    let ws_manager = WebSocketManager::new();
-    let emitter = WebSocketsEventEmitter::new(ws_manager.clone());
-    let backend = GeminiBackend::new(emitter);
-
-    // Store in app state
-    let app_state = AppState {
-        backend: Arc::new(Mutex::new(backend)),
-        ws_manager,
+    let config = ServerConfig::load().unwrap_or_else(|e| {
+        eprintln!("Error: {e:#}");
+        std::process::exit(2);
+    });
+    // Before anything looks up the data directory, logging included
+    if let Some(dir) = &config.data_dir {
+        backend::paths::set_data_dir(dir.clone());
+    }
+
+    // Keep the guard in managed state so buffered file logs are flushed on shutdown
+    let mut log_config = LogConfig {
+        // The config already weighed the environment against the flags
+        filter_from_env: false,
+        ..LogConfig::default()
     };
+    if let Some(filter) = &config.log_level {
+        log_config.filter = filter.clone();
+    }
+    let log_guard = logging::init(log_config)
+        .inspect_err(|e| eprintln!("Failed to initialize logging: {e:#}"))
+        .ok();
+
+    // A backend and event stream for each user, or one for everybody
+    let mut status_monitors = Vec::new();
+    let mut replay = None;
+    let users = if config.users.is_empty() {
+        let (state, status_monitor) = AppState::new(&config, None);
+        status_monitors.push(status_monitor);
+        replay = Replay::from_env()
+            .inspect_err(|e| error!("Failed to load event recording: {e:#}"))
+            .ok()
+            .flatten()
+            .map(|replay| (replay, state.ws_manager.clone(), state.emitter.clone()));
+        Users::single(state)
+    } else {
+        Users::shared(config.users.iter().map(|user| {
+            let (state, status_monitor) = AppState::new(&config, Some(user));
+            status_monitors.push(status_monitor);
+            (user.name.clone(), state)
+        }))
+    };
+    let auth = if config.users.is_empty() {
+        Auth::from_env(&config.local_url())
+    } else {
+        if std::env::var_os(auth::PASSWORD_ENV).is_some() {
+            info!("Ignoring {} since users are configured", auth::PASSWORD_ENV);
+        }
+        Auth::for_users(&config.users)
+    };
+    let auth = match auth {
+        Ok(auth) => auth.with_secure_cookies(config.tls.is_some()),
+        Err(e) => {
+            eprintln!("Failed to set up authentication: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    let origin_policy =
+        OriginPolicy::new(config.allowed_origins.clone(), config.allowed_hosts.clone());
+
+    rocket::custom(config.figment())
+        .manage(users)
+        .manage(config)
+        .manage(auth)
+        .manage(log_guard)
+        .attach(AdHoc::on_liftoff("Process status monitor", |_| {
+            Box::pin(async move {
+                // A replay stands in for live sessions, so their statuses would
+                // only get in its way
+                let Some((replay, replay_manager, replay_emitter)) = replay else {
+                    for status_monitor in status_monitors {
+                        tokio::spawn(status_monitor.run());
+                    }
+                    return;
+                };
+                tokio::spawn(async move {
+                    // Nobody would see events played before the UI connects
+                    replay_manager.wait_for_client().await;
+                    info!("Replaying {} recorded events", replay.len());
+                    if let Err(e) = replay.play(&replay_emitter).await {
+                        error!("Event replay failed: {e:#}");
+                    }
+                });
+            })
+        }))
+        .attach(origin_policy)
+        .mount("/", routes![index, auth::login_page])
+        .register("/api", catchers![auth::unauthorized, origin::forbidden])
+        .mount("/api", api_routes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    rocket::custom(
-        rocket::Config::figment()
-            .merge(("port", 1858))
-            .merge(("address", "0.0.0.0")),
-    )
-    .manage(app_state)
-    .mount("/", routes![index])
-    .mount(
-        "/api",
-        routes![
-            websocket_handler,
-            check_cli_installed,
-            start_session,
-            send_message,
-            get_process_statuses,
-            kill_process,
-            send_tool_call_confirmation_response,
-            execute_confirmed_command,
-            generate_conversation_title,
-            validate_directory,
-            is_home_directory,
-            get_home_directory,
-            get_parent_directory,
-            list_directory_contents,
-            list_files_recursive,
-            list_volumes,
-            get_git_info,
-            get_recent_chats,
-            search_chats,
-            list_projects,
-            list_enriched_projects,
-            get_enriched_project_http,
-            get_project_discussions,
-            get_detailed_conversation,
-            export_conversation_history,
-            delete_conversation,
-            delete_project,
-            read_file_content,
-            read_binary_file_as_base64,
-            get_canonical_path,
-            read_file_content_with_options,
-            write_file_content,
-        ],
-    )
+    fn event(sequence: u64) -> BufferedEvent {
+        BufferedEvent {
+            sequence,
+            family: "gemini-output",
+            session_id: Some("session".to_string()),
+            message: format!("{{\"sequence\":{sequence}}}").into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_client_does_not_hold_up_events() {
+        let manager = WebSocketManager::new();
+        let (tx, mut rx) = tokio_mpsc::channel(CONNECTION_QUEUE_CAPACITY);
+        manager
+            .add_connection(tx, HashSet::from([Topic::Global]), None)
+            .await;
+
+        // The client never reads, yet every event goes out right away
+        let total = 2 * CONNECTION_QUEUE_CAPACITY as u64;
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            for sequence in 1..=total {
+                manager.broadcast(event(sequence)).await.unwrap();
+            }
+        })
+        .await
+        .expect("a stalled client blocked the broadcast");
+        assert_eq!(manager.connection_count().await, 0);
+
+        // It only ever held a queue's worth, then its stream ends
+        let mut received = Vec::new();
+        while let Some(outgoing) = rx.recv().await {
+            received.extend(outgoing.sequence);
+        }
+        let queued = CONNECTION_QUEUE_CAPACITY as u64;
+        assert_eq!(received, (1..=queued).collect::<Vec<_>>());
+
+        // Reconnecting from there replays the rest
+        let (tx, _rx) = tokio_mpsc::channel(CONNECTION_QUEUE_CAPACITY);
+        let (_, replay) = manager
+            .add_connection(tx, HashSet::from([Topic::Global]), Some(queued))
+            .await;
+        let replayed: Vec<_> = replay.iter().map(|outgoing| outgoing.sequence).collect();
+        assert_eq!(replayed, (queued + 1..=total).map(Some).collect::<Vec<_>>());
+    }
 }
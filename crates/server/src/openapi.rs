@@ -0,0 +1,817 @@
+//! OpenAPI document for the `/api` routes.
+//!
+//! Rocket can't describe its routes, so each one is listed here with the
+//! types its handler takes and returns, and their schemas come from the
+//! types' `JsonSchema` derives. The document is served at
+//! `/api/openapi.json` and browsable at `/api/docs`.
+//!
+//! A copy is checked in as `openapi.json`; the snapshot test below fails when
+//! it is out of date or when a mounted route is missing from the document,
+//! and `UPDATE_OPENAPI=1 cargo test -p server openapi` rewrites it.
+
+use crate::auth::{AuthStatus, LoginRequest};
+use crate::origin::TrustedOrigin;
+use crate::{
+    CanonicalPathRequest, DeleteProjectRequest, DiffRequest, ExecuteCommandRequest,
+    ExportConversationRequest, GenerateTitleRequest, GetGitInfoRequest, GetParentDirectoryRequest,
+    IsHomeDirectoryRequest, KillProcessRequest, ListDirectoryRequest, ListFilesRecursiveRequest,
+    ReadBinaryFileAsBase64Request, ReadFileContentRequest, ReadFileContentWithOptionsRequest,
+    SearchChatsRequest, SendMessageRequest, SpawnTerminalRequest, StartSessionRequest,
+    TerminalInputRequest, TerminalResizeRequest, ToolConfirmationRequest, UserProcessStatus,
+    ValidateDirectoryRequest, VaultPassphraseRequest, WriteFileContentRequest,
+};
+use backend::projects::ProjectsResponse;
+use backend::{
+    BatchReport, BatchRequest, DetailedConversation, DiffApplyResult, DiffPreview, DirEntry,
+    EnrichedProject, FileContent, GitInfo, JobInfo, ProcessStatus, ProfileInput,
+    ProjectHistorySettings, ProviderProfile, RecentChat, SearchResult, TerminalInfo, VaultStatus,
+};
+use rocket::get;
+use rocket::http::{ContentType, Method};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, SchemaGenerator};
+use serde_json::{Map, Value, json};
+use std::sync::OnceLock;
+
+static DOCS_PAGE: &str = include_str!("docs.html");
+
+const API_DESCRIPTION: &str = "\
+The API behind the web version of Gemini CLI Desktop. Scripts authenticate with \
+`Authorization: Bearer <secret>`. Browsers log in at `/api/auth/login` and then send the \
+session cookie, and on requests that change something also the CSRF token from the \
+`gcd_csrf` cookie in `X-CSRF-Token`.";
+
+const LOGIN_DESCRIPTION: &str = "\
+`username` is required on a server with users. Wrong credentials get a 401 after a delay.";
+
+const WEBSOCKET_DESCRIPTION: &str = "\
+Sends the events the connection is subscribed to. A reconnecting client passes the last \
+`sequence` it saw as `since` to get the events it missed first; a client that falls too far \
+behind is closed with code 1013 to do so. `topics` is a comma-separated list such as \
+`session:abc,family:process-status-changed`; without it every event is sent.
+
+Clients can change their subscriptions by sending `{\"type\":\"subscribe\",\"topics\":[...]}` \
+or `{\"type\":\"unsubscribe\",\"topics\":[...]}`, and send JSON-RPC 2.0 commands \
+(`start_session`, `send_message`, `tool_confirmation`, `cancel_prompt`, `kill_process`).";
+
+const EVENT_STREAM_DESCRIPTION: &str = "\
+Takes the same `since` and `topics` as `/ws` and sends the same messages, with each event's \
+`sequence` as its id. A `Last-Event-ID` header takes precedence over `since`. The stream of a \
+client that falls too far behind ends, and it resumes from there.";
+
+const TERMINAL_WEBSOCKET_DESCRIPTION: &str = "\
+The client first receives the scrollback as an `output` message, then live `output` and \
+`exit` messages, and sends `input` and `resize` messages back.";
+
+/// The document, built once.
+fn openapi_string() -> &'static str {
+    static DOCUMENT: OnceLock<String> = OnceLock::new();
+    DOCUMENT.get_or_init(|| serde_json::to_string_pretty(&openapi()).unwrap_or_default() + "\n")
+}
+
+#[get("/openapi.json")]
+pub fn openapi_json(_origin: TrustedOrigin) -> (ContentType, &'static str) {
+    (ContentType::JSON, openapi_string())
+}
+
+/// A page that renders the document, for browsing the API.
+#[get("/docs")]
+pub fn docs(_origin: TrustedOrigin) -> (ContentType, &'static str) {
+    (ContentType::HTML, DOCS_PAGE)
+}
+
+/// OpenAPI 3.1 document describing every route in [`crate::api_routes`].
+pub fn openapi() -> Value {
+    let mut spec = Spec::new();
+
+    spec.section("Auth");
+    spec.post("login", "/auth/login", "Log in and get a session cookie")
+        .describe(LOGIN_DESCRIPTION)
+        .public()
+        .body::<LoginRequest>()
+        .no_content();
+    spec.post("logout", "/auth/logout", "End the browser session")
+        .public()
+        .no_content();
+    spec.get(
+        "auth_status",
+        "/auth/status",
+        "How to log in, and whether the request is logged in",
+    )
+    .public()
+    .json::<AuthStatus>();
+    spec.get("openapi_json", "/openapi.json", "This document")
+        .public()
+        .json::<Value>();
+    spec.get("docs", "/docs", "A page for browsing this document")
+        .public()
+        .html();
+
+    spec.section("Events");
+    spec.get(
+        "websocket_handler",
+        "/ws",
+        "Live event stream over a WebSocket",
+    )
+    .describe(WEBSOCKET_DESCRIPTION)
+    .query::<u64>("since")
+    .query::<String>("topics")
+    .websocket();
+    spec.get(
+        "event_stream",
+        "/events",
+        "Live event stream as Server-Sent Events",
+    )
+    .describe(EVENT_STREAM_DESCRIPTION)
+    .query::<u64>("since")
+    .query::<String>("topics")
+    .event_stream();
+
+    spec.section("Sessions");
+    spec.get(
+        "check_cli_installed",
+        "/check-cli-installed",
+        "Whether the CLI is installed",
+    )
+    .json::<bool>();
+    spec.post("start_session", "/start-session", "Start a session")
+        .body::<StartSessionRequest>()
+        .empty();
+    spec.post(
+        "send_message",
+        "/send-message",
+        "Send a message to a session, starting it if needed",
+    )
+    .body::<SendMessageRequest>()
+    .empty();
+    spec.get(
+        "get_process_statuses",
+        "/process-statuses",
+        "Statuses of the session processes",
+    )
+    .json::<Vec<ProcessStatus>>();
+    spec.get(
+        "list_all_sessions",
+        "/admin/sessions",
+        "Every user's sessions",
+    )
+    .describe("Admins only.")
+    .json::<Vec<UserProcessStatus>>();
+    spec.post("kill_process", "/kill-process", "Kill a session's process")
+        .body::<KillProcessRequest>()
+        .empty();
+    spec.post(
+        "send_tool_call_confirmation_response",
+        "/tool-confirmation",
+        "Answer a tool call confirmation request",
+    )
+    .body::<ToolConfirmationRequest>()
+    .empty();
+    spec.post(
+        "generate_conversation_title",
+        "/generate-title",
+        "Generate a title for a conversation",
+    )
+    .body::<GenerateTitleRequest>()
+    .json::<String>();
+
+    spec.section("Jobs");
+    spec.post(
+        "execute_confirmed_command",
+        "/execute-command",
+        "Run a confirmed command as a job",
+    )
+    .body::<ExecuteCommandRequest>()
+    .json::<JobInfo>();
+    spec.get("list_jobs", "/jobs", "List jobs")
+        .json::<Vec<JobInfo>>();
+    spec.post("cancel_job", "/jobs/<job_id>/cancel", "Ask a job to stop")
+        .empty();
+    spec.post("kill_job", "/jobs/<job_id>/kill", "Kill a job")
+        .empty();
+
+    spec.section("Batches");
+    spec.post("start_batch", "/batches", "Start a batch of prompts")
+        .body::<BatchRequest>()
+        .json::<BatchReport>();
+    spec.get("list_batches", "/batches", "List batches")
+        .json::<Vec<BatchReport>>();
+    spec.get("get_batch", "/batches/<batch_id>", "Get a batch")
+        .json::<BatchReport>();
+    spec.post(
+        "cancel_batch",
+        "/batches/<batch_id>/cancel",
+        "Cancel a batch",
+    )
+    .empty();
+    spec.get(
+        "export_batch",
+        "/batches/<batch_id>/export",
+        "Export a batch's results",
+    )
+    .describe("`format` is `markdown` (the default) or `json`.")
+    .query::<String>("format")
+    .text();
+
+    spec.section("Terminals");
+    spec.post("spawn_terminal", "/terminals", "Open a terminal")
+        .body::<SpawnTerminalRequest>()
+        .json::<TerminalInfo>();
+    spec.get(
+        "list_terminals",
+        "/terminals",
+        "List terminals, optionally of one project",
+    )
+    .query::<String>("project_id")
+    .json::<Vec<TerminalInfo>>();
+    spec.post(
+        "write_terminal",
+        "/terminals/<terminal_id>/input",
+        "Write input to a terminal",
+    )
+    .body::<TerminalInputRequest>()
+    .empty();
+    spec.post(
+        "resize_terminal",
+        "/terminals/<terminal_id>/resize",
+        "Resize a terminal",
+    )
+    .body::<TerminalResizeRequest>()
+    .empty();
+    spec.get(
+        "terminal_scrollback",
+        "/terminals/<terminal_id>/scrollback",
+        "A terminal's scrollback",
+    )
+    .json::<String>();
+    spec.delete(
+        "kill_terminal",
+        "/terminals/<terminal_id>",
+        "Close a terminal",
+    )
+    .empty();
+    spec.get(
+        "terminal_websocket",
+        "/terminals/<terminal_id>/ws",
+        "Attach to a terminal over a WebSocket",
+    )
+    .describe(TERMINAL_WEBSOCKET_DESCRIPTION)
+    .websocket();
+
+    spec.section("Files");
+    spec.post(
+        "validate_directory",
+        "/validate-directory",
+        "Whether a path is a directory",
+    )
+    .body::<ValidateDirectoryRequest>()
+    .json::<bool>();
+    spec.post(
+        "is_home_directory",
+        "/is-home-directory",
+        "Whether a path is the home directory",
+    )
+    .body::<IsHomeDirectoryRequest>()
+    .json::<bool>();
+    spec.get(
+        "get_home_directory",
+        "/get-home-directory",
+        "The home directory",
+    )
+    .json::<String>();
+    spec.post(
+        "get_parent_directory",
+        "/get-parent-directory",
+        "The parent of a directory",
+    )
+    .body::<GetParentDirectoryRequest>()
+    .json::<Option<String>>();
+    spec.post(
+        "list_directory_contents",
+        "/list-directory",
+        "List a directory",
+    )
+    .body::<ListDirectoryRequest>()
+    .json::<Vec<DirEntry>>();
+    spec.post(
+        "list_files_recursive",
+        "/list-files-recursive",
+        "List the files under a directory",
+    )
+    .body::<ListFilesRecursiveRequest>()
+    .json::<Vec<DirEntry>>();
+    spec.get("list_volumes", "/list-volumes", "List volumes or drives")
+        .json::<Vec<DirEntry>>();
+    spec.post("get_git_info", "/get-git-info", "Git status of a directory")
+        .body::<GetGitInfoRequest>()
+        .json::<Option<GitInfo>>();
+    spec.post("read_file_content", "/read-file-content", "Read a file")
+        .body::<ReadFileContentRequest>()
+        .json::<FileContent>();
+    spec.post(
+        "read_binary_file_as_base64",
+        "/read-binary-file-as-base64",
+        "Read a file as base64",
+    )
+    .body::<ReadBinaryFileAsBase64Request>()
+    .json::<String>();
+    spec.post(
+        "get_canonical_path",
+        "/get-canonical-path",
+        "Canonicalize a path",
+    )
+    .body::<CanonicalPathRequest>()
+    .json::<String>();
+    spec.post(
+        "read_file_content_with_options",
+        "/read-file-content-with-options",
+        "Read a file, optionally as text whatever its type",
+    )
+    .body::<ReadFileContentWithOptionsRequest>()
+    .json::<FileContent>();
+    spec.post("write_file_content", "/write-file-content", "Write a file")
+        .body::<WriteFileContentRequest>()
+        .json::<FileContent>();
+    spec.post(
+        "preview_diff",
+        "/preview-diff",
+        "Preview replacing text in a file",
+    )
+    .body::<DiffRequest>()
+    .json::<DiffPreview>();
+    spec.post("apply_diff", "/apply-diff", "Replace text in a file")
+        .body::<DiffRequest>()
+        .json::<DiffApplyResult>();
+    spec.post(
+        "revert_diff",
+        "/revert-diff",
+        "Undo a replacement made by apply-diff",
+    )
+    .body::<DiffRequest>()
+    .json::<DiffApplyResult>();
+
+    spec.section("Projects");
+    spec.get("list_projects", "/projects", "List projects")
+        .query::<u32>("limit")
+        .query::<u32>("offset")
+        .json::<ProjectsResponse>();
+    spec.get(
+        "list_enriched_projects",
+        "/projects-enriched",
+        "List projects with their metadata",
+    )
+    .json::<Vec<EnrichedProject>>();
+    spec.get(
+        "get_enriched_project_http",
+        "/project",
+        "Get a project with its metadata",
+    )
+    .required_query::<String>("sha256")
+    .required_query::<String>("external_root_path")
+    .json::<EnrichedProject>();
+    spec.get(
+        "get_project_discussions",
+        "/projects/<project_id>/discussions",
+        "A project's conversations",
+    )
+    .json::<Vec<RecentChat>>();
+    spec.get(
+        "get_project_history_settings",
+        "/projects/<project_id>/history-settings",
+        "A project's history settings",
+    )
+    .json::<ProjectHistorySettings>();
+    spec.post(
+        "set_project_history_settings",
+        "/projects/<project_id>/history-settings",
+        "Change a project's history settings",
+    )
+    .body::<ProjectHistorySettings>()
+    .empty();
+    spec.post("delete_project", "/delete-project", "Forget a project")
+        .body::<DeleteProjectRequest>()
+        .empty();
+
+    spec.section("Conversations");
+    spec.get("get_recent_chats", "/recent-chats", "Recent conversations")
+        .json::<Vec<RecentChat>>();
+    spec.post("search_chats", "/search-chats", "Search conversations")
+        .body::<SearchChatsRequest>()
+        .json::<Vec<SearchResult>>();
+    spec.get(
+        "get_detailed_conversation",
+        "/conversations/<chat_id>",
+        "Get a conversation",
+    )
+    .json::<DetailedConversation>();
+    spec.post(
+        "export_conversation_history",
+        "/conversations/<chat_id>/export",
+        "Export a conversation",
+    )
+    .describe("`format` is `markdown` or `json`.")
+    .body::<ExportConversationRequest>()
+    .text();
+    spec.delete(
+        "delete_conversation",
+        "/conversations/<chat_id>",
+        "Delete a conversation",
+    )
+    .empty();
+
+    spec.section("Vault");
+    spec.get(
+        "vault_status",
+        "/vault/status",
+        "Whether the vault exists and is unlocked",
+    )
+    .json::<VaultStatus>();
+    spec.post("create_vault", "/vault/create", "Create the vault")
+        .body::<VaultPassphraseRequest>()
+        .empty();
+    spec.post("unlock_vault", "/vault/unlock", "Unlock the vault")
+        .body::<VaultPassphraseRequest>()
+        .empty();
+    spec.post("lock_vault", "/vault/lock", "Lock the vault")
+        .empty();
+    spec.get(
+        "list_profiles",
+        "/vault/profiles",
+        "List provider profiles, with their keys masked",
+    )
+    .json::<Vec<ProviderProfile>>();
+    spec.post(
+        "create_profile",
+        "/vault/profiles",
+        "Create a provider profile",
+    )
+    .body::<ProfileInput>()
+    .json::<ProviderProfile>();
+    spec.put(
+        "update_profile",
+        "/vault/profiles/<profile_id>",
+        "Change a provider profile",
+    )
+    .body::<ProfileInput>()
+    .json::<ProviderProfile>();
+    spec.delete(
+        "delete_profile",
+        "/vault/profiles/<profile_id>",
+        "Delete a provider profile",
+    )
+    .empty();
+
+    spec.finish()
+}
+
+/// The document being built: the operations so far, and the generator that
+/// collects the schemas they refer to.
+struct Spec {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+    tag: &'static str,
+}
+
+impl Spec {
+    fn new() -> Self {
+        let generator = SchemaSettings::draft2020_12()
+            .with(|settings| {
+                settings.definitions_path = "/components/schemas".into();
+                settings.meta_schema = None;
+            })
+            .into_generator();
+        Self {
+            generator,
+            paths: Map::new(),
+            tag: "",
+        }
+    }
+
+    /// Tag the operations added from now on.
+    fn section(&mut self, tag: &'static str) {
+        self.tag = tag;
+    }
+
+    fn get(&mut self, id: &str, path: &str, summary: &str) -> Operation<'_> {
+        Operation::new(self, Method::Get, id, path, summary)
+    }
+
+    fn post(&mut self, id: &str, path: &str, summary: &str) -> Operation<'_> {
+        Operation::new(self, Method::Post, id, path, summary)
+    }
+
+    fn put(&mut self, id: &str, path: &str, summary: &str) -> Operation<'_> {
+        Operation::new(self, Method::Put, id, path, summary)
+    }
+
+    fn delete(&mut self, id: &str, path: &str, summary: &str) -> Operation<'_> {
+        Operation::new(self, Method::Delete, id, path, summary)
+    }
+
+    fn finish(mut self) -> Value {
+        let error = json!({
+            "type": "object",
+            "properties": { "error": { "type": "string" } },
+            "required": ["error"],
+        });
+        let mut schemas = self.generator.take_definitions(true);
+        schemas.insert("Error".to_string(), error);
+        let forbidden = "Refused by the Host, Origin, CSRF, admin or path checks";
+        json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "Gemini CLI Desktop web API",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": API_DESCRIPTION,
+            },
+            "servers": [{ "url": "/api" }],
+            "security": [{ "bearer": [] }, { "session": [] }],
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "securitySchemes": {
+                    "bearer": {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "description": "The access token or password, unless the server has users.",
+                    },
+                    "session": {
+                        "type": "apiKey",
+                        "in": "cookie",
+                        "name": "gcd_session",
+                        "description": "Set by `/auth/login`.",
+                    },
+                },
+                "responses": {
+                    "Unauthorized": error_response("Not logged in"),
+                    "Forbidden": error_response(forbidden),
+                    "Failed": {
+                        "description": "Failed; the body is an Error, sent as plain text",
+                        "content": { "text/plain": { "schema": { "type": "string" } } },
+                    },
+                },
+            },
+        })
+    }
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": { "schema": { "$ref": "#/components/schemas/Error" } },
+        },
+    })
+}
+
+/// One route being described. Its path parameters are taken from the
+/// `<name>` segments of its Rocket path.
+struct Operation<'a> {
+    spec: &'a mut Spec,
+    method: Method,
+    path: String,
+    operation: Map<String, Value>,
+    parameters: Vec<Value>,
+    public: bool,
+}
+
+impl<'a> Operation<'a> {
+    fn new(spec: &'a mut Spec, method: Method, id: &str, path: &str, summary: &str) -> Self {
+        let mut parameters = Vec::new();
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match segment.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+                Some(name) => {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    }));
+                    segments.push(format!("{{{name}}}"));
+                }
+                None => segments.push(segment.to_string()),
+            }
+        }
+        let mut operation = Map::new();
+        operation.insert("operationId".to_string(), id.into());
+        operation.insert("summary".to_string(), summary.into());
+        operation.insert("tags".to_string(), json!([spec.tag]));
+        Self {
+            spec,
+            method,
+            path: segments.join("/"),
+            operation,
+            parameters,
+            public: false,
+        }
+    }
+
+    fn describe(mut self, description: &str) -> Self {
+        self.operation
+            .insert("description".to_string(), description.into());
+        self
+    }
+
+    /// Doesn't need a login.
+    fn public(mut self) -> Self {
+        self.public = true;
+        self.operation.insert("security".to_string(), json!([]));
+        self
+    }
+
+    fn query<T: JsonSchema>(self, name: &str) -> Self {
+        self.query_parameter::<T>(name, false)
+    }
+
+    fn required_query<T: JsonSchema>(self, name: &str) -> Self {
+        self.query_parameter::<T>(name, true)
+    }
+
+    fn query_parameter<T: JsonSchema>(mut self, name: &str, required: bool) -> Self {
+        let schema = self.spec.generator.subschema_for::<T>();
+        self.parameters.push(json!({
+            "name": name,
+            "in": "query",
+            "required": required,
+            "schema": schema,
+        }));
+        self
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        let schema = self.spec.generator.subschema_for::<T>();
+        self.operation.insert(
+            "requestBody".to_string(),
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } },
+            }),
+        );
+        self
+    }
+
+    fn json<T: JsonSchema>(self) {
+        let schema = self.spec.generator.subschema_for::<T>();
+        self.respond(
+            "200",
+            json!({
+                "description": "OK",
+                "content": { "application/json": { "schema": schema } },
+            }),
+        );
+    }
+
+    fn text(self) {
+        self.respond(
+            "200",
+            json!({
+                "description": "OK",
+                "content": { "text/plain": { "schema": { "type": "string" } } },
+            }),
+        );
+    }
+
+    fn html(self) {
+        self.respond(
+            "200",
+            json!({
+                "description": "OK",
+                "content": { "text/html": { "schema": { "type": "string" } } },
+            }),
+        );
+    }
+
+    fn event_stream(self) {
+        self.respond(
+            "200",
+            json!({
+                "description": "A stream of events, each a JSON message",
+                "content": { "text/event-stream": { "schema": { "type": "string" } } },
+            }),
+        );
+    }
+
+    fn websocket(self) {
+        self.respond(
+            "101",
+            json!({ "description": "Switched to a WebSocket carrying JSON messages" }),
+        );
+    }
+
+    /// Succeeds with an empty body.
+    fn empty(self) {
+        self.respond("200", json!({ "description": "Done" }));
+    }
+
+    fn no_content(self) {
+        self.respond("204", json!({ "description": "Done" }));
+    }
+
+    fn respond(mut self, status: &str, success: Value) {
+        let mut responses = Map::new();
+        responses.insert(status.to_string(), success);
+        if !self.public {
+            responses.insert(
+                "401".to_string(),
+                json!({ "$ref": "#/components/responses/Unauthorized" }),
+            );
+        }
+        responses.insert(
+            "403".to_string(),
+            json!({ "$ref": "#/components/responses/Forbidden" }),
+        );
+        if !self.public {
+            responses.insert(
+                "500".to_string(),
+                json!({ "$ref": "#/components/responses/Failed" }),
+            );
+        }
+        if !self.parameters.is_empty() {
+            self.operation
+                .insert("parameters".to_string(), self.parameters.into());
+        }
+        self.operation
+            .insert("responses".to_string(), responses.into());
+
+        let method = self.method.as_str().to_ascii_lowercase();
+        let path = self
+            .spec
+            .paths
+            .entry(self.path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(path) = path {
+            path.insert(method, self.operation.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_routes;
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_openapi_is_up_to_date() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+        let generated = openapi_string();
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(&path, generated).unwrap();
+            return;
+        }
+
+        let committed = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "{} is out of date with the API; rerun with UPDATE_OPENAPI=1 to regenerate it",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_openapi_documents_every_route() {
+        let mounted: BTreeSet<(String, String, String)> = api_routes()
+            .iter()
+            .map(|route| {
+                let path = route.uri.path().replace('<', "{").replace('>', "}");
+                (
+                    route.method.as_str().to_ascii_lowercase(),
+                    path,
+                    route.name.as_deref().unwrap_or_default().to_string(),
+                )
+            })
+            .collect();
+
+        let document = openapi();
+        let mut documented = BTreeSet::new();
+        for (path, operations) in document["paths"].as_object().unwrap() {
+            for (method, operation) in operations.as_object().unwrap() {
+                let id = operation["operationId"].as_str().unwrap();
+                documented.insert((method.clone(), path.clone(), id.to_string()));
+            }
+        }
+
+        let undocumented: Vec<_> = mounted.difference(&documented).collect();
+        let unmounted: Vec<_> = documented.difference(&mounted).collect();
+        assert!(
+            undocumented.is_empty() && unmounted.is_empty(),
+            "undocumented routes: {undocumented:?}; documented but not mounted: {unmounted:?}"
+        );
+    }
+
+    #[test]
+    fn test_every_reference_resolves() {
+        let document = openapi();
+        let text = document.to_string();
+        for reference in text.split("\"$ref\":\"").skip(1) {
+            let pointer = reference.split('"').next().unwrap();
+            let pointer = pointer.strip_prefix('#').unwrap();
+            assert!(
+                document.pointer(pointer).is_some(),
+                "{pointer} doesn't resolve"
+            );
+        }
+    }
+}
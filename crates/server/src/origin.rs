@@ -0,0 +1,214 @@
+//! Origin and Host checks for the `/api` routes.
+//!
+//! Without them any page open in the user's browser could drive sessions
+//! through a server on localhost:
+//!
+//! - The `Host` header must be localhost, an IP address or an allowed host
+//!   name, so a DNS rebinding page can't pass for this server.
+//! - State-changing requests and WebSocket upgrades that carry an `Origin`
+//!   must come from this server or an allowed origin. Browsers always send
+//!   one there; scripts without one are left to authentication.
+//! - Allowed origins get CORS headers and preflight answers; preflights from
+//!   anywhere else are refused.
+//!
+//! The fairing only judges requests; [`TrustedOrigin`] turns the verdict
+//! into a 403 before any handler runs.
+
+use crate::auth::NotAdmin;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Method, Status};
+use rocket::request::{self, FromRequest};
+use rocket::{Data, Request, Response, catch};
+use std::io::Cursor;
+use std::net::IpAddr;
+
+const API_PREFIX: &str = "/api";
+const ALLOWED_METHODS: &str = "GET, POST, PUT, DELETE";
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, Last-Event-ID, X-CSRF-Token";
+/// How long browsers may cache a preflight answer, in seconds.
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Trusted,
+    HostNotAllowed,
+    OriginNotAllowed,
+}
+
+pub struct OriginPolicy {
+    /// Normalized `scheme://host[:port]` origins.
+    allowed_origins: Vec<String>,
+    /// Lowercase host names, without ports.
+    allowed_hosts: Vec<String>,
+}
+
+impl OriginPolicy {
+    pub fn new(allowed_origins: Vec<String>, allowed_hosts: Vec<String>) -> Self {
+        // An origin allowed to call the API may also be the host it calls
+        let origin_hosts = allowed_origins
+            .iter()
+            .filter_map(|origin| origin.split_once("://"))
+            .map(|(_, authority)| host_name(authority).to_string());
+        let allowed_hosts = allowed_hosts
+            .iter()
+            .map(|host| host.to_ascii_lowercase())
+            .chain(origin_hosts)
+            .collect();
+        Self {
+            allowed_origins,
+            allowed_hosts,
+        }
+    }
+
+    fn verdict(&self, request: &Request<'_>) -> Verdict {
+        if !is_api(request) {
+            return Verdict::Trusted;
+        }
+        let host = request.headers().get_one("Host");
+        if let Some(host) = host
+            && !self.host_allowed(host)
+        {
+            return Verdict::HostNotAllowed;
+        }
+        if !changes_state(request) {
+            return Verdict::Trusted;
+        }
+        match request.headers().get_one("Origin") {
+            Some(origin) if !self.origin_allowed(origin, host) => Verdict::OriginNotAllowed,
+            _ => Verdict::Trusted,
+        }
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let name = host_name(host).to_ascii_lowercase();
+        name == "localhost"
+            || name.ends_with(".localhost")
+            || name.parse::<IpAddr>().is_ok()
+            || self.allowed_hosts.contains(&name)
+    }
+
+    fn origin_allowed(&self, origin: &str, host: Option<&str>) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        let same_origin = host.is_some_and(|host| {
+            origin
+                .split_once("://")
+                .is_some_and(|(_, authority)| authority == host.to_ascii_lowercase())
+        });
+        same_origin || self.is_cross_origin_allowed(&origin)
+    }
+
+    fn is_cross_origin_allowed(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for OriginPolicy {
+    fn info(&self) -> Info {
+        Info {
+            name: "Origin policy",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let verdict = self.verdict(request);
+        request.local_cache(|| verdict);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if !is_api(request) {
+            return;
+        }
+        let Some(origin) = request.headers().get_one("Origin") else {
+            return;
+        };
+        let origin = origin.to_ascii_lowercase();
+        let allowed = self.is_cross_origin_allowed(&origin)
+            && *request.local_cache(|| Verdict::Trusted) == Verdict::Trusted;
+        response.adjoin_raw_header("Vary", "Origin");
+
+        // No route handles OPTIONS, so this answers preflights in place of
+        // Rocket's 404
+        let preflight = request.method() == Method::Options
+            && request.headers().contains("Access-Control-Request-Method");
+        if preflight {
+            response.set_status(if allowed {
+                Status::NoContent
+            } else {
+                Status::Forbidden
+            });
+            response.set_sized_body(0, Cursor::new(""));
+            response.remove_header("Content-Type");
+            if allowed {
+                response.set_raw_header("Access-Control-Allow-Methods", ALLOWED_METHODS);
+                response.set_raw_header("Access-Control-Allow-Headers", ALLOWED_HEADERS);
+                response.set_raw_header("Access-Control-Max-Age", PREFLIGHT_MAX_AGE);
+            }
+        }
+        if allowed {
+            response.set_raw_header("Access-Control-Allow-Origin", origin);
+            response.set_raw_header("Access-Control-Allow-Credentials", "true");
+        }
+    }
+}
+
+/// Request guard that refuses requests the [`OriginPolicy`] didn't trust.
+pub struct TrustedOrigin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TrustedOrigin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match request.local_cache(|| Verdict::Trusted) {
+            Verdict::Trusted => request::Outcome::Success(Self),
+            _ => request::Outcome::Error((Status::Forbidden, ())),
+        }
+    }
+}
+
+#[catch(403)]
+pub fn forbidden(request: &Request<'_>) -> (Status, (ContentType, String)) {
+    let error = match request.local_cache(|| Verdict::Trusted) {
+        Verdict::HostNotAllowed => "Host not allowed",
+        Verdict::OriginNotAllowed => "Origin not allowed",
+        Verdict::Trusted if request.local_cache(|| NotAdmin(false)).0 => "Admins only",
+        // The only other guard refusing requests is the CSRF check
+        Verdict::Trusted => "Missing or invalid CSRF token",
+    };
+    (
+        Status::Forbidden,
+        (
+            ContentType::JSON,
+            serde_json::json!({ "error": error }).to_string(),
+        ),
+    )
+}
+
+fn is_api(request: &Request<'_>) -> bool {
+    let path = request.uri().path();
+    path == API_PREFIX || path.starts_with("/api/")
+}
+
+/// Requests a cross-site page could use to change something: anything but a
+/// plain read, and WebSocket upgrades, which carry commands.
+fn changes_state(request: &Request<'_>) -> bool {
+    !matches!(
+        request.method(),
+        Method::Get | Method::Head | Method::Options
+    ) || request
+        .headers()
+        .get_one("Upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// The host name of a `host[:port]` authority, without IPv6 brackets.
+fn host_name(authority: &str) -> &str {
+    if let Some(rest) = authority.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    authority
+        .rsplit_once(':')
+        .map_or(authority, |(name, _)| name)
+}
@@ -0,0 +1,79 @@
+//! Per-user state on a shared server.
+//!
+//! Each user has a backend of their own, with their own data directory, path
+//! policy and process environment, and an event stream of their own, so
+//! WebSocket and SSE clients only see their user's events. A single-user
+//! server has one unnamed state.
+
+use crate::AppState;
+use crate::auth::Authenticated;
+use rocket::Request;
+use rocket::http::Status;
+use rocket::outcome::try_outcome;
+use rocket::request::{self, FromRequest};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+pub struct Users {
+    single: Option<AppState>,
+    users: BTreeMap<String, AppState>,
+}
+
+impl Users {
+    pub fn single(state: AppState) -> Self {
+        Self {
+            single: Some(state),
+            users: BTreeMap::new(),
+        }
+    }
+
+    pub fn shared(users: impl IntoIterator<Item = (String, AppState)>) -> Self {
+        Self {
+            single: None,
+            users: users.into_iter().collect(),
+        }
+    }
+
+    fn get(&self, user: Option<&str>) -> Option<&AppState> {
+        match user {
+            Some(user) => self.users.get(user),
+            None => self.single.as_ref(),
+        }
+    }
+
+    /// Every state, with its user's name on a shared server.
+    pub fn iter(&self) -> impl Iterator<Item = (Option<&str>, &AppState)> {
+        self.single.iter().map(|state| (None, state)).chain(
+            self.users
+                .iter()
+                .map(|(name, state)| (Some(name.as_str()), state)),
+        )
+    }
+}
+
+/// Request guard for the state of the user a request is authenticated as.
+pub struct UserState<'r>(&'r AppState);
+
+impl Deref for UserState<'_> {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserState<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let authenticated = try_outcome!(request.guard::<Authenticated>().await);
+        let Some(users) = request.rocket().state::<Users>() else {
+            return request::Outcome::Error((Status::InternalServerError, ()));
+        };
+        match users.get(authenticated.user.as_deref()) {
+            Some(state) => request::Outcome::Success(Self(state)),
+            None => request::Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
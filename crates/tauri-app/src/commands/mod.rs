@@ -1,10 +1,13 @@
 use crate::state::AppState;
+use backend::terminal;
 use backend::{
-    DetailedConversation, DirEntry, EnrichedProject, FileContent, GeminiAuthConfig, GitInfo,
-    LLxprtConfig, ProcessStatus, ProjectsResponse, QwenConfig, RecentChat, SearchFilters,
-    SearchResult,
+    BatchReport, BatchRequest, DetailedConversation, DiffApplyResult, DiffPreview, DirEntry,
+    EnrichedProject, FileContent, GeminiAuthConfig, GitInfo, JobInfo, JobSpec, LLxprtConfig,
+    ProcessStatus, ProfileInput, ProjectHistorySettings, ProjectsResponse, ProviderProfile,
+    QwenConfig, RecentChat, SearchFilters, SearchResult, TerminalInfo, VaultStatus,
 };
 use serde_json::Value;
+use std::collections::HashMap;
 use tauri::{AppHandle, State};
 
 #[cfg(windows)]
@@ -28,8 +31,21 @@ pub async fn start_session(
     backend_config: Option<QwenConfig>,
     gemini_auth: Option<GeminiAuthConfig>,
     llxprt_config: Option<LLxprtConfig>,
+    profile_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    if let (Some(working_directory), Some(profile_id)) = (&working_directory, &profile_id) {
+        return state
+            .backend
+            .initialize_session_with_profile(
+                session_id,
+                working_directory.clone(),
+                model,
+                profile_id,
+            )
+            .await
+            .map_err(|e| format!("{e:#}"));
+    }
     if let Some(working_directory) = working_directory {
         let model = model.unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
         state
@@ -147,11 +163,183 @@ pub async fn send_tool_call_confirmation_response(
 #[tauri::command]
 pub async fn execute_confirmed_command(
     command: String,
+    working_directory: String,
+    env: Option<HashMap<String, String>>,
+    timeout_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<JobInfo, String> {
+    state
+        .backend
+        .execute_confirmed_command(JobSpec {
+            command,
+            working_directory,
+            env: env.unwrap_or_default(),
+            timeout_secs,
+        })
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<JobInfo>, String> {
+    state
+        .backend
+        .list_jobs()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn cancel_job(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .cancel_job(&job_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn kill_job(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .kill_job(&job_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn start_batch(
+    request: BatchRequest,
+    state: State<'_, AppState>,
+) -> Result<BatchReport, String> {
+    state
+        .backend
+        .start_batch(request)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn list_batches(state: State<'_, AppState>) -> Result<Vec<BatchReport>, String> {
+    state
+        .backend
+        .list_batches()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn get_batch(
+    batch_id: String,
+    state: State<'_, AppState>,
+) -> Result<BatchReport, String> {
+    state
+        .backend
+        .get_batch(&batch_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn cancel_batch(batch_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .cancel_batch(&batch_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn export_batch(
+    batch_id: String,
+    format: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    state
+        .backend
+        .export_batch(&batch_id, format.as_deref().unwrap_or("markdown"))
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn spawn_terminal(
+    project_id: String,
+    working_directory: Option<String>,
+    shell: Option<String>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+    state: State<'_, AppState>,
+) -> Result<TerminalInfo, String> {
+    state
+        .backend
+        .spawn_terminal(
+            project_id,
+            working_directory,
+            shell,
+            cols.unwrap_or(terminal::DEFAULT_COLS),
+            rows.unwrap_or(terminal::DEFAULT_ROWS),
+        )
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn list_terminals(
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TerminalInfo>, String> {
+    state
+        .backend
+        .list_terminals(project_id.as_deref())
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn write_terminal(
+    terminal_id: String,
+    data: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .backend
+        .write_terminal(&terminal_id, &data)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn resize_terminal(
+    terminal_id: String,
+    cols: u16,
+    rows: u16,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .backend
+        .resize_terminal(&terminal_id, cols, rows)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn terminal_scrollback(
+    terminal_id: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     state
         .backend
-        .execute_confirmed_command(command)
+        .terminal_scrollback(&terminal_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn kill_terminal(terminal_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .kill_terminal(&terminal_id)
         .await
         .map_err(|e| format!("{e:#}"))
 }
@@ -160,11 +348,12 @@ pub async fn execute_confirmed_command(
 pub async fn generate_conversation_title(
     message: String,
     model: Option<String>,
+    session_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     state
         .backend
-        .generate_conversation_title(message, model)
+        .generate_conversation_title(message, model, session_id)
         .await
         .map_err(|e| format!("{e:#}"))
 }
@@ -568,6 +757,116 @@ pub async fn delete_project(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_project_history_settings(
+    project_id: String,
+    state: State<'_, AppState>,
+) -> Result<ProjectHistorySettings, String> {
+    state
+        .backend
+        .get_project_history_settings(&project_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn set_project_history_settings(
+    project_id: String,
+    settings: ProjectHistorySettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .backend
+        .set_project_history_settings(&project_id, settings)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn vault_status(state: State<'_, AppState>) -> Result<VaultStatus, String> {
+    state
+        .backend
+        .vault_status()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn create_vault(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .backend
+        .create_vault(passphrase)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn unlock_vault(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .backend
+        .unlock_vault(passphrase)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .lock_vault()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<ProviderProfile>, String> {
+    state
+        .backend
+        .list_profiles()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    input: ProfileInput,
+    state: State<'_, AppState>,
+) -> Result<ProviderProfile, String> {
+    state
+        .backend
+        .create_profile(input)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn update_profile(
+    profile_id: String,
+    input: ProfileInput,
+    state: State<'_, AppState>,
+) -> Result<ProviderProfile, String> {
+    state
+        .backend
+        .update_profile(profile_id, input)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn delete_profile(profile_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .backend
+        .delete_profile(profile_id)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
 #[tauri::command]
 pub async fn get_canonical_path(
     path: String,
@@ -605,3 +904,46 @@ pub async fn write_file_content(
         .await
         .map_err(|e| format!("{e:#}"))
 }
+
+#[tauri::command]
+pub async fn preview_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+    context_lines: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<DiffPreview, String> {
+    state
+        .backend
+        .preview_diff(path, old_text, new_text, context_lines)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn apply_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+    state: State<'_, AppState>,
+) -> Result<DiffApplyResult, String> {
+    state
+        .backend
+        .apply_diff(path, old_text, new_text)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+#[tauri::command]
+pub async fn revert_diff(
+    path: String,
+    old_text: String,
+    new_text: String,
+    state: State<'_, AppState>,
+) -> Result<DiffApplyResult, String> {
+    state
+        .backend
+        .revert_diff(path, old_text, new_text)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
@@ -1,7 +1,7 @@
+use crate::notifications::Notifier;
 use anyhow::{Context, Result};
-use backend::EventEmitter;
-use serde::Serialize;
-use tauri::{AppHandle, Emitter};
+use backend::{AppEvent, EventEmitter};
+use tauri::{AppHandle, Emitter, Manager};
 
 #[derive(Clone)]
 pub struct TauriEventEmitter {
@@ -15,9 +15,13 @@ impl TauriEventEmitter {
 }
 
 impl EventEmitter for TauriEventEmitter {
-    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> Result<()> {
+    fn emit(&self, event: AppEvent) -> Result<()> {
+        if let Some(notifier) = self.app_handle.try_state::<Notifier>() {
+            notifier.observe(&event);
+        }
+        let (event, payload) = event.to_wire()?;
         self.app_handle
-            .emit(event, payload)
+            .emit(&event, payload)
             .context("Failed to emit event through Tauri")?;
         Ok(())
     }
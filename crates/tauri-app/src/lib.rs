@@ -3,24 +3,56 @@
 mod commands;
 mod event_emitter;
 mod menu;
+mod notifications;
 mod state;
 
-use backend::GeminiBackend;
+use backend::{
+    logging, GeminiBackend, LogConfig, PathPolicy, RecordingEventEmitter, Replay,
+    STATUS_MONITOR_INTERVAL,
+};
 use event_emitter::TauriEventEmitter;
+use notifications::Notifier;
 use state::AppState;
 use std::sync::Arc;
-use tauri::{Emitter, Manager};
+use std::time::Duration;
+use tauri::webview::PageLoadEvent;
+use tauri::{Emitter, Manager, WindowEvent};
+use tracing::{error, info};
+
+/// Time the frontend gets to register its listeners before a replay starts.
+const REPLAY_LEAD_IN: Duration = Duration::from_secs(1);
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Held until the app exits so buffered file logs get flushed
+    let _log_guard = logging::init(LogConfig::default())
+        .inspect_err(|e| eprintln!("Failed to initialize logging: {e:#}"))
+        .ok();
+
+    let replay = Replay::from_env()
+        .inspect_err(|e| error!("Failed to load event recording: {e:#}"))
+        .ok()
+        .flatten()
+        .map(Arc::new);
+    let replay_on_load = replay.clone();
+
     let builder = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_process::init())
-        .setup(|app| {
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
+            app.manage(Notifier::load(app.handle().clone()));
             let emitter = TauriEventEmitter::new(app.handle().clone());
-            let backend = GeminiBackend::new(emitter);
+            // Unrestricted unless GEMINI_CLI_DESKTOP_ALLOWED_PATHS is set
+            let backend = GeminiBackend::new(RecordingEventEmitter::from_env(emitter))
+                .with_path_policy(PathPolicy::from_env());
+            // A replay stands in for live sessions, so their statuses would
+            // only get in its way
+            if replay.is_none() {
+                tauri::async_runtime::spawn(backend.status_monitor(STATUS_MONITOR_INTERVAL).run());
+            }
 
             let app_state = AppState {
                 backend: Arc::new(backend),
@@ -31,12 +63,36 @@ pub fn run() {
             #[cfg(not(windows))]
             {
                 if let Err(e) = menu::init_menu(app.handle().clone()) {
-                    eprintln!("Failed to initialize menu: {e}");
+                    error!("Failed to initialize menu: {e}");
                 }
             }
 
             Ok(())
         })
+        .on_page_load(move |webview, payload| {
+            // Play the recording every time the UI (re)loads
+            let Some(replay) = replay_on_load.clone() else {
+                return;
+            };
+            if !matches!(payload.event(), PageLoadEvent::Finished) {
+                return;
+            }
+            let emitter = TauriEventEmitter::new(webview.app_handle().clone());
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(REPLAY_LEAD_IN).await;
+                info!("Replaying {} recorded events", replay.len());
+                if let Err(e) = replay.play(&emitter).await {
+                    error!("Event replay failed: {e:#}");
+                }
+            });
+        })
+        .on_window_event(|window, event| {
+            if matches!(event, WindowEvent::Focused(true)) {
+                if let Some(notifier) = window.try_state::<Notifier>() {
+                    notifier.window_focused();
+                }
+            }
+        })
         .on_menu_event(|app, event| {
             // Get the window that triggered the event
             if let Some(window) = app.get_webview_window("main") {
@@ -61,6 +117,20 @@ pub fn run() {
             commands::test_cli_command,
             commands::send_tool_call_confirmation_response,
             commands::execute_confirmed_command,
+            commands::list_jobs,
+            commands::cancel_job,
+            commands::kill_job,
+            commands::start_batch,
+            commands::list_batches,
+            commands::get_batch,
+            commands::cancel_batch,
+            commands::export_batch,
+            commands::spawn_terminal,
+            commands::list_terminals,
+            commands::write_terminal,
+            commands::resize_terminal,
+            commands::terminal_scrollback,
+            commands::kill_terminal,
             commands::generate_conversation_title,
             commands::validate_directory,
             commands::is_home_directory,
@@ -81,6 +151,16 @@ pub fn run() {
             commands::export_conversation_history,
             commands::delete_conversation,
             commands::delete_project,
+            commands::get_project_history_settings,
+            commands::set_project_history_settings,
+            commands::vault_status,
+            commands::create_vault,
+            commands::unlock_vault,
+            commands::lock_vault,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::update_profile,
+            commands::delete_profile,
             commands::get_settings_file_path,
             commands::read_settings_file,
             commands::write_settings_file,
@@ -89,8 +169,14 @@ pub fn run() {
             commands::get_canonical_path,
             commands::read_file_content_with_options,
             commands::write_file_content,
+            commands::preview_diff,
+            commands::apply_diff,
+            commands::revert_diff,
             menu::init_menu,
-            menu::update_menu_labels
+            menu::update_menu_labels,
+            notifications::get_notification_preferences,
+            notifications::set_notification_preferences,
+            notifications::set_notification_texts
         ]);
 
     builder
@@ -0,0 +1,249 @@
+//! OS notifications for session events that need attention while the main
+//! window is in the background.
+//!
+//! Desktop notification servers don't tell the app when a notification is
+//! clicked, but clicking one brings the app to the front. So when the main
+//! window gains focus after a notification, the frontend is told to open
+//! that notification's conversation.
+//!
+//! Notifications are shown here rather than by the frontend, so the frontend
+//! hands over their text in the UI's language.
+
+use backend::AppEvent;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tracing::warn;
+
+const PREFERENCES_FILE_NAME: &str = "notifications.json";
+const MAIN_WINDOW: &str = "main";
+/// Sent to the main window with the session id of the conversation to open.
+const OPEN_CONVERSATION_EVENT: &str = "notification:open";
+/// Longest notification body, in characters.
+const MAX_BODY_CHARS: usize = 200;
+
+/// Which events produce notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub turn_finished: bool,
+    pub errors: bool,
+    pub permission_requests: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            turn_finished: true,
+            errors: true,
+            permission_requests: true,
+        }
+    }
+}
+
+/// Notification text, translated by the frontend. It is saved so that
+/// notifications shown before the window has loaded are translated too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationTexts {
+    pub turn_finished_title: String,
+    pub turn_finished_body: String,
+    pub error_title: String,
+    pub permission_title: String,
+}
+
+impl Default for NotificationTexts {
+    fn default() -> Self {
+        Self {
+            turn_finished_title: "Turn finished".to_string(),
+            turn_finished_body: "The agent is waiting for your next message.".to_string(),
+            error_title: "Agent error".to_string(),
+            permission_title: "Permission needed".to_string(),
+        }
+    }
+}
+
+/// Contents of the preferences file. The text is kept apart from the
+/// preferences so a settings dialog opened before a language change can't
+/// save stale text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    #[serde(flatten)]
+    preferences: NotificationPreferences,
+    #[serde(default)]
+    texts: NotificationTexts,
+}
+
+pub struct Notifier {
+    app_handle: AppHandle,
+    settings: RwLock<Settings>,
+    path: Option<PathBuf>,
+    /// Session of the last notification shown, opened when the window is
+    /// next focused.
+    pending: Mutex<Option<String>>,
+}
+
+impl Notifier {
+    /// A notifier with the preferences saved in the app's config directory.
+    pub fn load(app_handle: AppHandle) -> Self {
+        let path = app_handle
+            .path()
+            .app_config_dir()
+            .map(|dir| dir.join(PREFERENCES_FILE_NAME))
+            .inspect_err(|e| warn!("No config directory for notification preferences: {e}"))
+            .ok();
+        let settings = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| {
+                serde_json::from_str(&content)
+                    .inspect_err(|e| warn!("Invalid notification preferences: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            app_handle,
+            settings: RwLock::new(settings),
+            path,
+            pending: Mutex::new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn preferences(&self) -> NotificationPreferences {
+        self.settings().preferences
+    }
+
+    pub fn set_preferences(&self, preferences: NotificationPreferences) -> anyhow::Result<()> {
+        self.update(|settings| settings.preferences = preferences)
+    }
+
+    pub fn set_texts(&self, texts: NotificationTexts) -> anyhow::Result<()> {
+        self.update(|settings| settings.texts = texts)
+    }
+
+    fn settings(&self) -> Settings {
+        self.settings
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Change the settings and save them. The lock is held while saving so
+    /// concurrent changes can't overwrite each other on disk.
+    fn update(&self, change: impl FnOnce(&mut Settings)) -> anyhow::Result<()> {
+        let mut settings = self
+            .settings
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut next = settings.clone();
+        change(&mut next);
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&next)?)?;
+        }
+        *settings = next;
+        Ok(())
+    }
+
+    /// Show a notification for an event if it is one the user wants to hear
+    /// about and the main window isn't focused.
+    pub fn observe(&self, event: &AppEvent) {
+        let Settings { preferences, texts } = self.settings();
+        let (session_id, title, body) = match event {
+            AppEvent::AiTurnFinished { session_id, .. } if preferences.turn_finished => (
+                session_id,
+                texts.turn_finished_title,
+                texts.turn_finished_body,
+            ),
+            AppEvent::AiError {
+                session_id,
+                payload,
+            } if preferences.errors => (session_id, texts.error_title, payload.clone()),
+            AppEvent::AcpPermissionRequest {
+                session_id,
+                payload,
+            } if preferences.permission_requests => (
+                session_id,
+                texts.permission_title,
+                payload.request.tool_call.title.clone(),
+            ),
+            _ => return,
+        };
+        if self.main_window_focused() {
+            return;
+        }
+
+        let body: String = body.chars().take(MAX_BODY_CHARS).collect();
+        match self
+            .app_handle
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            Ok(()) => {
+                *self
+                    .pending
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(session_id.clone());
+            }
+            Err(e) => warn!("Failed to show notification: {e}"),
+        }
+    }
+
+    /// Open the conversation of the last notification, now that the main
+    /// window has been brought to the front.
+    pub fn window_focused(&self) {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take();
+        if let Some(session_id) = pending {
+            if let Err(e) =
+                self.app_handle
+                    .emit_to(MAIN_WINDOW, OPEN_CONVERSATION_EVENT, session_id)
+            {
+                warn!("Failed to open notified conversation: {e}");
+            }
+        }
+    }
+
+    fn main_window_focused(&self) -> bool {
+        self.app_handle
+            .get_webview_window(MAIN_WINDOW)
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false)
+    }
+}
+
+#[tauri::command]
+pub fn get_notification_preferences(notifier: State<'_, Notifier>) -> NotificationPreferences {
+    notifier.preferences()
+}
+
+#[tauri::command]
+pub fn set_notification_preferences(
+    preferences: NotificationPreferences,
+    notifier: State<'_, Notifier>,
+) -> Result<(), String> {
+    notifier
+        .set_preferences(preferences)
+        .map_err(|e| format!("Failed to save notification preferences: {e:#}"))
+}
+
+#[tauri::command]
+pub fn set_notification_texts(
+    texts: NotificationTexts,
+    notifier: State<'_, Notifier>,
+) -> Result<(), String> {
+    notifier
+        .set_texts(texts)
+        .map_err(|e| format!("Failed to save notification text: {e:#}"))
+}
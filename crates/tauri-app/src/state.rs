@@ -1,7 +1,7 @@
 use crate::event_emitter::TauriEventEmitter;
-use backend::GeminiBackend;
+use backend::{GeminiBackend, RecordingEventEmitter};
 use std::sync::Arc;
 
 pub struct AppState {
-    pub backend: Arc<GeminiBackend<TauriEventEmitter>>,
+    pub backend: Arc<GeminiBackend<RecordingEventEmitter<TauriEventEmitter>>>,
 }
@@ -8,6 +8,8 @@ import {
   useNavigate,
 } from "react-router-dom";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
 import { api } from "./lib/api";
 import { AppSidebar } from "./components/layout/AppSidebar";
 import {
@@ -27,10 +29,12 @@ import {
   useBackend,
 } from "./contexts/BackendContext";
 import { getBackendText } from "./utils/backendText";
+import i18n from "./i18n";
 import { HomeDashboard } from "./pages/HomeDashboard";
 import ProjectsPage from "./pages/Projects";
 import ProjectDetailPage from "./pages/ProjectDetail";
 import { McpServersPage } from "./pages/McpServersPage";
+import AdminSessionsPage from "./pages/AdminSessionsPage";
 
 // Hooks
 import { useConversationManager } from "./hooks/useConversationManager";
@@ -223,6 +227,46 @@ function RootLayoutContent() {
       );
   }, []);
 
+  // Open a desktop notification's conversation once it brings the window
+  // to the front
+  useEffect(() => {
+    if (__WEB__) {
+      return;
+    }
+    const unlisten = listen<string>("notification:open", (event) => {
+      setActiveConversation(event.payload);
+      navigate("/");
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [navigate, setActiveConversation]);
+
+  // Desktop notifications are shown by the backend, so keep their text in
+  // the current language
+  useEffect(() => {
+    if (__WEB__) {
+      return;
+    }
+    const sendTexts = () => {
+      invoke("set_notification_texts", {
+        texts: {
+          turn_finished_title: i18n.t("notifications.turnFinishedTitle"),
+          turn_finished_body: i18n.t("notifications.turnFinishedBody"),
+          error_title: i18n.t("notifications.errorTitle"),
+          permission_title: i18n.t("notifications.permissionTitle"),
+        },
+      }).catch((error) =>
+        console.error("Failed to update notification text:", error)
+      );
+    };
+    sendTexts();
+    i18n.on("languageChanged", sendTexts);
+    return () => {
+      i18n.off("languageChanged", sendTexts);
+    };
+  }, []);
+
   // Progress listener started in startNewConversation
 
   useEffect(() => {
@@ -277,6 +321,14 @@ function RootLayoutContent() {
       initialMessages: Message[] = [],
       conversationId?: string
     ): Promise<string> => {
+      // Like useMessageHandler, the web version only starts sessions from
+      // vault profiles so API keys never leave the server.
+      const profileId = backendState.profileId ?? undefined;
+      if (workingDirectory && __WEB__ && !profileId) {
+        setIsSettingsOpen(true);
+        throw new Error(i18n.t("profiles.required"));
+      }
+
       const convId = conversationId || Date.now().toString();
       createNewConversation(
         convId,
@@ -374,13 +426,22 @@ function RootLayoutContent() {
           );
         }
 
-        await api.start_session({
-          sessionId: convId,
-          workingDirectory,
-          model: selectedModel,
-          backendConfig,
-          geminiAuth,
-        });
+        await api.start_session(
+          profileId
+            ? {
+                sessionId: convId,
+                workingDirectory,
+                model: selectedModel,
+                profileId,
+              }
+            : {
+                sessionId: convId,
+                workingDirectory,
+                model: selectedModel,
+                backendConfig,
+                geminiAuth,
+              }
+        );
       }
 
       return convId;
@@ -390,6 +451,7 @@ function RootLayoutContent() {
       selectedBackend,
       apiConfig,
       backendState.configs.gemini,
+      backendState.profileId,
       createNewConversation,
       setActiveConversation,
       startListeningForSession,
@@ -639,6 +701,7 @@ function App() {
           <Route path="projects" element={<ProjectsPage />} />
           <Route path="projects/:id" element={<ProjectDetailPage />} />
           <Route path="mcp" element={<McpServersPage />} />
+          <Route path="admin/sessions" element={<AdminSessionsPage />} />
           <Route path="*" element={<Navigate to="/" replace />} />
         </Route>
       </Routes>
@@ -0,0 +1,79 @@
+import React, { useEffect, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
+import { Checkbox } from "@/components/ui/checkbox";
+
+interface NotificationPreferences {
+  turn_finished: boolean;
+  errors: boolean;
+  permission_requests: boolean;
+}
+
+const options: Array<{
+  key: keyof NotificationPreferences;
+  label: "turnFinished" | "errors" | "permissionRequests";
+}> = [
+  { key: "turn_finished", label: "turnFinished" },
+  { key: "errors", label: "errors" },
+  { key: "permission_requests", label: "permissionRequests" },
+];
+
+/** Which session events show desktop notifications. Desktop app only. */
+export const NotificationSettings: React.FC = () => {
+  const { t } = useTranslation();
+  const [preferences, setPreferences] =
+    useState<NotificationPreferences | null>(null);
+
+  useEffect(() => {
+    invoke<NotificationPreferences>("get_notification_preferences")
+      .then(setPreferences)
+      .catch((error) =>
+        console.error("Failed to load notification preferences:", error)
+      );
+  }, []);
+
+  if (!preferences) {
+    return null;
+  }
+
+  const update = async (key: keyof NotificationPreferences, value: boolean) => {
+    const previous = preferences;
+    const next = { ...preferences, [key]: value };
+    setPreferences(next);
+    try {
+      await invoke("set_notification_preferences", { preferences: next });
+    } catch (error) {
+      setPreferences(previous);
+      toast.error(t("notifications.saveFailed"), {
+        description: String(error),
+      });
+    }
+  };
+
+  return (
+    <div className="space-y-2">
+      <label className="text-sm font-medium text-gray-700 dark:text-gray-300 block">
+        {t("notifications.title")}
+      </label>
+      <p className="text-xs text-gray-500 dark:text-gray-400">
+        {t("notifications.description")}
+      </p>
+      {options.map(({ key, label }) => (
+        <div key={key} className="flex items-center space-x-2">
+          <Checkbox
+            id={`notify-${key}`}
+            checked={preferences[key]}
+            onCheckedChange={(checked) => update(key, checked === true)}
+          />
+          <label
+            htmlFor={`notify-${key}`}
+            className="text-sm font-medium text-gray-700 dark:text-gray-300 cursor-pointer"
+          >
+            {t(`notifications.${label}`)}
+          </label>
+        </div>
+      ))}
+    </div>
+  );
+};
@@ -0,0 +1,307 @@
+import React, { useCallback, useEffect, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { toast } from "sonner";
+import { Lock, Trash2 } from "lucide-react";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import { api } from "@/lib/api";
+import { ProfileConfig, ProviderProfile, VaultStatus } from "@/lib/webApi";
+import { useBackend } from "@/contexts/BackendContext";
+import { BackendState, BackendType } from "@/types/backend";
+
+// Radix selects can't have an empty value
+const NO_PROFILE = "none";
+
+/** The settings of `backend` as the vault stores them. */
+const profileConfig = (
+  backend: BackendType,
+  configs: BackendState["configs"]
+): ProfileConfig => {
+  switch (backend) {
+    case "gemini": {
+      const gemini = configs.gemini;
+      return {
+        backend,
+        config: {
+          method: gemini.authMethod,
+          api_key:
+            gemini.authMethod === "gemini-api-key" ? gemini.apiKey : undefined,
+          vertex_project:
+            gemini.authMethod === "vertex-ai"
+              ? gemini.vertexProject
+              : undefined,
+          vertex_location:
+            gemini.authMethod === "vertex-ai"
+              ? gemini.vertexLocation
+              : undefined,
+          yolo: gemini.yolo,
+        },
+      };
+    }
+    case "qwen": {
+      const qwen = configs.qwen;
+      return {
+        backend,
+        config: {
+          api_key: qwen.useOAuth ? "" : qwen.apiKey,
+          base_url: qwen.baseUrl,
+          model: qwen.model,
+          yolo: qwen.yolo,
+        },
+      };
+    }
+    case "llxprt": {
+      const llxprt = configs.llxprt;
+      return {
+        backend,
+        config: {
+          provider: llxprt.provider,
+          api_key: llxprt.apiKey,
+          model: llxprt.model,
+          base_url: llxprt.baseUrl || undefined,
+        },
+      };
+    }
+  }
+};
+
+/**
+ * Unlocks the credential vault and picks the profile new sessions use. The
+ * web version needs a profile; the desktop app can also use the raw settings
+ * below.
+ */
+export const ProfileSettings: React.FC = () => {
+  const { t } = useTranslation();
+  const {
+    state,
+    selectedBackend,
+    switchBackend,
+    updateConfig,
+    selectProfile,
+  } = useBackend();
+  const [status, setStatus] = useState<VaultStatus | null>(null);
+  const [profiles, setProfiles] = useState<ProviderProfile[]>([]);
+  const [passphrase, setPassphrase] = useState("");
+  const [profileName, setProfileName] = useState("");
+  const [busy, setBusy] = useState(false);
+
+  const refresh = useCallback(async () => {
+    try {
+      const next = await api.vault_status();
+      setStatus(next);
+      setProfiles(next.unlocked ? await api.list_profiles() : []);
+    } catch (error) {
+      console.error("Failed to load the credential vault:", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  // Forget a selection whose profile was deleted
+  useEffect(() => {
+    if (
+      status?.unlocked &&
+      state.profileId &&
+      !profiles.some((profile) => profile.id === state.profileId)
+    ) {
+      selectProfile(null);
+    }
+  }, [status, profiles, state.profileId, selectProfile]);
+
+  // Errors are already shown by `api`
+  const run = async (action: () => Promise<unknown>) => {
+    setBusy(true);
+    try {
+      await action();
+      setPassphrase("");
+      await refresh();
+    } catch (error) {
+      console.error("Credential vault action failed:", error);
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const choose = (value: string) => {
+    const profile = profiles.find((profile) => profile.id === value);
+    selectProfile(profile ? profile.id : null);
+    if (profile) {
+      switchBackend(profile.backend);
+    }
+  };
+
+  const saveProfile = () =>
+    run(async () => {
+      const profile = await api.create_profile({
+        input: {
+          name: profileName.trim(),
+          ...profileConfig(selectedBackend, state.configs),
+        },
+      });
+      setProfileName("");
+      selectProfile(profile.id);
+      // The key now lives in the vault; don't keep a copy in the browser
+      if (__WEB__) {
+        updateConfig(selectedBackend, { apiKey: "" });
+      }
+      toast.success(t("profiles.saved", { name: profile.name }));
+    });
+
+  const deleteProfile = (profile: ProviderProfile) =>
+    run(() => api.delete_profile({ profileId: profile.id }));
+
+  if (!status) {
+    return null;
+  }
+
+  const selected = profiles.find((profile) => profile.id === state.profileId);
+
+  return (
+    <div className="space-y-3 p-3 border border-gray-200 dark:border-gray-700 rounded-md">
+      <div>
+        <h4 className="text-sm font-medium text-gray-700 dark:text-gray-300">
+          {t("profiles.title")}
+        </h4>
+        <p className="text-xs text-gray-500 dark:text-gray-400">
+          {__WEB__ ? t("profiles.descriptionWeb") : t("profiles.description")}
+        </p>
+      </div>
+
+      {!status.exists && (
+        <form
+          className="flex gap-2"
+          onSubmit={(e) => {
+            e.preventDefault();
+            run(() => api.create_vault({ passphrase }));
+          }}
+        >
+          <Input
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            placeholder={t("profiles.newPassphrase")}
+          />
+          <Button type="submit" size="sm" disabled={busy || !passphrase}>
+            {t("profiles.createVault")}
+          </Button>
+          {status.keyring_available && (
+            <Button
+              type="button"
+              size="sm"
+              variant="outline"
+              disabled={busy}
+              onClick={() => run(() => api.create_vault({}))}
+            >
+              {t("profiles.useKeyring")}
+            </Button>
+          )}
+        </form>
+      )}
+
+      {status.exists && !status.unlocked && (
+        <form
+          className="flex gap-2"
+          onSubmit={(e) => {
+            e.preventDefault();
+            run(() =>
+              api.unlock_vault(
+                status.key_source === "keyring" ? {} : { passphrase }
+              )
+            );
+          }}
+        >
+          {status.key_source !== "keyring" && (
+            <Input
+              type="password"
+              value={passphrase}
+              onChange={(e) => setPassphrase(e.target.value)}
+              placeholder={t("profiles.passphrase")}
+            />
+          )}
+          <Button
+            type="submit"
+            size="sm"
+            disabled={busy || (status.key_source !== "keyring" && !passphrase)}
+          >
+            {t("profiles.unlock")}
+          </Button>
+        </form>
+      )}
+
+      {status.unlocked && (
+        <>
+          <div className="flex gap-2">
+            <Select
+              value={selected ? selected.id : NO_PROFILE}
+              onValueChange={choose}
+            >
+              <SelectTrigger className="w-full">
+                <SelectValue placeholder={t("profiles.select")} />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value={NO_PROFILE}>
+                  {__WEB__ ? t("profiles.select") : t("profiles.none")}
+                </SelectItem>
+                {profiles.map((profile) => (
+                  <SelectItem key={profile.id} value={profile.id}>
+                    {profile.name} ({profile.backend})
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            {selected && (
+              <Button
+                size="sm"
+                variant="outline"
+                disabled={busy}
+                onClick={() => deleteProfile(selected)}
+                title={t("profiles.delete")}
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            )}
+            <Button
+              size="sm"
+              variant="outline"
+              disabled={busy}
+              onClick={() => run(() => api.lock_vault())}
+              title={t("profiles.lock")}
+            >
+              <Lock className="h-4 w-4" />
+            </Button>
+          </div>
+
+          <form
+            className="flex gap-2"
+            onSubmit={(e) => {
+              e.preventDefault();
+              saveProfile();
+            }}
+          >
+            <Input
+              value={profileName}
+              onChange={(e) => setProfileName(e.target.value)}
+              placeholder={t("profiles.name")}
+            />
+            <Button
+              type="submit"
+              size="sm"
+              disabled={busy || !profileName.trim()}
+            >
+              {t("profiles.saveCurrent")}
+            </Button>
+          </form>
+        </>
+      )}
+    </div>
+  );
+};
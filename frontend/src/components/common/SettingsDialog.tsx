@@ -47,6 +47,8 @@ import { cn } from "@/lib/utils";
 import { useBackend, useBackendConfig } from "@/contexts/BackendContext";
 import { GeminiAuthMethod, LLxprtProvider } from "@/types/backend";
 import { supportedLanguages, languageNames } from "@/i18n";
+import { NotificationSettings } from "./NotificationSettings";
+import { ProfileSettings } from "./ProfileSettings";
 
 interface OpenRouterModel {
   id: string;
@@ -259,6 +261,12 @@ export const SettingsDialog: React.FC<SettingsDialogProps> = ({
             </Select>
           </div>
 
+          {/* Desktop notifications */}
+          {!__WEB__ && <NotificationSettings />}
+
+          {/* Credential vault */}
+          <ProfileSettings />
+
           {/* Backend Selector */}
           <div>
             <label className="text-sm font-medium text-gray-700 dark:text-gray-300 mb-2 block">
@@ -162,6 +162,12 @@ const backendReducer = (
       };
     }
 
+    case "SELECT_PROFILE":
+      return {
+        ...state,
+        profileId: action.profileId,
+      };
+
     case "LOAD_FROM_STORAGE":
       return action.state;
 
@@ -210,6 +216,10 @@ export const BackendProvider: React.FC<BackendProviderProps> = ({
       resetConfig: (backend: BackendType) => {
         dispatch({ type: "RESET_CONFIG", backend });
       },
+
+      selectProfile: (profileId: string | null) => {
+        dispatch({ type: "SELECT_PROFILE", profileId });
+      },
     }),
     []
   );
@@ -6,6 +6,17 @@ import { api } from "../lib/api";
 import { Conversation, Message, CliIO } from "../types";
 import { ToolCallConfirmationRequest } from "../utils/toolCallParser";
 import { type ToolCall } from "../utils/toolCallParser";
+import type {
+  AppEventPayload,
+  CliIoType,
+  Location,
+  ToolCallContentItem,
+} from "../types/events";
+
+// Payloads of the events below whose names don't fit on one line
+type SessionUpdatePayload = AppEventPayload<"acp-session-update">;
+type PermissionPayload = AppEventPayload<"acp-permission-request">;
+type TurnFinishedPayload = AppEventPayload<"ai-turn-finished">;
 
 // Sanitize conversation ID for use in Tauri event names
 // Tauri event names must include only alphanumeric characters, `-`, `/`, `:` and `_`
@@ -13,34 +24,6 @@ function sanitizeEventName(conversationId: string): string {
   return conversationId.replace(/[^a-zA-Z0-9\-/:_]/g, "_");
 }
 
-interface ToolLocation {
-  path?: string; // Most common - full file path
-  file?: string; // Legacy - filename only
-  directory?: string; // Directory path
-  line?: number; // Optional line number
-  column?: number; // Optional column number
-  [key: string]: unknown;
-}
-
-interface AcpTextContent {
-  type: "content";
-  content: {
-    type: "text";
-    text: string;
-  };
-}
-
-interface AcpDiffContent {
-  type: "diff";
-  path: string;
-  oldText?: string;
-  old_text?: string;
-  newText?: string;
-  new_text?: string;
-}
-
-type AcpContent = AcpTextContent | AcpDiffContent;
-
 interface LegacyDiffResult {
   type: "diff";
   path: string;
@@ -57,71 +40,11 @@ interface LegacyGenericResult {
 
 type LegacyResult = LegacyDiffResult | LegacyGenericResult;
 
-// Event payload types for ACP session updates
-interface SessionUpdateEventPayload {
-  sessionUpdate:
-    | "tool_call"
-    | "tool_call_update"
-    | "agent_message_chunk"
-    | "agent_thought_chunk";
-  toolCallId?: string;
-  kind?: string;
-  title?: string;
-  locations?: ToolLocation[];
-  status?: string;
-  result?: string;
-  content?: AcpContent[] | string | { text: string };
-  chunk?: string;
-  thought?: string;
-  serverName?: string;
-  toolName?: string;
-}
-
-interface PermissionRequestEventPayload {
-  request_id: string;
-  request: {
-    sessionId?: string;
-    toolCall: {
-      toolCallId: string;
-      name?: string;
-      title?: string;
-      kind?: string;
-      status?: string;
-      parameters?: Record<string, unknown>;
-      content?: AcpContent[];
-      locations?: ToolLocation[];
-    };
-    question?: string;
-    options?:
-      | string[]
-      | {
-          optionId: string;
-          name: string;
-          kind: "allow_once" | "allow_always" | "reject_once" | "reject_always";
-        }[];
-  };
-}
-
-type EventPayload = SessionUpdateEventPayload | PermissionRequestEventPayload;
-
-// Type guards for event payload discrimination
-function isSessionUpdateEvent(
-  payload: EventPayload
-): payload is SessionUpdateEventPayload {
-  return "sessionUpdate" in payload;
-}
-
-function isPermissionRequestEvent(
-  payload: EventPayload
-): payload is PermissionRequestEventPayload {
-  return "request_id" in payload && "request" in payload;
-}
-
 // Helper functions for ACP conversion - CORRECTED with verified tool names
 function getToolNameFromKind(
   kind: string | undefined,
   title?: string,
-  locations?: ToolLocation[],
+  locations?: Location[],
   toolCallId?: string
 ): string {
   if (!kind) {
@@ -212,7 +135,9 @@ function mapAcpStatus(acpStatus: string | undefined): ToolCall["status"] {
   }
 }
 
-function convertAcpContentToLegacy(acpContent: AcpContent[]): LegacyResult {
+function convertAcpContentToLegacy(
+  acpContent: ToolCallContentItem[]
+): LegacyResult {
   if (!acpContent || acpContent.length === 0) {
     return {
       type: "generic" as const,
@@ -234,8 +159,8 @@ function convertAcpContentToLegacy(acpContent: AcpContent[]): LegacyResult {
     const result = {
       type: "diff" as const,
       path: contentItem.path,
-      oldText: contentItem.oldText || contentItem.old_text,
-      newText: contentItem.newText || contentItem.new_text,
+      oldText: contentItem.oldText,
+      newText: contentItem.newText,
     };
     return result;
   }
@@ -247,21 +172,6 @@ function convertAcpContentToLegacy(acpContent: AcpContent[]): LegacyResult {
   };
 }
 
-function getOptionKind(
-  idx: number
-): "allow_once" | "allow_always" | "reject_once" | "reject_always" {
-  switch (idx) {
-    case 0:
-      return "allow_once";
-    case 1:
-      return "allow_always";
-    case 2:
-      return "reject_once";
-    default:
-      return "reject_always";
-  }
-}
-
 /**
  * Custom hook to set up event listeners for conversation events.
  * Handles CLI I/O logging, tool call confirmations, and AI turn events.
@@ -310,25 +220,23 @@ export const useConversationEvents = (
       const sanitizedId = sanitizeEventName(conversationId);
 
       try {
-        const unlistenCliIo = await listen<{
-          type: "input" | "output";
-          data: string;
-        }>(`cli-io-${sanitizedId}`, (event) => {
+        // Handles one line the CLI read or wrote
+        const handleCliIoLine = (type: CliIoType, data: string) => {
           setCliIOLogs((prev) => [
             ...prev,
             {
               timestamp: new Date(),
-              type: event.payload.type,
-              data: event.payload.data,
+              type,
+              data,
               conversationId,
             },
           ]);
 
           // Check if this is a tool call related JSON-RPC message
           try {
-            const jsonData = JSON.parse(event.payload.data);
+            const jsonData = JSON.parse(data);
 
-            if (event.payload.type === "output") {
+            if (type === "output") {
               // Collect assistant text chunks for defensive fallback
               try {
                 let extractedText: string | undefined;
@@ -366,7 +274,7 @@ export const useConversationEvents = (
 
               // If it's a requestToolCallConfirmation input, store it for when the tool call is created
               if (jsonData.method === "requestToolCallConfirmation") {
-                window.pendingToolCallInput = event.payload.data;
+                window.pendingToolCallInput = data;
               }
 
               // If it's an updateToolCall input, store it for updating the tool call
@@ -378,7 +286,7 @@ export const useConversationEvents = (
                         msgPart.type === "toolCall" &&
                         msgPart.toolCall.id === jsonData.params!.toolCallId
                       ) {
-                        msgPart.toolCall.outputJsonRpc = event.payload.data;
+                        msgPart.toolCall.outputJsonRpc = data;
                       }
                     }
                   }
@@ -388,11 +296,21 @@ export const useConversationEvents = (
           } catch {
             // Not JSON, ignore
           }
-        });
+        };
+
+        const unlistenCliIo = await listen<AppEventPayload<"cli-io">>(
+          `cli-io-${sanitizedId}`,
+          (event) => {
+            // Lines read in quick succession arrive together
+            for (const data of event.payload.data.split("\n")) {
+              handleCliIoLine(event.payload.type, data);
+            }
+          }
+        );
         unlistenFunctions.push(unlistenCliIo);
 
         // Listen for streaming text chunks.
-        const unlistenAiOutput = await listen<string>(
+        const unlistenAiOutput = await listen<AppEventPayload<"ai-output">>(
           `ai-output-${sanitizedId}`,
           (event) => {
             // Mark that we received streaming for this turn to avoid fallback duplication
@@ -430,7 +348,7 @@ export const useConversationEvents = (
         unlistenFunctions.push(unlistenAiOutput);
 
         // Listen for thinking chunks.
-        const unlistenAiThought = await listen<string>(
+        const unlistenAiThought = await listen<AppEventPayload<"ai-thought">>(
           `ai-thought-${sanitizedId}`,
           (event) => {
             updateConversation(conversationId, (conv, lastMsg) => {
@@ -465,15 +383,9 @@ export const useConversationEvents = (
         unlistenFunctions.push(unlistenAiThought);
 
         // Listen for pure ACP session updates (replaces ai-tool-call and ai-tool-call-update)
-        const unlistenAcpSessionUpdate = await listen<EventPayload>(
+        const unlistenAcpSessionUpdate = await listen<SessionUpdatePayload>(
           `acp-session-update-${sanitizedId}`,
-          ({ payload: update }: { payload: EventPayload }) => {
-            if (!isSessionUpdateEvent(update)) {
-              console.warn(
-                "Received non-session-update event on session-update channel"
-              );
-              return;
-            }
+          ({ payload: update }) => {
             if (update.sessionUpdate === "tool_call") {
               // Handle tool call start
               updateConversation(conversationId, (conv, lastMsg) => {
@@ -596,10 +508,7 @@ export const useConversationEvents = (
 
                         // Handle content updates - but don't overwrite user rejections
                         if (update.content && !isCurrentlyRejected) {
-                          if (
-                            Array.isArray(update.content) &&
-                            update.content.length > 0
-                          ) {
+                          if (update.content.length > 0) {
                             // Use the existing convertAcpContentToLegacy function to properly handle diff content
                             const legacyResult = convertAcpContentToLegacy(
                               update.content
@@ -617,8 +526,6 @@ export const useConversationEvents = (
                               msgPart.toolCall.result =
                                 legacyResult.newText || "";
                             }
-                          } else if (typeof update.content === "string") {
-                            msgPart.toolCall.result = update.content;
                           }
                         }
 
@@ -649,7 +556,7 @@ export const useConversationEvents = (
         // Note: Tool call updates are now handled by the ACP session update listener above
 
         // Also listen for errors
-        const unlistenAiError = await listen<string>(
+        const unlistenAiError = await listen<AppEventPayload<"ai-error">>(
           `ai-error-${sanitizedId}`,
           (event) => {
             updateConversation(conversationId, (conv) => {
@@ -671,16 +578,9 @@ export const useConversationEvents = (
         unlistenFunctions.push(unlistenAiError);
 
         // Listen for pure ACP permission requests (replaces ai-tool-call-confirmation)
-        const unlistenAcpPermissionRequest = await listen<EventPayload>(
+        const unlistenAcpPermissionRequest = await listen<PermissionPayload>(
           `acp-permission-request-${sanitizedId}`,
           (event) => {
-            if (!isPermissionRequestEvent(event.payload)) {
-              console.warn(
-                "Received non-permission-request event on permission-request channel"
-              );
-              return;
-            }
-
             const { request_id, request } = event.payload;
             const toolCallId = request.toolCall.toolCallId;
 
@@ -706,13 +606,10 @@ export const useConversationEvents = (
 
               // Create the confirmation request object
               const confirmationRequest: ToolCallConfirmationRequest = {
-                requestId: parseInt(request_id, 10),
+                requestId: request_id,
                 sessionId: request.sessionId || "",
                 toolCallId: toolCallId,
-                label:
-                  request.toolCall.title ||
-                  request.toolCall.name ||
-                  "Unknown Tool",
+                label: request.toolCall.title || "Unknown Tool",
                 icon: "", // ACP doesn't use icons
                 content: convertAcpContentToLegacy(
                   request.toolCall.content || []
@@ -727,30 +624,12 @@ export const useConversationEvents = (
                   rootCommand: undefined,
                   command: undefined,
                 },
-                locations: (request.toolCall.locations || []).map(
-                  (loc: ToolLocation) => ({
-                    path: loc.file || loc.directory || "unknown",
-                  })
-                ),
+                locations: request.toolCall.locations.map((loc) => ({
+                path: loc.path,
+              })),
                 inputJsonRpc: window.pendingToolCallInput,
                 // Include ACP permission options for enhanced approval flows
-                options: Array.isArray(request.options)
-                  ? typeof request.options[0] === "string"
-                    ? (request.options as string[]).map((opt, idx) => ({
-                        optionId: `option_${idx}`,
-                        name: opt,
-                        kind: getOptionKind(idx),
-                      }))
-                    : (request.options as {
-                        optionId: string;
-                        name: string;
-                        kind:
-                          | "allow_once"
-                          | "allow_always"
-                          | "reject_once"
-                          | "reject_always";
-                      }[])
-                  : [],
+                options: request.options,
               };
 
               // If tool call doesn't exist, create one with the confirmation request
@@ -769,10 +648,7 @@ export const useConversationEvents = (
                 // Extract server and tool names from the title/label
                 let serverName: string | undefined;
                 let actualToolName: string | undefined;
-                const title =
-                  request.toolCall.title ||
-                  request.toolCall.name ||
-                  "Unknown Tool";
+                const title = request.toolCall.title || "Unknown Tool";
 
                 // Try to parse from title format: "tool-name (ServerName MCP Server)"
                 const serverMatch = title.match(/\((.+?) MCP Server\)$/);
@@ -863,13 +739,10 @@ export const useConversationEvents = (
 
             // Also store in the confirmation requests Map for backward compatibility
             const legacyConfirmationRequest: ToolCallConfirmationRequest = {
-              requestId: parseInt(request_id, 10),
+              requestId: request_id,
               sessionId: request.sessionId || "",
               toolCallId: toolCallId,
-              label:
-                request.toolCall.title ||
-                request.toolCall.name ||
-                "Unknown Tool",
+              label: request.toolCall.title || "Unknown Tool",
               icon: "", // ACP doesn't use icons
               content: convertAcpContentToLegacy(
                 request.toolCall.content || []
@@ -884,40 +757,12 @@ export const useConversationEvents = (
                 rootCommand: undefined,
                 command: undefined,
               },
-              locations: (request.toolCall.locations || []).map(
-                (loc: ToolLocation) => ({
-                  path: loc.file || loc.directory || "unknown",
-                })
-              ),
+              locations: request.toolCall.locations.map((loc) => ({
+                path: loc.path,
+              })),
               inputJsonRpc: window.pendingToolCallInput,
               // Include ACP permission options for enhanced approval flows
-              options: Array.isArray(request.options)
-                ? typeof request.options[0] === "string"
-                  ? (request.options as string[]).map((opt, idx) => ({
-                      optionId: `option_${idx}`,
-                      name: opt,
-                      kind: (idx < 2
-                        ? idx === 0
-                          ? "allow_once"
-                          : "allow_always"
-                        : idx === 2
-                          ? "reject_once"
-                          : "reject_always") as
-                        | "allow_once"
-                        | "allow_always"
-                        | "reject_once"
-                        | "reject_always",
-                    }))
-                  : (request.options as {
-                      optionId: string;
-                      name: string;
-                      kind:
-                        | "allow_once"
-                        | "allow_always"
-                        | "reject_once"
-                        | "reject_always";
-                    }[])
-                : [],
+              options: request.options,
             };
 
             // If yolo mode is enabled, auto-approve the tool call
@@ -985,7 +830,7 @@ export const useConversationEvents = (
         unlistenFunctions.push(unlistenAcpPermissionRequest);
 
         // Listen for turn finished events to stop streaming indicator
-        const unlistenAiTurnFinished = await listen<boolean>(
+        const unlistenAiTurnFinished = await listen<TurnFinishedPayload>(
           `ai-turn-finished-${sanitizedId}`,
           () => {
             updateConversation(conversationId, (conv, lastMsg) => {
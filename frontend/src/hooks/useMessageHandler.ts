@@ -2,6 +2,7 @@ import React, { useState, useCallback } from "react";
 import { api } from "../lib/api";
 import { Message, Conversation } from "../types";
 import { useBackend } from "../contexts/BackendContext";
+import i18n from "../i18n";
 
 interface UseMessageHandlerProps {
   activeConversation: string | null;
@@ -67,6 +68,7 @@ export const useMessageHandler = ({
           const generatedTitle = await api.generate_conversation_title({
             message: userMessages,
             model: selectedModel,
+            sessionId: conversationId,
           });
           updateConversation(conversationId, (conv) => {
             conv.title = generatedTitle;
@@ -204,23 +206,41 @@ export const useMessageHandler = ({
           };
         }
 
+        // A vault profile replaces the raw configs above. The web version
+        // always uses one so API keys never leave the server.
+        const profileId = backendState.profileId ?? undefined;
+        if (__WEB__ && !profileId) {
+          throw new Error(i18n.t("profiles.required"));
+        }
+
         // Session progress will be handled by useSessionProgress hook
         // which should be integrated at the component level
-        await api.start_session({
-          sessionId: convId,
-          workingDirectory: ".",
-          model: selectedModel,
-          backendConfig,
-          geminiAuth,
-          llxprtConfig,
-        });
+        await api.start_session(
+          profileId
+            ? {
+                sessionId: convId,
+                workingDirectory: ".",
+                model: selectedModel,
+                profileId,
+              }
+            : {
+                sessionId: convId,
+                workingDirectory: ".",
+                model: selectedModel,
+                backendConfig,
+                geminiAuth,
+                llxprtConfig,
+              }
+        );
 
         await api.send_message({
           sessionId: convId, // Tauri auto-converts to session_id
           message: messageText,
           conversationHistory: "", // Tauri auto-converts to conversation_history
           model: selectedModel,
-          backendConfig: backendConfig, // Tauri auto-converts to backend_config
+          profileId,
+          // Tauri auto-converts to backend_config
+          backendConfig: profileId ? undefined : backendConfig,
         });
 
         // Refresh process statuses after sending message
@@ -247,6 +267,7 @@ export const useMessageHandler = ({
       backendState.configs.gemini,
       backendState.configs.qwen,
       backendState.configs.llxprt,
+      backendState.profileId,
       getApiConfig,
       selectedBackend,
       updateConversation,
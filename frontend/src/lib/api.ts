@@ -1,12 +1,20 @@
 import { invoke, InvokeArgs } from "@tauri-apps/api/core";
 import {
+  BatchReport,
+  BatchRequest,
   DetailedConversation,
   DirEntry,
   EnrichedProject,
+  JobInfo,
+  ProfileInput,
+  ProjectHistorySettings,
   ProjectsResponse,
+  ProviderProfile,
   RecentChat,
   SearchFilters,
   SearchResult,
+  TerminalInfo,
+  VaultStatus,
   webApi,
 } from "./webApi";
 import { ProcessStatus } from "@/types";
@@ -44,12 +52,14 @@ export interface API {
       model: string;
       base_url?: string;
     };
+    profileId?: string;
   }): Promise<void>;
   send_message(params: {
     sessionId: string;
     message: string;
     conversationHistory: string;
     model?: string;
+    profileId?: string;
     backendConfig?: {
       api_key: string;
       base_url: string;
@@ -65,10 +75,43 @@ export interface API {
     toolCallId: string;
     outcome: string;
   }): Promise<void>;
-  execute_confirmed_command(params: { command: string }): Promise<string>;
+  execute_confirmed_command(params: {
+    command: string;
+    workingDirectory: string;
+    env?: Record<string, string>;
+    timeoutSecs?: number;
+  }): Promise<JobInfo>;
+  list_jobs(): Promise<JobInfo[]>;
+  cancel_job(params: { jobId: string }): Promise<void>;
+  kill_job(params: { jobId: string }): Promise<void>;
+  start_batch(params: { request: BatchRequest }): Promise<BatchReport>;
+  list_batches(): Promise<BatchReport[]>;
+  get_batch(params: { batchId: string }): Promise<BatchReport>;
+  cancel_batch(params: { batchId: string }): Promise<void>;
+  export_batch(params: {
+    batchId: string;
+    format?: "json" | "markdown";
+  }): Promise<string>;
+  spawn_terminal(params: {
+    projectId: string;
+    workingDirectory?: string;
+    shell?: string;
+    cols?: number;
+    rows?: number;
+  }): Promise<TerminalInfo>;
+  list_terminals(params: { projectId?: string }): Promise<TerminalInfo[]>;
+  write_terminal(params: { terminalId: string; data: string }): Promise<void>;
+  resize_terminal(params: {
+    terminalId: string;
+    cols: number;
+    rows: number;
+  }): Promise<void>;
+  terminal_scrollback(params: { terminalId: string }): Promise<string>;
+  kill_terminal(params: { terminalId: string }): Promise<void>;
   generate_conversation_title(params: {
     message: string;
     model?: string;
+    sessionId?: string;
   }): Promise<string>;
   validate_directory(params: { path: string }): Promise<boolean>;
   is_home_directory(params: { path: string }): Promise<boolean>;
@@ -105,6 +148,24 @@ export interface API {
     externalRootPath: string;
   }): Promise<EnrichedProject>;
   delete_project(params: { projectId: string }): Promise<void>;
+  get_project_history_settings(params: {
+    projectId: string;
+  }): Promise<ProjectHistorySettings>;
+  set_project_history_settings(params: {
+    projectId: string;
+    settings: ProjectHistorySettings;
+  }): Promise<void>;
+  vault_status(): Promise<VaultStatus>;
+  create_vault(params: { passphrase?: string }): Promise<void>;
+  unlock_vault(params: { passphrase?: string }): Promise<void>;
+  lock_vault(): Promise<void>;
+  list_profiles(): Promise<ProviderProfile[]>;
+  create_profile(params: { input: ProfileInput }): Promise<ProviderProfile>;
+  update_profile(params: {
+    profileId: string;
+    input: ProfileInput;
+  }): Promise<ProviderProfile>;
+  delete_profile(params: { profileId: string }): Promise<void>;
   get_git_info(params: { path: string }): Promise<{
     current_directory: string;
     branch: string;
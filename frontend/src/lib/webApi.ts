@@ -1,10 +1,23 @@
 import axios from "axios";
 import { API } from "./api";
+import type { ProcessStatus as SessionStatus } from "../types";
 
 // Create axios client with base URL /api
 const apiClient = axios.create({
   baseURL: "/api",
   timeout: 120000, // Increased to 2 minutes timeout to accommodate slow Gemini startup
+  // The server wants the session's CSRF token echoed on state-changing requests
+  xsrfCookieName: "gcd_csrf",
+  xsrfHeaderName: "X-CSRF-Token",
+});
+
+// Send the browser to the login page when the session is missing or expired
+apiClient.interceptors.response.use(undefined, (error) => {
+  if (axios.isAxiosError(error) && error.response?.status === 401) {
+    const next = window.location.pathname + window.location.search;
+    window.location.assign(`/login?next=${encodeURIComponent(next)}`);
+  }
+  return Promise.reject(error);
 });
 
 export interface DirEntry {
@@ -31,6 +44,18 @@ interface ProcessStatus {
   is_alive: boolean;
 }
 
+// Session commands go over the event WebSocket when there is one, so their
+// responses are ordered with the events they cause
+async function sessionCommand(method: string, path: string, params: unknown) {
+  const events = getWebSocketManager();
+  await events.waitForConnection();
+  if (events.usesWebSocket()) {
+    await events.call(method, params);
+  } else {
+    await apiClient.post(path, params);
+  }
+}
+
 // Web API functions that mirror Tauri invoke calls
 export const webApi: API = {
   async check_cli_installed() {
@@ -39,11 +64,11 @@ export const webApi: API = {
   },
 
   async start_session(params) {
-    await apiClient.post("/start-session", params);
+    await sessionCommand("start_session", "/start-session", params);
   },
 
   async send_message(params) {
-    await apiClient.post("/send-message", params);
+    await sessionCommand("send_message", "/send-message", params);
   },
 
   async get_process_statuses() {
@@ -52,18 +77,104 @@ export const webApi: API = {
   },
 
   async kill_process(params) {
-    await apiClient.post("/kill-process", params);
+    await sessionCommand("kill_process", "/kill-process", params);
   },
 
   async send_tool_call_confirmation_response(params) {
-    await apiClient.post("/tool-confirmation", params);
+    await sessionCommand("tool_confirmation", "/tool-confirmation", params);
   },
 
   async execute_confirmed_command(params) {
-    const response = await apiClient.post<string>("/execute-command", params);
+    const response = await apiClient.post<JobInfo>("/execute-command", params);
+    return response.data;
+  },
+
+  async list_jobs() {
+    const response = await apiClient.get<JobInfo[]>("/jobs");
+    return response.data;
+  },
+
+  async cancel_job(params) {
+    await apiClient.post(`/jobs/${encodeURIComponent(params.jobId)}/cancel`);
+  },
+
+  async kill_job(params) {
+    await apiClient.post(`/jobs/${encodeURIComponent(params.jobId)}/kill`);
+  },
+
+  async start_batch(params) {
+    const response = await apiClient.post<BatchReport>(
+      "/batches",
+      params.request
+    );
+    return response.data;
+  },
+
+  async list_batches() {
+    const response = await apiClient.get<BatchReport[]>("/batches");
+    return response.data;
+  },
+
+  async get_batch(params) {
+    const response = await apiClient.get<BatchReport>(
+      `/batches/${encodeURIComponent(params.batchId)}`
+    );
     return response.data;
   },
 
+  async cancel_batch(params) {
+    await apiClient.post(
+      `/batches/${encodeURIComponent(params.batchId)}/cancel`
+    );
+  },
+
+  async export_batch(params) {
+    const response = await apiClient.get<string>(
+      `/batches/${encodeURIComponent(params.batchId)}/export`,
+      { params: { format: params.format } }
+    );
+    return response.data;
+  },
+
+  async spawn_terminal(params) {
+    const response = await apiClient.post<TerminalInfo>("/terminals", params);
+    return response.data;
+  },
+
+  async list_terminals(params) {
+    const response = await apiClient.get<TerminalInfo[]>("/terminals", {
+      params: { project_id: params.projectId },
+    });
+    return response.data;
+  },
+
+  async write_terminal(params) {
+    await apiClient.post(
+      `/terminals/${encodeURIComponent(params.terminalId)}/input`,
+      { data: params.data }
+    );
+  },
+
+  async resize_terminal(params) {
+    await apiClient.post(
+      `/terminals/${encodeURIComponent(params.terminalId)}/resize`,
+      { cols: params.cols, rows: params.rows }
+    );
+  },
+
+  async terminal_scrollback(params) {
+    const response = await apiClient.get<string>(
+      `/terminals/${encodeURIComponent(params.terminalId)}/scrollback`
+    );
+    return response.data;
+  },
+
+  async kill_terminal(params) {
+    await apiClient.delete(
+      `/terminals/${encodeURIComponent(params.terminalId)}`
+    );
+  },
+
   async generate_conversation_title(params) {
     const response = await apiClient.post<string>("/generate-title", params);
     return response.data;
@@ -180,6 +291,64 @@ export const webApi: API = {
     await apiClient.post("/delete-project", params);
   },
 
+  async get_project_history_settings(params) {
+    const response = await apiClient.get<ProjectHistorySettings>(
+      `/projects/${encodeURIComponent(params.projectId)}/history-settings`
+    );
+    return response.data;
+  },
+
+  async set_project_history_settings(params) {
+    await apiClient.post(
+      `/projects/${encodeURIComponent(params.projectId)}/history-settings`,
+      params.settings
+    );
+  },
+
+  async vault_status() {
+    const response = await apiClient.get<VaultStatus>("/vault/status");
+    return response.data;
+  },
+
+  async create_vault(params) {
+    await apiClient.post("/vault/create", params);
+  },
+
+  async unlock_vault(params) {
+    await apiClient.post("/vault/unlock", params);
+  },
+
+  async lock_vault() {
+    await apiClient.post("/vault/lock");
+  },
+
+  async list_profiles() {
+    const response = await apiClient.get<ProviderProfile[]>("/vault/profiles");
+    return response.data;
+  },
+
+  async create_profile(params) {
+    const response = await apiClient.post<ProviderProfile>(
+      "/vault/profiles",
+      params.input
+    );
+    return response.data;
+  },
+
+  async update_profile(params) {
+    const response = await apiClient.put<ProviderProfile>(
+      `/vault/profiles/${encodeURIComponent(params.profileId)}`,
+      params.input
+    );
+    return response.data;
+  },
+
+  async delete_profile(params) {
+    await apiClient.delete(
+      `/vault/profiles/${encodeURIComponent(params.profileId)}`
+    );
+  },
+
   async read_file_content(params) {
     const response = await apiClient.post("/read-file-content", params);
     return response.data;
@@ -306,11 +475,207 @@ export interface EnrichedProject {
   metadata: ProjectMetadata;
 }
 
+export interface ProjectHistorySettings {
+  persist_rpc_log: boolean;
+  redaction_patterns: string[];
+}
+
+export type JobStatus =
+  | { state: "running" }
+  | { state: "exited"; code: number | null }
+  | { state: "cancelled" }
+  | { state: "killed" }
+  | { state: "timed_out" }
+  | { state: "failed"; error: string };
+
+export interface JobInfo {
+  id: string;
+  command: string;
+  working_directory: string;
+  pid: number | null;
+  started_at: string;
+  finished_at: string | null;
+  status: JobStatus;
+}
+
+/** Payload of `command-output-{job_id}` events; `exit` is always last. */
+export type CommandOutputEvent =
+  | { type: "stdout"; data: string }
+  | { type: "stderr"; data: string }
+  | { type: "exit"; status: JobStatus };
+
+export interface BatchRequest {
+  projects: string[];
+  /** `{{project_name}}` and `{{project_path}}` are replaced per project. */
+  prompt: string;
+  profile_id?: string;
+  model?: string;
+  permissions?: "reject_all" | "allow_all";
+  max_concurrency?: number;
+  timeout_secs?: number;
+  record_history?: boolean;
+}
+
+export type ProjectRunStatus =
+  | { state: "pending" }
+  | { state: "running" }
+  | { state: "succeeded" }
+  | { state: "failed"; error: string }
+  | { state: "cancelled" };
+
+export interface BatchProjectResult {
+  project_path: string;
+  status: ProjectRunStatus;
+  final_message: string | null;
+  tool_calls: { id: string; title: string; kind: string; status: string }[];
+  permission_requests: number;
+  diff: string | null;
+  untracked_files: string[];
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+export type BatchStatus = "running" | "completed" | "cancelled";
+
+export interface BatchReport {
+  id: string;
+  prompt: string;
+  status: BatchStatus;
+  created_at: string;
+  finished_at: string | null;
+  results: BatchProjectResult[];
+}
+
+/** Payload of `batch-progress-{batch_id}` events; `finished` is always last. */
+export type BatchEvent =
+  | { type: "project"; index: number; result: BatchProjectResult }
+  | { type: "finished"; status: BatchStatus };
+
+export interface TerminalInfo {
+  id: string;
+  project_id: string;
+  working_directory: string;
+  pid: number | null;
+  cols: number;
+  rows: number;
+  created_at: string;
+  alive: boolean;
+  exit_code: number | null;
+}
+
+/** Payload of `terminal-output-{terminal_id}` events and terminal socket messages. */
+export type TerminalEvent =
+  | { type: "output"; data: string }
+  | { type: "exit"; code: number | null };
+
+/** Messages a client sends over a terminal socket. */
+export type TerminalClientMessage =
+  | { type: "input"; data: string }
+  | { type: "resize"; cols: number; rows: number };
+
+/**
+ * Open the bidirectional socket of a terminal. The scrollback arrives first,
+ * as an `output` message.
+ */
+export function openTerminalSocket(terminalId: string): WebSocket {
+  const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
+  return new WebSocket(
+    `${protocol}//${window.location.host}/api/terminals/${encodeURIComponent(terminalId)}/ws`
+  );
+}
+
+export interface AuthStatus {
+  authenticated: boolean;
+  method: "token" | "password" | "users";
+  /** Who is logged in, on a server with user accounts. */
+  user: { name: string; admin: boolean } | null;
+}
+
+export async function getAuthStatus(): Promise<AuthStatus> {
+  const response = await apiClient.get<AuthStatus>("/auth/status");
+  return response.data;
+}
+
+/** A session and the user it belongs to; `user` is null without accounts. */
+export type UserSessionStatus = SessionStatus & { user: string | null };
+
+/** Every user's sessions. Only admins may list them. */
+export async function listAllSessions(): Promise<UserSessionStatus[]> {
+  const response = await apiClient.get<UserSessionStatus[]>("/admin/sessions");
+  return response.data;
+}
+
+export type KeySource = "passphrase" | "keyring";
+
+export interface VaultStatus {
+  exists: boolean;
+  unlocked: boolean;
+  key_source: KeySource | null;
+  keyring_available: boolean;
+}
+
+export type ProfileConfig =
+  | {
+      backend: "gemini";
+      config: {
+        method: string;
+        api_key?: string;
+        vertex_project?: string;
+        vertex_location?: string;
+        yolo?: boolean;
+      };
+    }
+  | {
+      backend: "qwen";
+      config: {
+        api_key: string;
+        base_url: string;
+        model: string;
+        yolo?: boolean;
+      };
+    }
+  | {
+      backend: "llxprt";
+      config: {
+        provider: string;
+        api_key: string;
+        model: string;
+        base_url?: string;
+      };
+    };
+
+export type ProfileInput = { name: string } & ProfileConfig;
+
+export type ProviderProfile = {
+  id: string;
+  name: string;
+  created_at: string;
+  updated_at: string;
+} & ProfileConfig;
+
 // WebSocket event types and management
 interface WebSocketEvent<T = unknown> {
   event: string;
   payload: T;
-  sequence: number;
+  // Missing on the "gap" marker
+  sequence?: number;
+}
+
+// Response to a command sent with WebSocketManager.call
+interface RpcResponse {
+  jsonrpc: "2.0";
+  id: number | null;
+  result?: unknown;
+  error?: { code: number; message: string };
+}
+
+// Payload of the "gap" event, sent on reconnect when the server no longer has
+// every event the client missed.
+export interface EventGap {
+  since: number;
+  first_available: number | null;
+  // Sessions whose missed events were all replayed despite the gap
+  complete_sessions: string[];
 }
 
 export class WebSocketManager {
@@ -322,6 +687,22 @@ export class WebSocketManager {
   private isConnecting = false;
   private connectionReadyPromise: Promise<void> | null = null;
   private connectionReadyResolve: (() => void) | null = null;
+  // Last sequence received, so a reconnect can ask for what it missed
+  private lastSequence: number | null = null;
+  // Topics to receive ("global", "session:<id>" or "family:<event>"). null
+  // until subscribe/unsubscribe is first called, which means every event.
+  private topics: Set<string> | null = null;
+  // Topics the server has for the current socket
+  private serverTopics: Set<string> | null = null;
+  // Server-Sent Events stream used instead when WebSockets don't get through
+  // (e.g. a proxy that breaks the upgrade)
+  private eventSource: EventSource | null = null;
+  private everConnected = false;
+  private nextRequestId = 1;
+  private pendingRequests: Map<
+    number,
+    { resolve: (result: unknown) => void; reject: (error: Error) => void }
+  > = new Map();
 
   constructor() {
     this.connect();
@@ -344,7 +725,8 @@ export class WebSocketManager {
 
     // Use current host for WebSocket connection
     const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
-    const wsUrl = `${protocol}//${window.location.host}/api/ws`;
+    const query = this.streamQuery();
+    const wsUrl = `${protocol}//${window.location.host}/api/ws${query}`;
 
     console.log("🔌 Connecting to WebSocket:", wsUrl);
 
@@ -352,6 +734,7 @@ export class WebSocketManager {
 
     this.ws.onopen = () => {
       console.log("✅ WebSocket connected");
+      this.everConnected = true;
       this.isConnecting = false;
       this.reconnectAttempts = 0;
       if (this.reconnectTimeout) {
@@ -359,6 +742,9 @@ export class WebSocketManager {
         this.reconnectTimeout = null;
       }
 
+      // Catch up on subscription changes made while connecting
+      this.syncSubscriptions();
+
       // Resolve the connection ready promise
       if (this.connectionReadyResolve) {
         this.connectionReadyResolve();
@@ -366,30 +752,20 @@ export class WebSocketManager {
       }
     };
 
-    this.ws.onmessage = (event) => {
-      try {
-        const wsEvent: WebSocketEvent = JSON.parse(event.data);
-        console.log("📨 WebSocket event:", wsEvent.event, wsEvent.payload);
-
-        const eventListeners = this.listeners.get(wsEvent.event);
-        if (eventListeners) {
-          eventListeners.forEach((listener) => {
-            try {
-              listener(wsEvent.payload);
-            } catch (error) {
-              console.error("❌ Error in WebSocket event listener:", error);
-            }
-          });
-        }
-      } catch (error) {
-        console.error("❌ Failed to parse WebSocket message:", error);
-      }
-    };
+    this.ws.onmessage = (event) => this.handleMessage(event.data);
 
     this.ws.onclose = (event) => {
       console.log("❌ WebSocket disconnected:", event.code, event.reason);
       this.isConnecting = false;
       this.ws = null;
+      this.rejectPending("WebSocket disconnected");
+
+      // Twice without ever getting through: stop trying and use SSE
+      if (!this.everConnected && this.reconnectAttempts >= 1) {
+        console.log("🔄 WebSocket unavailable, falling back to SSE");
+        this.connectEventSource();
+        return;
+      }
 
       // Attempt to reconnect if not a normal closure
       if (
@@ -419,8 +795,89 @@ export class WebSocketManager {
     };
   }
 
+  private connectEventSource() {
+    this.eventSource?.close();
+    this.eventSource = new EventSource(`/api/events${this.streamQuery()}`);
+
+    this.eventSource.onopen = () => {
+      console.log("✅ SSE connected");
+      if (this.connectionReadyResolve) {
+        this.connectionReadyResolve();
+        this.connectionReadyResolve = null;
+      }
+    };
+
+    this.eventSource.onmessage = (event) => this.handleMessage(event.data);
+
+    // EventSource reconnects by itself, resuming from Last-Event-ID
+    this.eventSource.onerror = (error) => {
+      console.error("❌ SSE error:", error);
+    };
+  }
+
+  // Query string shared by /api/ws and /api/events
+  private streamQuery(): string {
+    const params = new URLSearchParams();
+    if (this.lastSequence !== null) {
+      params.set("since", String(this.lastSequence));
+    }
+    this.serverTopics = this.topics && new Set(this.topics);
+    if (this.serverTopics) {
+      params.set("topics", [...this.serverTopics].join(","));
+    }
+    return params.toString() ? `?${params}` : "";
+  }
+
+  private handleMessage(data: string) {
+    try {
+      const message = JSON.parse(data);
+      if ("jsonrpc" in message) {
+        this.handleResponse(message as RpcResponse);
+        return;
+      }
+
+      const wsEvent: WebSocketEvent = message;
+      console.log("📨 WebSocket event:", wsEvent.event, wsEvent.payload);
+
+      if (wsEvent.event === "gap") {
+        // The server may have restarted, so sequences can start over
+        this.lastSequence = null;
+      } else if (wsEvent.sequence !== undefined) {
+        if (
+          this.lastSequence !== null &&
+          wsEvent.sequence <= this.lastSequence
+        ) {
+          return;
+        }
+        this.lastSequence = wsEvent.sequence;
+      }
+
+      const eventListeners = this.listeners.get(wsEvent.event);
+      if (eventListeners) {
+        eventListeners.forEach((listener) => {
+          try {
+            listener(wsEvent.payload);
+          } catch (error) {
+            console.error("❌ Error in WebSocket event listener:", error);
+          }
+        });
+      }
+    } catch (error) {
+      console.error("❌ Failed to parse WebSocket message:", error);
+    }
+  }
+
+  // Whether commands can go over the event connection, which they can't
+  // after falling back to SSE
+  public usesWebSocket(): boolean {
+    return this.eventSource === null;
+  }
+
   public async waitForConnection(): Promise<void> {
-    if (this.ws && this.ws.readyState === WebSocket.OPEN) {
+    if (
+      (this.ws && this.ws.readyState === WebSocket.OPEN) ||
+      this.eventSource?.readyState === EventSource.OPEN
+    ) {
       return Promise.resolve();
     }
 
@@ -467,6 +924,84 @@ export class WebSocketManager {
     };
   }
 
+  // Run a command on the server and resolve with its result. `params` are the
+  // body of the matching HTTP route.
+  public async call<T = unknown>(method: string, params: unknown): Promise<T> {
+    await this.waitForConnection();
+    const id = this.nextRequestId++;
+    return new Promise<T>((resolve, reject) => {
+      if (this.ws?.readyState !== WebSocket.OPEN) {
+        reject(new Error("WebSocket is not connected"));
+        return;
+      }
+      this.pendingRequests.set(id, {
+        resolve: (result) => resolve(result as T),
+        reject,
+      });
+      this.ws.send(JSON.stringify({ jsonrpc: "2.0", id, method, params }));
+    });
+  }
+
+  private handleResponse(response: RpcResponse) {
+    const pending =
+      response.id === null ? undefined : this.pendingRequests.get(response.id);
+    if (!pending) {
+      console.warn("⚠️ Unexpected JSON-RPC response:", response);
+      return;
+    }
+    this.pendingRequests.delete(response.id!);
+    if (response.error) {
+      pending.reject(new Error(response.error.message));
+    } else {
+      pending.resolve(response.result);
+    }
+  }
+
+  // Commands in flight on a closed socket may or may not have run
+  private rejectPending(reason: string) {
+    this.pendingRequests.forEach(({ reject }) => reject(new Error(reason)));
+    this.pendingRequests.clear();
+  }
+
+  // Receive events matching any of `topics`. Until the first call to
+  // subscribe/unsubscribe the connection receives every event, so unsubscribe
+  // from "global" to receive only the subscribed topics.
+  public subscribe(topics: string[]) {
+    this.topics ??= new Set(["global"]);
+    topics.forEach((topic) => this.topics!.add(topic));
+    this.syncSubscriptions();
+  }
+
+  public unsubscribe(topics: string[]) {
+    this.topics ??= new Set(["global"]);
+    topics.forEach((topic) => this.topics!.delete(topic));
+    this.syncSubscriptions();
+  }
+
+  // Send the server whatever changed since it last heard from us. A socket
+  // that isn't open yet is synced in onopen.
+  private syncSubscriptions() {
+    // An SSE stream's topics are fixed, so reopen it from where it left off
+    if (this.topics && this.eventSource) {
+      this.connectEventSource();
+      return;
+    }
+    if (!this.topics || this.ws?.readyState !== WebSocket.OPEN) {
+      return;
+    }
+    const current = this.topics;
+    const server = this.serverTopics ?? new Set(["global"]);
+    const removed = [...server].filter((topic) => !current.has(topic));
+    const added = [...current].filter((topic) => !server.has(topic));
+    if (removed.length > 0) {
+      this.ws.send(JSON.stringify({ type: "unsubscribe", topics: removed }));
+    }
+    if (added.length > 0) {
+      this.ws.send(JSON.stringify({ type: "subscribe", topics: added }));
+    }
+    this.serverTopics = new Set(current);
+  }
+
   public disconnect() {
     if (this.reconnectTimeout) {
       clearTimeout(this.reconnectTimeout);
@@ -479,8 +1014,11 @@ export class WebSocketManager {
       this.ws.close(1000, "Manual disconnect");
       this.ws = null;
     }
+    this.eventSource?.close();
+    this.eventSource = null;
 
     this.listeners.clear();
+    this.rejectPending("WebSocket disconnected");
     console.log("🔌 WebSocket disconnected manually");
   }
 }
@@ -0,0 +1,129 @@
+import React from "react";
+import { useNavigate } from "react-router-dom";
+import { useTranslation } from "react-i18next";
+import { ArrowLeft } from "lucide-react";
+import { Card } from "../components/ui/card";
+import { listAllSessions, UserSessionStatus } from "../lib/webApi";
+
+const REFRESH_INTERVAL_MS = 5000;
+
+function formatUptime(secs: number): string {
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  return hours > 0 ? `${hours}h ${minutes}m` : `${minutes}m ${secs % 60}s`;
+}
+
+export default function AdminSessionsPage() {
+  const { t } = useTranslation();
+  const navigate = useNavigate();
+  const [sessions, setSessions] = React.useState<UserSessionStatus[] | null>(
+    null
+  );
+  const [error, setError] = React.useState<string | null>(null);
+
+  const refreshSessions = React.useCallback(async () => {
+    try {
+      setSessions(await listAllSessions());
+      setError(null);
+    } catch (e) {
+      setError(t("admin.failedToLoad"));
+      console.error(e);
+    }
+  }, [t]);
+
+  React.useEffect(() => {
+    refreshSessions();
+    const interval = setInterval(refreshSessions, REFRESH_INTERVAL_MS);
+    return () => clearInterval(interval);
+  }, [refreshSessions]);
+
+  return (
+    <div className="w-full">
+      <div className="mx-auto w-full max-w-4xl px-6 py-8">
+        <button
+          type="button"
+          onClick={() => navigate("/")}
+          className="mb-4 inline-flex items-center text-sm text-muted-foreground hover:text-foreground transition cursor-pointer"
+          aria-label={t("projects.backToHome")}
+        >
+          <ArrowLeft className="h-4 w-4 mr-2" aria-hidden="true" />
+          <span>{t("projects.backToHome")}</span>
+        </button>
+
+        <h1 className="text-3xl font-semibold tracking-tight">
+          {t("admin.sessionsTitle")}
+        </h1>
+        <p className="mt-2 text-muted-foreground">
+          {t("admin.sessionsDescription")}
+        </p>
+
+        <div className="mt-6">
+          {error ? (
+            <p className="text-sm text-muted-foreground">{error}</p>
+          ) : sessions === null ? (
+            <p className="text-sm text-muted-foreground">
+              {t("admin.loadingSessions")}
+            </p>
+          ) : sessions.length === 0 ? (
+            <p className="text-sm text-muted-foreground">
+              {t("admin.noSessions")}
+            </p>
+          ) : (
+            <Card className="overflow-x-auto p-0">
+              <table className="w-full text-sm">
+                <thead className="text-left text-muted-foreground">
+                  <tr className="border-b">
+                    <th className="px-4 py-2 font-medium">{t("admin.user")}</th>
+                    <th className="px-4 py-2 font-medium">
+                      {t("admin.session")}
+                    </th>
+                    <th className="px-4 py-2 font-medium">
+                      {t("admin.backend")}
+                    </th>
+                    <th className="px-4 py-2 font-medium">
+                      {t("admin.state")}
+                    </th>
+                    <th className="px-4 py-2 font-medium">{t("admin.pid")}</th>
+                    <th className="px-4 py-2 font-medium">
+                      {t("admin.uptime")}
+                    </th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {sessions.map((session) => (
+                    <tr
+                      key={`${session.user ?? ""}/${session.conversation_id}`}
+                      className="border-b last:border-0"
+                    >
+                      <td className="px-4 py-2">{session.user ?? "—"}</td>
+                      <td
+                        className="px-4 py-2 font-mono text-xs"
+                        title={session.conversation_id}
+                      >
+                        {session.conversation_id.slice(0, 12)}
+                      </td>
+                      <td className="px-4 py-2">
+                        {session.backend_type ?? "—"}
+                      </td>
+                      <td className="px-4 py-2">
+                        {session.is_alive
+                          ? t(
+                              `admin.turnStates.${session.turn_state ?? "idle"}`
+                            )
+                          : t("admin.exited")}
+                      </td>
+                      <td className="px-4 py-2">{session.pid ?? "—"}</td>
+                      <td className="px-4 py-2">
+                        {formatUptime(session.uptime_secs ?? 0)}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </Card>
+          )}
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -23,12 +23,13 @@ import {
   CardTitle,
   CardDescription,
 } from "../components/ui/card";
-import { Info, UserRound, FolderKanban } from "lucide-react";
+import { Info, UserRound, FolderKanban, Users } from "lucide-react";
 import { ModelContextProtocol } from "../components/common/ModelContextProtocol";
 import { CodeMirrorViewer } from "../components/common/CodeMirrorViewer";
 import { getBackendText } from "../utils/backendText";
 import { useBackend } from "../contexts/BackendContext";
 import { GeminiMessagePart } from "../types";
+import { getAuthStatus } from "../lib/webApi";
 
 export const HomeDashboard: React.FC = () => {
   const { t } = useTranslation();
@@ -39,6 +40,17 @@ export const HomeDashboard: React.FC = () => {
   const { selectedBackend } = useBackend();
   const backendText = getBackendText(selectedBackend);
 
+  // Admins of a server with user accounts can see every user's sessions
+  const [isAdmin, setIsAdmin] = React.useState(false);
+  React.useEffect(() => {
+    if (!__WEB__) return;
+    getAuthStatus()
+      .then((status) =>
+        setIsAdmin(status.method === "users" && !!status.user?.admin)
+      )
+      .catch(() => setIsAdmin(false));
+  }, []);
+
   // Use a local ref for auto-scrolling
   const localContainerRef = React.useRef<HTMLDivElement>(null);
 
@@ -325,6 +337,26 @@ ${part.thinking}`;
                 </div>
               </CardHeader>
             </Card>
+            {isAdmin && (
+              <Card
+                className="cursor-pointer transition-colors hover:bg-accent w-full"
+                onClick={() => navigate("/admin/sessions")}
+              >
+                <CardHeader className="flex flex-row items-center gap-3">
+                  <div className="shrink-0 h-6 w-6 flex items-center justify-center">
+                    <Users className="h-5 w-5 text-muted-foreground" />
+                  </div>
+                  <div className="text-left">
+                    <CardTitle className="text-base">
+                      {t("dashboard.adminCard.title")}
+                    </CardTitle>
+                    <CardDescription>
+                      {t("dashboard.adminCard.description")}
+                    </CardDescription>
+                  </div>
+                </CardHeader>
+              </Card>
+            )}
           </div>
 
           {/* Settings link moved to sidebar footer */}
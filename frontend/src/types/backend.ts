@@ -114,6 +114,8 @@ export interface BackendState {
     qwen: QwenConfig;
     llxprt: LLxprtConfig;
   };
+  /** Vault profile sessions take their credentials from. */
+  profileId: string | null;
   isValid: boolean;
   errors: Record<string, string>;
 }
@@ -174,6 +176,7 @@ export interface BackendContextValue {
   ) => void;
   validateConfig: (backend: BackendType) => boolean;
   resetConfig: (backend: BackendType) => void;
+  selectProfile: (profileId: string | null) => void;
 
   // Computed values
   currentConfig: BackendConfig;
@@ -196,6 +199,7 @@ export type BackendAction =
   | { type: "SET_VALIDATION_ERROR"; backend: string; error: string }
   | { type: "CLEAR_VALIDATION_ERROR"; backend: string }
   | { type: "RESET_CONFIG"; backend: BackendType }
+  | { type: "SELECT_PROFILE"; profileId: string | null }
   | { type: "LOAD_FROM_STORAGE"; state: BackendState };
 
 export interface GitInfo {
@@ -0,0 +1,461 @@
+// Generated from the AppEvent enum in crates/backend/src/events/mod.rs.
+// Do not edit by hand; run `UPDATE_EVENT_SCHEMA=1 cargo test -p backend events::schema`.
+//
+// An event arrives as `{event}-{id}` (e.g. `ai-output-{session_id}`), or just
+// `{event}` for events without an id, with `payload` as its payload. Use
+// `AppEventPayload<"ai-output">` to get the payload type of an event.
+
+/**
+ * Every event the backend pushes to a frontend.
+ *
+ * On the wire an event is a name plus a JSON payload: the name is the
+ * `event` tag, suffixed with the id of the session, job, terminal or batch
+ * the event belongs to (`ai-output-{session_id}`), and the payload is the
+ * `payload` field. `frontend/src/types/events.ts` and
+ * `crates/backend/schema/events.schema.json` are generated from this enum.
+ */
+export type AppEvent =
+  /** Raw JSON-RPC traffic with the CLI, for the debug console. */
+  | {
+      event: "cli-io";
+      session_id: string;
+      payload: CliIoPayload;
+    }
+  | {
+      event: "ai-output";
+      session_id: string;
+      payload: string;
+    }
+  | {
+      event: "ai-thought";
+      session_id: string;
+      payload: string;
+    }
+  /** The agent finished its turn; the payload is always `true`. */
+  | {
+      event: "ai-turn-finished";
+      session_id: string;
+      payload: boolean;
+    }
+  | {
+      event: "ai-error";
+      session_id: string;
+      payload: string;
+    }
+  | {
+      event: "session-progress";
+      session_id: string;
+      payload: SessionProgressPayload;
+    }
+  | {
+      event: "acp-session-update";
+      session_id: string;
+      payload: SessionUpdate;
+    }
+  | {
+      event: "acp-permission-request";
+      session_id: string;
+      payload: PermissionRequestPayload;
+    }
+  | {
+      event: "gemini-output";
+      session_id: string;
+      payload: GeminiOutputPayload;
+    }
+  | {
+      event: "gemini-thought";
+      session_id: string;
+      payload: GeminiThoughtPayload;
+    }
+  | {
+      event: "gemini-tool-call";
+      session_id: string;
+      payload: ToolCallEvent;
+    }
+  | {
+      event: "gemini-tool-call-update";
+      session_id: string;
+      payload: ToolCallUpdate;
+    }
+  | {
+      event: "gemini-tool-call-confirmation";
+      session_id: string;
+      payload: ToolCallConfirmationRequest;
+    }
+  | {
+      event: "gemini-error";
+      session_id: string;
+      payload: ErrorPayload;
+    }
+  | {
+      event: "command-result";
+      payload: CommandResult;
+    }
+  /** Periodic status of every session process. */
+  | {
+      event: "process-status-changed";
+      payload: Array<ProcessStatus>;
+    }
+  | {
+      event: "command-output";
+      job_id: string;
+      payload: CommandOutputEvent;
+    }
+  | {
+      event: "terminal-output";
+      terminal_id: string;
+      payload: TerminalEvent;
+    }
+  | {
+      event: "batch-progress";
+      batch_id: string;
+      payload: BatchEvent;
+    };
+
+export type AppEventPayload<E extends AppEvent["event"]> = Extract<
+  AppEvent,
+  { event: E }
+>["payload"];
+
+/**
+ * Payload of the `batch-progress-{batch_id}` events. `finished` is the last
+ * one of a batch.
+ */
+export type BatchEvent =
+  | {
+      type: "project";
+      index: number;
+      result: BatchProjectResult;
+    }
+  | {
+      type: "finished";
+      status: BatchStatus;
+    };
+
+export type BatchProjectResult = {
+  project_path: string;
+  status: ProjectRunStatus;
+  /** The agent's reply to the prompt. */
+  final_message: string | null;
+  tool_calls: Array<BatchToolCall>;
+  permission_requests: number;
+  /** `git diff HEAD` after the run, or `None` outside a git repository. */
+  diff: string | null;
+  untracked_files: Array<string>;
+  started_at: string | null;
+  finished_at: string | null;
+};
+
+export type BatchStatus = "running" | "completed" | "cancelled";
+
+/** A tool call the agent made, with its last known status. */
+export type BatchToolCall = {
+  id: string;
+  title: string;
+  kind: ToolCallKind;
+  status: ToolCallStatus;
+};
+
+export type CliIoPayload = {
+  type: CliIoType;
+  /**
+   * One or more lines, separated by `\n`: lines read in quick succession
+   * are sent together.
+   */
+  data: string;
+};
+
+export type CliIoType = "input" | "output" | "error";
+
+/**
+ * Payload of the `command-output-{job_id}` events. Output chunks arrive in
+ * order and the `exit` event is always the last one for a job.
+ */
+export type CommandOutputEvent =
+  | {
+      type: "stdout";
+      data: string;
+    }
+  | {
+      type: "stderr";
+      data: string;
+    }
+  | {
+      type: "exit";
+      status: JobStatus;
+    };
+
+export type CommandResult = {
+  command: string;
+  success: boolean;
+  output: string | null;
+  error: string | null;
+};
+
+/** Content block for prompts and responses */
+export type ContentBlock =
+  | {
+      type: "text";
+      text: string;
+    }
+  | {
+      type: "image";
+      data: string;
+      mime_type: string;
+    }
+  | {
+      type: "audio";
+      data: string;
+      mime_type: string;
+    }
+  | {
+      type: "resource_link";
+      uri: string;
+      name: string;
+    }
+  | {
+      type: "resource";
+      resource: ResourceInfo;
+    };
+
+export type ErrorPayload = {
+  error: string;
+};
+
+export type GeminiOutputPayload = {
+  text: string;
+};
+
+export type GeminiThoughtPayload = {
+  thought: string;
+};
+
+export type JobStatus =
+  | {
+      state: "running";
+    }
+  /**
+   * The process exited on its own. `code` is `None` if it was ended by a
+   * signal that didn't come from us.
+   */
+  | {
+      state: "exited";
+      code: number | null;
+    }
+  | {
+      state: "cancelled";
+    }
+  | {
+      state: "killed";
+    }
+  | {
+      state: "timed_out";
+    }
+  | {
+      state: "failed";
+      error: string;
+    };
+
+/** Location information for tool calls */
+export type Location = {
+  path: string;
+  line: number | null;
+  column: number | null;
+};
+
+/** Permission option for user selection */
+export type PermissionOption = {
+  optionId: string;
+  name: string;
+  kind: PermissionOptionKind;
+};
+
+/** Permission option kinds */
+export type PermissionOptionKind = "allow_once" | "allow_always" | "reject_once" | "reject_always";
+
+/**
+ * Payload of `acp-permission-request-{session_id}`: the CLI's request plus
+ * the JSON-RPC id the answer has to be sent back with.
+ */
+export type PermissionRequestPayload = {
+  request_id: number;
+  request: SessionRequestPermissionParams;
+};
+
+/** Tool call information in permission request */
+export type PermissionToolCall = {
+  toolCallId: string;
+  status: ToolCallStatus;
+  title: string;
+  content: Array<ToolCallContentItem>;
+  locations: Array<Location>;
+  kind: ToolCallKind;
+  serverName?: string | null;
+  toolName?: string | null;
+};
+
+/**
+ * Status of one session process, as listed by `get_process_statuses` and
+ * pushed in `process-status-changed` events.
+ */
+export type ProcessStatus = {
+  conversation_id: string;
+  pid: number | null;
+  created_at: number;
+  is_alive: boolean;
+  backend_type: string;
+  uptime_secs: number;
+  cpu_percent: number | null;
+  memory_rss_bytes: number | null;
+  child_process_count: number | null;
+  turn_state: TurnState;
+  last_activity: number;
+};
+
+export type ProjectRunStatus =
+  | {
+      state: "pending";
+    }
+  | {
+      state: "running";
+    }
+  | {
+      state: "succeeded";
+    }
+  | {
+      state: "failed";
+      error: string;
+    }
+  | {
+      state: "cancelled";
+    };
+
+/** Resource information for embedded resources */
+export type ResourceInfo = {
+  uri: string;
+  text: string;
+};
+
+export type SessionProgressPayload = {
+  stage: SessionProgressStage;
+  message: string;
+  progress_percent: number | null;
+  details: string | null;
+};
+
+export type SessionProgressStage = "starting" | "validating_cli" | "spawning_process" | "initializing" | "authenticating" | "creating_session" | "ready" | "failed";
+
+/** Session/request_permission notification parameters */
+export type SessionRequestPermissionParams = {
+  sessionId: string;
+  options: Array<PermissionOption>;
+  toolCall: PermissionToolCall;
+};
+
+/** Session update types */
+export type SessionUpdate =
+  | {
+      sessionUpdate: "agent_message_chunk";
+      content: ContentBlock;
+    }
+  | {
+      sessionUpdate: "agent_thought_chunk";
+      content: ContentBlock;
+    }
+  | {
+      sessionUpdate: "tool_call";
+      toolCallId: string;
+      status: ToolCallStatus;
+      title: string;
+      content: Array<ToolCallContentItem>;
+      locations: Array<Location>;
+      kind: ToolCallKind;
+      serverName?: string | null;
+      toolName?: string | null;
+    }
+  | {
+      sessionUpdate: "tool_call_update";
+      toolCallId: string;
+      status: ToolCallStatus;
+      content: Array<ToolCallContentItem>;
+      serverName?: string | null;
+      toolName?: string | null;
+    };
+
+/**
+ * Payload of the `terminal-output-{terminal_id}` events and of the messages
+ * sent to terminal subscribers. `exit` is always the last one.
+ */
+export type TerminalEvent =
+  | {
+      type: "output";
+      data: string;
+    }
+  | {
+      type: "exit";
+      code: number | null;
+    };
+
+export type ToolCallConfirmation = {
+  type: string;
+  rootCommand: string | null;
+  command: string | null;
+};
+
+export type ToolCallConfirmationContent = {
+  type: string;
+  path: string | null;
+  oldText: string | null;
+  newText: string | null;
+};
+
+export type ToolCallConfirmationRequest = {
+  requestId: number;
+  sessionId: string;
+  label: string;
+  icon: string;
+  content: ToolCallConfirmationContent | null;
+  confirmation: ToolCallConfirmation;
+  locations: Array<ToolCallLocation>;
+};
+
+/** Tool call content item */
+export type ToolCallContentItem =
+  | {
+      type: "content";
+      content: ContentBlock;
+    }
+  | {
+      type: "diff";
+      path: string;
+      oldText: string;
+      newText: string;
+    };
+
+export type ToolCallEvent = {
+  id: number;
+  name: string;
+  icon: string;
+  label: string;
+  locations: Array<ToolCallLocation>;
+  status: string;
+};
+
+/** Tool call kind */
+export type ToolCallKind = "read" | "edit" | "execute" | "search" | "fetch" | "other";
+
+export type ToolCallLocation = {
+  path: string;
+};
+
+/** Tool call status */
+export type ToolCallStatus = "pending" | "in_progress" | "completed" | "failed";
+
+export type ToolCallUpdate = {
+  toolCallId: string;
+  status: string;
+  content: unknown;
+};
+
+/** What a session is doing within the current prompt turn. */
+export type TurnState = "idle" | "running_tool" | "awaiting_permission" | "prompting" | "streaming";
@@ -1,4 +1,5 @@
 import { type ToolCall, type ToolCallResult } from "../utils/toolCallParser";
+import type { CliIoType } from "./events";
 
 export interface ThinkingMessagePart {
   type: "thinking";
@@ -53,7 +54,7 @@ export interface Conversation {
 
 export interface CliIO {
   timestamp: Date;
-  type: "input" | "output";
+  type: CliIoType;
   data: string;
   conversationId: string;
 }
@@ -64,11 +65,25 @@ export interface Location {
   column?: number;
 }
 
+export type TurnState =
+  | "idle"
+  | "prompting"
+  | "streaming"
+  | "running_tool"
+  | "awaiting_permission";
+
 export interface ProcessStatus {
   conversation_id: string;
   pid: number | null;
   created_at: number;
   is_alive: boolean;
+  backend_type?: string;
+  uptime_secs?: number;
+  cpu_percent?: number | null;
+  memory_rss_bytes?: number | null;
+  child_process_count?: number | null;
+  turn_state?: TurnState;
+  last_activity?: number;
 }
 
 export interface ToolCallEvent {
@@ -44,6 +44,7 @@ export const defaultBackendState: BackendState = {
     qwen: defaultQwenConfig,
     llxprt: defaultLLxprtConfig,
   },
+  profileId: null,
   isValid: true,
   errors: {},
 };
@@ -55,6 +55,10 @@ export default defineConfig({
         changeOrigin: true,
         ws: true,
       },
+      "/login": {
+        target: "http://localhost:1858",
+        changeOrigin: true,
+      },
     },
     watch: {
       // 3. tell vite to ignore watching `src-tauri`